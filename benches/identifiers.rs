@@ -0,0 +1,57 @@
+// Identifier and string heavy scripts, for seeing what interning saves. Run with
+// `cargo bench --bench identifiers`, it prints the best of a few runs of each
+use std::time::{Duration, Instant};
+
+use interpreter::{Interpreter, LoggingLevel};
+
+const RUNS: usize = 5;
+
+// Many distinct variables read and written in a loop
+fn variables() -> String {
+    let names = (0..50).map(|i| format!("variable_{}", i)).collect::<Vec<_>>();
+    let mut source = names.iter().map(|name| format!("var {} = 0;\n", name)).collect::<String>();
+    source += "var i = 0;\nwhile i < 2000 {\n";
+    for (previous, name) in names.iter().zip(names.iter().skip(1)) {
+        source += &format!("    {} = {} + 1;\n", name, previous);
+    }
+    source += "    i = i + 1;\n}\n";
+    source
+}
+
+// Object literals with long keys, and reading them back by name
+fn properties() -> String {
+    let mut source = String::from("var total = 0;\nfor i in range(0, 2000) {\n    var record = ({");
+    source += &(0..20).map(|i| format!("property_number_{}: i", i)).collect::<Vec<_>>().join(", ");
+    source += "});\n";
+    for i in 0..20 {
+        source += &format!("    total = total + record.property_number_{};\n", i);
+    }
+    source += "}\n";
+    source
+}
+
+// String literals evaluated over and over
+fn strings() -> String {
+    let mut source = String::from("var count = 0;\nfor i in range(0, 2000) {\n");
+    for i in 0..20 {
+        source += &format!("    if \"a string literal that is a little longer than most {}\" == \"\" {{ count = count + 1; }}\n", i);
+    }
+    source += "}\n";
+    source
+}
+
+fn best_of(source: &str) -> Duration {
+    (0..RUNS).map(|_| {
+        let mut interpreter = Interpreter::new();
+        interpreter.set_diagnostics(|_: LoggingLevel, _: &str| {});
+        let start = Instant::now();
+        interpreter.eval_str(source).unwrap();
+        start.elapsed()
+    }).min().unwrap()
+}
+
+fn main() {
+    for (name, source) in [("variables", variables()), ("properties", properties()), ("strings", strings())] {
+        println!("{:<12} {:>10.2?}", name, best_of(&source));
+    }
+}
\ No newline at end of file
@@ -0,0 +1,71 @@
+// Tokenizing and parsing throughput on generated source. Run with `cargo bench --bench lexer`,
+// it prints the best of a few runs of each along with how much source that is per second
+use std::time::{Duration, Instant};
+
+use interpreter::frontend::{lexer::Tokenizer, parser::Parser};
+
+const RUNS: usize = 5;
+
+// Long names, keywords and the punctuation between them, what most scripts are made of
+fn identifiers() -> String {
+    let mut source = String::new();
+    for i in 0..20000 {
+        source += &format!("var some_longer_variable_name_{} = another_variable_name + {};\n", i, i);
+    }
+    source
+}
+
+// Numbers and operators with little else
+fn numbers() -> String {
+    let mut source = String::new();
+    for i in 0..20000 {
+        source += &format!("{} + {}.5 * 1234567 - {} % 7 <= 98765.4321;\n", i, i, i * 31);
+    }
+    source
+}
+
+// String literals with escapes, and comments the lexer keeps for the formatter
+fn strings() -> String {
+    let mut source = String::new();
+    for i in 0..20000 {
+        source += &format!("print(\"line {} of a longer string \\\"quoted\\\" with a tab\\t\"); // comment {}\n", i, i);
+    }
+    source
+}
+
+// Nested functions, ifs and loops, for the parser more than the lexer
+fn program() -> String {
+    let mut source = String::new();
+    for i in 0..2000 {
+        source += &format!("function handler_{}(event, options?) {{\n", i);
+        source += "    var total = 0;\n    for item in event.items {\n";
+        source += "        if item.value > options.limit { total = total + item.value; } else { total = total - 1; }\n";
+        source += "    }\n    while total > 100 { total = total / 2; }\n    return { total: total, name: event.name };\n}\n";
+    }
+    source
+}
+
+fn best_of(run: impl Fn()) -> Duration {
+    (0..RUNS).map(|_| {
+        let start = Instant::now();
+        run();
+        start.elapsed()
+    }).min().unwrap()
+}
+
+fn report(name: &str, source: &str, time: Duration) {
+    let megabytes = source.len() as f64 / (1024.0 * 1024.0);
+    println!("{:<22} {:>10.2?} {:>8.1} MB/s", name, time, megabytes / time.as_secs_f64());
+}
+
+fn main() {
+    let tokenizer = Tokenizer::new();
+    for (name, source) in [("identifiers", identifiers()), ("numbers", numbers()), ("strings", strings()), ("program", program())] {
+        report(&format!("tokenize/{}", name), &source, best_of(|| {
+            tokenizer.try_tokenize(source.clone()).unwrap();
+        }));
+        report(&format!("parse/{}", name), &source, best_of(|| {
+            Parser::new().parse(source.clone()).unwrap();
+        }));
+    }
+}
\ No newline at end of file
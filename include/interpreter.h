@@ -0,0 +1,29 @@
+// C interface to the interpreter, built with `cargo build --release --features ffi` and linked
+// against the resulting shared library (libinterpreter.so, .dylib or interpreter.dll)
+#ifndef INTERPRETER_H
+#define INTERPRETER_H
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+typedef struct FfiInterpreter FfiInterpreter;
+
+// A new interpreter with its own global scope, free it with interpreter_free
+FfiInterpreter *interpreter_new(void);
+void interpreter_free(FfiInterpreter *interpreter);
+
+// Runs source in the interpreter's global scope, declarations stay around for later calls.
+// Returns the result as a string to free with interpreter_string_free, or NULL on error
+char *interpreter_eval(FfiInterpreter *interpreter, const char *source);
+
+// The last interpreter_eval error, or NULL. Valid until the next interpreter_eval
+const char *interpreter_last_error(const FfiInterpreter *interpreter);
+
+void interpreter_string_free(char *string);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif
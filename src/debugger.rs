@@ -0,0 +1,196 @@
+use std::{cell::{Cell, RefCell}, collections::{BTreeSet, HashSet}, io::{self, Write}, panic, sync::{Arc, Mutex}};
+
+use crate::{frontend::symbol::Symbol, runtime::{environment::Environment, trace::Tracer}, FatalError};
+
+// A command line debugger, `interpreter --debug script.tl`. It pauses before the first statement and
+// then wherever stepping or a breakpoint says to, reading commands from stdin:
+//   s, step            run to the next statement, going into function calls
+//   n, next            run to the next statement in this function or its callers
+//   f, finish          run until the current function returns
+//   c, continue        run until a breakpoint
+//   b, break <line>    pause before the statement on <line>, d, delete <line> removes it
+//   breakpoints        list the breakpoints
+//   p, print <name>    show a variable visible from here
+//   locals             show the variables of the current function, or the script's own globals
+//   l, list            show the source around the current line
+//   q, quit            stop the script
+pub struct Debugger {
+    // Empty for compiled scripts, lines are then shown by number only
+    source: Vec<String>,
+    breakpoints: RefCell<BTreeSet<usize>>,
+    mode: Cell<Mode>,
+    // How many script function calls deep the script currently is
+    depth: Cell<usize>,
+    quit: Cell<bool>
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum Mode {
+    Step,
+    // Pause once back at or above this call depth
+    Next(usize),
+    Continue,
+    Quit
+}
+
+impl Debugger {
+    pub fn new(source: &str) -> Self {
+        Debugger {
+            source: source.lines().map(str::to_string).collect(),
+            breakpoints: RefCell::new(BTreeSet::new()),
+            mode: Cell::new(Mode::Step),
+            depth: Cell::new(0),
+            quit: Cell::new(false)
+        }
+    }
+
+    pub fn add_breakpoint(&self, line: usize) {
+        self.breakpoints.borrow_mut().insert(line);
+    }
+
+    // Whether the user quit, the script was stopped early by a fatal error rather than failing
+    pub fn quit(&self) -> bool {
+        self.quit.get()
+    }
+
+    fn source_line(&self, line: usize) -> &str {
+        self.source.get(line - 1).map(String::as_str).unwrap_or("")
+    }
+
+    fn list(&self, current: usize) {
+        if self.source.is_empty() {
+            println!("No source for this script");
+            return;
+        }
+
+        let first = current.saturating_sub(3).max(1);
+        let last = (current + 3).min(self.source.len());
+        for line in first..=last {
+            let marker = if line == current { "->" } else if self.breakpoints.borrow().contains(&line) { " *" } else { "  " };
+            println!("{} {:>4} | {}", marker, line, self.source_line(line));
+        }
+    }
+
+    // Reads commands until one resumes the script
+    fn prompt(&self, line: usize, env: &Arc<Mutex<Environment>>) {
+        println!("{:>4} | {}", line, self.source_line(line));
+
+        loop {
+            print!("(debug) ");
+            io::stdout().flush().unwrap();
+
+            let mut input = String::new();
+            // Running out of input leaves nobody to resume the script, so it ends like quit
+            if io::stdin().read_line(&mut input).unwrap_or(0) == 0 {
+                println!();
+                return self.stop();
+            }
+
+            let mut words = input.split_whitespace();
+            let command = words.next().unwrap_or("");
+            let argument = words.next();
+
+            match (command, argument) {
+                ("s" | "step", _) => return self.mode.set(Mode::Step),
+                ("n" | "next", _) => return self.mode.set(Mode::Next(self.depth.get())),
+                ("f" | "finish", _) => return self.mode.set(Mode::Next(self.depth.get().saturating_sub(1))),
+                ("c" | "continue", _) => return self.mode.set(Mode::Continue),
+                ("q" | "quit", _) => return self.stop(),
+                ("b" | "break", Some(line)) => match line.parse::<usize>() {
+                    Ok(line) if line > 0 => self.add_breakpoint(line),
+                    _ => println!("Expected a line number, found {}", line)
+                },
+                ("d" | "delete", Some(line)) => match line.parse::<usize>() {
+                    Ok(line) if self.breakpoints.borrow_mut().remove(&line) => {},
+                    _ => println!("No breakpoint on line {}", line)
+                },
+                ("breakpoints", _) => {
+                    let lines = self.breakpoints.borrow().iter().map(usize::to_string).collect::<Vec<String>>();
+                    println!("Breakpoints: {}", if lines.is_empty() { "none".to_string() } else { lines.join(", ") });
+                },
+                ("p" | "print", Some(name)) => match lookup(env, Symbol::intern(name)) {
+                    Some(value) => println!("{} = {}", name, value),
+                    None => println!("{} is not defined here", name)
+                },
+                ("locals", _) => {
+                    let mut names = locals(env);
+                    names.sort();
+                    for (name, value) in names {
+                        println!("{} = {}", name, value);
+                    }
+                },
+                ("l" | "list", _) => self.list(line),
+                ("", _) => {},
+                _ => println!("Unknown command {}, try step, next, finish, continue, break, delete, breakpoints, print, locals, list or quit", input.trim())
+            }
+        }
+    }
+
+    fn stop(&self) {
+        self.quit.set(true);
+        self.mode.set(Mode::Quit);
+    }
+}
+
+impl Tracer for Debugger {
+    fn line(&self, line: usize, env: &Arc<Mutex<Environment>>) {
+        let pause = match self.mode.get() {
+            Mode::Step => true,
+            Mode::Next(depth) => self.depth.get() <= depth,
+            Mode::Continue | Mode::Quit => false
+        } || self.breakpoints.borrow().contains(&line);
+
+        if pause {
+            self.prompt(line, env);
+        }
+        if self.mode.get() == Mode::Quit {
+            // Unwinds like a fatal error, but there's nothing to report
+            panic::panic_any(FatalError { message: "Stopped by the debugger".to_string() });
+        }
+    }
+
+    fn enter(&self, _function: &str) {
+        self.depth.set(self.depth.get() + 1);
+    }
+
+    fn leave(&self, _function: &str) {
+        self.depth.set(self.depth.get().saturating_sub(1));
+    }
+}
+
+// Walks out from `env` like a lookup would, without the error when the name isn't there
+fn lookup(env: &Arc<Mutex<Environment>>, name: Symbol) -> Option<String> {
+    let mut current = Some(Arc::clone(env));
+    while let Some(scope) = current {
+        let scope = scope.lock().unwrap();
+        if let Some(value) = scope.variables.get(&name) {
+            return Some(value.to_string());
+        }
+        current = scope.parent.clone();
+    }
+    None
+}
+
+// Every scope up to the enclosing function's. At the top level that's the global scope, where
+// everything a fresh global scope already has is left out
+fn locals(env: &Arc<Mutex<Environment>>) -> Vec<(String, String)> {
+    let builtins = Environment::new(None).variables.into_keys().collect::<HashSet<Symbol>>();
+    let mut names = Vec::new();
+    let mut current = Some(Arc::clone(env));
+    while let Some(scope) = current {
+        let scope = scope.lock().unwrap();
+        for (name, value) in scope.variables.iter() {
+            if scope.is_global() && builtins.contains(name) {
+                continue;
+            }
+            if !names.iter().any(|(existing, _)| existing == name.as_str()) {
+                names.push((name.to_string(), value.to_string()));
+            }
+        }
+        if scope.function {
+            break;
+        }
+        current = scope.parent.clone();
+    }
+    names
+}
\ No newline at end of file
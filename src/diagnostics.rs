@@ -0,0 +1,189 @@
+use std::{cell::RefCell, env, io::{self, IsTerminal}, mem, rc::Rc};
+
+use crate::{frontend::errors::ParseError, runtime::trace, LoggingLevel};
+
+// Receives every message the interpreter reports (parser warnings, runtime and script errors).
+// The default prints to the console, an embedder can swap in its own to collect, forward or
+// suppress them. Fatal errors still unwind after being reported
+pub trait Diagnostics {
+    fn report(&self, level: LoggingLevel, message: &str);
+}
+
+// Closures work as diagnostics, |_, _| {} silences everything
+impl<F: Fn(LoggingLevel, &str)> Diagnostics for F {
+    fn report(&self, level: LoggingLevel, message: &str) {
+        self(level, message)
+    }
+}
+
+pub struct ConsoleDiagnostics;
+
+impl Diagnostics for ConsoleDiagnostics {
+    fn report(&self, level: LoggingLevel, message: &str) {
+        match level {
+            LoggingLevel::Info => println!("[+] {}", message),
+            LoggingLevel::Warn => println!("[-] {}", message),
+            LoggingLevel::Error => println!("[-] ERROR: {}", message),
+            LoggingLevel::Fatal => println!("[-] FATAL ERROR: {}", message)
+        }
+    }
+}
+
+// Reports like ConsoleDiagnostics, but errors go to stderr with the line of the script they happened
+// on (see trace::current_line) shown under them
+pub struct SourceDiagnostics {
+    pub path: String,
+    pub source: String
+}
+
+impl Diagnostics for SourceDiagnostics {
+    fn report(&self, level: LoggingLevel, message: &str) {
+        match level {
+            LoggingLevel::Info | LoggingLevel::Warn => ConsoleDiagnostics.report(level, message),
+            LoggingLevel::Error | LoggingLevel::Fatal => {
+                let frame = Frame {
+                    path: &self.path,
+                    source: &self.source,
+                    line: trace::current_line(),
+                    column: 0,
+                    width: 0,
+                    note: None
+                };
+                eprint!("{}", render(level, message, Some(&frame), use_color()));
+            }
+        }
+    }
+}
+
+// The part of the source a message is about: its line, with carets under `width` characters from
+// `column`. Column 0 underlines the whole line, for errors only known by line
+pub struct Frame<'a> {
+    pub path: &'a str,
+    pub source: &'a str,
+    pub line: usize,
+    pub column: usize,
+    pub width: usize,
+    // Shown after the carets
+    pub note: Option<String>
+}
+
+// Only for a terminal, and never when NO_COLOR is set (https://no-color.org)
+pub fn use_color() -> bool {
+    env::var_os("NO_COLOR").is_none() && io::stderr().is_terminal()
+}
+
+fn paint(text: &str, code: &str, color: bool) -> String {
+    if color {
+        format!("\x1b[{}m{}\x1b[0m", code, text)
+    } else {
+        text.to_string()
+    }
+}
+
+// A message as a compiler would print it:
+//
+//   error: Expected closing brace in body
+//    --> script.tl:3:1
+//     |
+//   3 | var x = 1
+//     | ^ expected `}`, found end of input
+//
+// The frame is left out when there is none, or its line isn't in the source
+pub fn render(level: LoggingLevel, message: &str, frame: Option<&Frame>, color: bool) -> String {
+    let (label, code) = match level {
+        LoggingLevel::Info => ("info", "1;36"),
+        LoggingLevel::Warn => ("warning", "1;33"),
+        LoggingLevel::Error | LoggingLevel::Fatal => ("error", "1;31")
+    };
+    let mut out = format!("{}{} {}\n", paint(label, code, color), paint(":", "1", color), paint(message, "1", color));
+
+    let Some(frame) = frame.filter(|frame| frame.line != 0) else {
+        return out;
+    };
+    let lines = frame.source.lines().collect::<Vec<_>>();
+    // Running out of input is reported past the last line, it's shown at the end of that line instead
+    let (line, text, column, width) = match lines.get(frame.line - 1) {
+        Some(text) if frame.column == 0 => {
+            let indent = text.chars().take_while(|c| c.is_whitespace()).count();
+            (frame.line, *text, indent + 1, text.trim().chars().count())
+        },
+        Some(text) => (frame.line, *text, frame.column, frame.width),
+        None => match lines.iter().rposition(|text| !text.trim().is_empty()) {
+            Some(index) => (index + 1, lines[index], lines[index].chars().count() + 1, 1),
+            None => return out
+        }
+    };
+
+    let number = line.to_string();
+    let gutter = " ".repeat(number.len());
+    let bar = paint("|", "1;34", color);
+    out += &format!("{}{} {}:{}:{}\n", gutter, paint("-->", "1;34", color), frame.path, line, column);
+    out += &format!("{} {}\n", gutter, bar);
+    out += &format!("{} {} {}\n", paint(&number, "1;34", color), bar, text);
+
+    // Tabs are kept so the carets line up however wide the terminal draws them
+    let padding = text.chars().take(column - 1).map(|c| if c == '\t' { '\t' } else { ' ' }).collect::<String>();
+    let mut carets = "^".repeat(width.max(1));
+    if let Some(note) = &frame.note {
+        carets = format!("{} {}", carets, note);
+    }
+    out += &format!("{} {} {}{}\n", gutter, bar, padding, paint(&carets, code, color));
+    out
+}
+
+// A parse error of `source` rendered with its frame, the expected and found tokens under the carets
+pub fn render_parse_error(path: &str, source: &str, error: &ParseError, color: bool) -> String {
+    let note = match (&error.expected, &error.found) {
+        (Some(expected), Some(found)) => Some(format!("expected {}, found {}", expected, found)),
+        (None, Some(found)) => Some(format!("found {}", found)),
+        _ => None
+    };
+    let frame = Frame {
+        path,
+        source,
+        line: error.line,
+        column: error.column,
+        width: error.width,
+        note
+    };
+    match (&frame.note, error.line) {
+        // Nowhere to show the note, so it goes with the message
+        (Some(note), 0) => render(LoggingLevel::Error, &format!("{} ({})", error.message, note), None, color),
+        _ => render(LoggingLevel::Error, &error.message, Some(&frame), color)
+    }
+}
+
+thread_local! {
+    static DIAGNOSTICS: RefCell<Rc<dyn Diagnostics>> = RefCell::new(Rc::new(ConsoleDiagnostics));
+}
+
+pub fn set_diagnostics(diagnostics: impl Diagnostics + 'static) {
+    DIAGNOSTICS.with(|current| *current.borrow_mut() = Rc::new(diagnostics));
+}
+
+pub fn reset_diagnostics() {
+    set_diagnostics(ConsoleDiagnostics);
+}
+
+// Puts the previous diagnostics back when dropped, also when a fatal error unwinds
+struct Restore(Option<Rc<dyn Diagnostics>>);
+
+impl Drop for Restore {
+    fn drop(&mut self) {
+        if let Some(previous) = self.0.take() {
+            DIAGNOSTICS.with(|current| *current.borrow_mut() = previous);
+        }
+    }
+}
+
+// Reports through `diagnostics` while `run` runs, for an interpreter with its own
+pub fn scoped<T>(diagnostics: Rc<dyn Diagnostics>, run: impl FnOnce() -> T) -> T {
+    let _restore = Restore(Some(DIAGNOSTICS.with(|current| mem::replace(&mut *current.borrow_mut(), diagnostics))));
+    run()
+}
+
+pub fn report(level: LoggingLevel, message: &str) {
+    // Cloned out first so a diagnostics that reports or replaces itself doesn't hit a borrowed RefCell
+    let diagnostics = DIAGNOSTICS.with(|current| Rc::clone(&current.borrow()));
+    diagnostics.report(level, message);
+}
\ No newline at end of file
@@ -1,307 +1,376 @@
+use std::cmp::Ordering;
 use std::collections::HashMap;
 use std::sync::{Arc, Mutex};
 
-use crate::{error, fatal_error, MK_BOOL, MK_NULL, MK_NUMBER, MK_STRING};
-use crate::runtime::values::{BooleanValue, FunctionValue, ListValue, NativeFnValue, NullValue, NumberValue, ObjectValue, RuntimeValue, StringValue, ValueType};
-use crate::frontend::ast::{AssignmentExpr, BinaryExpr, CallExpr, ComparativeExpr, Expr, Identifier, ListLiteral, MemberExpr, NodeType, ObjectLiteral, Stmt};
+use crate::{error, fatal_error, raise, warn, MK_BOOL, MK_NULL, MK_NUMBER, MK_STRING};
+use crate::runtime::context::NativeContext;
+use crate::runtime::values::{index_error, number_to_string, position, strict_equals, ListValue, ObjectValue, Value, ValueType};
+use crate::frontend::ast::{Ast, AssignmentExpr, BinaryExpr, BinaryOp, CallExpr, CompareOp, ComparativeExpr, Expr, Identifier, ListLiteral, MemberExpr, ObjectLiteral, ExprId};
 use crate::runtime::environment::{Environment, SharedEnvironment};
-use crate::runtime::interpreter::eval;
-
-pub fn eval_binop_expr(binop: BinaryExpr, env: Arc<Mutex<Environment>>) -> Box<dyn RuntimeValue> {
-    let lhs = eval(binop.left.to_stmt_from_expr(), Arc::clone(&env));
-    let rhs = eval(binop.right.to_stmt_from_expr(), Arc::clone(&env));
-
-    if lhs.get_type() == ValueType::Number && rhs.get_type() == ValueType::Number {
-        let lhs = lhs.as_any().downcast_ref::<NumberValue>().expect("Failed to downcast to NumberValue");
-        let rhs = rhs.as_any().downcast_ref::<NumberValue>().expect("Failed to downcast to NumberValue");
-        eval_numeric_binary_expr(*lhs, *rhs, binop.operator)
-    } else if lhs.get_type() == ValueType::String && rhs.get_type() == ValueType::String {
-        let lhs = lhs.as_any().downcast_ref::<StringValue>().expect("Failed to downcast to StringValue");
-        let rhs = rhs.as_any().downcast_ref::<StringValue>().expect("Failed to downcast to StringValue");
-        eval_string_binary_expr(lhs.clone(), rhs.clone(), binop.operator)
-    } else if (lhs.get_type() == ValueType::String && rhs.get_type() == ValueType::Number) || (rhs.get_type() == ValueType::String && lhs.get_type() == ValueType::Number) {
-        let string;
-        let number;
-        if lhs.get_type() == ValueType::String {
-            string = lhs.as_any().downcast_ref::<StringValue>().expect("Failed to downcast to StringValue").clone();
-            number = rhs.as_any().downcast_ref::<NumberValue>().expect("Failed to downcast to NumberValue").clone();
-        } else {
-            string = rhs.as_any().downcast_ref::<StringValue>().expect("Failed to downcast to StringValue").clone();
-            number = lhs.as_any().downcast_ref::<NumberValue>().expect("Failed to downcast to NumberValue").clone();
+use crate::runtime::policy;
+use crate::runtime::interpreter::eval_expr;
+
+pub fn eval_binop_expr(binop: &BinaryExpr, ast: &Arc<Ast>, env: Arc<Mutex<Environment>>) -> Value {
+    let lhs = eval_expr(binop.left, ast, Arc::clone(&env));
+    let rhs = eval_expr(binop.right, ast, Arc::clone(&env));
+
+    match (&lhs, &rhs) {
+        (Value::Number(lhs), Value::Number(rhs)) => {
+            if *rhs == 0.0 && SharedEnvironment(Arc::clone(&env)).global_scope().lock().unwrap().strict {
+                match binop.operator {
+                    BinaryOp::Divide => raise(&format!("Division by zero ({} / 0)", number_to_string(*lhs))),
+                    BinaryOp::Modulo => raise(&format!("Remainder of division by zero ({} % 0)", number_to_string(*lhs))),
+                    _ => {}
+                }
+            }
+            eval_numeric_binary_expr(*lhs, *rhs, binop.operator)
         }
-
-        eval_string_numeric_binary_expr(string, number, binop.operator)
-    } else{
-        Box::new(MK_NULL!())
+        (Value::Str(lhs), Value::Str(rhs)) => eval_string_binary_expr(lhs.clone(), rhs, binop.operator),
+        (Value::Str(string), Value::Number(number)) | (Value::Number(number), Value::Str(string)) => {
+            eval_string_numeric_binary_expr(string.clone(), *number, binop.operator)
+        }
+        _ => MK_NULL!()
     }
 }
 
-pub fn eval_numeric_binary_expr(lhs: NumberValue, rhs: NumberValue, operator: String) -> Box<NumberValue> {
-    match &*operator {
-        "+" => Box::new(MK_NUMBER!(lhs.value + rhs.value)),
-        "-" => Box::new(MK_NUMBER!(lhs.value - rhs.value)),
-        "*" => Box::new(MK_NUMBER!(lhs.value * rhs.value)),
-        "/" => Box::new(MK_NUMBER!(lhs.value / rhs.value)),
-        "%" => Box::new(MK_NUMBER!(lhs.value % rhs.value)),
-        _ => Box::new(MK_NUMBER!(0.0))
+pub fn eval_numeric_binary_expr(lhs: f64, rhs: f64, operator: BinaryOp) -> Value {
+    match operator {
+        BinaryOp::Add => MK_NUMBER!(lhs + rhs),
+        BinaryOp::Subtract => MK_NUMBER!(lhs - rhs),
+        BinaryOp::Multiply => MK_NUMBER!(lhs * rhs),
+        BinaryOp::Divide => MK_NUMBER!(lhs / rhs),
+        BinaryOp::Modulo => MK_NUMBER!(lhs % rhs)
     }
 }
 
-pub fn eval_string_binary_expr(lhs: StringValue, rhs: StringValue, operator: String) -> Box<StringValue> {
-    match &*operator {
-        "+" => Box::new(MK_STRING!(lhs.value + &rhs.value)),
+pub fn eval_string_binary_expr(lhs: String, rhs: &str, operator: BinaryOp) -> Value {
+    match operator {
+        BinaryOp::Add => MK_STRING!(lhs + rhs),
         _ => {
             error("Invalid operator between string and string");
-            Box::new(MK_STRING!(String::from("") ))
+            MK_STRING!(String::from("") )
         }
     }
 }
 
-pub fn eval_string_numeric_binary_expr(string: StringValue, number: NumberValue, operator: String) -> Box<StringValue> {
-    match &*operator {
-        "+" => Box::new(MK_STRING!(string.value + &number.value.to_string())),
-        "*" => Box::new(MK_STRING!(string.value.repeat(number.value as usize))),
+pub fn eval_string_numeric_binary_expr(string: String, number: f64, operator: BinaryOp) -> Value {
+    match operator {
+        BinaryOp::Add => MK_STRING!(string + &number.to_string()),
+        BinaryOp::Multiply => MK_STRING!(string.repeat(number as usize)),
         _ => {
             error("Invalid operator between string and number");
-            Box::new(MK_STRING!(String::from("")))
+            MK_STRING!(String::from(""))
         }
     }
 }
 
-pub fn eval_comp_expr(comp: ComparativeExpr, env: Arc<Mutex<Environment>>) -> Box<dyn RuntimeValue> {
-    let left = eval(comp.left.to_stmt_from_expr(), Arc::clone(&env));
-    let right = eval(comp.right.to_stmt_from_expr(), Arc::clone(&env));
-    match &*comp.operator {
-        "==" => {
-            if left.get_type() != right.get_type() {
-                Box::new(MK_BOOL!(false))
-            } else {
-                if left.equals(right) {
-                    Box::new(MK_BOOL!(true))
-                } else {
-                    Box::new(MK_BOOL!(false))
-                }
-            }
-        },
-        ">" => {
-            if left.get_type() != right.get_type() {
-                Box::new(MK_BOOL!(false))
-            } else {
-                if left.greater_than(right) {
-                    Box::new(MK_BOOL!(true))
-                } else {
-                    Box::new(MK_BOOL!(false))
-                }
-            }
-        },
-        "<" => {
-            if left.get_type() != right.get_type() {
-                Box::new(MK_BOOL!(false))
-            } else {
-                if left.less_than(right) {
-                    Box::new(MK_BOOL!(true))
-                } else {
-                    Box::new(MK_BOOL!(false))
-                }
-            }
-        },
-        ">=" => {
-            if left.get_type() != right.get_type() {
-                Box::new(MK_BOOL!(false))
-            } else {
-                if left.greater_than(right.clone()) || left.equals(right) {
-                    Box::new(MK_BOOL!(true))
-                } else {
-                    Box::new(MK_BOOL!(false))
-                }
-            }
-        },
-        "<=" => {
-            if left.get_type() != right.get_type() {
-                Box::new(MK_BOOL!(false))
-            } else {
-                if left.less_than(right.clone()) || left.equals(right) {
-                    Box::new(MK_BOOL!(true))
-                } else {
-                    Box::new(MK_BOOL!(false))
+// How values compare:
+// - === and !== only count values of the same type as equal, nothing is converted
+// - == and != do the same, except that a number and a string holding a number compare as numbers
+//   ("1" == 1). != is always the opposite of ==
+// - <, >, <= and >= order numbers by value (numeric strings included, against a number) and
+//   strings by code point. Other pairs have no order: the comparison is false with a warning
+//   saying so, or an error in strict mode
+pub fn eval_comp_expr(comp: &ComparativeExpr, ast: &Arc<Ast>, env: Arc<Mutex<Environment>>) -> Value {
+    let left = eval_expr(comp.left, ast, Arc::clone(&env));
+    let right = eval_expr(comp.right, ast, Arc::clone(&env));
+    let (left, right) = (&left, &right);
+
+    let result = match comp.operator {
+        CompareOp::StrictEqual => strict_equals(left, right),
+        CompareOp::StrictNotEqual => !strict_equals(left, right),
+        CompareOp::Equal => loose_equals(left, right),
+        CompareOp::NotEqual => !loose_equals(left, right),
+        operator => match order(left, right) {
+            Some(ordering) => match operator {
+                CompareOp::LessThan => ordering == Ordering::Less,
+                CompareOp::GreaterThan => ordering == Ordering::Greater,
+                CompareOp::LessThanOrEqual => ordering != Ordering::Greater,
+                _ => ordering != Ordering::Less
+            },
+            // NaN is unordered too, but that's a number doing what numbers do
+            None if is_nan(left) || is_nan(right) => false,
+            None => {
+                let message = format!("Cannot order {} and {} with {}", describe(left), describe(right), operator.symbol());
+                if SharedEnvironment(Arc::clone(&env)).global_scope().lock().unwrap().strict {
+                    raise(&message);
                 }
+                warn(&format!("{}, the comparison is always false", message));
+                false
             }
-        },
-        "!=" => {
-            if left.get_type() != right.get_type() {
-                Box::new(MK_BOOL!(true))
-            } else {
-                if left.equals(right.clone()) {
-                    Box::new(MK_BOOL!(false))
-                } else {
-                    Box::new(MK_BOOL!(true))
-                }
-            }
-        }
-        _ => {
-            error("Invalid operator in comparative expression.");
-            Box::new(NullValue {})
         }
+    };
+
+    MK_BOOL!(result)
+}
+
+// A string only counts as a number if all of it is one, surrounding whitespace aside
+fn as_number(value: &Value) -> Option<f64> {
+    if let Value::Number(number) = value {
+        return Some(*number);
+    }
+
+    let string = match value {
+        Value::Str(string) => string.trim(),
+        _ => return None
+    };
+    if string.is_empty() || !string.chars().all(|c| c.is_ascii_digit() || matches!(c, '.' | '-' | '+' | 'e' | 'E')) {
+        return None;
     }
+    string.parse().ok()
 }
 
-pub fn eval_identifier(identifier: Identifier, env: Arc<Mutex<Environment>>) -> Box<dyn RuntimeValue> {
-    SharedEnvironment(env).lookup_var(identifier.symbol)
+fn is_nan(value: &Value) -> bool {
+    matches!(value, Value::Number(number) if number.is_nan())
 }
 
-pub fn eval_assignment(node: AssignmentExpr, env: Arc<Mutex<Environment>>) -> Box<dyn RuntimeValue> {
-    let mut shared_env = SharedEnvironment(Arc::clone(&env));
-    match node.assignee.get_kind() {
-        NodeType::Identifier => {
-            let identifier = node.assignee.as_any().downcast_ref::<Identifier>().expect("Failed to downcast to Identifier.").clone();
-            let value = eval(node.value.to_stmt_from_expr(), Arc::clone(&env));
-            shared_env.assign_var(identifier.symbol, value, false)
+fn loose_equals(left: &Value, right: &Value) -> bool {
+    match (left.get_type(), right.get_type()) {
+        (ValueType::Number, ValueType::String) | (ValueType::String, ValueType::Number) => match (as_number(left), as_number(right)) {
+            (Some(left), Some(right)) => left == right,
+            _ => false
         },
-        NodeType::MemberExpr => {
-            let member_expr = node.assignee.as_any().downcast_ref::<MemberExpr>().expect("Failed to downcast to MemberExpr.").clone();
-
-            let object_identifier = member_expr.object.as_any().downcast_ref::<Identifier>().expect("Failed to downcast to Identifier.").clone();
-            let obj = shared_env.lookup_var(object_identifier.symbol.clone());
-            
-            if obj.get_type() == ValueType::Object {
-                let mut obj = obj.as_any().downcast_ref::<ObjectValue>().unwrap().clone();
-                let property;
-
-                if member_expr.property.get_kind() == NodeType::Identifier {
-                    property = member_expr.property.as_any().downcast_ref::<Identifier>().expect("Failed to downcast to Identifier.").clone().symbol;
-                } else if member_expr.property.get_kind() == NodeType::String {
-                    property = eval(member_expr.property.to_stmt_from_expr(), Arc::clone(&env)).as_any().downcast_ref::<StringValue>().expect("Failed to downcast to StrinvValue.").clone().value;
-                } else {
-                    fatal_error("Unexpected value in member assignment expr");
-                }
+        _ => strict_equals(left, right)
+    }
+}
 
-                let value = eval(node.value.to_stmt_from_expr(), Arc::clone(&env));
+// None when the two can't be ordered
+fn order(left: &Value, right: &Value) -> Option<Ordering> {
+    match (left.get_type(), right.get_type()) {
+        (ValueType::String, ValueType::String) => Some(left.as_str()?.cmp(right.as_str()?)),
+        (ValueType::Number | ValueType::String, ValueType::Number | ValueType::String) => as_number(left)?.partial_cmp(&as_number(right)?),
+        _ => None
+    }
+}
 
-                obj.properties.insert(property, value);
-                shared_env.assign_var(object_identifier.symbol, Box::new(obj), false)
-            } else if obj.get_type() == ValueType::List {
-                let mut obj = obj.as_any().downcast_ref::<ListValue>().unwrap().clone();
-                let property = eval(member_expr.property.to_stmt_from_expr(), Arc::clone(&env)).as_any().downcast_ref::<NumberValue>().expect("Expected number when indexing list").clone();
+// For warnings, strings show what's in them as a non-numeric one is the usual surprise
+fn describe(value: &Value) -> String {
+    match value {
+        Value::Str(string) => format!("string {:?}", string),
+        _ => value.get_type().to_string()
+    }
+}
 
-                let value = eval(node.value.to_stmt_from_expr(), env);
+pub fn eval_identifier(identifier: &Identifier, env: Arc<Mutex<Environment>>) -> Value {
+    SharedEnvironment(env).lookup_var(identifier.symbol)
+}
 
-                let len = obj.elements.len();
-                if len < (property.value + 1.0) as usize {
-                    fatal_error("Index out of range.");
-                } else if len > 0 {
-                    if property.value == -1.0 {
-                        obj.elements[len - 1] = value;
-                    } else if property.value >= 0.0 {
-                        obj.elements[property.value as usize] = value;
-                    }
-                } else {
-                    fatal_error("Index out of range.")
-                }
+pub fn eval_assignment(node: &AssignmentExpr, ast: &Arc<Ast>, env: Arc<Mutex<Environment>>) -> Value {
+    assign(node.assignee, || eval_expr(node.value, ast, Arc::clone(&env)), ast, Arc::clone(&env))
+}
 
-                shared_env.assign_var(object_identifier.symbol, Box::new(obj), false)
-            } else {
-                fatal_error(&format!("Invalid assignment expr: {:?}", node));
+// Stores a value where an assignment's left side points. The value is only worked out once the
+// target has been checked, so nothing it does happens first
+fn assign(assignee: ExprId, value: impl FnOnce() -> Value, ast: &Arc<Ast>, env: Arc<Mutex<Environment>>) -> Value {
+    let mut shared_env = SharedEnvironment(Arc::clone(&env));
+    match &ast[assignee] {
+        Expr::Identifier(identifier) => {
+            shared_env.assign_scoped(identifier.symbol, value())
+        },
+        Expr::Member(member_expr) => {
+            // Lists and objects are shared, so the change is made in place and seen through every
+            // variable holding them. A constant still can't have its members set, however deep,
+            // so `c.a.b = 1` is checked against c
+            let mut root = member_expr.object;
+            while let Expr::Member(member) = &ast[root] {
+                root = member.object;
+            }
+            if let Expr::Identifier(identifier) = &ast[root] {
+                shared_env.check_member_assignment(identifier.symbol);
+            }
+            let obj = eval_expr(member_expr.object, ast, Arc::clone(&env));
+
+            match &obj {
+                Value::Object(object) => {
+                    let property = if member_expr.computed {
+                        match eval_expr(member_expr.property, ast, Arc::clone(&env)) {
+                            Value::Str(property) => property,
+                            _ => fatal_error("Unexpected value in member assignment expr")
+                        }
+                    } else {
+                        match &ast[member_expr.property] {
+                            Expr::Identifier(identifier) => identifier.symbol.to_string(),
+                            _ => fatal_error("Unexpected value in member assignment expr")
+                        }
+                    };
+
+                    let value = value();
+                    object.set(property, value.clone());
+                    policy::check_value(&obj);
+                    value
+                },
+                Value::List(list) => {
+                    let index = match eval_expr(member_expr.property, ast, Arc::clone(&env)) {
+                        Value::Number(index) => index,
+                        _ => fatal_error("Expected number when indexing list")
+                    };
+
+                    let position = match list.position(index) {
+                        Some(position) => position,
+                        None => raise(&list.index_error(index))
+                    };
+                    let value = value();
+                    // What the value did may have shortened the list
+                    if position >= list.len() {
+                        raise(&list.index_error(index));
+                    }
+                    list.set(position, value.clone());
+                    policy::check_value(&obj);
+                    value
+                },
+                _ => fatal_error(&format!("Invalid assignment expr, cannot set a member of {}", obj.get_type()))
             }
         },
-        _ => {
-            fatal_error(&format!("Invalid LHS inside assignment expression: {:?}", node.assignee));
+        assignee => {
+            fatal_error(&format!("Invalid LHS inside assignment expression: {:?}", assignee));
         }
     }
 }
 
-pub fn eval_object_expr(obj: ObjectLiteral, env: Arc<Mutex<Environment>>) -> Box<dyn RuntimeValue> {
-    let mut object = ObjectValue { properties: HashMap::<String, Box<dyn RuntimeValue>>::new() };
+pub fn eval_object_expr(obj: &ObjectLiteral, ast: &Arc<Ast>, env: Arc<Mutex<Environment>>) -> Value {
+    let mut properties = HashMap::<String, Value>::new();
 
-    for i in obj.properties {
+    for i in obj.properties.iter() {
         if let Some(value) = i.value {
-            object.properties.insert(i.key.unwrap(), eval(value.to_stmt_from_expr(), Arc::clone(&env)));
+            properties.insert(i.key.unwrap().to_string(), eval_expr(value, ast, Arc::clone(&env)));
         } else {
-            object.properties.insert(i.key.clone().unwrap(), SharedEnvironment(Arc::clone(&env)).lookup_var(i.key.unwrap()));
+            let key = i.key.unwrap();
+            let value = SharedEnvironment(Arc::clone(&env)).lookup_var(key);
+            properties.insert(key.to_string(), value);
         }
     }
 
-    return Box::new(object);
+    return Value::Object(ObjectValue::new(properties));
 }
 
-pub fn eval_list_expr(list: ListLiteral, env: Arc<Mutex<Environment>>) -> Box<dyn RuntimeValue> {
-    let mut elements = vec![];
+pub fn eval_list_expr(list: &ListLiteral, ast: &Arc<Ast>, env: Arc<Mutex<Environment>>) -> Value {
+    let mut elements = Vec::with_capacity(list.elements.len());
 
-    for i in list.elements {
-        elements.push(eval(i.to_stmt_from_expr(), Arc::clone(&env)));
+    for i in list.elements.iter() {
+        elements.push(eval_expr(*i, ast, Arc::clone(&env)));
     }
 
-    Box::new(ListValue {
-        elements
-    })
+    Value::List(ListValue::new(elements))
 }
 
-pub fn eval_member_expr(node: MemberExpr, env: Arc<Mutex<Environment>>) -> Box<dyn RuntimeValue> {
-    let obj = eval(node.object.to_stmt_from_expr(), Arc::clone(&env));
-    if obj.get_type() == ValueType::Object {
-        let obj = obj.as_any().downcast_ref::<ObjectValue>().unwrap().clone();
-        if !node.computed {
-            if node.property.get_expr_kind() != NodeType::Identifier {
-                fatal_error("Unexpected value found in member expression.");
-            }
-            let identifier = node.property.as_any().downcast_ref::<Identifier>().unwrap().clone();
-
-            return obj.properties.get(&identifier.symbol).unwrap().clone();
-        }
-
-        let property = eval(node.property.to_stmt_from_expr(), env);
+// A property that isn't there is null, or an error listing the ones that are in strict mode
+fn get_property(obj: &ObjectValue, name: &str, env: &Arc<Mutex<Environment>>) -> Value {
+    if let Some(value) = obj.get(name) {
+        return value;
+    }
 
-        if property.get_type() != ValueType::String {
-            fatal_error("Unexpected value found in member expression.");
+    if SharedEnvironment(Arc::clone(env)).global_scope().lock().unwrap().strict {
+        let keys = obj.keys();
+        if keys.is_empty() {
+            raise(&format!("No such property '{}' on object, it has no properties", name));
         }
+        raise(&format!("No such property '{}' on object, it has: {}", name, keys.join(", ")));
+    }
+    MK_NULL!()
+}
 
-        let property = property.as_any().downcast_ref::<StringValue>().expect("Failed to downcast to StringValue.");
+pub fn eval_member_expr(node: &MemberExpr, ast: &Arc<Ast>, env: Arc<Mutex<Environment>>) -> Value {
+    let obj = eval_expr(node.object, ast, Arc::clone(&env));
+    member_of(obj, node.property, node.computed, ast, env)
+}
 
-        obj.properties.get(&property.value).unwrap().clone()
-    } else if obj.get_type() == ValueType::List {
-        if !node.computed {
-            fatal_error("List cannot be indexed like this");
-        }
+// The member of an already evaluated object, so a method call only evaluates its receiver once
+fn member_of(obj: Value, property: ExprId, computed: bool, ast: &Arc<Ast>, env: Arc<Mutex<Environment>>) -> Value {
+    match &obj {
+        Value::Object(obj) => {
+            if !computed {
+                return match &ast[property] {
+                    Expr::Identifier(identifier) => get_property(obj, identifier.symbol.as_str(), &env),
+                    _ => fatal_error("Unexpected value found in member expression.")
+                };
+            }
 
-        let value = eval(node.property.to_stmt_from_expr(), Arc::clone(&env));
+            match eval_expr(property, ast, Arc::clone(&env)) {
+                Value::Str(property) => get_property(obj, &property, &env),
+                _ => fatal_error("Unexpected value found in member expression.")
+            }
+        },
+        Value::List(list) => {
+            if !computed {
+                fatal_error("List cannot be indexed like this");
+            }
 
-        if value.get_type() != ValueType::Number {
-            fatal_error("List can only be indexed by numbers");
-        }
+            let index = match eval_expr(property, ast, Arc::clone(&env)) {
+                Value::Number(index) => index,
+                _ => fatal_error("List can only be indexed by numbers")
+            };
 
-        let index = value.as_any().downcast_ref::<NumberValue>().expect("Failed to downcast to number").value as i32;
+            match list.position(index) {
+                Some(position) => list.get(position).unwrap(),
+                None => raise(&list.index_error(index))
+            }
+        },
+        Value::Str(string) => {
+            if !computed {
+                fatal_error("String cannot be indexed like this");
+            }
 
-        let obj = obj.as_any().downcast_ref::<ListValue>().unwrap().clone();
+            let index = match eval_expr(property, ast, Arc::clone(&env)) {
+                Value::Number(index) => index,
+                _ => fatal_error("String can only be indexed by numbers")
+            };
 
-        if (obj.elements.len() as i32) < index + 1 || index <= -2 {
-            fatal_error("Index out of range");
-        }
-        
-        if index == -1 {
-            return obj.elements.get(obj.elements.len() - 1).unwrap().clone();
-        }
+            // By character rather than byte, so "héllo"[1] is "é"
+            let length = string.chars().count();
+            match position(index, length) {
+                Some(position) => MK_STRING!(string.chars().nth(position).unwrap().to_string()),
+                None => raise(&index_error("string", index, length))
+            }
+        },
+        Value::UserData(obj) => {
+            let name = if computed {
+                eval_expr(property, ast, env).to_string()
+            } else {
+                match &ast[property] {
+                    Expr::Identifier(identifier) => identifier.symbol.as_str().to_string(),
+                    _ => fatal_error("Unexpected value found in member expression.")
+                }
+            };
 
-        obj.elements.get(index as usize).unwrap().clone()
-    } else {
-        fatal_error("Invalid member expression");
+            match obj.method(&name) {
+                Some(method) => Value::Native(method),
+                None => fatal_error(&format!("{} has no method {}", obj.type_name, name))
+            }
+        },
+        _ => fatal_error("Invalid member expression")
     }
 }
 
-pub fn eval_call(expr: CallExpr, env: Arc<Mutex<Environment>>) -> Box<dyn RuntimeValue> {
-    let mut evaluated_args = vec![];
+pub fn eval_call(expr: &CallExpr, ast: &Arc<Ast>, env: Arc<Mutex<Environment>>) -> Value {
+    let mut evaluated_args = Vec::with_capacity(expr.args.len());
 
-    for arg in expr.args {
-        evaluated_args.push(eval(arg.to_stmt_from_expr(), Arc::clone(&env)));
+    for arg in expr.args.iter() {
+        evaluated_args.push(eval_expr(*arg, ast, Arc::clone(&env)));
     }
 
-    let func = eval(expr.caller.to_stmt_from_expr(), Arc::clone(&env));
+    match &ast[expr.caller] {
+        Expr::Member(member_expr) => {
+            let receiver = eval_expr(member_expr.object, ast, Arc::clone(&env));
+            let func = member_of(receiver.clone(), member_expr.property, member_expr.computed, ast, Arc::clone(&env));
 
-    if func.get_type() == ValueType::NativeFn {
-        let func = func.as_any().downcast_ref::<NativeFnValue>().expect("Failed to downcast to NativeFnValue.").clone();
-        return (func.call.func)(evaluated_args, &env);
-    } else if func.get_type() == ValueType::Function {
-        let func = func.as_any().downcast_ref::<FunctionValue>().expect("Failed to downcast to FunctionValue.").clone();
-        return func.call(env, evaluated_args);
+            // obj.method(arg) runs method with this set to obj. Objects are shared, so changes the
+            // method makes to this are changes to obj
+            if let (Value::Object(_), Value::Function(func)) = (&receiver, &func) {
+                return func.call_method(receiver.clone(), evaluated_args);
+            }
+            call_value(func, evaluated_args, env)
+        },
+        _ => {
+            let func = eval_expr(expr.caller, ast, Arc::clone(&env));
+            call_value(func, evaluated_args, env)
+        }
     }
+}
 
-    fatal_error(&format!("Cannot call {:?}", func.get_type()));
+fn call_value(func: Value, evaluated_args: Vec<Value>, env: Arc<Mutex<Environment>>) -> Value {
+    match func {
+        Value::Native(func) => (func.call.func)(evaluated_args, &NativeContext::new(env)),
+        Value::Function(func) => func.call(evaluated_args),
+        _ => fatal_error(&format!("Cannot call {:?}", func.get_type()))
+    }
 }
\ No newline at end of file
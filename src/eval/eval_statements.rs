@@ -1,93 +1,160 @@
 use std::sync::{Arc, Mutex};
 
 use crate::fatal_error;
-use crate::runtime::values::{FunctionValue, ListValue, NullValue, RuntimeValue, ValueType};
-use crate::frontend::ast::{Expr, ForStmt, FunctionDeclaration, Identifier, IfStmt, Program, ReturnStmt, Stmt, VarDeclaration, WhileStmt};
+use crate::runtime::values::{FunctionValue, Value};
+use crate::frontend::ast::{Ast, BodyId, Expr, ForStmt, FunctionBody, FunctionDeclaration, IfStmt, OuterScopeStmt, ReturnStmt, UnsetStmt, VarDeclaration, WhileStmt};
 
-use crate::runtime::interpreter::eval;
-use crate::runtime::environment::{Environment, SharedEnvironment};
+use crate::runtime::interpreter::eval_expr;
+use crate::runtime::environment::{Environment, OuterScope, SharedEnvironment};
 
-pub fn eval_program(program: Program, env: Arc<Mutex<Environment>>) -> Box<dyn RuntimeValue> {
-    program.body.run(env, false).0
-}
+pub fn eval_var_declaration(var_declaration: &VarDeclaration, ast: &Arc<Ast>, env: Arc<Mutex<Environment>>) -> Value {
+    // Hot reloading keeps the values variables already have
+    if env.lock().unwrap().reloading {
+        if let Some(value) = env.lock().unwrap().variables.get(&var_declaration.identifier) {
+            return value.clone();
+        }
+    }
 
-pub fn eval_var_declaration(var_declaration: VarDeclaration, env: Arc<Mutex<Environment>>) -> Box<dyn RuntimeValue> {
-    let value = eval(var_declaration.value.unwrap().to_stmt_from_expr(), Arc::clone(&env));
+    let value = eval_expr(var_declaration.value.unwrap(), ast, Arc::clone(&env));
     env.lock().unwrap().declare_var(var_declaration.identifier, value, var_declaration.constant)
 }
 
-pub fn eval_function_declaration(function_declaration: FunctionDeclaration, env: Arc<Mutex<Environment>>) -> Box<dyn RuntimeValue> {
-    let function = FunctionValue {
-        name: function_declaration.name,
-        parameters: function_declaration.parameters,
-        declaration_env: Arc::clone(&env),
-        body: function_declaration.body
+pub fn eval_unset(unset_stmt: &UnsetStmt, env: Arc<Mutex<Environment>>) -> Value {
+    SharedEnvironment(env).delete_var(unset_stmt.identifier);
+
+    Value::Null
+}
+
+pub fn eval_outer_scope(outer_scope_stmt: &OuterScopeStmt, outer: OuterScope, env: Arc<Mutex<Environment>>) -> Value {
+    let keyword = match outer {
+        OuterScope::Global => "global",
+        OuterScope::Nonlocal => "nonlocal"
+    };
+
+    let function = match SharedEnvironment(env).function_scope() {
+        Some(function) => function,
+        // Top level names are already global
+        None if outer == OuterScope::Global => return Value::Null,
+        None => fatal_error("Cannot use nonlocal outside of function.")
     };
 
-    env.lock().unwrap().declare_var(function.name.clone(), Box::new(function), true);
+    let mut function = function.lock().unwrap();
+    if function.variables.contains_key(&outer_scope_stmt.identifier) {
+        fatal_error(&format!("Cannot declare {} {} as it is already a local variable.", outer_scope_stmt.identifier, keyword));
+    }
+    function.outer_names.insert(outer_scope_stmt.identifier, outer);
+
+    Value::Null
+}
 
-    return Box::new(NullValue {});
+// A new function each time the declaration runs, sharing the Ast it came from
+fn function_value(function: &FunctionDeclaration, ast: &Arc<Ast>, env: Arc<Mutex<Environment>>) -> FunctionValue {
+    FunctionValue {
+        name: function.name,
+        parameters: function.parameters.clone(),
+        required: function.required,
+        declaration_env: env,
+        body: Arc::new(FunctionBody { ast: Arc::clone(ast), body: function.body })
+    }
 }
 
-pub fn eval_return(return_stmt: ReturnStmt, env: Arc<Mutex<Environment>>) -> Box<dyn RuntimeValue> {
-    if env.lock().unwrap().is_global() {
-        fatal_error("Cannot use return statement outside of function.");
+pub fn eval_function_declaration(function_declaration: &FunctionDeclaration, ast: &Arc<Ast>, env: Arc<Mutex<Environment>>) -> Value {
+    let function = function_value(function_declaration, ast, Arc::clone(&env));
+
+    let mut env = env.lock().unwrap();
+    if env.reloading {
+        env.redeclare_var(function.name, Value::Function(function), true);
+    } else {
+        env.declare_var(function.name, Value::Function(function), true);
     }
 
-    let return_value = eval(return_stmt.value.to_stmt_from_expr(), Arc::clone(&env));
+    return Value::Null;
+}
 
-    env.lock().unwrap().continue_interpreting = false;
+pub fn eval_function_expr(function_expr: &FunctionDeclaration, ast: &Arc<Ast>, env: Arc<Mutex<Environment>>) -> Value {
+    Value::Function(function_value(function_expr, ast, env))
+}
+
+// Gives back the returned value, which the bodies and loops it's inside pass on as they stop
+pub fn eval_return(return_stmt: &ReturnStmt, ast: &Arc<Ast>, env: Arc<Mutex<Environment>>) -> Value {
+    let function = match SharedEnvironment(Arc::clone(&env)).function_scope() {
+        Some(function) => function,
+        None => fatal_error("Cannot use return statement outside of function.")
+    };
+
+    let return_value = eval_expr(return_stmt.value, ast, Arc::clone(&env));
+
+    function.lock().unwrap().continue_interpreting = false;
     
     return_value
 }
 
-pub fn eval_if(if_stmt: IfStmt, env: Arc<Mutex<Environment>>) -> Box<dyn RuntimeValue> {
-    let condition = eval(if_stmt.condition.to_stmt_from_expr(), Arc::clone(&env));
+// The value of the branch that ran, its last statement, or null when neither did
+pub fn eval_if(if_stmt: &IfStmt, ast: &Arc<Ast>, env: Arc<Mutex<Environment>>) -> Value {
+    let condition = eval_expr(if_stmt.condition, ast, Arc::clone(&env));
 
     if condition.as_bool() {
-        if_stmt.body.run(env, true);
+        ast[if_stmt.body].run(ast, env, true).0
     } else if let Some(v) = if_stmt.else_stmt {
-        v.run(env, true);
+        ast[v].run(ast, env, true).0
+    } else {
+        Value::Null
     }
+}
+
+// A block has its own scope and is worth its last statement, so var t = { var a = 2; a * a };
+// keeps a out of the enclosing scope
+pub fn eval_block(body: BodyId, ast: &Arc<Ast>, env: Arc<Mutex<Environment>>) -> Value {
+    ast[body].run(ast, env, true).0
+}
 
-    Box::new(NullValue {})
+// Each time a loop statement runs it gets one loop scope, a child of the scope the loop is in.
+// The condition is checked and a for loop's variable lives there, so the variable doesn't leak
+// out or overwrite one of the same name outside. Every pass of the body then gets a fresh block
+// scope inside the loop scope, so its var declarations start over each time, while assignments
+// to anything declared outside the body carry over to the next pass and the next condition
+fn loop_scope(env: &Arc<Mutex<Environment>>) -> Arc<Mutex<Environment>> {
+    Arc::new(Mutex::new(Environment::new(Some(Arc::clone(env)))))
 }
 
-pub fn eval_while(while_stmt: WhileStmt, env: Arc<Mutex<Environment>>) -> Box<dyn RuntimeValue> {
-    let mut last_env = Arc::clone(&env);
-    while eval(while_stmt.condition.to_stmt_from_expr(), last_env).as_bool() == true {
-        last_env = while_stmt.body.run(Arc::clone(&env), true).1;
+pub fn eval_while(while_stmt: &WhileStmt, ast: &Arc<Ast>, env: Arc<Mutex<Environment>>) -> Value {
+    let loop_env = loop_scope(&env);
+    let body = &ast[while_stmt.body];
+
+    while eval_expr(while_stmt.condition, ast, Arc::clone(&loop_env)).as_bool() {
+        let value = body.run(ast, Arc::clone(&loop_env), true).0;
+        if SharedEnvironment(Arc::clone(&loop_env)).returning() {
+            return value;
+        }
     }
 
-    Box::new(NullValue {})
+    Value::Null
 }
 
-pub fn eval_for(for_stmt: ForStmt, env: Arc<Mutex<Environment>>) -> Box<dyn RuntimeValue> {
-    let iterable = eval(for_stmt.iterable.to_stmt_from_expr(), Arc::clone(&env));
-    
-    if iterable.get_type() == ValueType::List {
-        let iterable = iterable.as_any().downcast_ref::<ListValue>().unwrap().clone();
-
-        if iterable.elements.len() == 0 {
-            return Box::new(NullValue {});
-        } else {
-            let ident = for_stmt.variable.as_any().downcast_ref::<Identifier>().expect("Expected identifier in for loop").clone().symbol;
+pub fn eval_for(for_stmt: &ForStmt, ast: &Arc<Ast>, env: Arc<Mutex<Environment>>) -> Value {
+    let iterable = eval_expr(for_stmt.iterable, ast, Arc::clone(&env));
+    let ident = match &ast[for_stmt.variable] {
+        Expr::Identifier(identifier) => identifier.symbol,
+        _ => fatal_error("Expected identifier in for loop")
+    };
 
-            let mut index = 0;
+    let values = match iterable.iterate() {
+        Some(values) => values,
+        None => fatal_error(&format!("Cannot iterate over {:?}", iterable.get_type()))
+    };
 
-            let mut parent_env = SharedEnvironment(Arc::clone(&env));
+    let loop_env = loop_scope(&env);
+    let mut scope = SharedEnvironment(Arc::clone(&loop_env));
+    let body = &ast[for_stmt.body];
 
-            while index != iterable.elements.len() {
-                parent_env.assign_var(ident.clone(), iterable.elements[index].clone(), true);
-                
-                for_stmt.body.run(Arc::clone(&env), true);
+    for value in values {
+        scope.assign_var(ident, value, true);
 
-                index += 1;
-            }
+        let value = body.run(ast, Arc::clone(&loop_env), true).0;
+        if scope.returning() {
+            return value;
         }
-    } else {
-        fatal_error("Cannot iterate over non-iterable thing (duh)");
     }
 
-    Box::new(NullValue {})
+    Value::Null
 }
\ No newline at end of file
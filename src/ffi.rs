@@ -0,0 +1,91 @@
+// C bindings for embedding from other languages, enabled with the ffi feature and linked against
+// the cdylib. Declarations for C are in include/interpreter.h. Strings handed out by
+// interpreter_eval belong to the caller and go back through interpreter_string_free
+use std::{ffi::{c_char, CStr, CString}, panic::{self, AssertUnwindSafe}, ptr};
+
+use crate::Interpreter;
+
+pub struct FfiInterpreter {
+    interpreter: Interpreter,
+    last_error: Option<CString>
+}
+
+// C strings can't hold NUL, so any in the text are dropped
+fn to_c_string(text: String) -> CString {
+    CString::new(text.replace('\0', "")).unwrap()
+}
+
+#[no_mangle]
+pub extern "C" fn interpreter_new() -> *mut FfiInterpreter {
+    Box::into_raw(Box::new(FfiInterpreter {
+        interpreter: Interpreter::new(),
+        last_error: None
+    }))
+}
+
+/// # Safety
+/// `handle` must come from interpreter_new and not be used again afterwards
+#[no_mangle]
+pub unsafe extern "C" fn interpreter_free(handle: *mut FfiInterpreter) {
+    if !handle.is_null() {
+        drop(Box::from_raw(handle));
+    }
+}
+
+/// Runs NUL terminated UTF-8 source in the interpreter's global scope and returns the result as
+/// a string, or NULL on error with the message available from interpreter_last_error
+///
+/// # Safety
+/// `handle` must come from interpreter_new and `source` must be a valid C string
+#[no_mangle]
+pub unsafe extern "C" fn interpreter_eval(handle: *mut FfiInterpreter, source: *const c_char) -> *mut c_char {
+    let handle = match handle.as_mut() {
+        Some(handle) => handle,
+        None => return ptr::null_mut()
+    };
+    handle.last_error = None;
+
+    let source = match CStr::from_ptr(source).to_str() {
+        Ok(source) => source,
+        Err(_) => {
+            handle.last_error = Some(to_c_string("Source is not valid UTF-8".to_string()));
+            return ptr::null_mut();
+        }
+    };
+
+    // Unwinding into C is undefined, so panics that aren't script errors are stopped here too
+    let result = panic::catch_unwind(AssertUnwindSafe(|| handle.interpreter.eval_str(source)));
+    match result {
+        Ok(Ok(value)) => to_c_string(value.to_string()).into_raw(),
+        Ok(Err(e)) => {
+            handle.last_error = Some(to_c_string(e.to_string()));
+            ptr::null_mut()
+        },
+        Err(_) => {
+            handle.last_error = Some(to_c_string("The interpreter panicked".to_string()));
+            ptr::null_mut()
+        }
+    }
+}
+
+/// The error from the last interpreter_eval, or NULL if it succeeded. Owned by the interpreter
+/// and valid until the next call to interpreter_eval
+///
+/// # Safety
+/// `handle` must come from interpreter_new
+#[no_mangle]
+pub unsafe extern "C" fn interpreter_last_error(handle: *const FfiInterpreter) -> *const c_char {
+    match handle.as_ref().and_then(|handle| handle.last_error.as_ref()) {
+        Some(error) => error.as_ptr(),
+        None => ptr::null()
+    }
+}
+
+/// # Safety
+/// `string` must come from interpreter_eval and not be used again afterwards
+#[no_mangle]
+pub unsafe extern "C" fn interpreter_string_free(string: *mut c_char) {
+    if !string.is_null() {
+        drop(CString::from_raw(string));
+    }
+}
\ No newline at end of file
@@ -0,0 +1,359 @@
+use std::{collections::HashSet, fmt::Display};
+
+use super::ast::{Ast, BodyId, Expr, ExprId, FunctionDeclaration, Program, Stmt};
+use super::symbol::{Symbol, SymbolMap};
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Warning {
+    // 0 when the code wasn't parsed from source
+    pub line: usize,
+    pub message: String
+}
+
+impl Display for Warning {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.line == 0 {
+            write!(f, "{}", self.message)
+        } else {
+            write!(f, "Line {}: {}", self.line, self.message)
+        }
+    }
+}
+
+// Looks over a parsed program for code that is valid but probably a mistake. Only names declared
+// in the program itself are known, so anything else (natives, earlier REPL input) is left alone
+pub fn analyze(program: &Program) -> Vec<Warning> {
+    run(program, false)
+}
+
+// Same as analyze, for a whole script rather than one piece of a REPL session, so top level
+// names that are never used are reported too. Warnings are in line order
+pub fn lint(program: &Program) -> Vec<Warning> {
+    let mut warnings = run(program, true);
+    warnings.sort_by_key(|warning| warning.line);
+    warnings
+}
+
+fn run(program: &Program, whole_program: bool) -> Vec<Warning> {
+    let mut analyzer = Analyzer {
+        ast: &program.ast,
+        scopes: vec![Scope::new(false)],
+        pending: Vec::new(),
+        used_early: HashSet::new(),
+        next_id: 1,
+        line: 0,
+        whole_program,
+        warnings: Vec::new()
+    };
+
+    analyzer.visit_statements(program.body);
+    if whole_program {
+        let global = analyzer.scopes.pop().unwrap();
+        analyzer.report_unused(&global);
+    }
+    analyzer.warnings
+}
+
+struct Declaration {
+    kind: &'static str,
+    used: bool,
+    line: usize
+}
+
+struct Scope {
+    id: usize,
+    function: bool,
+    declarations: SymbolMap<Declaration>,
+    // Keeps declaration order so warnings come out in a stable order
+    order: Vec<Symbol>
+}
+
+impl Scope {
+    fn new(function: bool) -> Self {
+        Scope {
+            id: 0,
+            function,
+            declarations: SymbolMap::default(),
+            order: Vec::new()
+        }
+    }
+}
+
+// A name used before any declaration of it was seen, along with the scopes that a later
+// declaration would have to be in for the use to be a mistake
+struct PendingUse {
+    name: Symbol,
+    scopes: Vec<usize>,
+    line: usize
+}
+
+struct Analyzer<'a> {
+    ast: &'a Ast,
+    scopes: Vec<Scope>,
+    pending: Vec<PendingUse>,
+    // Every name used before a declaration of it was seen, kept after the pending use is dropped
+    // since a function can call one declared after it
+    used_early: HashSet<Symbol>,
+    next_id: usize,
+    // Line of the statement being looked at
+    line: usize,
+    whole_program: bool,
+    warnings: Vec<Warning>
+}
+
+impl Analyzer<'_> {
+    fn push_scope(&mut self, function: bool) {
+        let mut scope = Scope::new(function);
+        scope.id = self.next_id;
+        self.next_id += 1;
+        self.scopes.push(scope);
+    }
+
+    fn warn(&mut self, line: usize, message: String) {
+        self.warnings.push(Warning { line, message });
+    }
+
+    fn report_unused(&mut self, scope: &Scope) {
+        for name in scope.order.iter() {
+            let declaration = &scope.declarations[name];
+            if !declaration.used && !name.as_str().starts_with('_') {
+                self.warn(declaration.line, format!("{} {} is declared but never used.", declaration.kind, name));
+            }
+        }
+    }
+
+    fn pop_scope(&mut self) {
+        let scope = self.scopes.pop().unwrap();
+        self.report_unused(&scope);
+
+        for pending in self.pending.iter_mut() {
+            pending.scopes.retain(|id| *id != scope.id);
+        }
+        self.pending.retain(|pending| !pending.scopes.is_empty());
+    }
+
+    fn declare(&mut self, name: Symbol, kind: &'static str) {
+        let (current, outer) = self.scopes.split_last_mut().unwrap();
+
+        if current.declarations.contains_key(&name) {
+            return;
+        }
+
+        let shadows = outer.iter().any(|scope| scope.declarations.contains_key(&name));
+        let id = current.id;
+        if shadows {
+            self.warn(self.line, format!("{} {} shadows a variable from an outer scope.", kind, name));
+        }
+
+        let mut used_early = None;
+        self.pending.retain(|pending| {
+            if pending.name == name && pending.scopes.contains(&id) {
+                used_early = used_early.or(Some(pending.line));
+                false
+            } else {
+                true
+            }
+        });
+
+        if let Some(line) = used_early {
+            self.warn(line, format!("{} is used before it is declared.", name));
+        }
+
+        // Only local names can go unused, globals may still be used by later input. A whole
+        // program has no later input, but its functions may use names declared after them
+        let global = self.scopes.len() == 1;
+        let used = if self.whole_program { self.used_early.contains(&name) } else { global } || used_early.is_some();
+        let line = self.line;
+        let current = self.scopes.last_mut().unwrap();
+        current.declarations.insert(name, Declaration { kind, used, line });
+        current.order.push(name);
+    }
+
+    fn use_name(&mut self, name: Symbol, read: bool) {
+        for scope in self.scopes.iter_mut().rev() {
+            if let Some(declaration) = scope.declarations.get_mut(&name) {
+                declaration.used |= read;
+                return;
+            }
+        }
+
+        // A function body only runs once it's called, by which point a later declaration
+        // outside of it will have happened, so only scopes up to the function count
+        let mut scopes = Vec::new();
+        for scope in self.scopes.iter().rev() {
+            scopes.push(scope.id);
+            if scope.function {
+                break;
+            }
+        }
+
+        self.used_early.insert(name);
+        self.pending.push(PendingUse { name, scopes, line: self.line });
+    }
+
+    fn visit_statements(&mut self, body: BodyId) {
+        let body = &self.ast[body];
+        let mut returned = false;
+        for stmt in body.statements() {
+            if stmt.line != 0 {
+                self.line = stmt.line;
+            }
+            if returned {
+                self.warn(self.line, "Unreachable code after return.".to_string());
+                returned = false;
+            }
+
+            self.visit(&stmt.stmt);
+            // Warned once per body, at the first statement that can't run
+            if matches!(stmt.stmt, Stmt::Return(_)) && !std::ptr::eq(stmt, body.statements().last().unwrap()) {
+                returned = true;
+            }
+        }
+    }
+
+    fn check_condition(&mut self, condition: ExprId, statement: &str) {
+        if matches!(self.ast[condition], Expr::Assignment(_)) {
+            self.warn(self.line, format!("Assignment used as the {} condition, did you mean ==?", statement));
+        }
+
+        match (self.constant(condition), statement) {
+            // while (true) is the way to loop until a return or exit
+            (Some(true), "while") => {},
+            (Some(value), _) => self.warn(self.line, format!("The {} condition is always {}.", statement, value)),
+            (None, _) => {}
+        }
+    }
+
+    // Whether a condition made only of literals is truthy, matching as_bool
+    fn constant(&self, expr: ExprId) -> Option<bool> {
+        match &self.ast[expr] {
+            Expr::Number(number) => Some(number.value != 0.0),
+            Expr::String(string) => Some(!string.string.as_str().is_empty()),
+            Expr::List(list) => Some(!list.elements.is_empty()),
+            Expr::Object(object) => Some(!object.properties.is_empty()),
+            Expr::Identifier(identifier) => {
+                let symbol = identifier.symbol;
+                // Only the built in ones, a script could declare its own
+                if self.scopes.iter().any(|scope| scope.declarations.contains_key(&symbol)) {
+                    return None;
+                }
+                match symbol.as_str() {
+                    "true" => Some(true),
+                    "false" | "null" => Some(false),
+                    _ => None
+                }
+            },
+            _ => None
+        }
+    }
+
+    fn visit_body(&mut self, body: BodyId) {
+        self.push_scope(false);
+        self.visit_statements(body);
+        self.pop_scope();
+    }
+
+    fn visit(&mut self, stmt: &Stmt) {
+        match stmt {
+            Stmt::VarDeclaration(var_declaration) => {
+                if let Some(value) = &var_declaration.value {
+                    self.visit_expr(*value);
+                }
+                self.declare(var_declaration.identifier, if var_declaration.constant { "Constant" } else { "Variable" });
+            },
+            Stmt::Unset(unset_stmt) => self.use_name(unset_stmt.identifier, false),
+            Stmt::FunctionDeclaration(function) => {
+                self.declare(function.name, "Function");
+                self.visit_function(function);
+            },
+            Stmt::Return(return_stmt) => self.visit_expr(return_stmt.value),
+            Stmt::While(while_stmt) => {
+                self.check_condition(while_stmt.condition, "while");
+                self.visit_expr(while_stmt.condition);
+                self.visit_body(while_stmt.body);
+            },
+            Stmt::For(for_stmt) => {
+                self.visit_expr(for_stmt.iterable);
+                // The loop variable belongs to the loop, not the scope around it
+                self.push_scope(false);
+                if let Expr::Identifier(variable) = &self.ast[for_stmt.variable] {
+                    self.declare(variable.symbol, "Loop variable");
+                }
+                self.visit_body(for_stmt.body);
+                self.pop_scope();
+            },
+            Stmt::Expr(expr) => self.visit_expr(*expr),
+            Stmt::Global(_) | Stmt::Nonlocal(_) => {}
+        }
+    }
+
+    fn visit_function(&mut self, function: &FunctionDeclaration) {
+        self.push_scope(true);
+        for parameter in function.parameters.iter() {
+            self.declare(*parameter, "Parameter");
+        }
+        self.visit_body(function.body);
+        self.pop_scope();
+    }
+
+    fn visit_expr(&mut self, expr: ExprId) {
+        let ast = self.ast;
+        match &ast[expr] {
+            Expr::Function(function) => self.visit_function(function),
+            Expr::If(if_stmt) => {
+                self.check_condition(if_stmt.condition, "if");
+                self.visit_expr(if_stmt.condition);
+                self.visit_body(if_stmt.body);
+                if let Some(else_stmt) = &if_stmt.else_stmt {
+                    self.visit_body(*else_stmt);
+                }
+            },
+            Expr::Body(body) => self.visit_body(*body),
+            Expr::Identifier(identifier) => self.use_name(identifier.symbol, true),
+            Expr::Binary(bin_expr) => {
+                self.visit_expr(bin_expr.left);
+                self.visit_expr(bin_expr.right);
+            },
+            Expr::Comparative(comp_expr) => {
+                self.visit_expr(comp_expr.left);
+                self.visit_expr(comp_expr.right);
+            },
+            Expr::Assignment(assignment) => {
+                self.visit_expr(assignment.value);
+                // Assigning to a name isn't reading it
+                match &ast[assignment.assignee] {
+                    Expr::Identifier(identifier) => self.use_name(identifier.symbol, false),
+                    _ => self.visit_expr(assignment.assignee)
+                }
+            },
+            Expr::Member(member_expr) => {
+                self.visit_expr(member_expr.object);
+                if member_expr.computed {
+                    self.visit_expr(member_expr.property);
+                }
+            },
+            Expr::Call(call_expr) => {
+                self.visit_expr(call_expr.caller);
+                for arg in call_expr.args.iter() {
+                    self.visit_expr(*arg);
+                }
+            },
+            Expr::Object(object) => {
+                for property in object.properties.iter() {
+                    match (&property.key, &property.value) {
+                        (_, Some(value)) => self.visit_expr(*value),
+                        // { x } is short for { x: x }
+                        (Some(key), None) => self.use_name(*key, true),
+                        (None, None) => {}
+                    }
+                }
+            },
+            Expr::List(list) => {
+                for element in list.elements.iter() {
+                    self.visit_expr(*element);
+                }
+            },
+            Expr::Number(_) | Expr::String(_) => {}
+        }
+    }
+}
\ No newline at end of file
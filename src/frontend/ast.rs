@@ -1,831 +1,344 @@
-use std::{any::Any, fmt::Debug, sync::{Arc, Mutex}};
+use std::ops::Index;
+use std::sync::{Arc, Mutex};
 
-use crate::runtime::{environment::Environment, interpreter::eval, values::{NullValue, RuntimeValue}};
+use crate::runtime::{environment::{Environment, SharedEnvironment}, interpreter::eval, trace, values::Value};
 
-#[derive(Clone, Copy, Debug, PartialEq)]
-pub enum NodeType {
-    Program,
-    Body,
-
-    // Statements
-    VarDeclaration,
-
-    FunctionDeclaration,
-
-    Return,
-
-    If,
+use super::symbol::Symbol;
 
-    While,
-    For,
-
-    // Expressions
-    Identifier,
-    BinaryExpr,
-    ComparativeExpr,
-    AssignmentExpr,
-    MemberExpr,
-    CallExpr,
-    
-    // Literals
-    NumericLiteral,
-    NullLiteral,
-    Property,
-    Object,
-    List,
-    String
-}
-
-pub enum StmtValue {
-    StringVal(String),
-    F64(f64)
-}
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum BinaryOp {
+    Add,
+    Subtract,
+    Multiply,
+    Divide,
+    Modulo
+}
+
+impl BinaryOp {
+    pub fn from_symbol(symbol: &str) -> Option<Self> {
+        match symbol {
+            "+" => Some(BinaryOp::Add),
+            "-" => Some(BinaryOp::Subtract),
+            "*" => Some(BinaryOp::Multiply),
+            "/" => Some(BinaryOp::Divide),
+            "%" => Some(BinaryOp::Modulo),
+            _ => None
+        }
+    }
 
-pub trait Stmt: Debug + Any + 'static {
-    fn get_kind(&self) -> NodeType;
-    fn get_value(&self) -> Option<StmtValue>;
-    fn clone_boxed(&self) -> Box<dyn Stmt>;
-    fn clone_as_wrapper(&self) -> StmtWrapper;
-    fn as_any(&self) -> &dyn Any;
+    pub fn symbol(&self) -> &'static str {
+        match self {
+            BinaryOp::Add => "+",
+            BinaryOp::Subtract => "-",
+            BinaryOp::Multiply => "*",
+            BinaryOp::Divide => "/",
+            BinaryOp::Modulo => "%"
+        }
+    }
 }
 
-impl Clone for Box<dyn Stmt> {
-    fn clone(&self) -> Self {
-        self.clone_boxed()
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum CompareOp {
+    Equal,
+    NotEqual,
+    // === and !==, no coercion
+    StrictEqual,
+    StrictNotEqual,
+    LessThan,
+    GreaterThan,
+    LessThanOrEqual,
+    GreaterThanOrEqual
+}
+
+impl CompareOp {
+    pub fn symbol(&self) -> &'static str {
+        match self {
+            CompareOp::Equal => "==",
+            CompareOp::NotEqual => "!=",
+            CompareOp::StrictEqual => "===",
+            CompareOp::StrictNotEqual => "!==",
+            CompareOp::LessThan => "<",
+            CompareOp::GreaterThan => ">",
+            CompareOp::LessThanOrEqual => "<=",
+            CompareOp::GreaterThanOrEqual => ">="
+        }
     }
 }
 
+// Something a body runs. Expressions can stand on their own as statements too
+#[derive(Debug, Clone)]
+pub enum Stmt {
+    VarDeclaration(VarDeclaration),
+    Unset(UnsetStmt),
+    // global x; and nonlocal x;
+    Global(OuterScopeStmt),
+    Nonlocal(OuterScopeStmt),
+    FunctionDeclaration(FunctionDeclaration),
+    Return(ReturnStmt),
+    While(WhileStmt),
+    For(ForStmt),
+    Expr(ExprId)
+}
+
+// Something that has a value
+#[derive(Debug, Clone)]
+pub enum Expr {
+    Identifier(Identifier),
+    Binary(BinaryExpr),
+    Comparative(ComparativeExpr),
+    Assignment(AssignmentExpr),
+    Member(MemberExpr),
+    Call(CallExpr),
+    Number(NumericLiteral),
+    String(StringLiteral),
+    Object(ObjectLiteral),
+    List(ListLiteral),
+    // A function written where a value goes, `var double = function (x) { x * 2 };`
+    Function(FunctionDeclaration),
+    // var y = if x > 0 { "pos" } else { "neg" }; takes the value of the branch that ran
+    If(IfStmt),
+    // { var a = 2; a * a } is worth its last statement
+    Body(BodyId)
+}
+
+// A statement in a body along with the line it starts on, 0 when it wasn't parsed from source
 #[derive(Debug, Clone)]
 pub struct StmtWrapper {
-    inner: Box<dyn Stmt>
+    pub stmt: Stmt,
+    pub line: usize
 }
 
 impl StmtWrapper {
-    pub fn new(stmt: Box<dyn Stmt>) -> Self {
+    pub fn new(stmt: Stmt) -> Self {
         StmtWrapper {
-            inner: stmt
+            stmt,
+            line: 0
         }
     }
 }
 
-impl Stmt for StmtWrapper {
-    fn as_any(&self) -> &dyn Any {
-        self.inner.as_any()
+// Which expression in a program's Ast a node refers to
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ExprId(u32);
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct BodyId(u32);
+
+// Every expression and body of a program. Nodes point at their children by id instead of
+// owning them, so evaluating borrows the tree rather than copying parts of it
+#[derive(Debug, Clone, Default)]
+pub struct Ast {
+    exprs: Vec<Expr>,
+    bodies: Vec<Body>
+}
+
+impl Ast {
+    pub fn new() -> Self {
+        Ast::default()
     }
-    fn clone_boxed(&self) -> Box<dyn Stmt> {
-        self.inner.clone_boxed()
+
+    pub fn alloc(&mut self, expr: Expr) -> ExprId {
+        self.exprs.push(expr);
+        ExprId(self.exprs.len() as u32 - 1)
     }
-    fn get_kind(&self) -> NodeType {
-        self.inner.get_kind()
+
+    pub fn alloc_body(&mut self, body: Body) -> BodyId {
+        self.bodies.push(body);
+        BodyId(self.bodies.len() as u32 - 1)
     }
-    fn get_value(&self) -> Option<StmtValue> {
-        self.inner.get_value()
+}
+
+impl Index<ExprId> for Ast {
+    type Output = Expr;
+
+    fn index(&self, id: ExprId) -> &Expr {
+        &self.exprs[id.0 as usize]
     }
-    fn clone_as_wrapper(&self) -> StmtWrapper {
-        StmtWrapper::new(self.clone_boxed())
+}
+
+impl Index<BodyId> for Ast {
+    type Output = Body;
+
+    fn index(&self, id: BodyId) -> &Body {
+        &self.bodies[id.0 as usize]
     }
 }
 
 #[derive(Debug, Clone)]
 pub struct Program {
-    pub kind: NodeType,
-    pub body: Body
-}
-
-impl Stmt for Program {
-    fn get_kind(&self) -> NodeType {
-        NodeType::Program
-    }
-    fn get_value(&self) -> Option<StmtValue> {
-        None
-    }
-    fn clone_boxed(&self) -> Box<dyn Stmt> {
-        Box::new(self.clone())
-    }
-    fn as_any(&self) -> &dyn Any {
-        self
-    }
-    fn clone_as_wrapper(&self) -> StmtWrapper {
-        StmtWrapper::new(self.clone_boxed())
-    }
+    pub ast: Ast,
+    pub body: BodyId
 }
 
 #[derive(Debug, Clone)]
 pub struct Body {
-    pub kind: NodeType,
     body: Vec<StmtWrapper>
 }
 
-impl Stmt for Body {
-    fn get_kind(&self) -> NodeType {
-        self.kind
-    }
-    fn get_value(&self) -> Option<StmtValue> {
-        None
-    }
-    fn clone_boxed(&self) -> Box<dyn Stmt> {
-        Box::new(self.clone())
-    }
-    fn as_any(&self) -> &dyn Any {
-        self
-    }
-    fn clone_as_wrapper(&self) -> StmtWrapper {
-        StmtWrapper::new(self.clone_boxed())
-    }
-}
-
-impl Expr for Body {
-    fn get_expr_kind(&self) -> NodeType {
-        self.kind
-    }
-    fn get_expr_value(&self) -> Option<StmtValue> {
-        None
-    }
-    fn clone_box(&self) -> Box<dyn Expr> {
-        Box::new(self.clone())
-    }
-    fn to_stmt_from_expr(&self) -> StmtWrapper {
-        StmtWrapper::new(Box::new(self.clone()))
-    }
-}
-
 impl Body {
     pub fn new(body: Vec<StmtWrapper>) -> Self {
         Body {
-            body,
-            kind: NodeType::Body
+            body
         }
     }
 
-    pub fn run(&self, env: Arc<Mutex<Environment>>, make_env: bool) -> (Box<dyn RuntimeValue>, Arc<Mutex<Environment>>) {
-        if make_env {
-            let new_env = Arc::new(Mutex::new(Environment::new(Some(Arc::clone(&env)))));
-
-            let mut last_value: Box<dyn RuntimeValue> = Box::new(NullValue {});
-            for stmt in self.body.iter() {
-                last_value = eval(stmt.clone(), Arc::clone(&new_env));
-            }
+    pub fn statements(&self) -> &[StmtWrapper] {
+        &self.body
+    }
 
-            (last_value, new_env)
+    // Stops early once a return has run, with the returned value as the last one
+    pub fn run(&self, ast: &Arc<Ast>, env: Arc<Mutex<Environment>>, make_env: bool) -> (Value, Arc<Mutex<Environment>>) {
+        let env = if make_env {
+            Arc::new(Mutex::new(Environment::new(Some(env))))
         } else {
-            let mut last_value: Box<dyn RuntimeValue> = Box::new(NullValue {});
-            for stmt in self.body.iter() {
-                last_value = eval(stmt.clone(), Arc::clone(&env));
+            env
+        };
+
+        let mut last_value: Value = Value::Null;
+        for stmt in self.body.iter() {
+            trace::line(stmt.line, &env);
+            last_value = eval(&stmt.stmt, ast, Arc::clone(&env));
+            if SharedEnvironment(Arc::clone(&env)).returning() {
+                break;
             }
-
-            (last_value, env)
         }
-    }
-}
-
-// var x; means x is undefined
-#[derive(Debug, Clone)]
-pub struct VarDeclaration {
-    pub kind: NodeType,
-    pub constant: bool,
-    pub identifier: String,
-    pub value: Option<ExprWrapper>
-}
 
-impl Stmt for VarDeclaration {
-    fn get_kind(&self) -> NodeType {
-        NodeType::VarDeclaration
-    }
-    fn get_value(&self) -> Option<StmtValue> {
-        None
-    }
-    fn clone_boxed(&self) -> Box<dyn Stmt> {
-        Box::new(self.clone())
-    }
-    fn as_any(&self) -> &dyn Any {
-        self
-    }
-    fn clone_as_wrapper(&self) -> StmtWrapper {
-        StmtWrapper::new(self.clone_boxed())
+        (last_value, env)
     }
 }
 
-#[derive(Debug, Clone)]
-pub struct FunctionDeclaration {
-    pub kind: NodeType,
-    pub parameters: Vec<String>,
-    pub name: String,
-    pub body: Body
+// A function's body along with the Ast it's in, which stays alive for as long as the function
+// does, after the rest of the program has finished
+#[derive(Debug)]
+pub struct FunctionBody {
+    pub ast: Arc<Ast>,
+    pub body: BodyId
 }
 
-impl Stmt for FunctionDeclaration {
-    fn get_kind(&self) -> NodeType {
-        NodeType::FunctionDeclaration
-    }
-    fn get_value(&self) -> Option<StmtValue> {
-        None
-    }
-    fn clone_boxed(&self) -> Box<dyn Stmt> {
-        Box::new(self.clone())
-    }
-    fn as_any(&self) -> &dyn Any {
-        self
-    }
-    fn clone_as_wrapper(&self) -> StmtWrapper {
-        StmtWrapper::new(self.clone_boxed())
-    }
-}
-
-pub trait Expr: Stmt {
-    fn get_expr_kind(&self) -> NodeType;
-    fn get_expr_value(&self) -> Option<StmtValue>;
-    fn clone_box(&self) -> Box<dyn Expr>;
-    fn to_stmt_from_expr(&self) -> StmtWrapper;
-}
-
-impl Clone for Box<dyn Expr> {
-    fn clone(&self) -> Self {
-        self.clone_box()
+impl FunctionBody {
+    pub fn run(&self, env: Arc<Mutex<Environment>>) -> Value {
+        self.ast[self.body].run(&self.ast, env, false).0
     }
 }
 
+// var x; means x is undefined
 #[derive(Debug, Clone)]
-pub struct ExprWrapper {
-    inner: Box<dyn Expr>
+pub struct VarDeclaration {
+    pub constant: bool,
+    pub identifier: Symbol,
+    pub value: Option<ExprId>
 }
 
-impl ExprWrapper {
-    pub fn new(expr: Box<dyn Expr>) -> Self {
-        ExprWrapper {
-            inner: expr
-        }
-    }
+// unset x; removes x from the scope it was declared in
+#[derive(Debug, Clone)]
+pub struct UnsetStmt {
+    pub identifier: Symbol
 }
 
-impl Stmt for ExprWrapper {
-    fn as_any(&self) -> &dyn Any {
-        self.inner.as_any()
-    }
-    fn clone_boxed(&self) -> Box<dyn Stmt> {
-        self.inner.clone_boxed()
-    }
-    fn get_kind(&self) -> NodeType {
-        self.inner.get_kind()
-    }
-    fn get_value(&self) -> Option<StmtValue> {
-        self.inner.get_value()
-    }
-    fn clone_as_wrapper(&self) -> StmtWrapper {
-        StmtWrapper::new(self.clone_boxed())
-    }
+// global x; and nonlocal x; make assignments to x inside a function go to the global scope
+// or the enclosing scope instead of creating a local
+#[derive(Debug, Clone)]
+pub struct OuterScopeStmt {
+    pub identifier: Symbol
 }
 
-impl Expr for ExprWrapper {
-    fn clone_box(&self) -> Box<dyn Expr> {
-        self.inner.clone_box()
-    }
-    fn get_expr_kind(&self) -> NodeType {
-        self.inner.get_expr_kind()
-    }
-    fn get_expr_value(&self) -> Option<StmtValue> {
-        self.inner.get_expr_value()
-    }
-    fn to_stmt_from_expr(&self) -> StmtWrapper {
-        self.inner.to_stmt_from_expr()
-    }
+// Both a declaration and, as Expr::Function, a function expression, which makes the function
+// without declaring its name
+#[derive(Debug, Clone)]
+pub struct FunctionDeclaration {
+    pub parameters: Vec<Symbol>,
+    // How many of the parameters have to be passed, the rest were marked optional with `?`
+    pub required: usize,
+    pub name: Symbol,
+    pub body: BodyId
 }
 
 #[derive(Debug, Clone)]
 pub struct AssignmentExpr {
-    pub kind: NodeType,
-    pub assignee: ExprWrapper,
-    pub value: ExprWrapper
-}
-
-impl Stmt for AssignmentExpr {
-    fn get_kind(&self) -> NodeType {
-        self.get_expr_kind()
-    }
-    fn get_value(&self) -> Option<StmtValue> {
-        Some(self.get_expr_value().unwrap())
-    }
-    fn clone_boxed(&self) -> Box<dyn Stmt> {
-        Box::new(self.clone())
-    }
-    fn as_any(&self) -> &dyn Any {
-        self
-    }
-    fn clone_as_wrapper(&self) -> StmtWrapper {
-        StmtWrapper::new(self.clone_boxed())
-    }
-}
-
-impl Expr for AssignmentExpr {
-    fn get_expr_kind(&self) -> NodeType {
-        self.kind
-    }
-    fn get_expr_value(&self) -> Option<StmtValue> {
-        None
-    }
-    fn clone_box(&self) -> Box<dyn Expr> {
-        Box::new(self.clone())
-    }
-    fn to_stmt_from_expr(&self) -> StmtWrapper {
-        StmtWrapper::new(Box::new(self.clone()))
-    }
+    pub assignee: ExprId,
+    pub value: ExprId
 }
 
 // 10 - 5 is binary expression
 #[derive(Debug, Clone)]
 pub struct BinaryExpr {
-    pub kind: NodeType,
-    pub left: ExprWrapper,
-    pub right: ExprWrapper,
-    pub operator: String
-}
-
-impl Stmt for BinaryExpr {
-    fn get_kind(&self) -> NodeType {
-        self.get_expr_kind()
-    }
-    fn get_value(&self) -> Option<StmtValue> {
-        Some(self.get_expr_value().unwrap())
-    }
-    fn clone_boxed(&self) -> Box<dyn Stmt> {
-        Box::new(self.clone())
-    }
-    fn as_any(&self) -> &dyn Any {
-        self
-    }
-    fn clone_as_wrapper(&self) -> StmtWrapper {
-        StmtWrapper::new(self.clone_boxed())
-    }
-}
-
-impl Expr for BinaryExpr {
-    fn get_expr_kind(&self) -> NodeType {
-        self.kind
-    }
-    fn get_expr_value(&self) -> Option<StmtValue> {
-        None
-    }
-    fn clone_box(&self) -> Box<dyn Expr> {
-        Box::new(self.clone())
-    }
-    fn to_stmt_from_expr(&self) -> StmtWrapper {
-        StmtWrapper::new(Box::new(self.clone()))
-    }
+    pub left: ExprId,
+    pub right: ExprId,
+    pub operator: BinaryOp
 }
 
 #[derive(Debug, Clone)]
 pub struct ComparativeExpr {
-    pub kind: NodeType,
-    pub left: ExprWrapper,
-    pub right: ExprWrapper,
-    pub operator: String
-}
-
-impl Stmt for ComparativeExpr {
-    fn get_kind(&self) -> NodeType {
-        self.get_expr_kind()
-    }
-    fn get_value(&self) -> Option<StmtValue> {
-        Some(self.get_expr_value().unwrap())
-    }
-    fn clone_boxed(&self) -> Box<dyn Stmt> {
-        Box::new(self.clone())
-    }
-    fn as_any(&self) -> &dyn Any {
-        self
-    }
-    fn clone_as_wrapper(&self) -> StmtWrapper {
-        StmtWrapper::new(self.clone_boxed())
-    }
-}
-
-impl Expr for ComparativeExpr {
-    fn get_expr_kind(&self) -> NodeType {
-        self.kind
-    }
-    fn get_expr_value(&self) -> Option<StmtValue> {
-        None
-    }
-    fn clone_box(&self) -> Box<dyn Expr> {
-        Box::new(self.clone())
-    }
-    fn to_stmt_from_expr(&self) -> StmtWrapper {
-        StmtWrapper::new(Box::new(self.clone()))
-    }
+    pub left: ExprId,
+    pub right: ExprId,
+    pub operator: CompareOp
 }
 
 #[derive(Debug, Clone)]
 pub struct Identifier {
-    pub kind: NodeType,
-    pub symbol: String
-}
-
-impl Stmt for Identifier {
-    fn get_kind(&self) -> NodeType {
-        self.get_expr_kind()
-    }
-    fn get_value(&self) -> Option<StmtValue> {
-        Some(self.get_expr_value().unwrap())
-    }
-    fn clone_boxed(&self) -> Box<dyn Stmt> {
-        Box::new(self.clone())
-    }
-    fn as_any(&self) -> &dyn Any {
-        self
-    }
-    fn clone_as_wrapper(&self) -> StmtWrapper {
-        StmtWrapper::new(self.clone_boxed())
-    }
-}
-
-impl Expr for Identifier {
-    fn get_expr_kind(&self) -> NodeType {
-        self.kind
-    }
-    fn get_expr_value(&self) -> Option<StmtValue> {
-        None
-    }
-    fn clone_box(&self) -> Box<dyn Expr> {
-        Box::new(self.clone())
-    }
-    fn to_stmt_from_expr(&self) -> StmtWrapper {
-        StmtWrapper::new(Box::new(self.clone()))
-    }
+    pub symbol: Symbol
 }
 
 #[derive(Debug, Clone)]
 pub struct NumericLiteral {
-    pub kind: NodeType,
     pub value: f64
 }
 
-impl Stmt for NumericLiteral {
-    fn get_kind(&self) -> NodeType {
-        self.get_expr_kind()
-    }
-    fn get_value(&self) -> Option<StmtValue> {
-        Some(self.get_expr_value().unwrap())
-    }
-    fn clone_boxed(&self) -> Box<dyn Stmt> {
-        Box::new(self.clone())
-    }
-    fn as_any(&self) -> &dyn Any {
-        self
-    }
-    fn clone_as_wrapper(&self) -> StmtWrapper {
-        StmtWrapper::new(self.clone_boxed())
-    }
-}
-
-impl Expr for NumericLiteral {
-    fn get_expr_kind(&self) -> NodeType {
-        self.kind
-    }
-    fn get_expr_value(&self) -> Option<StmtValue> {
-        Some(StmtValue::F64(self.value))
-    }
-    fn clone_box(&self) -> Box<dyn Expr> {
-        Box::new(self.clone())
-    }
-    fn to_stmt_from_expr(&self) -> StmtWrapper {
-        StmtWrapper::new(Box::new(self.clone()))
-    }
-}
-
 #[derive(Debug, Clone)]
 pub struct Property {
-    pub kind: NodeType,
-    pub key: Option<String>,
-    pub value: Option<ExprWrapper>
-}
-
-impl Stmt for Property {
-    fn get_kind(&self) -> NodeType {
-        self.get_expr_kind()
-    }
-    fn get_value(&self) -> Option<StmtValue> {
-        None
-    }
-    fn clone_boxed(&self) -> Box<dyn Stmt> {
-        Box::new(self.clone())
-    }
-    fn as_any(&self) -> &dyn Any {
-        self
-    }
-    fn clone_as_wrapper(&self) -> StmtWrapper {
-        StmtWrapper::new(self.clone_boxed())
-    }
-}
-
-impl Expr for Property {
-    fn get_expr_kind(&self) -> NodeType {
-        self.kind
-    }
-    fn get_expr_value(&self) -> Option<StmtValue> {
-        None
-    }
-    fn clone_box(&self) -> Box<dyn Expr> {
-        Box::new(self.clone())
-    }
-    fn to_stmt_from_expr(&self) -> StmtWrapper {
-        StmtWrapper::new(Box::new(self.clone()))
-    }
+    pub key: Option<Symbol>,
+    pub value: Option<ExprId>
 }
 
 #[derive(Debug, Clone)]
 pub struct ObjectLiteral {
-    pub kind: NodeType,
     pub properties: Vec<Property>
 }
 
-impl Stmt for ObjectLiteral {
-    fn get_kind(&self) -> NodeType {
-        self.get_expr_kind()
-    }
-    fn get_value(&self) -> Option<StmtValue> {
-        Some(self.get_expr_value().unwrap())
-    }
-    fn clone_boxed(&self) -> Box<dyn Stmt> {
-        Box::new(self.clone())
-    }
-    fn as_any(&self) -> &dyn Any {
-        self
-    }
-    fn clone_as_wrapper(&self) -> StmtWrapper {
-        StmtWrapper::new(self.clone_boxed())
-    }
-}
-
-impl Expr for ObjectLiteral {
-    fn get_expr_kind(&self) -> NodeType {
-        self.kind
-    }
-    fn get_expr_value(&self) -> Option<StmtValue> {
-        None
-    }
-    fn clone_box(&self) -> Box<dyn Expr> {
-        Box::new(self.clone())
-    }
-    fn to_stmt_from_expr(&self) -> StmtWrapper {
-        StmtWrapper::new(Box::new(self.clone()))
-    }
-}
-
 #[derive(Debug, Clone)]
 pub struct ListLiteral {
-    pub kind: NodeType,
-    pub elements: Vec<ExprWrapper>
-}
-
-impl Stmt for ListLiteral {
-    fn get_kind(&self) -> NodeType {
-        self.get_expr_kind()
-    }
-    fn get_value(&self) -> Option<StmtValue> {
-        Some(self.get_expr_value().unwrap())
-    }
-    fn clone_boxed(&self) -> Box<dyn Stmt> {
-        Box::new(self.clone())
-    }
-    fn as_any(&self) -> &dyn Any {
-        self
-    }
-    fn clone_as_wrapper(&self) -> StmtWrapper {
-        StmtWrapper::new(self.clone_boxed())
-    }
-}
-
-impl Expr for ListLiteral {
-    fn get_expr_kind(&self) -> NodeType {
-        self.kind
-    }
-    fn get_expr_value(&self) -> Option<StmtValue> {
-        None
-    }
-    fn clone_box(&self) -> Box<dyn Expr> {
-        Box::new(self.clone())
-    }
-    fn to_stmt_from_expr(&self) -> StmtWrapper {
-        StmtWrapper::new(Box::new(self.clone()))
-    }
+    pub elements: Vec<ExprId>
 }
 
 #[derive(Debug, Clone)]
 pub struct CallExpr {
-    pub kind: NodeType,
-    pub args: Vec<ExprWrapper>,
-    pub caller: ExprWrapper
-}
-
-impl Stmt for CallExpr {
-    fn get_kind(&self) -> NodeType {
-        self.get_expr_kind()
-    }
-    fn get_value(&self) -> Option<StmtValue> {
-        Some(self.get_expr_value().unwrap())
-    }
-    fn clone_boxed(&self) -> Box<dyn Stmt> {
-        Box::new(self.clone())
-    }
-    fn as_any(&self) -> &dyn Any {
-        self
-    }
-    fn clone_as_wrapper(&self) -> StmtWrapper {
-        StmtWrapper::new(self.clone_boxed())
-    }
-}
-
-impl Expr for CallExpr {
-    fn get_expr_kind(&self) -> NodeType {
-        self.kind
-    }
-    fn get_expr_value(&self) -> Option<StmtValue> {
-        None
-    }
-    fn clone_box(&self) -> Box<dyn Expr> {
-        Box::new(self.clone())
-    }
-    fn to_stmt_from_expr(&self) -> StmtWrapper {
-        StmtWrapper::new(Box::new(self.clone()))
-    }
+    pub args: Vec<ExprId>,
+    pub caller: ExprId
 }
 
 #[derive(Debug, Clone)]
 pub struct MemberExpr {
-    pub kind: NodeType,
-    pub object: ExprWrapper,
-    pub property: ExprWrapper,
+    pub object: ExprId,
+    pub property: ExprId,
     pub computed: bool
 }
 
-impl Stmt for MemberExpr {
-    fn get_kind(&self) -> NodeType {
-        self.get_expr_kind()
-    }
-    fn get_value(&self) -> Option<StmtValue> {
-        Some(self.get_expr_value().unwrap())
-    }
-    fn clone_boxed(&self) -> Box<dyn Stmt> {
-        Box::new(self.clone())
-    }
-    fn as_any(&self) -> &dyn Any {
-        self
-    }
-    fn clone_as_wrapper(&self) -> StmtWrapper {
-        StmtWrapper::new(self.clone_boxed())
-    }
-}
-
-impl Expr for MemberExpr {
-    fn get_expr_kind(&self) -> NodeType {
-        self.kind
-    }
-    fn get_expr_value(&self) -> Option<StmtValue> {
-        None
-    }
-    fn clone_box(&self) -> Box<dyn Expr> {
-        Box::new(self.clone())
-    }
-    fn to_stmt_from_expr(&self) -> StmtWrapper {
-        StmtWrapper::new(Box::new(self.clone()))
-    }
-}
-
 #[derive(Debug, Clone)]
 pub struct StringLiteral {
-    pub kind: NodeType,
-    pub string: String,
-}
-
-impl Stmt for StringLiteral {
-    fn get_kind(&self) -> NodeType {
-        self.get_expr_kind()
-    }
-    fn get_value(&self) -> Option<StmtValue> {
-        Some(self.get_expr_value().unwrap())
-    }
-    fn clone_boxed(&self) -> Box<dyn Stmt> {
-        Box::new(self.clone())
-    }
-    fn as_any(&self) -> &dyn Any {
-        self
-    }
-    fn clone_as_wrapper(&self) -> StmtWrapper {
-        StmtWrapper::new(self.clone_boxed())
-    }
-}
-
-impl Expr for StringLiteral {
-    fn get_expr_kind(&self) -> NodeType {
-        self.kind
-    }
-    fn get_expr_value(&self) -> Option<StmtValue> {
-        Some(StmtValue::StringVal(self.string.clone()))
-    }
-    fn clone_box(&self) -> Box<dyn Expr> {
-        Box::new(self.clone())
-    }
-    fn to_stmt_from_expr(&self) -> StmtWrapper {
-        StmtWrapper::new(Box::new(self.clone()))
-    }
+    pub string: Symbol,
 }
 
 #[derive(Debug, Clone)]
 pub struct ReturnStmt {
-    pub kind: NodeType,
-    pub value: ExprWrapper
-}
-
-impl Stmt for ReturnStmt {
-    fn get_kind(&self) -> NodeType {
-        self.kind
-    }
-    fn get_value(&self) -> Option<StmtValue> {
-        None
-    }
-    fn clone_boxed(&self) -> Box<dyn Stmt> {
-        Box::new(self.clone())
-    }
-    fn as_any(&self) -> &dyn Any {
-        self
-    }
-    fn clone_as_wrapper(&self) -> StmtWrapper {
-        StmtWrapper::new(self.clone_boxed())
-    }
+    pub value: ExprId
 }
 
 #[derive(Debug, Clone)]
 pub struct IfStmt {
-    pub kind: NodeType,
-    pub condition: ExprWrapper,
-    pub body: Body,
-    pub else_stmt: Option<Body>
-}
-
-impl Stmt for IfStmt {
-    fn get_kind(&self) -> NodeType {
-        self.kind
-    }
-    fn get_value(&self) -> Option<StmtValue> {
-        None
-    }
-    fn clone_boxed(&self) -> Box<dyn Stmt> {
-        Box::new(self.clone())
-    }
-    fn as_any(&self) -> &dyn Any {
-        self
-    }
-    fn clone_as_wrapper(&self) -> StmtWrapper {
-        StmtWrapper::new(self.clone_boxed())
-    }
+    pub condition: ExprId,
+    pub body: BodyId,
+    pub else_stmt: Option<BodyId>
 }
 
 #[derive(Debug, Clone)]
 pub struct WhileStmt {
-    pub kind: NodeType,
-    pub condition: ExprWrapper,
-    pub body: Body
-}
-
-impl Stmt for WhileStmt {
-    fn get_kind(&self) -> NodeType {
-        self.kind
-    }
-    fn get_value(&self) -> Option<StmtValue> {
-        None
-    }
-    fn clone_boxed(&self) -> Box<dyn Stmt> {
-        Box::new(self.clone())
-    }
-    fn as_any(&self) -> &dyn Any {
-        self
-    }
-    fn clone_as_wrapper(&self) -> StmtWrapper {
-        StmtWrapper::new(self.clone_boxed())
-    }
+    pub condition: ExprId,
+    pub body: BodyId
 }
 
 #[derive(Debug, Clone)]
 pub struct ForStmt {
-    pub kind: NodeType,
-    pub iterable: ExprWrapper,
-    pub variable: ExprWrapper,
-    pub body: Body
-}
-
-impl Stmt for ForStmt {
-    fn get_kind(&self) -> NodeType {
-        self.kind
-    }
-    fn get_value(&self) -> Option<StmtValue> {
-        None
-    }
-    fn clone_boxed(&self) -> Box<dyn Stmt> {
-        Box::new(self.clone())
-    }
-    fn as_any(&self) -> &dyn Any {
-        self
-    }
-    fn clone_as_wrapper(&self) -> StmtWrapper {
-        StmtWrapper::new(self.clone_boxed())
-    }
+    pub iterable: ExprId,
+    pub variable: ExprId,
+    pub body: BodyId
 }
\ No newline at end of file
@@ -0,0 +1,545 @@
+use std::collections::HashMap;
+
+use super::{ast::{AssignmentExpr, Ast, BinaryExpr, BinaryOp, Body, BodyId, CallExpr, CompareOp, ComparativeExpr, Expr, ExprId, ForStmt, FunctionDeclaration, Identifier, IfStmt, ListLiteral, MemberExpr, NumericLiteral, ObjectLiteral, OuterScopeStmt, Program, Property, ReturnStmt, Stmt, StmtWrapper, StringLiteral, UnsetStmt, VarDeclaration, WhileStmt}, errors::ParseError, symbol::Symbol};
+
+// Parsed programs stored in a compact binary form, so they can be shipped and run later without
+// the source or parsing it again. The layout is the magic bytes, a format version, a table of
+// every name and string used, then the program's statements. Each node is a tag byte followed by
+// its fields, counts and string table indices are LEB128 varints and numbers are little endian f64s.
+// Statements in a body are each preceded by the line they started on, for errors and the debugger
+
+pub const MAGIC: &[u8; 4] = b"TLPC";
+pub const VERSION: u8 = 4;
+
+// Well past what the parser allows, so this only stops crafted input from overflowing the stack
+const MAX_DEPTH: usize = 1024;
+
+const TAG_VAR: u8 = 0;
+const TAG_UNSET: u8 = 1;
+const TAG_GLOBAL: u8 = 2;
+const TAG_NONLOCAL: u8 = 3;
+const TAG_FUNCTION: u8 = 4;
+const TAG_RETURN: u8 = 5;
+const TAG_IF: u8 = 6;
+const TAG_WHILE: u8 = 7;
+const TAG_FOR: u8 = 8;
+const TAG_IDENTIFIER: u8 = 9;
+const TAG_BINARY: u8 = 10;
+const TAG_COMPARE: u8 = 11;
+const TAG_ASSIGNMENT: u8 = 12;
+const TAG_MEMBER: u8 = 13;
+const TAG_CALL: u8 = 14;
+const TAG_NUMBER: u8 = 15;
+const TAG_OBJECT: u8 = 16;
+const TAG_LIST: u8 = 17;
+const TAG_STRING: u8 = 18;
+const TAG_BLOCK: u8 = 19;
+const TAG_FUNCTION_EXPR: u8 = 20;
+
+const BINARY_OPS: [BinaryOp; 5] = [BinaryOp::Add, BinaryOp::Subtract, BinaryOp::Multiply, BinaryOp::Divide, BinaryOp::Modulo];
+const COMPARE_OPS: [CompareOp; 8] = [CompareOp::Equal, CompareOp::NotEqual, CompareOp::LessThan, CompareOp::GreaterThan, CompareOp::LessThanOrEqual, CompareOp::GreaterThanOrEqual, CompareOp::StrictEqual, CompareOp::StrictNotEqual];
+
+// With `fold`, arithmetic on literals (1 + 2, "a" + "b") is worked out now and stored as the result
+pub fn encode(program: &Program, fold: bool) -> Vec<u8> {
+    let mut encoder = Encoder {
+        ast: &program.ast,
+        fold,
+        strings: Vec::new(),
+        indices: HashMap::new(),
+        out: Vec::new()
+    };
+    encoder.body(program.body);
+
+    let mut bytes = MAGIC.to_vec();
+    bytes.push(VERSION);
+    write_varint(&mut bytes, encoder.strings.len());
+    for string in encoder.strings.iter() {
+        write_varint(&mut bytes, string.len());
+        bytes.extend_from_slice(string.as_bytes());
+    }
+    bytes.extend_from_slice(&encoder.out);
+
+    bytes
+}
+
+// Whether the bytes look like a compiled program rather than source code
+pub fn is_compiled(bytes: &[u8]) -> bool {
+    bytes.starts_with(MAGIC)
+}
+
+pub fn decode(bytes: &[u8]) -> Result<Program, ParseError> {
+    if !is_compiled(bytes) {
+        return Err(ParseError::compiled("Not a compiled program"));
+    }
+    if bytes.get(MAGIC.len()) != Some(&VERSION) {
+        return Err(ParseError::compiled(&format!("Unsupported format version, expected {}", VERSION)));
+    }
+
+    let mut decoder = Decoder {
+        bytes,
+        position: MAGIC.len() + 1,
+        strings: Vec::new(),
+        depth: 0,
+        ast: Ast::new()
+    };
+
+    let count = decoder.varint()?;
+    for _ in 0..count {
+        let length = decoder.varint()?;
+        let string = decoder.take(length)?;
+        match String::from_utf8(string.to_vec()) {
+            Ok(string) => decoder.strings.push(string),
+            Err(_) => return Err(ParseError::compiled("String table is not valid UTF-8"))
+        }
+    }
+
+    let body = decoder.body()?;
+    if decoder.position != bytes.len() {
+        return Err(ParseError::compiled("Unexpected data after the program"));
+    }
+
+    Ok(Program {
+        ast: decoder.ast,
+        body
+    })
+}
+
+fn write_varint(out: &mut Vec<u8>, mut value: usize) {
+    loop {
+        let byte = (value & 0x7f) as u8;
+        value >>= 7;
+        if value == 0 {
+            out.push(byte);
+            return;
+        }
+        out.push(byte | 0x80);
+    }
+}
+
+enum Constant {
+    Number(f64),
+    String(String)
+}
+
+// The value of an expression made only of literals, matching what eval_binop_expr would give
+fn constant(ast: &Ast, expr: ExprId) -> Option<Constant> {
+    let binary = match &ast[expr] {
+        Expr::Number(number) => return Some(Constant::Number(number.value)),
+        Expr::String(string) => return Some(Constant::String(string.string.to_string())),
+        Expr::Binary(binary) => binary,
+        _ => return None
+    };
+    match (constant(ast, binary.left)?, constant(ast, binary.right)?, binary.operator) {
+        // Left for the runtime, which knows whether strict mode makes it an error
+        (Constant::Number(_), Constant::Number(0.0), BinaryOp::Divide | BinaryOp::Modulo) => None,
+        (Constant::Number(left), Constant::Number(right), operator) => Some(Constant::Number(match operator {
+            BinaryOp::Add => left + right,
+            BinaryOp::Subtract => left - right,
+            BinaryOp::Multiply => left * right,
+            BinaryOp::Divide => left / right,
+            BinaryOp::Modulo => left % right
+        })),
+        (Constant::String(left), Constant::String(right), BinaryOp::Add) => Some(Constant::String(left + &right)),
+        // Mixed operands and invalid operators are left for the runtime to handle as it always has
+        _ => None
+    }
+}
+
+struct Encoder<'a> {
+    ast: &'a Ast,
+    fold: bool,
+    strings: Vec<String>,
+    indices: HashMap<String, usize>,
+    out: Vec<u8>
+}
+
+impl Encoder<'_> {
+    fn varint(&mut self, value: usize) {
+        write_varint(&mut self.out, value);
+    }
+
+    fn string(&mut self, string: &str) {
+        let index = match self.indices.get(string) {
+            Some(index) => *index,
+            None => {
+                self.strings.push(string.to_string());
+                self.indices.insert(string.to_string(), self.strings.len() - 1);
+                self.strings.len() - 1
+            }
+        };
+        self.varint(index);
+    }
+
+    fn body(&mut self, body: BodyId) {
+        let body = &self.ast[body];
+        self.varint(body.statements().len());
+        for stmt in body.statements() {
+            self.varint(stmt.line);
+            self.stmt(&stmt.stmt);
+        }
+    }
+
+    fn optional(&mut self, expr: Option<ExprId>) {
+        match expr {
+            Some(expr) => {
+                self.out.push(1);
+                self.expr(expr);
+            },
+            None => self.out.push(0)
+        }
+    }
+
+    fn stmt(&mut self, stmt: &Stmt) {
+        match stmt {
+            Stmt::VarDeclaration(var_declaration) => {
+                self.out.extend_from_slice(&[TAG_VAR, var_declaration.constant as u8]);
+                self.string(var_declaration.identifier.as_str());
+                self.optional(var_declaration.value);
+            },
+            Stmt::Unset(unset_stmt) => {
+                self.out.push(TAG_UNSET);
+                self.string(unset_stmt.identifier.as_str());
+            },
+            Stmt::Global(outer_scope_stmt) => {
+                self.out.push(TAG_GLOBAL);
+                self.string(outer_scope_stmt.identifier.as_str());
+            },
+            Stmt::Nonlocal(outer_scope_stmt) => {
+                self.out.push(TAG_NONLOCAL);
+                self.string(outer_scope_stmt.identifier.as_str());
+            },
+            Stmt::FunctionDeclaration(function) => {
+                self.out.push(TAG_FUNCTION);
+                self.function(function);
+            },
+            Stmt::Return(return_stmt) => {
+                self.out.push(TAG_RETURN);
+                self.expr(return_stmt.value);
+            },
+            Stmt::While(while_stmt) => {
+                self.out.push(TAG_WHILE);
+                self.expr(while_stmt.condition);
+                self.body(while_stmt.body);
+            },
+            Stmt::For(for_stmt) => {
+                self.out.push(TAG_FOR);
+                self.expr(for_stmt.iterable);
+                self.expr(for_stmt.variable);
+                self.body(for_stmt.body);
+            },
+            Stmt::Expr(expr) => self.expr(*expr),
+        }
+    }
+
+    // A function declaration or expression, which are laid out the same after their tag
+    fn function(&mut self, function: &FunctionDeclaration) {
+        self.string(function.name.as_str());
+        self.varint(function.parameters.len());
+        for parameter in function.parameters.iter() {
+            self.string(parameter.as_str());
+        }
+        self.varint(function.required);
+        self.body(function.body);
+    }
+
+    fn expr(&mut self, expr: ExprId) {
+        let ast = self.ast;
+        match &ast[expr] {
+            Expr::Function(function) => {
+                self.out.push(TAG_FUNCTION_EXPR);
+                self.function(function);
+            },
+            Expr::If(if_stmt) => {
+                self.out.push(TAG_IF);
+                self.expr(if_stmt.condition);
+                self.body(if_stmt.body);
+                match &if_stmt.else_stmt {
+                    Some(else_stmt) => {
+                        self.out.push(1);
+                        self.body(*else_stmt);
+                    },
+                    None => self.out.push(0)
+                }
+            },
+            Expr::Identifier(identifier) => {
+                self.out.push(TAG_IDENTIFIER);
+                self.string(identifier.symbol.as_str());
+            },
+            Expr::Binary(bin_expr) => {
+                match constant(ast, expr).filter(|_| self.fold) {
+                    Some(Constant::Number(value)) => {
+                        self.out.push(TAG_NUMBER);
+                        self.out.extend_from_slice(&value.to_le_bytes());
+                    },
+                    Some(Constant::String(value)) => {
+                        self.out.push(TAG_STRING);
+                        self.string(&value);
+                    },
+                    None => {
+                        let operator = BINARY_OPS.iter().position(|op| *op == bin_expr.operator).unwrap();
+                        self.out.extend_from_slice(&[TAG_BINARY, operator as u8]);
+                        self.expr(bin_expr.left);
+                        self.expr(bin_expr.right);
+                    }
+                }
+            },
+            Expr::Comparative(comp_expr) => {
+                let operator = COMPARE_OPS.iter().position(|op| *op == comp_expr.operator).unwrap();
+                self.out.extend_from_slice(&[TAG_COMPARE, operator as u8]);
+                self.expr(comp_expr.left);
+                self.expr(comp_expr.right);
+            },
+            Expr::Assignment(assignment) => {
+                self.out.push(TAG_ASSIGNMENT);
+                self.expr(assignment.assignee);
+                self.expr(assignment.value);
+            },
+            Expr::Member(member_expr) => {
+                self.out.extend_from_slice(&[TAG_MEMBER, member_expr.computed as u8]);
+                self.expr(member_expr.object);
+                self.expr(member_expr.property);
+            },
+            Expr::Call(call_expr) => {
+                self.out.push(TAG_CALL);
+                self.expr(call_expr.caller);
+                self.varint(call_expr.args.len());
+                for arg in call_expr.args.iter() {
+                    self.expr(*arg);
+                }
+            },
+            Expr::Number(number) => {
+                self.out.push(TAG_NUMBER);
+                self.out.extend_from_slice(&number.value.to_le_bytes());
+            },
+            Expr::Object(object) => {
+                self.out.push(TAG_OBJECT);
+                self.varint(object.properties.len());
+                for property in object.properties.iter() {
+                    match &property.key {
+                        Some(key) => {
+                            self.out.push(1);
+                            self.string(key.as_str());
+                        },
+                        None => self.out.push(0)
+                    }
+                    self.optional(property.value);
+                }
+            },
+            Expr::List(list) => {
+                self.out.push(TAG_LIST);
+                self.varint(list.elements.len());
+                for element in list.elements.iter() {
+                    self.expr(*element);
+                }
+            },
+            Expr::String(string) => {
+                self.out.push(TAG_STRING);
+                self.string(string.string.as_str());
+            },
+            Expr::Body(body) => {
+                self.out.push(TAG_BLOCK);
+                self.body(*body);
+            }
+        }
+    }
+}
+
+struct Decoder<'a> {
+    bytes: &'a [u8],
+    position: usize,
+    strings: Vec<String>,
+    depth: usize,
+    ast: Ast
+}
+
+impl Decoder<'_> {
+    fn take(&mut self, length: usize) -> Result<&[u8], ParseError> {
+        match self.bytes.get(self.position..self.position.saturating_add(length)) {
+            Some(bytes) => {
+                self.position += length;
+                Ok(bytes)
+            },
+            None => Err(ParseError::compiled("Unexpected end of data"))
+        }
+    }
+
+    fn byte(&mut self) -> Result<u8, ParseError> {
+        Ok(self.take(1)?[0])
+    }
+
+    fn flag(&mut self) -> Result<bool, ParseError> {
+        match self.byte()? {
+            0 => Ok(false),
+            1 => Ok(true),
+            other => Err(ParseError::compiled(&format!("Invalid flag {}", other)))
+        }
+    }
+
+    fn varint(&mut self) -> Result<usize, ParseError> {
+        let mut value: usize = 0;
+        let mut shift = 0;
+        loop {
+            let byte = self.byte()?;
+            if shift >= usize::BITS {
+                return Err(ParseError::compiled("Number too large"));
+            }
+            value |= ((byte & 0x7f) as usize) << shift;
+            if byte & 0x80 == 0 {
+                return Ok(value);
+            }
+            shift += 7;
+        }
+    }
+
+    fn number(&mut self) -> Result<f64, ParseError> {
+        Ok(f64::from_le_bytes(self.take(8)?.try_into().unwrap()))
+    }
+
+    fn string(&mut self) -> Result<String, ParseError> {
+        let index = self.varint()?;
+        match self.strings.get(index) {
+            Some(string) => Ok(string.clone()),
+            None => Err(ParseError::compiled(&format!("String index {} out of range", index)))
+        }
+    }
+
+    fn symbol(&mut self) -> Result<Symbol, ParseError> {
+        Ok(Symbol::intern(&self.string()?))
+    }
+
+    fn body(&mut self) -> Result<BodyId, ParseError> {
+        let count = self.varint()?;
+        let mut statements = Vec::new();
+        for _ in 0..count {
+            let line = self.varint()?;
+            let mut stmt = self.stmt()?;
+            stmt.line = line;
+            statements.push(stmt);
+        }
+        Ok(self.ast.alloc_body(Body::new(statements)))
+    }
+
+    // A function declaration or expression, which are laid out the same
+    fn function(&mut self) -> Result<FunctionDeclaration, ParseError> {
+        let name = self.symbol()?;
+        let count = self.varint()?;
+        let mut parameters = Vec::new();
+        for _ in 0..count {
+            parameters.push(self.symbol()?);
+        }
+        let required = self.varint()?;
+        if required > parameters.len() {
+            return Err(ParseError::compiled("Function requires more parameters than it has"));
+        }
+        Ok(FunctionDeclaration { parameters, required, name, body: self.body()? })
+    }
+
+    fn optional(&mut self) -> Result<Option<ExprId>, ParseError> {
+        if self.flag()? {
+            Ok(Some(self.expr()?))
+        } else {
+            Ok(None)
+        }
+    }
+
+    fn nested<T>(&mut self, decode: impl FnOnce(&mut Self) -> Result<T, ParseError>) -> Result<T, ParseError> {
+        self.depth += 1;
+        if self.depth > MAX_DEPTH {
+            return Err(ParseError::compiled("Program too deeply nested"));
+        }
+        let result = decode(self);
+        self.depth -= 1;
+        result
+    }
+
+    fn stmt(&mut self) -> Result<StmtWrapper, ParseError> {
+        self.nested(|decoder| {
+            let tag = decoder.byte()?;
+            let stmt = match tag {
+                TAG_VAR => {
+                    let constant = decoder.flag()?;
+                    Stmt::VarDeclaration(VarDeclaration { constant, identifier: decoder.symbol()?, value: decoder.optional()? })
+                },
+                TAG_UNSET => Stmt::Unset(UnsetStmt { identifier: decoder.symbol()? }),
+                TAG_GLOBAL => Stmt::Global(OuterScopeStmt { identifier: decoder.symbol()? }),
+                TAG_NONLOCAL => Stmt::Nonlocal(OuterScopeStmt { identifier: decoder.symbol()? }),
+                TAG_FUNCTION => Stmt::FunctionDeclaration(decoder.function()?),
+                TAG_RETURN => Stmt::Return(ReturnStmt { value: decoder.expr()? }),
+                TAG_WHILE => Stmt::While(WhileStmt { condition: decoder.expr()?, body: decoder.body()? }),
+                TAG_FOR => Stmt::For(ForStmt { iterable: decoder.expr()?, variable: decoder.expr()?, body: decoder.body()? }),
+                tag => Stmt::Expr(decoder.tagged_expr(tag)?)
+            };
+            Ok(StmtWrapper::new(stmt))
+        })
+    }
+
+    fn expr(&mut self) -> Result<ExprId, ParseError> {
+        self.nested(|decoder| {
+            let tag = decoder.byte()?;
+            decoder.tagged_expr(tag)
+        })
+    }
+
+    fn tagged_expr(&mut self, tag: u8) -> Result<ExprId, ParseError> {
+        let expr = match tag {
+            TAG_IDENTIFIER => Expr::Identifier(Identifier { symbol: self.symbol()? }),
+            TAG_BLOCK => Expr::Body(self.body()?),
+            TAG_FUNCTION_EXPR => Expr::Function(self.function()?),
+            TAG_IF => {
+                let condition = self.expr()?;
+                let body = self.body()?;
+                let else_stmt = if self.flag()? { Some(self.body()?) } else { None };
+                Expr::If(IfStmt { condition, body, else_stmt })
+            },
+            TAG_BINARY => {
+                let operator = match BINARY_OPS.get(self.byte()? as usize) {
+                    Some(operator) => *operator,
+                    None => return Err(ParseError::compiled("Invalid binary operator"))
+                };
+                Expr::Binary(BinaryExpr { left: self.expr()?, right: self.expr()?, operator })
+            },
+            TAG_COMPARE => {
+                let operator = match COMPARE_OPS.get(self.byte()? as usize) {
+                    Some(operator) => *operator,
+                    None => return Err(ParseError::compiled("Invalid comparison operator"))
+                };
+                Expr::Comparative(ComparativeExpr { left: self.expr()?, right: self.expr()?, operator })
+            },
+            TAG_ASSIGNMENT => Expr::Assignment(AssignmentExpr { assignee: self.expr()?, value: self.expr()? }),
+            TAG_MEMBER => {
+                let computed = self.flag()?;
+                Expr::Member(MemberExpr { object: self.expr()?, property: self.expr()?, computed })
+            },
+            TAG_CALL => {
+                let caller = self.expr()?;
+                let count = self.varint()?;
+                let mut args = Vec::new();
+                for _ in 0..count {
+                    args.push(self.expr()?);
+                }
+                Expr::Call(CallExpr { args, caller })
+            },
+            TAG_NUMBER => Expr::Number(NumericLiteral { value: self.number()? }),
+            TAG_OBJECT => {
+                let count = self.varint()?;
+                let mut properties = Vec::new();
+                for _ in 0..count {
+                    let key = if self.flag()? { Some(self.symbol()?) } else { None };
+                    properties.push(Property { key, value: self.optional()? });
+                }
+                Expr::Object(ObjectLiteral { properties })
+            },
+            TAG_LIST => {
+                let count = self.varint()?;
+                let mut elements = Vec::new();
+                for _ in 0..count {
+                    elements.push(self.expr()?);
+                }
+                Expr::List(ListLiteral { elements })
+            },
+            TAG_STRING => Expr::String(StringLiteral { string: self.symbol()? }),
+            tag => return Err(ParseError::compiled(&format!("Unknown node tag {}", tag)))
+        };
+        Ok(self.ast.alloc(expr))
+    }
+}
\ No newline at end of file
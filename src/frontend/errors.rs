@@ -0,0 +1,105 @@
+use std::fmt::Display;
+
+use super::lexer::Token;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ParseErrorKind {
+    // The source ended before the statement was finished, more input may complete it
+    Incomplete,
+    Syntax,
+    // The source couldn't be read (only happens when lexing from a reader)
+    Io,
+    // A compiled program that is corrupt or from another format version
+    Compiled
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ParseError {
+    pub kind: ParseErrorKind,
+    pub message: String,
+    // Where in the source the error is, counted from 1 like token positions. Line 0 is unknown
+    pub line: usize,
+    pub column: usize,
+    // How many characters from `column` the error covers
+    pub width: usize,
+    // What the parser wanted instead, and what it got, both ready to show (`)`, identifier)
+    pub expected: Option<String>,
+    pub found: Option<String>
+}
+
+impl ParseError {
+    fn new(kind: ParseErrorKind, message: String) -> Self {
+        ParseError {
+            kind,
+            message,
+            line: 0,
+            column: 0,
+            width: 0,
+            expected: None,
+            found: None
+        }
+    }
+
+    pub fn incomplete(message: &str) -> Self {
+        ParseError::new(ParseErrorKind::Incomplete, message.to_string())
+    }
+
+    pub fn syntax(message: &str) -> Self {
+        ParseError::new(ParseErrorKind::Syntax, message.to_string())
+    }
+
+    pub fn io(error: std::io::Error) -> Self {
+        ParseError::new(ParseErrorKind::Io, error.to_string())
+    }
+
+    pub fn compiled(message: &str) -> Self {
+        ParseError::new(ParseErrorKind::Compiled, message.to_string())
+    }
+
+    pub fn at(mut self, line: usize, column: usize, width: usize) -> Self {
+        self.line = line;
+        self.column = column;
+        self.width = width;
+        self
+    }
+
+    // Points the error at the token the parser stopped on
+    pub fn found(self, token: &Token) -> Self {
+        let found = Some(token.describe());
+        let mut error = if token.line == 0 { self } else { self.at(token.line, token.column, token.width()) };
+        error.found = found;
+        error
+    }
+
+    pub fn expected(mut self, expected: &str) -> Self {
+        self.expected = Some(expected.to_string());
+        self
+    }
+
+    pub fn is_incomplete(&self) -> bool {
+        self.kind == ParseErrorKind::Incomplete
+    }
+}
+
+impl Display for ParseError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.kind {
+            ParseErrorKind::Incomplete => write!(f, "Incomplete input: {}", self.message)?,
+            ParseErrorKind::Syntax => write!(f, "Parser Error: {}", self.message)?,
+            ParseErrorKind::Io => write!(f, "Failed to read source: {}", self.message)?,
+            ParseErrorKind::Compiled => write!(f, "Invalid compiled program: {}", self.message)?
+        }
+
+        match (&self.expected, &self.found) {
+            (Some(expected), Some(found)) => write!(f, " (expected {}, found {})", expected, found)?,
+            (None, Some(found)) => write!(f, " (found {})", found)?,
+            _ => {}
+        }
+        if self.line != 0 {
+            write!(f, " at line {}, column {}", self.line, self.column)?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for ParseError {}
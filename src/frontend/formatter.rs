@@ -0,0 +1,193 @@
+use std::rc::Rc;
+
+use crate::diagnostics;
+
+use super::{errors::ParseError, lexer::{Token, TokenType}, parser::{opens_block, Parser}};
+
+// The canonical layout for source code, what `interpreter fmt` writes. Works on the token stream
+// rather than the tree so comments and the author's line breaks survive: blocks are indented by
+// four spaces with their braces on the statement's line, each statement starts a line, at most
+// one blank line is kept in a row and spacing inside a line is normalised. Code that doesn't
+// parse is refused rather than guessed at
+pub fn format(parser: &mut Parser, source: &str) -> Result<String, ParseError> {
+    // Only errors matter here, warnings are for when the code runs
+    diagnostics::scoped(Rc::new(|_, _: &str| {}), || parser.parse(source.to_string()))?;
+    let tokens = parser.tokenizer.try_tokenize(source.to_string())?;
+
+    let mut formatter = Formatter {
+        out: String::new(),
+        open: Vec::new(),
+        previous: None,
+        break_after: false
+    };
+    for index in 0..tokens.len() {
+        formatter.token(&tokens[index..]);
+    }
+
+    let mut out = formatter.out.trim_end().to_string();
+    if !out.is_empty() {
+        out.push('\n');
+    }
+
+    // The lexer skips a shebang, so it's put back as it was
+    if let Some(shebang) = source.lines().next().filter(|line| line.starts_with("#!")) {
+        out = format!("{}\n{}", shebang.trim_end(), out);
+    }
+    Ok(out)
+}
+
+#[derive(Clone, Copy)]
+struct Previous {
+    token_type: TokenType,
+    line: usize,
+    // A + or - in front of a value rather than between two
+    unary: bool,
+    // An opening or closing brace of a block, not an object literal
+    block: bool
+}
+
+struct Formatter {
+    out: String,
+    // Brackets, parentheses and braces that are still open, true for block braces
+    open: Vec<bool>,
+    previous: Option<Previous>,
+    // The last token ends a line, like a semicolon or a block's opening brace
+    break_after: bool
+}
+
+impl Formatter {
+    // Lays out the first of the remaining tokens, the rest are there to look ahead at
+    fn token(&mut self, tokens: &[Token]) {
+        let token = &tokens[0];
+        let token_type = token.get_token_type();
+        let previous_type = self.previous.map(|previous| previous.token_type);
+
+        let opens_block = token_type == TokenType::OpenBrace && (!starts_value(previous_type) || opens_block(tokens));
+        let closes_block = token_type == TokenType::CloseBrace && self.open.last() == Some(&true);
+        let closer = matches!(token_type, TokenType::CloseBrace | TokenType::CloseParen | TokenType::CloseBracket);
+        // A statement can start with a sign too, like the value of a branch in `if x { -1 }`
+        let unary = token_type == TokenType::BinaryOperator &&
+            (starts_value(previous_type) || matches!(previous_type, Some(TokenType::OpenBrace | TokenType::Semicolon)));
+
+        let trailing = token.leading_comments.iter().take_while(|comment| comment.trailing).collect::<Vec<_>>();
+        let own_line = &token.leading_comments[trailing.len()..];
+
+        let mut lines = 0;
+        if let Some(previous) = self.previous {
+            let empty_block = closes_block && previous.token_type == TokenType::OpenBrace;
+            // Tokens that stay on the line before even when the source broke it, `} else {`, `) {`, `};`, `{}`.
+            // A block on its own, after a statement or another block's brace, starts a line
+            let join = (opens_block && previous.token_type != TokenType::Semicolon && !previous.block) || empty_block || token_type == TokenType::Semicolon ||
+                (previous.block && previous.token_type == TokenType::CloseBrace &&
+                    matches!(token_type, TokenType::Else | TokenType::CloseParen | TokenType::Comma));
+
+            if !join && (self.break_after || closes_block || token.line > previous.line) {
+                lines = 1;
+                let blank = token.line > previous.line + 1 + own_line.len();
+                if blank && !closes_block && !(previous.block && previous.token_type == TokenType::OpenBrace) {
+                    lines = 2;
+                }
+            }
+            if !token.leading_comments.is_empty() {
+                lines = lines.max(1);
+            }
+        }
+
+        for comment in trailing {
+            self.out += " //";
+            self.out += &comment.text;
+        }
+        if lines > 0 {
+            self.newlines(lines);
+        }
+        for comment in own_line {
+            self.indent(self.open.len());
+            self.out += "//";
+            self.out += &comment.text;
+            self.newlines(1);
+        }
+
+        if closer {
+            self.open.pop();
+        }
+        if token_type == TokenType::EOF {
+            return;
+        }
+
+        if self.out.is_empty() || self.out.ends_with('\n') {
+            self.indent(self.open.len());
+        } else if self.previous.is_some_and(|previous| spaced(previous, token_type)) {
+            self.out.push(' ');
+        }
+        self.out += &text(token);
+
+        if matches!(token_type, TokenType::OpenBrace | TokenType::OpenParen | TokenType::OpenBracket) {
+            self.open.push(opens_block);
+        }
+        self.break_after = token_type == TokenType::Semicolon || opens_block || closes_block;
+        self.previous = Some(Previous {
+            token_type,
+            line: token.line,
+            unary,
+            block: opens_block || closes_block
+        });
+    }
+
+    fn newlines(&mut self, count: usize) {
+        while self.out.ends_with(' ') {
+            self.out.pop();
+        }
+        let existing = self.out.chars().rev().take_while(|c| *c == '\n').count();
+        for _ in existing..count {
+            self.out.push('\n');
+        }
+    }
+
+    fn indent(&mut self, depth: usize) {
+        self.out += &"    ".repeat(depth);
+    }
+}
+
+// Whether a value is expected next, so a { opens an object and a + or - is a sign
+fn starts_value(previous: Option<TokenType>) -> bool {
+    matches!(previous, None | Some(TokenType::Equals | TokenType::OpenParen | TokenType::OpenBracket | TokenType::Comma |
+        TokenType::Colon | TokenType::Return | TokenType::BinaryOperator | TokenType::Bang | TokenType::DoubleEquals |
+        TokenType::BangEquals | TokenType::TripleEquals | TokenType::BangDoubleEquals | TokenType::LeftAngleBracket | TokenType::RightAngleBracket |
+        TokenType::LeftAngleBracketEquals | TokenType::RightAngleBracketEquals | TokenType::In))
+}
+
+// Whether a space goes between two tokens on the same line
+fn spaced(previous: Previous, next: TokenType) -> bool {
+    if previous.unary || matches!(previous.token_type, TokenType::OpenParen | TokenType::OpenBracket | TokenType::Dot | TokenType::Bang) {
+        return false;
+    }
+
+    match next {
+        TokenType::CloseParen | TokenType::CloseBracket | TokenType::Comma | TokenType::Semicolon | TokenType::Dot | TokenType::Colon | TokenType::Question => false,
+        // Calls and indexing, `f(x)`, `xs[0]`
+        TokenType::OpenParen | TokenType::OpenBracket => !matches!(previous.token_type,
+            TokenType::Identifier | TokenType::CloseParen | TokenType::CloseBracket | TokenType::String),
+        TokenType::CloseBrace => previous.token_type != TokenType::OpenBrace,
+        _ => true
+    }
+}
+
+fn text(token: &Token) -> String {
+    let value = token.get_value().cloned().unwrap_or_default();
+    if token.get_token_type() != TokenType::String {
+        return value;
+    }
+
+    let mut string = String::from("\"");
+    for c in value.chars() {
+        match c {
+            '\\' => string += "\\\\",
+            '"' => string += "\\\"",
+            '\n' => string += "\\n",
+            '\t' => string += "\\t",
+            c => string.push(c)
+        }
+    }
+    string.push('"');
+    string
+}
\ No newline at end of file
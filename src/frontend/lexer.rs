@@ -1,7 +1,9 @@
-use std::{collections::HashMap, fmt::Debug};
+use std::{collections::{HashMap, VecDeque}, fmt::Debug, io::{self, BufRead, BufReader, Read}, iter::{self, Empty}, sync::{Arc, OnceLock}};
 
 use crate::{fatal_error, is_skippable, is_valid_ident_char, is_valid_ident_char_start};
 
+use super::errors::ParseError;
+
 #[derive(Clone, Copy, Debug, PartialEq)]
 pub enum TokenType {
     Identifier,
@@ -12,6 +14,9 @@ pub enum TokenType {
 
     Var,
     Const,
+    Unset,
+    Global,
+    Nonlocal,
 
     Function,
     Return,
@@ -37,17 +42,95 @@ pub enum TokenType {
     RightAngleBracket,
     LeftAngleBracket,
     Bang,
+    // After a parameter that can be left out
+    Question,
+    DoubleEquals,
+    BangEquals,
+    TripleEquals,
+    BangDoubleEquals,
+    RightAngleBracketEquals,
+    LeftAngleBracketEquals,
 
     EOF, // End of file
 }
 
+impl TokenType {
+    // How error messages refer to a token of this type
+    pub fn describe(&self) -> &'static str {
+        match self {
+            TokenType::Identifier => "identifier",
+            TokenType::Number => "number",
+            TokenType::String => "string",
+            TokenType::Semicolon => "`;`",
+            TokenType::Var => "`var`",
+            TokenType::Const => "`const`",
+            TokenType::Unset => "`unset`",
+            TokenType::Global => "`global`",
+            TokenType::Nonlocal => "`nonlocal`",
+            TokenType::Function => "`function`",
+            TokenType::Return => "`return`",
+            TokenType::If => "`if`",
+            TokenType::Else => "`else`",
+            TokenType::While => "`while`",
+            TokenType::For => "`for`",
+            TokenType::In => "`in`",
+            TokenType::Comma => "`,`",
+            TokenType::Colon => "`:`",
+            TokenType::Dot => "`.`",
+            TokenType::OpenBrace => "`{`",
+            TokenType::CloseBrace => "`}`",
+            TokenType::OpenParen => "`(`",
+            TokenType::CloseParen => "`)`",
+            TokenType::OpenBracket => "`[`",
+            TokenType::CloseBracket => "`]`",
+            TokenType::BinaryOperator => "operator",
+            TokenType::Equals => "`=`",
+            TokenType::RightAngleBracket => "`>`",
+            TokenType::LeftAngleBracket => "`<`",
+            TokenType::Bang => "`!`",
+            TokenType::Question => "`?`",
+            TokenType::DoubleEquals => "`==`",
+            TokenType::BangEquals => "`!=`",
+            TokenType::TripleEquals => "`===`",
+            TokenType::BangDoubleEquals => "`!==`",
+            TokenType::RightAngleBracketEquals => "`>=`",
+            TokenType::LeftAngleBracketEquals => "`<=`",
+            TokenType::EOF => "end of input"
+        }
+    }
+}
+
+// A `//` comment, kept so tools working on the token stream can put it back where it was
+#[derive(Debug, Clone, PartialEq)]
+pub struct Comment {
+    // Text after the `//`, up to the end of the line
+    pub text: String,
+    // Whether the comment was on the same line as the token before it
+    pub trailing: bool
+}
+
 #[derive(Debug, Clone)]
 pub struct Token {
     pub value: Option<String>,
-    token_type: TokenType
+    token_type: TokenType,
+    // Comments between the previous token and this one
+    pub leading_comments: Vec<Comment>,
+    // Where the token starts, both counted from 1. Tokens made outside the lexer are at 0, 0
+    pub line: usize,
+    pub column: usize
 }
 
 impl Token {
+    pub fn new(value: Option<String>, token_type: TokenType) -> Self {
+        Token {
+            value,
+            token_type,
+            leading_comments: Vec::new(),
+            line: 0,
+            column: 0
+        }
+    }
+
     pub fn get_value(&self) -> Option<&String> {
         self.value.as_ref()
     }
@@ -55,16 +138,53 @@ impl Token {
     pub fn get_token_type(&self) -> TokenType {
         self.token_type
     }
+
+    // The token as error messages show it, its text where it has any
+    pub fn describe(&self) -> String {
+        match (&self.value, self.token_type) {
+            (_, TokenType::EOF) | (None, _) => self.token_type.describe().to_string(),
+            (Some(value), TokenType::String) => format!("{:?}", value),
+            (Some(value), _) => format!("`{}`", value)
+        }
+    }
+
+    // How many characters of source the token takes up, near enough for strings with escapes
+    pub fn width(&self) -> usize {
+        match (&self.value, self.token_type) {
+            (_, TokenType::EOF) | (None, _) => 1,
+            (Some(value), TokenType::String) => value.chars().count() + 2,
+            (Some(value), _) => value.chars().count()
+        }
+    }
 }
 
-pub struct Tokenizer;
+// One token per line as `line:column  type  value`, what --dump-tokens and the REPL's :tokens print
+pub fn dump(tokens: &[Token]) -> String {
+    let mut out = String::new();
+    for token in tokens {
+        let position = format!("{}:{}", token.line, token.column);
+        let token_type = format!("{:?}", token.token_type);
+        let value = match (&token.value, token.token_type) {
+            (Some(value), TokenType::String) => format!("{:?}", value),
+            (Some(value), _) => value.clone(),
+            (None, _) => String::new()
+        };
+        out += &format!("{:<8} {:<24} {}\n", position, token_type, value);
+    }
+    out
+}
 
-impl Tokenizer {
-    fn get_keywords(&self) -> HashMap<&str, TokenType> {
+fn get_keywords() -> &'static HashMap<&'static str, TokenType> {
+    static KEYWORDS: OnceLock<HashMap<&'static str, TokenType>> = OnceLock::new();
+
+    KEYWORDS.get_or_init(|| {
         let mut keywords = HashMap::new();
 
         keywords.insert("var", TokenType::Var);
         keywords.insert("const", TokenType::Const);
+        keywords.insert("unset", TokenType::Unset);
+        keywords.insert("global", TokenType::Global);
+        keywords.insert("nonlocal", TokenType::Nonlocal);
         keywords.insert("function", TokenType::Function);
         keywords.insert("return", TokenType::Return);
         keywords.insert("if", TokenType::If);
@@ -73,124 +193,392 @@ impl Tokenizer {
         keywords.insert("for", TokenType::For);
         keywords.insert("in", TokenType::In);
 
+        keywords
+    })
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct Tokenizer {
+    // Extra keywords registered by the embedder, checked before the built in ones. Shared with
+    // every TokenStream rather than copied into each
+    extra_keywords: Arc<HashMap<String, TokenType>>
+}
+
+impl Tokenizer {
+    pub fn new() -> Self {
+        Tokenizer {
+            extra_keywords: Arc::new(HashMap::new())
+        }
+    }
+
+    // Makes `word` lex as `token_type`, e.g. register_keyword("fn", TokenType::Function) adds `fn` as an alias for `function`
+    pub fn register_keyword(&mut self, word: &str, token_type: TokenType) {
+        let mut chars = word.chars();
+        if !chars.next().is_some_and(is_valid_ident_char_start) || !chars.all(is_valid_ident_char) {
+            fatal_error(&format!("Cannot register keyword `{}` as it is not a valid identifier.", word));
+        }
+
+        Arc::make_mut(&mut self.extra_keywords).insert(word.to_string(), token_type);
+    }
+
+    pub fn get_keyword(&self, word: &str) -> Option<TokenType> {
+        get_keyword(&self.extra_keywords, word)
+    }
+
+    // Every word that lexes as a keyword, built in and registered, sorted
+    pub fn keywords(&self) -> Vec<String> {
+        let mut keywords = get_keywords().keys().map(|word| word.to_string())
+            .chain(self.extra_keywords.keys().cloned())
+            .collect::<Vec<String>>();
+        keywords.sort();
+        keywords.dedup();
         keywords
     }
 
     pub fn tokenize(&self, source: String) -> Vec<Token> {
-        let mut token_output: Vec<Token> = Vec::new();
-        let mut src = source.chars().collect::<Vec<char>>();
-
-        while src.len() > 0 {
-            if src[0] == '/' {
-                if src.len() > 1 {
-                    if src[1] == '/' {
-                        while src[0] != '\n' || src[0] != '\r' {
-                            src.remove(0);
-                        }
+        match self.try_tokenize(source) {
+            Ok(tokens) => tokens,
+            Err(e) => fatal_error(&e.to_string())
+        }
+    }
+
+    pub fn try_tokenize(&self, source: String) -> Result<Vec<Token>, ParseError> {
+        // Typical code has a token every several bytes, so this saves most of the regrowing
+        let mut tokens = Vec::with_capacity(source.len() / 8 + 1);
+        for token in TokenStream::from_source(&source, Arc::clone(&self.extra_keywords)) {
+            tokens.push(token?);
+        }
+        Ok(tokens)
+    }
+
+    // Lexes lazily from any reader, a line at a time, so the whole program never has to be in memory
+    pub fn tokenize_reader<R: Read>(&self, reader: R) -> TokenStream<ReaderChars<R>> {
+        TokenStream::new(ReaderChars::new(reader), Arc::clone(&self.extra_keywords))
+    }
+}
+
+pub struct ReaderChars<R: Read> {
+    reader: BufReader<R>,
+    line: Vec<char>,
+    position: usize
+}
+
+impl<R: Read> ReaderChars<R> {
+    pub fn new(reader: R) -> Self {
+        ReaderChars {
+            reader: BufReader::new(reader),
+            line: Vec::new(),
+            position: 0
+        }
+    }
+}
+
+impl<R: Read> Iterator for ReaderChars<R> {
+    type Item = io::Result<char>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.position == self.line.len() {
+            let mut line = String::new();
+            match self.reader.read_line(&mut line) {
+                Ok(0) => return None,
+                Ok(_) => {},
+                Err(e) => return Some(Err(e))
+            }
+            self.line = line.chars().collect();
+            self.position = 0;
+        }
+
+        self.position += 1;
+        Some(Ok(self.line[self.position - 1]))
+    }
+}
+
+fn get_keyword(extra_keywords: &HashMap<String, TokenType>, word: &str) -> Option<TokenType> {
+    extra_keywords.get(word).or_else(|| get_keywords().get(word)).copied()
+}
+
+pub struct TokenStream<I: Iterator<Item = io::Result<char>>> {
+    chars: I,
+    buffer: VecDeque<char>,
+    extra_keywords: Arc<HashMap<String, TokenType>>,
+    // Comments waiting to be attached to the next token
+    comments: Vec<Comment>,
+    newline_since_token: bool,
+    finished: bool,
+    // Position of the next character to be advanced past
+    line: usize,
+    column: usize
+}
+
+impl TokenStream<Empty<io::Result<char>>> {
+    // For source that's already in memory, every character goes in the lookahead buffer at once
+    // so lexing never has to stop and pull more
+    pub fn from_source(source: &str, extra_keywords: Arc<HashMap<String, TokenType>>) -> Self {
+        let mut stream = TokenStream::new(iter::empty(), extra_keywords);
+        stream.buffer = source.chars().collect();
+        stream
+    }
+}
+
+impl<I: Iterator<Item = io::Result<char>>> TokenStream<I> {
+    pub fn new(chars: I, extra_keywords: Arc<HashMap<String, TokenType>>) -> Self {
+        TokenStream {
+            chars,
+            buffer: VecDeque::new(),
+            extra_keywords,
+            comments: Vec::new(),
+            newline_since_token: true,
+            finished: false,
+            line: 1,
+            column: 1
+        }
+    }
+
+    fn peek(&mut self, amount: usize) -> Result<Option<char>, ParseError> {
+        while self.buffer.len() <= amount {
+            match self.chars.next() {
+                Some(Ok(c)) => self.buffer.push_back(c),
+                Some(Err(e)) => return Err(ParseError::io(e)),
+                None => return Ok(None)
+            }
+        }
+
+        Ok(Some(self.buffer[amount]))
+    }
+
+    fn at(&mut self) -> Result<Option<char>, ParseError> {
+        self.peek(0)
+    }
+
+    // Only call after peeking, the character has to be in the buffer
+    fn advance(&mut self) -> char {
+        let c = self.buffer.pop_front().unwrap();
+        if c == '\n' {
+            self.line += 1;
+            self.column = 1;
+        } else {
+            self.column += 1;
+        }
+        c
+    }
+
+    // Advances past every character `accept` holds for, into a string sized for them up front
+    // instead of one grown a character at a time
+    fn take_while(&mut self, accept: impl Fn(char) -> bool) -> Result<String, ParseError> {
+        let mut length = 0;
+        while self.peek(length)?.is_some_and(&accept) {
+            length += 1;
+        }
+
+        let mut value = String::with_capacity(length);
+        for _ in 0..length {
+            value.push(self.advance());
+        }
+        Ok(value)
+    }
+
+    fn single(&mut self, token_type: TokenType) -> Token {
+        Token::new(Some(String::from(self.advance())), token_type)
+    }
+
+    fn double(&mut self, token_type: TokenType) -> Token {
+        let mut value = String::with_capacity(3);
+        value.push(self.advance());
+        value.push(self.advance());
+        if matches!(token_type, TokenType::TripleEquals | TokenType::BangDoubleEquals) {
+            value.push(self.advance());
+        }
+        Token::new(Some(value), token_type)
+    }
+
+    // Operators made of two or three characters, checked before falling back to single character tokens
+    fn at_double_operator(&mut self, current: char) -> Result<Option<TokenType>, ParseError> {
+        if !matches!(current, '=' | '!' | '>' | '<') || self.peek(1)? != Some('=') {
+            return Ok(None);
+        }
+        if self.peek(2)? == Some('=') {
+            match current {
+                '=' => return Ok(Some(TokenType::TripleEquals)),
+                '!' => return Ok(Some(TokenType::BangDoubleEquals)),
+                _ => {}
+            }
+        }
+
+        Ok(match current {
+            '=' => Some(TokenType::DoubleEquals),
+            '!' => Some(TokenType::BangEquals),
+            '>' => Some(TokenType::RightAngleBracketEquals),
+            '<' => Some(TokenType::LeftAngleBracketEquals),
+            _ => None
+        })
+    }
+
+    fn next_token(&mut self) -> Result<Option<Token>, ParseError> {
+        loop {
+            let current = match self.at()? {
+                Some(c) => c,
+                None => return Ok(None)
+            };
+
+            // A `#!` line at the very start is for the OS running the file as a script, not for us
+            if self.line == 1 && self.column == 1 && current == '#' && self.peek(1)? == Some('!') {
+                while self.at()?.is_some_and(|c| c != '\n') {
+                    self.advance();
+                }
+                continue;
+            }
+
+            if current == '/' && self.peek(1)? == Some('/') {
+                self.advance();
+                self.advance();
+
+                let mut text = String::new();
+                while let Some(c) = self.at()? {
+                    if c == '\n' || c == '\r' {
+                        break;
                     }
+                    text.push(self.advance());
                 }
+
+                self.comments.push(Comment { text, trailing: !self.newline_since_token });
+                continue;
+            }
+
+            if is_skippable(current) {
+                if self.advance() == '\n' {
+                    self.newline_since_token = true;
+                }
+                continue;
             }
-            if src[0] == '(' {
-                token_output.push(Token { value: Some(src.remove(0).to_string()), token_type: TokenType::OpenParen });
-            } else if src[0] == ')' {
-                token_output.push(Token { value: Some(src.remove(0).to_string()), token_type: TokenType::CloseParen });
-            } else if src[0] == '{' {
-                token_output.push(Token { value: Some(src.remove(0).to_string()), token_type: TokenType::OpenBrace });
-            } else if src[0] == '}' {
-                token_output.push(Token { value: Some(src.remove(0).to_string()), token_type: TokenType::CloseBrace });
-            } else if src[0] == '[' {
-                token_output.push(Token { value: Some(src.remove(0).to_string()), token_type: TokenType::OpenBracket });
-            } else if src[0] == ']' {
-                token_output.push(Token { value: Some(src.remove(0).to_string()), token_type: TokenType::CloseBracket });
-            } else if src[0] == ',' {
-                token_output.push(Token { value: Some(src.remove(0).to_string()), token_type: TokenType::Comma });
-            } else if src[0] == '.' {
-                token_output.push(Token { value: Some(src.remove(0).to_string()), token_type: TokenType::Dot });
-            } else if src[0] == ':' {
-                token_output.push(Token { value: Some(src.remove(0).to_string()), token_type: TokenType::Colon });
-            } else if src[0] == '+' || src[0] == '-' ||
-                      src[0] == '*' || src[0] == '/' ||
-                      src[0] == '%' {
-                token_output.push(Token { value: Some(src.remove(0).to_string()), token_type: TokenType::BinaryOperator });
-            } else if src[0] == '=' {
-                token_output.push(Token { value: Some(src.remove(0).to_string()), token_type: TokenType::Equals });
-            } else if src[0] == '<' {
-                token_output.push(Token { value: Some(src.remove(0).to_string()), token_type: TokenType::LeftAngleBracket });
-            } else if src[0] == '>' {
-                token_output.push(Token { value: Some(src.remove(0).to_string()), token_type: TokenType::RightAngleBracket });
-            } else if src[0] == '!' {
-                token_output.push(Token { value: Some(src.remove(0).to_string()), token_type: TokenType::Bang });
-            } else if src[0] == ';' {
-                token_output.push(Token { value: Some(src.remove(0).to_string()), token_type: TokenType::Semicolon });
-            } else if src[0] == '"' {
-                src.remove(0);
+
+            let (line, column) = (self.line, self.column);
+            let mut token = if let Some(token_type) = self.at_double_operator(current)? {
+                self.double(token_type)
+            } else if current == '(' {
+                self.single(TokenType::OpenParen)
+            } else if current == ')' {
+                self.single(TokenType::CloseParen)
+            } else if current == '{' {
+                self.single(TokenType::OpenBrace)
+            } else if current == '}' {
+                self.single(TokenType::CloseBrace)
+            } else if current == '[' {
+                self.single(TokenType::OpenBracket)
+            } else if current == ']' {
+                self.single(TokenType::CloseBracket)
+            } else if current == ',' {
+                self.single(TokenType::Comma)
+            } else if current == '.' {
+                self.single(TokenType::Dot)
+            } else if current == ':' {
+                self.single(TokenType::Colon)
+            } else if current == '+' || current == '-' ||
+                      current == '*' || current == '/' ||
+                      current == '%' {
+                self.single(TokenType::BinaryOperator)
+            } else if current == '=' {
+                self.single(TokenType::Equals)
+            } else if current == '<' {
+                self.single(TokenType::LeftAngleBracket)
+            } else if current == '>' {
+                self.single(TokenType::RightAngleBracket)
+            } else if current == '!' {
+                self.single(TokenType::Bang)
+            } else if current == '?' {
+                self.single(TokenType::Question)
+            } else if current == ';' {
+                self.single(TokenType::Semicolon)
+            } else if current == '"' {
+                self.advance();
 
                 let mut escaped = false;
                 let mut string = String::new();
-                while src.len() > 0 && (src[0] != '"' || escaped) {
-                    if src[0] == '\\' && !escaped {
+                loop {
+                    let c = match self.at()? {
+                        Some(c) => c,
+                        None => return Err(ParseError::incomplete("Unterminated string literal.").at(line, column, 1))
+                    };
+                    if c == '"' && !escaped {
+                        break;
+                    }
+
+                    if c == '\\' && !escaped {
                         escaped = true;
-                        src.remove(0);
+                        self.advance();
                     } else if escaped {
-                        match src[0] {
-                            '\\' => string.push(src.remove(0)),
-                            '\"' => string.push(src.remove(0)),
-                            '\'' => string.push(src.remove(0)),
+                        match c {
+                            '\\' => string.push(self.advance()),
+                            '\"' => string.push(self.advance()),
+                            '\'' => string.push(self.advance()),
                             'n' => {
-                                src.remove(0);
+                                self.advance();
                                 string.push('\n');
                             },
                             't' => {
-                                src.remove(0);
+                                self.advance();
                                 string.push('\t')
                             },
-                            _ => fatal_error("Unexpected escaped token.")
+                            _ => return Err(ParseError::syntax(&format!("Unexpected escaped token ('{}').", c)).at(self.line, self.column - 1, 2))
                         };
                         escaped = false;
                     } else {
-                        string.push(src.remove(0));
+                        string.push(self.advance());
                     }
                 }
 
-                src.remove(0);
-
-                token_output.push(Token {
-                    value: Some(string),
-                    token_type: TokenType::String
-                });
-            } else { 
-                // Build number
-                if src[0].is_numeric() {
-                    let mut num = String::new();
-                    
-                    while src.len() > 0 && (src[0].is_numeric() || src[0] == '.') {
-                        num += &src.remove(0).to_string();
-                    }
+                self.advance();
 
-                    token_output.push(Token { value: Some(num), token_type: TokenType::Number });
-                } else if is_valid_ident_char_start(src[0]) {
-                    let mut identifier = String::new();
-                    
-                    while src.len() > 0 && (is_valid_ident_char(src[0])) {
-                        identifier += &src.remove(0).to_string();
-                    }
+                Token::new(Some(string), TokenType::String)
+            } else if current.is_numeric() {
+                let num = self.take_while(|c| c.is_numeric() || c == '.')?;
 
-                    // Check for reserved keyword
-                    if let Some(token_type) = self.get_keywords().get(&*identifier) {
-                        token_output.push(Token { value: Some(identifier), token_type: *token_type });
-                    } else {
-                        token_output.push(Token { value: Some(identifier), token_type: TokenType::Identifier });
-                    }
-                } else if is_skippable(src[0]) {
-                    src.remove(0);
+                Token::new(Some(num), TokenType::Number)
+            } else if is_valid_ident_char_start(current) {
+                let identifier = self.take_while(is_valid_ident_char)?;
+
+                // Check for reserved keyword
+                if let Some(token_type) = get_keyword(&self.extra_keywords, &identifier) {
+                    Token::new(Some(identifier), token_type)
                 } else {
-                    fatal_error(&format!("Unknown character found ('{}').", src[0]));
+                    Token::new(Some(identifier), TokenType::Identifier)
                 }
+            } else {
+                return Err(ParseError::syntax(&format!("Unknown character found ('{}').", current)).at(line, column, 1));
+            };
 
-            }
+            token.line = line;
+            token.column = column;
+            return Ok(Some(token));
+        }
+    }
+}
+
+impl<I: Iterator<Item = io::Result<char>>> Iterator for TokenStream<I> {
+    type Item = Result<Token, ParseError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.finished {
+            return None;
         }
 
-        token_output.push(Token { value: Some(String::from("EndOfFile")), token_type: TokenType::EOF });
-        token_output
+        match self.next_token() {
+            Ok(Some(mut token)) => {
+                token.leading_comments = std::mem::take(&mut self.comments);
+                self.newline_since_token = false;
+                Some(Ok(token))
+            },
+            Ok(None) => {
+                self.finished = true;
+                let mut token = Token::new(Some(String::from("EndOfFile")), TokenType::EOF);
+                token.leading_comments = std::mem::take(&mut self.comments);
+                token.line = self.line;
+                token.column = self.column;
+                Some(Ok(token))
+            },
+            Err(e) => {
+                self.finished = true;
+                Some(Err(e))
+            }
+        }
     }
-}
\ No newline at end of file
+}
@@ -1,3 +1,9 @@
+pub mod analysis;
 pub mod ast;
+pub mod compiled;
+pub mod errors;
+pub mod formatter;
 pub mod lexer;
-pub mod parser;
\ No newline at end of file
+pub mod parser;
+pub mod symbol;
+pub mod tree;
\ No newline at end of file
@@ -1,301 +1,538 @@
 use crate::frontend::ast::{
-    AssignmentExpr, BinaryExpr, CallExpr, ComparativeExpr, Expr, ExprWrapper, FunctionDeclaration, Identifier, NodeType, NumericLiteral, ObjectLiteral, Program, Property, Stmt, StmtWrapper, VarDeclaration
+    AssignmentExpr, Ast, BinaryExpr, BinaryOp, BodyId, CompareOp, CallExpr, ComparativeExpr, Expr, ExprId, FunctionDeclaration, Identifier, NumericLiteral, ObjectLiteral, Program, Property, Stmt, StmtWrapper, VarDeclaration
 };
 use crate::frontend::lexer::{Tokenizer, Token, TokenType};
+use crate::frontend::analysis::analyze;
+use crate::frontend::errors::ParseError;
+use crate::frontend::symbol::Symbol;
 use crate::*;
+use std::io::Read;
 
 
-use super::ast::{Body, ForStmt, IfStmt, ListLiteral, MemberExpr, ReturnStmt, StringLiteral, WhileStmt};
+use super::ast::{Body, ForStmt, IfStmt, ListLiteral, MemberExpr, OuterScopeStmt, ReturnStmt, StringLiteral, UnsetStmt, WhileStmt};
+
+// How many expressions / bodies can be nested inside each other before parsing gives up,
+// this keeps deeply nested input from overflowing the stack
+pub const DEFAULT_MAX_DEPTH: usize = 256;
 
 pub struct Parser {
-    pub tokens: Vec<Token>
+    pub tokens: Vec<Token>,
+    // Index of the current token, the ones before it have been eaten
+    position: usize,
+    pub tokenizer: Tokenizer,
+    pub max_depth: usize,
+    // Strict mode warns about likely mistakes found by analysis::analyze after parsing
+    pub strict: bool,
+    depth: usize,
+    // Nodes of the program being parsed
+    ast: Ast
+}
+
+impl Default for Parser {
+    fn default() -> Self {
+        Parser::new()
+    }
 }
 
 impl Parser {
+    pub fn new() -> Self {
+        Parser::with_max_depth(DEFAULT_MAX_DEPTH)
+    }
+
+    pub fn with_max_depth(max_depth: usize) -> Self {
+        Parser {
+            tokens: vec![],
+            position: 0,
+            tokenizer: Tokenizer::new(),
+            max_depth,
+            strict: false,
+            depth: 0,
+            ast: Ast::new()
+        }
+    }
+
     pub fn produce_ast(&mut self, source_code: String) -> Program {
-        self.tokens = Tokenizer {}.tokenize(source_code);
+        match self.parse(source_code) {
+            Ok(program) => program,
+            Err(e) => fatal_error(&e.to_string())
+        }
+    }
 
-        let mut body = Vec::new();
+    // Same as produce_ast, but hands back errors instead of exiting. Errors caused by
+    // running out of tokens are reported as incomplete so callers can ask for more input.
+    pub fn parse(&mut self, source_code: String) -> Result<Program, ParseError> {
+        self.tokens = self.tokenizer.try_tokenize(source_code)?;
+        self.parse_tokens()
+    }
+
+    pub fn parse_reader<R: Read>(&mut self, reader: R) -> Result<Program, ParseError> {
+        self.tokens = self.tokenizer.tokenize_reader(reader).collect::<Result<Vec<Token>, ParseError>>()?;
+        self.parse_tokens()
+    }
+
+    // Parses without building a program to run, collecting every error instead of stopping at the first
+    pub fn check(&mut self, source_code: String) -> Result<(), Vec<ParseError>> {
+        self.tokens = self.tokenizer.try_tokenize(source_code).map_err(|e| vec![e])?;
+        self.position = 0;
+        self.depth = 0;
+        self.ast = Ast::new();
+
+        let mut errors = Vec::new();
 
         while self.not_eof() {
-            let stmt = self.parse_stmt();
-            if let Some(v) = stmt {
-                body.push(v);
+            let position = self.position;
+
+            if let Err(e) = self.parse_stmt() {
+                let incomplete = e.is_incomplete();
+                errors.push(e);
+                if incomplete {
+                    break;
+                }
+
+                self.depth = 0;
+                if self.position == position {
+                    self.eat();
+                }
+                self.synchronize();
             }
         }
 
-        let body = Body::new(body);
-
-        Program {
-            kind: NodeType::Program,
-            body,
+        self.ast = Ast::new();
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(errors)
         }
     }
 
-    fn at_comparative_expr(&self) -> Option<usize> {
-        let token1 = self.at().get_token_type();
-        
-        if token1 == TokenType::EOF {
-            return None;
+    // Skips to the start of the next statement after an error
+    fn synchronize(&mut self) {
+        while self.not_eof() {
+            match self.at().get_token_type() {
+                TokenType::Semicolon => {
+                    self.eat();
+                    return;
+                },
+                TokenType::Var | TokenType::Const | TokenType::Unset | TokenType::Global | TokenType::Nonlocal | TokenType::Function | TokenType::Return |
+                TokenType::If | TokenType::While | TokenType::For => return,
+                _ => {
+                    self.eat();
+                }
+            }
         }
+    }
 
-        let token2 = self.look_ahead(1).get_token_type();
+    fn parse_tokens(&mut self) -> Result<Program, ParseError> {
+        self.position = 0;
+        self.depth = 0;
+        self.ast = Ast::new();
 
-        // ==
-        if token1 == TokenType::Equals && token2 == TokenType::Equals {
-            return Some(2);
-        }
-        // >=
-        if token1 == TokenType::RightAngleBracket && token2 == TokenType::Equals {
-            return Some(2);
-        }
-        // <=
-        if token1 == TokenType::LeftAngleBracket && token2 == TokenType::Equals {
-            return Some(2);
+        let mut body = Vec::new();
+
+        while self.not_eof() {
+            let stmt = self.parse_stmt()?;
+            if let Some(v) = stmt {
+                body.push(v);
+            }
         }
-        // !=
-        if token1 == TokenType::Bang && token2 == TokenType::Equals {
-            return Some(2);
+
+        let mut ast = std::mem::take(&mut self.ast);
+        let body = ast.alloc_body(Body::new(body));
+
+        let program = Program {
+            ast,
+            body
+        };
+
+        if self.strict {
+            for warning in analyze(&program) {
+                warn(&warning.to_string());
+            }
         }
-        // <
-        if token1 == TokenType::LeftAngleBracket {
-            return Some(1);
+
+        Ok(program)
+    }
+
+    fn at_comparative_operator(&self) -> Option<CompareOp> {
+        match self.at().get_token_type() {
+            TokenType::DoubleEquals => Some(CompareOp::Equal),
+            TokenType::BangEquals => Some(CompareOp::NotEqual),
+            TokenType::TripleEquals => Some(CompareOp::StrictEqual),
+            TokenType::BangDoubleEquals => Some(CompareOp::StrictNotEqual),
+            TokenType::LeftAngleBracket => Some(CompareOp::LessThan),
+            TokenType::RightAngleBracket => Some(CompareOp::GreaterThan),
+            TokenType::LeftAngleBracketEquals => Some(CompareOp::LessThanOrEqual),
+            TokenType::RightAngleBracketEquals => Some(CompareOp::GreaterThanOrEqual),
+            _ => None
         }
-        // >
-        if token1 == TokenType::RightAngleBracket {
-            return Some(1);
+    }
+
+    // Returns the binary operator at the current token if it is one of `operators`
+    fn at_binary_operator(&self, operators: &[BinaryOp]) -> Option<BinaryOp> {
+        if self.at().get_token_type() != TokenType::BinaryOperator {
+            return None;
         }
 
-        None
+        BinaryOp::from_symbol(self.at().value.as_ref().unwrap()).filter(|op| operators.contains(op))
     }
 
     fn at(&self) -> &Token {
-        &self.tokens[0]
+        &self.tokens[self.position]
     }
 
-    fn look_ahead(&self, amount: usize) -> &Token {
-        &self.tokens[amount]
+    // The tokens from the current one on
+    fn remaining(&self) -> &[Token] {
+        &self.tokens[self.position..]
     }
 
     fn eat(&mut self) -> Token {
-        self.tokens.remove(0)
+        // Never consume the EOF token, so incomplete input can't run off the end
+        if self.position == self.tokens.len() - 1 {
+            return self.at().clone();
+        }
+        // Eaten tokens are never looked at again, so this leaves an empty one in their place
+        // rather than moving every token after it along
+        self.position += 1;
+        std::mem::replace(&mut self.tokens[self.position - 1], Token::new(None, TokenType::EOF))
     }
 
-    fn eat_expect(&mut self, token_type: TokenType, error_msg: &str, level: LoggingLevel) -> Token {
+    fn eat_expect(&mut self, token_type: TokenType, error_msg: &str, level: LoggingLevel) -> Result<Token, ParseError> {
         if self.at().get_token_type() != token_type {
+            if level == LoggingLevel::Fatal {
+                return Err(self.error(error_msg).expected(token_type.describe()));
+            }
+
+            // Only reported, so it's a syntax problem even when the input ends here
+            let e = ParseError::syntax(error_msg).found(self.at()).expected(token_type.describe());
             match level {
-                LoggingLevel::Info => info(&format!("Parser Error:\n{} {:?}.\nExpecting {:?}", error_msg, self.at(), token_type)),
-                LoggingLevel::Warn => warn(&format!("Parser Error:\n{} {:?}.\nExpecting {:?}", error_msg, self.at(), token_type)),
-                LoggingLevel::Error => error(&format!("Parser Error:\n{} {:?}.\nExpecting {:?}", error_msg, self.at(), token_type)),
-                LoggingLevel::Fatal => fatal_error(&format!("Parser Error:\n{} {:?}.\nExpecting {:?}", error_msg, self.at(), token_type))
+                LoggingLevel::Info => info(&e.to_string()),
+                LoggingLevel::Warn => warn(&e.to_string()),
+                _ => error(&e.to_string())
             };
-            self.at().clone()
+            Ok(self.at().clone())
         } else {
-            self.eat()
+            Ok(self.eat())
+        }
+    }
+
+    // An error at the current token
+    fn error(&self, message: &str) -> ParseError {
+        let error = if self.not_eof() {
+            ParseError::syntax(message)
+        } else {
+            ParseError::incomplete(message)
+        };
+        error.found(self.at())
+    }
+
+    fn enter_nested(&mut self) -> Result<(), ParseError> {
+        self.depth += 1;
+        if self.depth > self.max_depth {
+            return Err(ParseError::syntax(&format!("Expression too deeply nested (maximum depth is {}).", self.max_depth)).found(self.at()));
         }
+        Ok(())
+    }
+
+    fn leave_nested(&mut self) {
+        self.depth -= 1;
+    }
+
+    // Operator and call chains like `1 + 1 + 1` or `f()()()` nest each link inside the one before
+    // it, so each counts as a level until the chain ends
+    fn leave_chain(&mut self, links: usize) {
+        self.depth -= links;
     }
 
     fn not_eof(&self) -> bool {
         self.at().get_token_type() != TokenType::EOF
     }
 
-    fn parse_stmt(&mut self) -> Option<StmtWrapper> {
-        match self.at().get_token_type() {
-            TokenType::Var => Some(self.parse_var_declaration()),
-            TokenType::Const => Some(self.parse_var_declaration()),
-            TokenType::Function => Some(self.parse_function_declaration()),
-            TokenType::Return => Some(self.parse_return()),
-            TokenType::If => Some(self.parse_if()),
-            TokenType::While => Some(self.parse_while()),
-            TokenType::For => Some(self.parse_for()),
+    fn parse_stmt(&mut self) -> Result<Option<StmtWrapper>, ParseError> {
+        let line = self.at().line;
+        let mut stmt = match self.at().get_token_type() {
+            TokenType::Var => Some(self.parse_var_declaration()?),
+            TokenType::Const => Some(self.parse_var_declaration()?),
+            TokenType::Unset => Some(self.parse_unset()?),
+            TokenType::Global | TokenType::Nonlocal => Some(self.parse_outer_scope()?),
+            // Without a name it's a function expression, like an immediately called `function () { ... }()`
+            TokenType::Function if self.remaining().get(1).is_some_and(|token| token.get_token_type() == TokenType::Identifier) => {
+                Some(StmtWrapper::new(Stmt::FunctionDeclaration(self.parse_function_declaration()?)))
+            },
+            TokenType::Return => Some(self.parse_return()?),
+            TokenType::If => {
+                let if_stmt = self.parse_if()?;
+                Some(StmtWrapper::new(Stmt::Expr(self.ast.alloc(Expr::If(if_stmt)))))
+            },
+            TokenType::While => Some(self.parse_while()?),
+            TokenType::For => Some(self.parse_for()?),
             TokenType::Semicolon => {
                 self.eat();
                 if self.not_eof() && self.at().get_token_type() != TokenType::CloseBrace {
-                    self.parse_stmt()
+                    self.parse_stmt()?
                 } else {
                     None
                 }
             },
-            TokenType::OpenBrace => Some(StmtWrapper::new(Box::new(self.parse_body()))),
-            _ => Some(self.parse_expr().to_stmt_from_expr())
+            TokenType::OpenBrace => {
+                let body = self.parse_body()?;
+                Some(StmtWrapper::new(Stmt::Expr(self.ast.alloc(Expr::Body(body)))))
+            },
+            _ => Some(StmtWrapper::new(Stmt::Expr(self.parse_expr()?)))
+        };
+
+        // Statements after a stray semicolon already have their own line
+        if let Some(stmt) = stmt.as_mut().filter(|stmt| stmt.line == 0) {
+            stmt.line = line;
         }
+        Ok(stmt)
     }
 
-    fn parse_body(&mut self) -> Body {
-        self.eat_expect(TokenType::OpenBrace, "Expected statement body", LoggingLevel::Fatal);
+    fn parse_body(&mut self) -> Result<BodyId, ParseError> {
+        self.enter_nested()?;
+        self.eat_expect(TokenType::OpenBrace, "Expected statement body", LoggingLevel::Fatal)?;
 
         let mut body = vec![];
         while self.at().get_token_type() != TokenType::CloseBrace && self.not_eof() {
-            if let Some(v) = self.parse_stmt() {
+            if let Some(v) = self.parse_stmt()? {
                 body.push(v);
             } else {
                 break
             }
         }
 
-        self.eat_expect(TokenType::CloseBrace, "Expected closing brace in body", LoggingLevel::Fatal);
+        self.eat_expect(TokenType::CloseBrace, "Expected closing brace in body", LoggingLevel::Fatal)?;
+        self.leave_nested();
 
-        Body::new(body)
+        Ok(self.ast.alloc_body(Body::new(body)))
     }
 
-    fn parse_if(&mut self) -> StmtWrapper {
+    fn parse_if(&mut self) -> Result<IfStmt, ParseError> {
         self.eat();
 
-        let condition = self.parse_comparative_expr();
+        let condition = self.parse_comparative_expr()?;
         
-        let body = self.parse_body();
+        let body = self.parse_body()?;
 
         let mut else_stmt = None;
         // Check for else / else if
         if self.at().get_token_type() == TokenType::Else {
             self.eat();
             if self.at().get_token_type() == TokenType::OpenBrace {
-                else_stmt = Some(self.parse_body())
+                else_stmt = Some(self.parse_body()?)
             } else if self.at().get_token_type() == TokenType::If {
-                let if_stmt = self.parse_if();
-                else_stmt = Some(Body::new(vec![if_stmt]));
+                let if_stmt = self.parse_if()?;
+                let if_stmt = StmtWrapper::new(Stmt::Expr(self.ast.alloc(Expr::If(if_stmt))));
+                else_stmt = Some(self.ast.alloc_body(Body::new(vec![if_stmt])));
+            } else {
+                return Err(self.error("Expected body or `if` after `else`"));
             }
         }
 
-        StmtWrapper::new(Box::new(IfStmt {
-            kind: NodeType::If,
+        Ok(IfStmt {
             condition,
             body,
             else_stmt
-        }))
+        })
     }
 
-    fn parse_for(&mut self) -> StmtWrapper {
+    fn parse_for(&mut self) -> Result<StmtWrapper, ParseError> {
         self.eat();
         
-        let variable = self.parse_expr();
+        let variable = self.parse_expr()?;
 
-        self.eat_expect(TokenType::In, "Expected `in` in for statement", LoggingLevel::Fatal);
+        self.eat_expect(TokenType::In, "Expected `in` in for statement", LoggingLevel::Fatal)?;
 
-        let iterable = self.parse_expr();
+        let iterable = self.parse_expr()?;
 
-        let body = self.parse_body();
+        let body = self.parse_body()?;
 
-        StmtWrapper::new(Box::new(ForStmt {
-            kind: NodeType::For,
+        Ok(StmtWrapper::new(Stmt::For(ForStmt {
             iterable,
             variable,
             body
-        }))
+        })))
     }
 
-    fn parse_while(&mut self) -> StmtWrapper {
+    fn parse_while(&mut self) -> Result<StmtWrapper, ParseError> {
         self.eat();
 
-        let condition = self.parse_expr();
+        let condition = self.parse_expr()?;
 
-        let body = self.parse_body();
+        let body = self.parse_body()?;
 
-        StmtWrapper::new(Box::new(WhileStmt {
-            kind: NodeType::While,
+        Ok(StmtWrapper::new(Stmt::While(WhileStmt {
             condition,
             body
-        }))
+        })))
     }
 
-    fn parse_return(&mut self) -> StmtWrapper {
+    fn parse_return(&mut self) -> Result<StmtWrapper, ParseError> {
         self.eat();
 
-        let value = self.parse_expr();
+        let value = self.parse_expr()?;
 
-        self.eat_expect(TokenType::Semicolon, "Expected semicolon after return statement", LoggingLevel::Fatal);
+        self.eat_expect(TokenType::Semicolon, "Expected semicolon after return statement", LoggingLevel::Fatal)?;
 
-        return StmtWrapper::new(Box::new(ReturnStmt {
-            kind: NodeType::Return,
+        Ok(StmtWrapper::new(Stmt::Return(ReturnStmt {
             value
-        }));
+        })))
     }
 
-    fn parse_function_declaration(&mut self) -> StmtWrapper {
+    fn parse_function_declaration(&mut self) -> Result<FunctionDeclaration, ParseError> {
         self.eat();
 
-        let name = self.eat_expect(TokenType::Identifier, "Unexpected token after function declaration", LoggingLevel::Fatal).value.unwrap();
+        let name = Symbol::intern(&self.eat_expect(TokenType::Identifier, "Unexpected token after function declaration", LoggingLevel::Fatal)?.value.unwrap());
+
+        let (params, required) = self.parse_parameters()?;
+        let body = self.parse_body()?;
+
+        Ok(FunctionDeclaration { 
+            parameters: params,
+            required,
+            name,
+            body,
+        })
+    }
+
+    // function [IDENTIFIER] ( PARAMETERS ) BODY where a value goes. The name is optional and
+    // only shows up in errors and traces, nothing gets declared
+    fn parse_function_expr(&mut self) -> Result<ExprId, ParseError> {
+        self.eat();
+
+        let name = match self.at().get_token_type() {
+            TokenType::Identifier => Symbol::intern(&self.eat().value.unwrap()),
+            _ => Symbol::intern("<anonymous>")
+        };
+
+        let (parameters, required) = self.parse_parameters()?;
+        let body = self.parse_body()?;
+
+        Ok(self.ast.alloc(Expr::Function(FunctionDeclaration {
+            parameters,
+            required,
+            name,
+            body
+        })))
+    }
+
+    // ( [ IDENTIFIER [?] { , IDENTIFIER [?] } ] ), optional parameters have to come last. Gives the
+    // names and how many of them are required
+    fn parse_parameters(&mut self) -> Result<(Vec<Symbol>, usize), ParseError> {
+        self.eat_expect(TokenType::OpenParen, "Expected open parenthesis after function name", LoggingLevel::Fatal)?;
 
-        let args = self.parse_args();
         let mut params = Vec::new();
+        let mut required = 0;
+        while self.at().get_token_type() != TokenType::CloseParen {
+            if !params.is_empty() {
+                self.eat_expect(TokenType::Comma, "Expected comma between parameters", LoggingLevel::Fatal)?;
+            }
 
-        for arg in args.into_iter() {
-            if arg.get_kind() == NodeType::Identifier {
-                params.push(arg.as_any().downcast_ref::<Identifier>().expect("Failed to downcast to Identifier.").clone().symbol);
+            let param = self.eat_expect(TokenType::Identifier, "Expected identifier inside function declaration", LoggingLevel::Fatal)?;
+            if self.at().get_token_type() == TokenType::Question {
+                self.eat();
+            } else if required < params.len() {
+                return Err(ParseError::syntax(&format!("Required parameter {} cannot follow an optional one", param.value.as_ref().unwrap())).found(&param));
             } else {
-                fatal_error("Expected identifier inside function declaration");
+                required += 1;
             }
+            params.push(Symbol::intern(param.value.as_ref().unwrap()));
         }
-        
-        let body = self.parse_body();
 
-        return StmtWrapper::new(Box::new(FunctionDeclaration { 
-            kind: NodeType::FunctionDeclaration,
-            parameters: params,
-            name,
-            body,
-        }));
+        self.eat_expect(TokenType::CloseParen, "Expected closing parenthesis after parameters", LoggingLevel::Fatal)?;
+        Ok((params, required))
+    }
+
+    // UNSET IDENTIFIER;
+    fn parse_unset(&mut self) -> Result<StmtWrapper, ParseError> {
+        self.eat();
+
+        let identifier = Symbol::intern(&self.eat_expect(TokenType::Identifier, "Expected identifier after unset.", LoggingLevel::Fatal)?.value.unwrap());
+
+        self.eat_expect(TokenType::Semicolon, "Expected semicolon after unset statement (automatically inserted).", LoggingLevel::Error)?;
+
+        Ok(StmtWrapper::new(Stmt::Unset(UnsetStmt {
+            identifier
+        })))
+    }
+
+    // ( GLOBAL | NONLOCAL ) IDENTIFIER;
+    fn parse_outer_scope(&mut self) -> Result<StmtWrapper, ParseError> {
+        let global = self.eat().get_token_type() == TokenType::Global;
+        let keyword = if global { "global" } else { "nonlocal" };
+
+        let identifier = Symbol::intern(&self.eat_expect(TokenType::Identifier, &format!("Expected identifier after {}.", keyword), LoggingLevel::Fatal)?.value.unwrap());
+
+        self.eat_expect(TokenType::Semicolon, &format!("Expected semicolon after {} statement (automatically inserted).", keyword), LoggingLevel::Error)?;
+
+        let outer_scope_stmt = OuterScopeStmt {
+            identifier
+        };
+        Ok(StmtWrapper::new(if global { Stmt::Global(outer_scope_stmt) } else { Stmt::Nonlocal(outer_scope_stmt) }))
     }
 
     // VAR IDENTIFIER;
     // ( CONST | VAR ) IDENTIFIER = EXPR;
-    fn parse_var_declaration(&mut self) -> StmtWrapper {
+    fn parse_var_declaration(&mut self) -> Result<StmtWrapper, ParseError> {
         let is_constant = self.eat().get_token_type() == TokenType::Const;
-        let identifier = self.eat_expect(TokenType::Identifier, "Error in var declaration.", LoggingLevel::Fatal).value.unwrap();
+        let identifier = Symbol::intern(&self.eat_expect(TokenType::Identifier, "Error in var declaration.", LoggingLevel::Fatal)?.value.unwrap());
 
         if self.at().get_token_type() == TokenType::Semicolon {
-            self.eat();
+            let semicolon = self.eat();
             if is_constant {
-                fatal_error("Must assign value to const expression. No value provided.");
+                return Err(ParseError::syntax("Must assign value to const expression. No value provided.").found(&semicolon));
             }
 
-            return StmtWrapper::new(Box::new(VarDeclaration {
-                kind: NodeType::VarDeclaration,
+            return Ok(StmtWrapper::new(Stmt::VarDeclaration(VarDeclaration {
                 constant: is_constant,
                 identifier,
-                value: Some(ExprWrapper::new(Box::new(Identifier { kind: NodeType::Identifier, symbol: String::from("null") })))
-            }));
+                value: Some(self.ast.alloc(Expr::Identifier(Identifier { symbol: Symbol::intern("null") })))
+            })));
         }
 
-        self.eat_expect(TokenType::Equals, "Expected equals token in var declaration.", LoggingLevel::Fatal);
+        self.eat_expect(TokenType::Equals, "Expected equals token in var declaration.", LoggingLevel::Fatal)?;
 
         let declaration = VarDeclaration { 
-            kind: NodeType::VarDeclaration,
             constant: is_constant,
             identifier,
-            value: Some(self.parse_expr())
+            value: Some(self.parse_expr()?)
         };
 
-        self.eat_expect(TokenType::Semicolon, "Expected semicolon after variable declaration (automatically inserted).", LoggingLevel::Error);
+        self.eat_expect(TokenType::Semicolon, "Expected semicolon after variable declaration (automatically inserted).", LoggingLevel::Error)?;
 
-        StmtWrapper::new(Box::new(declaration))
+        Ok(StmtWrapper::new(Stmt::VarDeclaration(declaration)))
     }
 
-    fn parse_expr(&mut self) -> ExprWrapper {
-        self.parse_assignment_expr()
+    fn parse_expr(&mut self) -> Result<ExprId, ParseError> {
+        self.enter_nested()?;
+        let expr = self.parse_assignment_expr()?;
+        self.leave_nested();
+
+        Ok(expr)
     }
 
-    fn parse_assignment_expr(&mut self) -> ExprWrapper {
-        let left = self.parse_comparative_expr();
+    fn parse_assignment_expr(&mut self) -> Result<ExprId, ParseError> {
+        let left = self.parse_comparative_expr()?;
         
         if self.at().get_token_type() == TokenType::Equals {
             self.eat();
-            let value = self.parse_assignment_expr();
+            // `a = b = c` chains nest like parentheses do
+            self.enter_nested()?;
+            let value = self.parse_assignment_expr()?;
+            self.leave_nested();
 
             if self.at().get_token_type() == TokenType::Semicolon {
                 self.eat();
             }
-            return ExprWrapper::new(Box::new(AssignmentExpr {
-                kind: NodeType::AssignmentExpr,
+            return Ok(self.ast.alloc(Expr::Assignment(AssignmentExpr {
                 assignee: left,
-                value: value
-            }));
+                value
+            })));
         }
 
-        left
+        Ok(left)
     }
 
-    fn parse_list_expr(&mut self) -> ExprWrapper {
+    fn parse_list_expr(&mut self) -> Result<ExprId, ParseError> {
         if self.at().get_token_type() != TokenType::OpenBracket {
             return self.parse_object_expr();
         }
@@ -305,7 +542,7 @@ impl Parser {
         let mut elements = Vec::new();
 
         while self.not_eof() && self.at().get_token_type() != TokenType::CloseBracket {
-            let value = self.parse_expr();
+            let value = self.parse_expr()?;
             elements.push(value);
 
             if self.at().get_token_type() == TokenType::Comma {
@@ -313,52 +550,58 @@ impl Parser {
             }
         }
 
-        self.eat();
+        self.eat_expect(TokenType::CloseBracket, "List literal missing closing bracket.", LoggingLevel::Fatal)?;
 
-        ExprWrapper::new(Box::new(ListLiteral {
-            kind: NodeType::List,
+        Ok(self.ast.alloc(Expr::List(ListLiteral {
             elements
-        }))
+        })))
     }
 
-    fn parse_object_expr(&mut self) -> ExprWrapper {
+    fn parse_object_expr(&mut self) -> Result<ExprId, ParseError> {
         if self.at().get_token_type() != TokenType::OpenBrace {
             return self.parse_additive_expr();
         }
 
+        if opens_block(self.remaining()) {
+            let body = self.parse_body()?;
+            return Ok(self.ast.alloc(Expr::Body(body)));
+        }
+
         self.eat();
 
         let mut properties = Vec::new();
 
         while self.not_eof() && self.at().get_token_type() != TokenType::CloseBrace {
-            let key = self.eat_expect(TokenType::Identifier, "Unexpected token in object literal creation.", LoggingLevel::Fatal).value;
+            let key = self.eat_expect(TokenType::Identifier, "Unexpected token in object literal creation.", LoggingLevel::Fatal)?.value.map(|key| Symbol::intern(&key));
 
             if self.at().get_token_type() == TokenType::Comma {
                 self.eat();
-                properties.push(Property { kind: NodeType::Property, key, value: None });
+                properties.push(Property { key, value: None });
                 continue;
             }
             if self.at().get_token_type() == TokenType::CloseBrace {
-                properties.push(Property { kind: NodeType::Property, key, value: None });
+                properties.push(Property { key, value: None });
                 continue;
             }
             
-            self.eat_expect(TokenType::Colon, "Missing colon following identifier in object literal creation.", LoggingLevel::Fatal);
-            let value = self.parse_expr();
+            self.eat_expect(TokenType::Colon, "Missing colon following identifier in object literal creation.", LoggingLevel::Fatal)?;
+            let value = self.parse_expr()?;
 
-            properties.push(Property { kind: NodeType::Property, key, value: Some(value) });
+            properties.push(Property { key, value: Some(value) });
 
             if self.at().get_token_type() != TokenType::CloseBrace {
-                self.eat_expect(TokenType::Comma, "Object literal missing comma.", LoggingLevel::Fatal);
+                self.eat_expect(TokenType::Comma, "Object literal missing comma.", LoggingLevel::Fatal)?;
             }
         }
 
-        self.eat_expect(TokenType::CloseBrace, "Object literal missing closing brace.", LoggingLevel::Error);
+        if !self.not_eof() {
+            return Err(self.error("Object literal missing closing brace."));
+        }
+        self.eat_expect(TokenType::CloseBrace, "Object literal missing closing brace.", LoggingLevel::Error)?;
 
-        ExprWrapper::new(Box::new(ObjectLiteral {
-            kind: NodeType::Object,
+        Ok(self.ast.alloc(Expr::Object(ObjectLiteral {
             properties
-        }))
+        })))
     }
 
     // Prescidence Order
@@ -372,195 +615,184 @@ impl Parser {
     // UnaryExpr
     // PrimaryExpr
 
-    fn parse_primary_expr(&mut self) -> ExprWrapper {
+    fn parse_primary_expr(&mut self) -> Result<ExprId, ParseError> {
         let token = self.at();
 
-        match token.get_token_type() {
-            TokenType::Identifier => ExprWrapper::new(Box::new(Identifier { kind: NodeType::Identifier, symbol: self.eat().value.unwrap() })),
-            TokenType::BinaryOperator => {
-                if token.value.as_ref().unwrap() == "-" {
-                    self.eat();
-                    ExprWrapper::new(Box::new(NumericLiteral { kind: NodeType::NumericLiteral, value: (String::from("-") + &self.eat().value.unwrap()).parse().expect("Problem converting numeric literal") }))
-                } else {
-                    fatal_error(&format!("Unexpected token found during parsing: {:?}", self.at()));
+        let expr = match token.get_token_type() {
+            TokenType::Identifier => Expr::Identifier(Identifier { symbol: Symbol::intern(&self.eat().value.unwrap()) }),
+            TokenType::BinaryOperator if token.value.as_ref().unwrap() == "-" => {
+                self.eat();
+                if self.at().get_token_type() != TokenType::Number {
+                    return Err(self.error("Expected number after `-`"));
                 }
+                let number = String::from("-") + &self.eat().value.unwrap();
+                Expr::Number(NumericLiteral { value: self.parse_number(&number)? })
+            },
+            TokenType::Number => {
+                let number = self.eat().value.unwrap();
+                Expr::Number(NumericLiteral { value: self.parse_number(&number)? })
             },
-            TokenType::Number => ExprWrapper::new(Box::new(NumericLiteral { kind: NodeType::NumericLiteral, value: self.eat().value.unwrap().parse().expect("Problem converting numeric literal") })),
-            TokenType::String => ExprWrapper::new(Box::new(StringLiteral { kind: NodeType::String, string: self.eat().value.unwrap()})),
+            TokenType::String => Expr::String(StringLiteral { string: Symbol::intern(&self.eat().value.unwrap())}),
+            TokenType::If => Expr::If(self.parse_if()?),
+            TokenType::Function => return self.parse_function_expr(),
+            // Objects are only parsed where a whole value goes, so an operand's { is always a block
+            TokenType::OpenBrace => Expr::Body(self.parse_body()?),
             TokenType::OpenParen => {
                 self.eat();
-                let value = self.parse_expr();
-                self.eat_expect(TokenType::CloseParen, "Unexpected token found inside parenthesis.", LoggingLevel::Fatal);
-                value
+                let value = self.parse_expr()?;
+                self.eat_expect(TokenType::CloseParen, "Unexpected token found inside parenthesis.", LoggingLevel::Fatal)?;
+                return Ok(value);
             },
-            _ => fatal_error(&format!("Unexpected token found during parsing: {:?}", self.at()))
-        }
+            _ => return Err(self.error("Unexpected token found during parsing"))
+        };
+        Ok(self.ast.alloc(expr))
     }
 
-    fn parse_comparative_expr(&mut self) -> ExprWrapper {
-        let mut left = self.parse_list_expr();
-        
-        if !self.not_eof() && !self.at_comparative_expr().is_none() {
-            return left;
-        }
-
-        while self.at_comparative_expr().is_some() && self.not_eof() {
-            let mut operator = String::new();
+    fn parse_number(&self, number: &str) -> Result<f64, ParseError> {
+        number.parse().map_err(|_| ParseError::syntax(&format!("Problem converting numeric literal `{}`", number)))
+    }
 
-            for _ in 0..(self.at_comparative_expr().unwrap()) {
-                operator += &self.eat().value.unwrap()
-            }
+    fn parse_comparative_expr(&mut self) -> Result<ExprId, ParseError> {
+        let mut left = self.parse_list_expr()?;
+        let mut links = 0;
 
-            let right = self.parse_list_expr();
+        while let Some(operator) = self.at_comparative_operator() {
+            self.enter_nested()?;
+            links += 1;
+            self.eat();
+            let right = self.parse_list_expr()?;
 
-            left = ExprWrapper::new(Box::new(ComparativeExpr {
-                kind: NodeType::ComparativeExpr,
+            left = self.ast.alloc(Expr::Comparative(ComparativeExpr {
                 left,
                 right,
                 operator
             }));
         }
 
-        left
+        self.leave_chain(links);
+        Ok(left)
     }
 
-    fn parse_additive_expr(&mut self) -> ExprWrapper {
-        let mut left = self.parse_multiplicative_expr();
+    fn parse_additive_expr(&mut self) -> Result<ExprId, ParseError> {
+        let mut left = self.parse_multiplicative_expr()?;
+        let mut links = 0;
 
-        while self.at().value.clone().unwrap() == "+" || self.at().value.clone().unwrap() == "-" {
-            let operator = self.eat().value.unwrap();
-            let right = self.parse_multiplicative_expr();
+        while let Some(operator) = self.at_binary_operator(&[BinaryOp::Add, BinaryOp::Subtract]) {
+            self.enter_nested()?;
+            links += 1;
+            self.eat();
+            let right = self.parse_multiplicative_expr()?;
 
-            left = ExprWrapper::new(Box::new(BinaryExpr {
-                kind: NodeType::BinaryExpr,
+            left = self.ast.alloc(Expr::Binary(BinaryExpr {
                 left,
                 right,
                 operator
-            }))
+            }));
         }
 
-        left
+        self.leave_chain(links);
+        Ok(left)
     }
 
-    fn parse_multiplicative_expr(&mut self) -> ExprWrapper {
-        let mut left = self.parse_call_member_expr();
+    fn parse_multiplicative_expr(&mut self) -> Result<ExprId, ParseError> {
+        let mut left = self.parse_call_member_expr()?;
+        let mut links = 0;
 
-        while self.at().value.clone().unwrap() == "*" || self.at().value.clone().unwrap() == "/" || self.at().value.clone().unwrap() == "%" {
-            let operator = self.eat().value.unwrap();
-            let right = self.parse_call_member_expr();
+        while let Some(operator) = self.at_binary_operator(&[BinaryOp::Multiply, BinaryOp::Divide, BinaryOp::Modulo]) {
+            self.enter_nested()?;
+            links += 1;
+            self.eat();
+            let right = self.parse_call_member_expr()?;
 
-            left = ExprWrapper::new(Box::new(BinaryExpr {
-                kind: NodeType::BinaryExpr,
+            left = self.ast.alloc(Expr::Binary(BinaryExpr {
                 left,
                 right,
                 operator
-            }))
-        }
-
-        left
-    }
-
-    fn parse_call_member_expr(&mut self) -> ExprWrapper {
-        let member = self.parse_member_expr();
-        
-        if self.at().get_token_type() == TokenType::OpenParen {
-            return self.parse_call_expr(member);
+            }));
         }
 
-        member
+        self.leave_chain(links);
+        Ok(left)
     }
 
-    fn parse_call_expr(&mut self, caller: ExprWrapper) -> ExprWrapper {
-        let mut call_expr = CallExpr {
-            kind: NodeType::CallExpr,
-            caller,
-            args: self.parse_args()
-        };
+    // Calls, `.name` and `[index]` can follow any value and each other in any order, like
+    // `get_config().port`, `make_adder(1)(2)` or `handlers[0](event).result`
+    fn parse_call_member_expr(&mut self) -> Result<ExprId, ParseError> {
+        let mut expr = self.parse_primary_expr()?;
+        let mut links = 0;
 
-        if self.at().get_token_type() == TokenType::OpenParen {
-            call_expr = self.parse_call_expr(ExprWrapper::new(Box::new(call_expr))).as_any().downcast_ref::<CallExpr>().unwrap().clone();
+        loop {
+            if matches!(self.at().get_token_type(), TokenType::OpenParen | TokenType::Dot | TokenType::OpenBracket) {
+                self.enter_nested()?;
+                links += 1;
+            }
+            expr = match self.at().get_token_type() {
+                TokenType::OpenParen => {
+                    let args = self.parse_args()?;
+                    self.ast.alloc(Expr::Call(CallExpr {
+                        caller: expr,
+                        args
+                    }))
+                },
+                TokenType::Dot => {
+                    self.eat();
+                    let property = self.parse_primary_expr()?;
+                    self.ast.alloc(Expr::Member(MemberExpr {
+                        object: expr,
+                        property,
+                        computed: false
+                    }))
+                },
+                TokenType::OpenBracket => {
+                    self.eat();
+                    let property = self.parse_expr()?;
+                    self.eat_expect(TokenType::CloseBracket, "Expected closing bracket in computed member expression", LoggingLevel::Fatal)?;
+                    self.ast.alloc(Expr::Member(MemberExpr {
+                        object: expr,
+                        property,
+                        computed: true
+                    }))
+                },
+                _ => break
+            };
         }
 
-        ExprWrapper::new(Box::new(call_expr))
+        self.leave_chain(links);
+        Ok(expr)
     }
 
-    fn parse_args(&mut self) -> Vec<ExprWrapper> {
-        self.eat_expect(TokenType::OpenParen, "Expected open parenthesis when parsing call arguments", LoggingLevel::Fatal);
+    fn parse_args(&mut self) -> Result<Vec<ExprId>, ParseError> {
+        self.eat_expect(TokenType::OpenParen, "Expected open parenthesis when parsing call arguments", LoggingLevel::Fatal)?;
 
         let args = if self.at().get_token_type() == TokenType::CloseParen {
             Vec::new()
         } else {
-            self.parse_arguments_list()
+            self.parse_arguments_list()?
         };
 
-        self.eat_expect(TokenType::CloseParen, "Expected closing parenthesis when parsing call arguments", LoggingLevel::Fatal);
+        self.eat_expect(TokenType::CloseParen, "Expected closing parenthesis when parsing call arguments", LoggingLevel::Fatal)?;
 
-       return args;
+        Ok(args)
     }
 
-    fn parse_arguments_list(&mut self) -> Vec<ExprWrapper> {
-        let mut args = vec![self.parse_assignment_expr()];
+    fn parse_arguments_list(&mut self) -> Result<Vec<ExprId>, ParseError> {
+        let mut args = vec![self.parse_expr()?];
 
         while self.at().get_token_type() == TokenType::Comma && self.not_eof() {
             self.eat();
-            args.push(self.parse_assignment_expr());
+            args.push(self.parse_expr()?);
         }
 
-        return args;
+        Ok(args)
     }
 
-    fn parse_member_expr(&mut self) -> ExprWrapper {
-        if self.at().get_token_type() == TokenType::Identifier {
-            let object = self.parse_primary_expr();
-            let property;
-            let computed;
-
-            if self.at().get_token_type() == TokenType::Dot {
-                self.eat();
-                property = self.parse_primary_expr();
-                computed = false;
-            } 
-            else if self.at().get_token_type() == TokenType::OpenBracket {
-                self.eat();
-                property = self.parse_expr();
-                computed = true;
-                self.eat();
-            }
-            else {
-                return object;
-            }
-
-            let mut member_expr = ExprWrapper::new(Box::new(MemberExpr {
-                kind: NodeType::MemberExpr,
-                object,
-                property,
-                computed
-            }));
-
-            while self.at().get_token_type() == TokenType::Dot || self.at().get_token_type() == TokenType::OpenBracket {
-                if self.at().get_token_type() == TokenType::Dot {
-                    self.eat();
-                    member_expr = ExprWrapper::new(Box::new(MemberExpr {
-                        kind: NodeType::MemberExpr,
-                        object: member_expr,
-                        property: self.parse_primary_expr(),
-                        computed: false
-                    }));
-                } else {
-                    self.eat();
-                    member_expr = ExprWrapper::new(Box::new(MemberExpr {
-                        kind: NodeType::MemberExpr,
-                        object: member_expr,
-                        property: self.parse_expr(),
-                        computed: true
-                    }));
-                    self.eat();
-                }
-            }
+}
 
-            return member_expr;
-        }
+// Where a value goes, the { starting these tokens opens an object when it's empty or starts with
+// a key, `{}`, `{a: 1}`, `{a, b}`, and a block worth its last statement otherwise, `{ var a = 2; a * a }`
+pub fn opens_block(tokens: &[Token]) -> bool {
+    let kind = |index: usize| tokens.get(index).map(|token| token.get_token_type());
 
-        self.parse_primary_expr()
-    }
+    kind(1).is_some_and(|kind| kind != TokenType::CloseBrace) &&
+        !(kind(1) == Some(TokenType::Identifier) && matches!(kind(2), Some(TokenType::Colon | TokenType::Comma | TokenType::CloseBrace)))
 }
\ No newline at end of file
@@ -0,0 +1,148 @@
+use super::ast::{Ast, BodyId, Expr, ExprId, FunctionDeclaration, Program, Stmt};
+
+// A parsed program as an indented tree, one node per line with its children two spaces further
+// in. Meant for people reading it, --dump-ast and the REPL's :ast print it
+pub fn dump(program: &Program) -> String {
+    let mut tree = Tree { out: String::new(), ast: &program.ast };
+    tree.line(0, "Program");
+    tree.body(1, program.body);
+    tree.out
+}
+
+struct Tree<'a> {
+    out: String,
+    ast: &'a Ast
+}
+
+impl Tree<'_> {
+    fn line(&mut self, depth: usize, text: &str) {
+        self.out += &"  ".repeat(depth);
+        self.out += text;
+        self.out.push('\n');
+    }
+
+    fn body(&mut self, depth: usize, body: BodyId) {
+        let ast = self.ast;
+        for stmt in ast[body].statements() {
+            self.stmt(depth, &stmt.stmt);
+        }
+    }
+
+    // A labelled group of children, for nodes with more than one body or part
+    fn section(&mut self, depth: usize, label: &str, body: BodyId) {
+        self.line(depth, label);
+        self.body(depth + 1, body);
+    }
+
+    fn labelled(&mut self, depth: usize, label: &str, expr: ExprId) {
+        self.line(depth, label);
+        self.expr(depth + 1, expr);
+    }
+
+    fn function(&mut self, depth: usize, label: &str, function: &FunctionDeclaration) {
+        let parameters = function.parameters.iter().enumerate()
+            .map(|(index, parameter)| if index < function.required { parameter.to_string() } else { format!("{}?", parameter) })
+            .collect::<Vec<String>>();
+        self.line(depth, &format!("{} {}({})", label, function.name, parameters.join(", ")));
+        self.body(depth + 1, function.body);
+    }
+
+    fn stmt(&mut self, depth: usize, stmt: &Stmt) {
+        match stmt {
+            Stmt::VarDeclaration(var_declaration) => {
+                let keyword = if var_declaration.constant { "const" } else { "var" };
+                self.line(depth, &format!("VarDeclaration {} {}", keyword, var_declaration.identifier));
+                if let Some(value) = &var_declaration.value {
+                    self.expr(depth + 1, *value);
+                }
+            },
+            Stmt::Unset(unset_stmt) => self.line(depth, &format!("Unset {}", unset_stmt.identifier)),
+            Stmt::Global(outer_scope_stmt) => self.line(depth, &format!("Global {}", outer_scope_stmt.identifier)),
+            Stmt::Nonlocal(outer_scope_stmt) => self.line(depth, &format!("Nonlocal {}", outer_scope_stmt.identifier)),
+            Stmt::FunctionDeclaration(function) => self.function(depth, "FunctionDeclaration", function),
+            Stmt::Return(return_stmt) => {
+                self.line(depth, "Return");
+                self.expr(depth + 1, return_stmt.value);
+            },
+            Stmt::While(while_stmt) => {
+                self.line(depth, "While");
+                self.labelled(depth + 1, "condition", while_stmt.condition);
+                self.section(depth + 1, "body", while_stmt.body);
+            },
+            Stmt::For(for_stmt) => {
+                self.line(depth, "For");
+                self.labelled(depth + 1, "variable", for_stmt.variable);
+                self.labelled(depth + 1, "iterable", for_stmt.iterable);
+                self.section(depth + 1, "body", for_stmt.body);
+            },
+            Stmt::Expr(expr) => self.expr(depth, *expr)
+        }
+    }
+
+    fn expr(&mut self, depth: usize, expr: ExprId) {
+        let ast = self.ast;
+        match &ast[expr] {
+            Expr::Function(function) => self.function(depth, "FunctionExpr", function),
+            Expr::If(if_stmt) => {
+                self.line(depth, "If");
+                self.labelled(depth + 1, "condition", if_stmt.condition);
+                self.section(depth + 1, "then", if_stmt.body);
+                if let Some(else_stmt) = &if_stmt.else_stmt {
+                    self.section(depth + 1, "else", *else_stmt);
+                }
+            },
+            Expr::Identifier(identifier) => self.line(depth, &format!("Identifier {}", identifier.symbol)),
+            Expr::Binary(bin_expr) => {
+                self.line(depth, &format!("BinaryExpr {}", bin_expr.operator.symbol()));
+                self.expr(depth + 1, bin_expr.left);
+                self.expr(depth + 1, bin_expr.right);
+            },
+            Expr::Comparative(comp_expr) => {
+                self.line(depth, &format!("ComparativeExpr {}", comp_expr.operator.symbol()));
+                self.expr(depth + 1, comp_expr.left);
+                self.expr(depth + 1, comp_expr.right);
+            },
+            Expr::Assignment(assignment) => {
+                self.line(depth, "AssignmentExpr");
+                self.expr(depth + 1, assignment.assignee);
+                self.expr(depth + 1, assignment.value);
+            },
+            Expr::Member(member_expr) => {
+                self.line(depth, if member_expr.computed { "MemberExpr [computed]" } else { "MemberExpr" });
+                self.expr(depth + 1, member_expr.object);
+                self.expr(depth + 1, member_expr.property);
+            },
+            Expr::Call(call_expr) => {
+                self.line(depth, "CallExpr");
+                self.expr(depth + 1, call_expr.caller);
+                if !call_expr.args.is_empty() {
+                    self.line(depth + 1, "args");
+                    for arg in call_expr.args.iter() {
+                        self.expr(depth + 2, *arg);
+                    }
+                }
+            },
+            Expr::Number(number) => self.line(depth, &format!("NumericLiteral {}", number.value)),
+            Expr::Object(object) => {
+                self.line(depth, "Object");
+                for property in object.properties.iter() {
+                    self.line(depth + 1, &format!("Property {}", property.key.map_or("<none>", |key| key.as_str())));
+                    if let Some(value) = &property.value {
+                        self.expr(depth + 2, *value);
+                    }
+                }
+            },
+            Expr::List(list) => {
+                self.line(depth, "List");
+                for element in list.elements.iter() {
+                    self.expr(depth + 1, *element);
+                }
+            },
+            Expr::String(string) => self.line(depth, &format!("String {:?}", string.string.as_str())),
+            Expr::Body(body) => {
+                self.line(depth, "Body");
+                self.body(depth + 1, *body);
+            }
+        }
+    }
+}
\ No newline at end of file
@@ -5,7 +5,249 @@ pub mod frontend;
 pub mod runtime;
 pub mod eval;
 pub mod macros;
+pub mod diagnostics;
+pub mod repl;
+pub mod debugger;
+pub mod testing;
+#[cfg(feature = "ffi")]
+pub mod ffi;
+#[cfg(target_arch = "wasm32")]
+pub mod wasm;
 
+use std::{fmt::Display, fs, io, panic::{self, AssertUnwindSafe}, path::Path, rc::Rc, sync::{Arc, Mutex, Once, TryLockError}};
+
+use diagnostics::Diagnostics;
+use frontend::{ast::Program, compiled, errors::ParseError, parser::Parser, symbol::Symbol};
+use runtime::{context::NativeContext, environment::{Environment, EnvironmentBuilder}, interpreter::eval_program, json::serialize, native_funcs::log::LogSink, policy::{self, Policy}, stdio::IoHandles, values::{Value, ValueType}};
+
+// Parses the source without running it, returning every syntax error found
+pub fn check(source: &str) -> Result<(), Vec<ParseError>> {
+    Parser::new().check(source.to_string())
+}
+
+#[derive(Debug)]
+pub enum Error {
+    Parse(ParseError),
+    // A fatal error while running. Whatever the script did before it stays done
+    Runtime(FatalError),
+    // The script failed an assert or called panic
+    Script(ScriptError)
+}
+
+impl Display for Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Error::Parse(e) => write!(f, "{}", e),
+            Error::Runtime(e) => write!(f, "Runtime Error: {}", e.message),
+            Error::Script(e) => write!(f, "{}", e.message)
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+impl From<ParseError> for Error {
+    fn from(error: ParseError) -> Self {
+        Error::Parse(error)
+    }
+}
+
+// Everything needed to run code from another Rust program. Each interpreter has its own global
+// scope that stays around between calls, like the REPL's
+pub struct Interpreter {
+    env: Arc<Mutex<Environment>>,
+    pub parser: Parser,
+    // Replaces the thread's diagnostics while this interpreter runs, see set_diagnostics
+    diagnostics: Option<Arc<dyn Diagnostics + Send + Sync>>
+}
+
+impl Default for Interpreter {
+    fn default() -> Self {
+        Interpreter::new()
+    }
+}
+
+impl Interpreter {
+    pub fn new() -> Self {
+        Interpreter::with_environment(Environment::new(None))
+    }
+
+    // For a global scope made with EnvironmentBuilder
+    pub fn with_environment(env: Environment) -> Self {
+        install_panic_hook();
+        Interpreter {
+            env: Arc::new(Mutex::new(env)),
+            parser: Parser::new(),
+            diagnostics: None
+        }
+    }
+
+    // Every native group the policy allows, with its limits applied to each eval and call
+    pub fn with_policy(policy: Policy) -> Self {
+        Interpreter::with_environment(EnvironmentBuilder::new().policy(policy).build())
+    }
+
+    pub fn policy(&self) -> Policy {
+        self.env.lock().unwrap().policy.clone()
+    }
+
+    pub fn env(&self) -> &Arc<Mutex<Environment>> {
+        &self.env
+    }
+
+    // Sends script output (print, printf, input's prompt) and takes script input from the given
+    // handles instead of the process's stdio
+    pub fn set_io(&mut self, io: IoHandles) {
+        self.env.lock().unwrap().io = io;
+    }
+
+    // Where this interpreter's log.info, log.warn and so on are sent instead of stderr. Other
+    // interpreters, on this thread or any other, keep their own
+    pub fn set_log_sink(&mut self, sink: LogSink) {
+        self.env.lock().unwrap().log.sink = Some(sink);
+    }
+
+    // Where this interpreter's warnings and errors are reported, instead of the thread's
+    // diagnostics. Other interpreters on the same thread are unaffected
+    pub fn set_diagnostics(&mut self, diagnostics: impl Diagnostics + Send + Sync + 'static) {
+        self.diagnostics = Some(Arc::new(diagnostics));
+    }
+
+    // The value of a global variable
+    pub fn get(&self, name: &str) -> Option<Value> {
+        self.env.lock().unwrap().variables.get(&Symbol::intern(name)).cloned()
+    }
+
+    // Makes a Rust closure callable from scripts as a global function. The closure can hold on to
+    // application state, anything shared with the rest of the program goes behind an Arc. It has
+    // to be Send and Sync as the interpreter can be moved to other threads. Registering a name
+    // again replaces the old function
+    pub fn register_fn<F>(&mut self, name: &str, function: F)
+    where
+        F: Fn(Vec<Value>, &NativeContext) -> Value + Send + Sync + 'static
+    {
+        self.env.lock().unwrap().redeclare_var(Symbol::intern(name), MK_NATIVE_FN!(function), true);
+    }
+
+    // Same as register_fn for closures that mutate what they capture. The closure can't be
+    // re-entered, so calling it again from a script function it calls is a runtime error
+    pub fn register_fn_mut<F>(&mut self, name: &str, function: F)
+    where
+        F: FnMut(Vec<Value>, &NativeContext) -> Value + Send + 'static
+    {
+        let function = Mutex::new(function);
+        let owned_name = name.to_string();
+        self.register_fn(name, move |args, ctx| match function.try_lock() {
+            Ok(mut function) => function(args, ctx),
+            // A call that errored part way through leaves it poisoned, it's still usable
+            Err(TryLockError::Poisoned(poisoned)) => (poisoned.into_inner())(args, ctx),
+            Err(TryLockError::WouldBlock) => fatal_error(&format!("Cannot call {} while it is already running", owned_name))
+        });
+    }
+
+    pub fn eval_str(&mut self, source: &str) -> Result<Value, Error> {
+        let program = with_diagnostics(&self.diagnostics, || self.parser.parse(source.to_string()))?;
+        self.eval_program(program)
+    }
+
+    pub fn eval_program(&mut self, program: Program) -> Result<Value, Error> {
+        self.run(|env| eval_program(program, Arc::clone(env)))
+    }
+
+    // Parses the source into a compiled program that eval_compiled can run later, see
+    // frontend::compiled. `fold` works out arithmetic on literals ahead of time
+    pub fn compile(&mut self, source: &str, fold: bool) -> Result<Vec<u8>, Error> {
+        let program = with_diagnostics(&self.diagnostics, || self.parser.parse(source.to_string()))?;
+        Ok(compiled::encode(&program, fold))
+    }
+
+    pub fn eval_compiled(&mut self, bytes: &[u8]) -> Result<Value, Error> {
+        let program = compiled::decode(bytes)?;
+        self.eval_program(program)
+    }
+
+    // Runs source and gives its result as JSON, see json::serialize
+    pub fn eval_json(&mut self, source: &str) -> Result<String, Error> {
+        let value = self.eval_str(source)?;
+        serialize(&value).map_err(|message| Error::Runtime(FatalError { message }))
+    }
+
+    // Runs a source file or a compiled program, told apart by compiled::MAGIC
+    pub fn eval_file(&mut self, path: impl AsRef<Path>) -> Result<Value, Error> {
+        let bytes = fs::read(path).map_err(ParseError::io)?;
+        if compiled::is_compiled(&bytes) {
+            return self.eval_compiled(&bytes);
+        }
+
+        let source = String::from_utf8(bytes).map_err(|e| ParseError::io(io::Error::new(io::ErrorKind::InvalidData, e)))?;
+        self.eval_str(&source)
+    }
+
+    // Calls a global function (a script function or a native) with the given arguments
+    pub fn call(&mut self, name: &str, args: Vec<Value>) -> Result<Value, Error> {
+        let function = match self.get(name) {
+            Some(function) => function,
+            None => return Err(Error::Runtime(FatalError { message: format!("{} is not defined", name) }))
+        };
+
+        if !matches!(function.get_type(), ValueType::Function | ValueType::NativeFn) {
+            return Err(Error::Runtime(FatalError { message: format!("Cannot call {} as it is a {}", name, function.get_type()) }));
+        }
+
+        self.run(|env| NativeContext::new(Arc::clone(env)).call(&function, args))
+    }
+
+    // Runtime errors unwind, so they're caught here and turned back into an Error
+    fn run(&mut self, run: impl FnOnce(&Arc<Mutex<Environment>>) -> Value) -> Result<Value, Error> {
+        let policy = self.policy();
+        let result = with_diagnostics(&self.diagnostics, || catch_errors(|| policy::enforce(&policy, || run(&self.env))));
+        // An error raised while the scope was locked leaves it poisoned, but it's still usable
+        self.env.clear_poison();
+        result
+    }
+}
+
+// Runs `run`, turning a runtime or script error that unwinds out of it back into an Error. Both
+// have already been reported by then. Any other panic carries on unwinding
+pub fn catch_errors<T>(run: impl FnOnce() -> T) -> Result<T, Error> {
+    match panic::catch_unwind(AssertUnwindSafe(run)) {
+        Ok(value) => Ok(value),
+        Err(payload) => match payload.downcast::<ScriptError>() {
+            Ok(error) => Err(Error::Script(*error)),
+            Err(payload) => match payload.downcast::<FatalError>() {
+                Ok(error) => Err(Error::Runtime(*error)),
+                Err(payload) => panic::resume_unwind(payload)
+            }
+        }
+    }
+}
+
+fn with_diagnostics<T>(diagnostics: &Option<Arc<dyn Diagnostics + Send + Sync>>, run: impl FnOnce() -> T) -> T {
+    match diagnostics {
+        Some(diagnostics) => {
+            let diagnostics = Arc::clone(diagnostics);
+            diagnostics::scoped(Rc::new(move |level: LoggingLevel, message: &str| diagnostics.report(level, message)), run)
+        },
+        None => run()
+    }
+}
+
+// Script and fatal errors are reported when they're raised, so the panics carrying them don't
+// need the default message. Other panics still get it. Installed once per process
+pub fn install_panic_hook() {
+    static INSTALL: Once = Once::new();
+
+    INSTALL.call_once(|| {
+        let default_hook = panic::take_hook();
+        panic::set_hook(Box::new(move |info| {
+            if !info.payload().is::<ScriptError>() && !info.payload().is::<FatalError>() {
+                default_hook(info);
+            }
+        }));
+    });
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
 pub enum LoggingLevel {
     Info,
     Warn,
@@ -14,20 +256,38 @@ pub enum LoggingLevel {
 }
 
 fn warn(information: &str) {
-    println!("[-] {}", information);
+    diagnostics::report(LoggingLevel::Warn, information);
 }
 
 fn info(information: &str) {
-    println!("[+] {}", information);
+    diagnostics::report(LoggingLevel::Info, information);
 }
 
 fn error(information: &str) {
-    println!("[-] ERROR: {}", information);
+    diagnostics::report(LoggingLevel::Error, information);
 }
 
 fn fatal_error(information: &str) -> ! {
-    println!("[-] FATAL ERROR: {}", information);
-    panic!("Fatal error.");
+    diagnostics::report(LoggingLevel::Fatal, information);
+    std::panic::panic_any(FatalError { message: information.to_string() });
+}
+
+// The payload of the panic fatal_error unwinds with, so the message isn't lost to whoever catches it
+#[derive(Debug, Clone)]
+pub struct FatalError {
+    pub message: String
+}
+
+// Raised by scripts through assert and panic. Unlike other fatal errors the panic carries this
+// as its payload, so whoever catches the unwind (an embedder, the REPL) can tell it apart and recover
+#[derive(Debug, Clone)]
+pub struct ScriptError {
+    pub message: String
+}
+
+fn raise(message: &str) -> ! {
+    error(message);
+    std::panic::panic_any(ScriptError { message: message.to_string() });
 }
 
 fn is_skippable(src: char) -> bool {
@@ -1,44 +1,38 @@
 #[macro_export]
 macro_rules! MK_BOOL {
     ($val:expr) => {
-        BooleanValue {
-            value: $val
-        }
+        $crate::runtime::values::Value::Bool($val)
     }
 }
 
 #[macro_export]
 macro_rules! MK_NULL {
     () => {
-        NullValue {}
+        $crate::runtime::values::Value::Null
     };
 }
 
 #[macro_export]
 macro_rules! MK_NUMBER {
     ($val:expr) => {
-        NumberValue {
-            value: $val
-        }
+        $crate::runtime::values::Value::Number($val)
     };
 }
 
 #[macro_export]
 macro_rules! MK_NATIVE_FN {
     ($function:expr) => {
-        NativeFnValue {
-            call: FunctionCall {
-                func: Rc::new($function)
+        $crate::runtime::values::Value::Native($crate::runtime::values::NativeFnValue {
+            call: $crate::runtime::values::FunctionCall {
+                func: std::sync::Arc::new($function)
             }
-        }
+        })
     };
 }
 
 #[macro_export]
 macro_rules! MK_STRING {
     ($val:expr) => {
-        StringValue {
-            value: $val
-        }
+        $crate::runtime::values::Value::Str($val)
     };
 }
\ No newline at end of file
@@ -1,75 +1,501 @@
 #![allow(unused_imports)]
 
+use interpreter::frontend::analysis;
+use interpreter::frontend::compiled;
+use interpreter::frontend::formatter;
+use interpreter::frontend::lexer;
+use interpreter::frontend::tree;
+use interpreter::testing;
 use interpreter::frontend::parser::Parser;
 use interpreter::runtime::environment::setup_scope;
+use interpreter::runtime::environment::Arity;
 use interpreter::runtime::environment::Environment;
-use interpreter::runtime::interpreter::eval;
-use interpreter::runtime::values::BooleanValue;
-use interpreter::runtime::values::NullValue;
-use interpreter::runtime::values::NumberValue;
-use interpreter::frontend::ast::StmtWrapper;
+use interpreter::runtime::environment::EnvironmentBuilder;
+use interpreter::runtime::hot_reload::HotReloader;
+use interpreter::runtime::interpreter::eval_program;
+use interpreter::runtime::values::Value;
+use interpreter::frontend::ast::Program;
+use interpreter::frontend::symbol::Symbol;
 use interpreter::MK_BOOL;
+use interpreter::install_panic_hook;
+use interpreter::catch_errors;
+use interpreter::repl;
+use interpreter::debugger::Debugger;
+use interpreter::runtime::trace::{self, Tracer};
+use interpreter::runtime::values::strict_equals;
+use interpreter::runtime::values::ValueType;
+use interpreter::LoggingLevel;
+use interpreter::diagnostics;
+use interpreter::diagnostics::SourceDiagnostics;
+use interpreter::FatalError;
+use interpreter::ScriptError;
+use interpreter::Error;
+use interpreter::Interpreter;
+use std::env;
 use std::fs;
 use std::io;
 use std::io::Write;
+use std::panic;
+use std::panic::AssertUnwindSafe;
+use std::rc::Rc;
 use std::sync::Arc;
 use std::sync::Mutex;
+use std::process::Child;
+use std::process::Command;
+use std::thread;
+use std::time::Duration;
+use std::time::Instant;
 
 fn main() {
-    let mut program = Parser { tokens: vec![] };
+    install_panic_hook();
 
-    // println!("{:?}", tokenizer.tokenize(fs::read_to_string("src/testingfile.tl").unwrap()));
+    let mut program = Parser::new();
 
     let env = Arc::new(Mutex::new(Environment::new(None)));
-    loop {
-        let mut input = String::new();
 
-        print!("> ");
-        io::stdout().flush().unwrap();
-        io::stdin()
-            .read_line(&mut input)
-            .unwrap();
+    let args = env::args().collect::<Vec<String>>();
+    // Everything after `--` goes to the script untouched, even if it looks like one of our flags
+    let (args, script_args) = match args.iter().position(|arg| arg == "--") {
+        Some(index) => (args[..index].to_vec(), args[index + 1..].to_vec()),
+        None => (args, Vec::new())
+    };
+    program.strict = args.iter().any(|arg| arg == "--strict");
+    // --lenient-arity lets script functions be called with too few arguments (null) or too many (dropped)
+    let arity = if args.iter().any(|arg| arg == "--lenient-arity") { Arity::Lenient } else { Arity::Strict };
+    env.lock().unwrap().strict = program.strict;
+    env.lock().unwrap().arity = arity;
+
+    // interpreter fmt [--check] files...
+    if args.get(1).is_some_and(|arg| arg == "fmt") {
+        std::process::exit(fmt(&mut program, &args[2..]));
+    }
+
+    // interpreter check files...
+    if args.get(1).is_some_and(|arg| arg == "check") {
+        std::process::exit(check(&mut program, &args[2..]));
+    }
+
+    // interpreter test [dir]
+    if args.get(1).is_some_and(|arg| arg == "test") {
+        std::process::exit(test(args.get(2).map_or(".", |dir| dir.as_str())));
+    }
+
+    // interpreter lint files...
+    if args.get(1).is_some_and(|arg| arg == "lint") {
+        std::process::exit(lint(&mut program, &args[2..]));
+    }
 
-        if input.trim() == "file" {
-            // let mut input = String::new();
+    // --compile <source> <output> saves the parsed program for eval_file to run later, --fold
+    // works out arithmetic on literals while doing so
+    if let Some(index) = args.iter().position(|arg| arg == "--compile") {
+        let (Some(source), Some(output)) = (args.get(index + 1), args.get(index + 2)) else {
+            eprintln!("Usage: --compile <source> <output> [--fold]");
+            std::process::exit(2);
+        };
+        let fold = args.iter().any(|arg| arg == "--fold");
 
-            // print!("File name > ");
-            // io::stdout().flush().unwrap();
-            // io::stdin()
-            //     .read_line(&mut input)
-            //     .unwrap();
+        let result = fs::read_to_string(source)
+            .map_err(|e| e.to_string())
+            .and_then(|source| program.parse(source).map_err(|e| e.to_string()))
+            .and_then(|ast| fs::write(output, compiled::encode(&ast, fold)).map_err(|e| e.to_string()));
+        if let Err(e) = result {
+            eprintln!("Could not compile {}: {}", source, e);
+            std::process::exit(1);
+        }
+        return;
+    }
 
-            let mut debug = false;
+    if let Some(index) = args.iter().position(|arg| arg == "--hot-reload") {
+        let mut reloader = HotReloader::new(&args[index + 1], Arc::clone(&env));
+        reloader.parser.strict = program.strict;
+        // Anything after the script path is for the script
+        env.lock().unwrap().set_args(&[&args[index + 2..], &script_args[..]].concat());
+        reloader.watch(Duration::from_millis(500));
+    }
 
-            if env.lock().unwrap().variables.get("debug").unwrap().equals(Box::new(MK_BOOL!(true))) {
-                debug = true;
+    // interpreter [flags] path/to/script.tl [script args] [-- script args], without a path the REPL starts
+    if let Some(index) = args.iter().skip(1).position(|arg| !arg.starts_with("--")).map(|index| index + 1) {
+        if args.iter().any(|arg| arg == "--dump-tokens") {
+            std::process::exit(dump_tokens(&program, &args[index]));
+        }
+        if args.iter().any(|arg| arg == "--dump-ast") {
+            std::process::exit(dump_ast(&mut program, &args[index]));
+        }
+        if args.iter().any(|arg| arg == "--watch") {
+            let flags = ["--strict", "--lenient-arity"].into_iter().filter(|flag| args.iter().any(|arg| arg == flag)).collect::<Vec<_>>();
+            watch_file(&args[index], &[&args[index + 1..], &script_args[..]].concat(), &flags);
+        }
+        let debug = args.iter().any(|arg| arg == "--debug");
+        std::process::exit(run_file(&args[index], &[&args[index + 1..], &script_args[..]].concat(), program.strict, arity, debug));
+    }
+
+    env.lock().unwrap().set_args(&script_args);
+
+    let keywords = program.tokenizer.keywords();
+
+    'repl: loop {
+        // Ctrl-D or the end of piped input ends the session, Ctrl-C only drops what's been typed
+        let mut input = match repl::read_line("> ", &env, &keywords) {
+            Ok(line) if line.is_empty() => break,
+            Ok(line) => line,
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+            Err(e) => {
+                eprintln!("Could not read input: {}", e);
+                std::process::exit(1);
             }
+        };
 
-            *env.lock().unwrap() = Environment::new(None);
+        // Keeps reading while the parser says the statement isn't finished, like after `function f(x) {`.
+        // A blank line gives up and lets the error through
+        while !input.trim().is_empty() && is_incomplete(&mut program, &input) {
+            let line = match repl::read_line("... ", &env, &keywords) {
+                Ok(line) => line,
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => continue 'repl,
+                Err(e) => {
+                    eprintln!("Could not read input: {}", e);
+                    std::process::exit(1);
+                }
+            };
+            if line.trim().is_empty() {
+                break;
+            }
+            input.push_str(&line);
+        }
 
-            let ast = program.produce_ast(fs::read_to_string("src/testingfile.txt").unwrap());
+        if matches!(input.trim(), ":quit" | ":q" | ":exit" | "quit" | "exit") {
+            break;
+        } else if input.trim() == ":reset" {
+            // Rebuilds the global scope in place so functions holding on to it see the fresh one
+            *env.lock().unwrap() = EnvironmentBuilder::new().strict(program.strict).arity(arity).build();
+        } else if let Some(path) = input.trim().strip_prefix(":save ") {
+            if let Err(e) = env.lock().unwrap().save(path.trim()) {
+                println!("Could not save session to {}: {}", path.trim(), e);
+            }
+        } else if let Some(path) = input.trim().strip_prefix(":restore ") {
+            if let Err(e) = env.lock().unwrap().load(path.trim()) {
+                println!("Could not restore session from {}: {}", path.trim(), e);
+            }
+        } else if let Some(path) = input.trim().strip_prefix(":load ") {
+            // Runs the file in this session, so what it declares can be used from here on
+            match read_program(&mut program, path.trim()) {
+                Ok(ast) => {
+                    // Like other input, an error only ends the load. It was reported when it happened
+                    let _ = catch_errors(|| eval_program(ast, Arc::clone(&env)));
+                    env.clear_poison();
+                },
+                Err(e) => println!("Could not load {}: {}", path.trim(), e)
+            }
+        } else if let Some(source) = input.trim_start().strip_prefix(":ast ") {
+            // Shows how the parser sees the input without running it
+            match program.parse(source.to_string()) {
+                Ok(ast) => print!("{}", tree::dump(&ast)),
+                Err(e) => diagnostics::report(LoggingLevel::Error, &e.to_string())
+            }
+        } else if let Some(source) = input.trim_start().strip_prefix(":tokens ") {
+            match program.tokenizer.try_tokenize(source.to_string()) {
+                Ok(tokens) => print!("{}", lexer::dump(&tokens)),
+                Err(e) => diagnostics::report(LoggingLevel::Error, &e.to_string())
+            }
+        } else {
+            let ast = match program.parse(input.clone()) {
+                Ok(ast) => ast,
+                // Bad input only costs that input, the session carries on
+                Err(e) => {
+                    eprint!("{}", diagnostics::render_parse_error("<repl>", &input, &e, diagnostics::use_color()));
+                    continue;
+                }
+            };
+
+            // `debug` can be unset like any other variable, a missing one just means off
+            let debug = env.lock().unwrap().variables.get(&Symbol::intern("debug")).is_some_and(|value| strict_equals(value, &MK_BOOL!(true)));
 
             if debug {
                 println!("AST: {:?}", ast);
             }
-            eval(StmtWrapper::new(Box::new(ast)), Arc::clone(&env)).to_string();
+            // An error only ends the current input, it was reported when it happened. One raised
+            // while the scope was locked leaves it poisoned, but it's still usable
+            let result = catch_errors(|| eval_program(ast, Arc::clone(&env)));
+            env.clear_poison();
+            let result = match result {
+                Ok(result) => result,
+                Err(_) => continue
+            };
+            if result.get_type() != ValueType::Null {
+                println!("{}", result);
+                // The last result stays around as _ to build on
+                env.lock().unwrap().redeclare_var(Symbol::intern("_"), result, false);
+            }
+        }
+    }
+
+}
+
+// Whether more lines could finish `input`. Warnings are left for when it's parsed to run
+fn is_incomplete(parser: &mut Parser, input: &str) -> bool {
+    diagnostics::scoped(Rc::new(|_, _: &str| {}), || parser.parse(input.to_string()))
+        .is_err_and(|e| e.is_incomplete())
+}
+
+// Rewrites each file in the canonical layout. With --check nothing is written, files that would
+// change are listed and the exit code is 1, for CI
+fn fmt(parser: &mut Parser, args: &[String]) -> i32 {
+    let check = args.iter().any(|arg| arg == "--check");
+    let paths = args.iter().filter(|arg| !arg.starts_with("--")).collect::<Vec<&String>>();
+    if paths.is_empty() {
+        eprintln!("Usage: fmt [--check] <files...>");
+        return 2;
+    }
+
+    let mut code = 0;
+    for path in paths {
+        let result = fs::read_to_string(path)
+            .map_err(|e| e.to_string())
+            .and_then(|source| formatter::format(parser, &source).map(|formatted| (source, formatted)).map_err(|e| e.to_string()));
+        let (source, formatted) = match result {
+            Ok(result) => result,
+            Err(e) => {
+                eprintln!("{}: {}", path, e);
+                code = 1;
+                continue;
+            }
+        };
+
+        if source == formatted {
+            continue;
+        }
+        if check {
+            println!("{} is not formatted", path);
+            code = 1;
+        } else if let Err(e) = fs::write(path, formatted) {
+            eprintln!("Could not write {}: {}", path, e);
+            code = 1;
         } else {
-            let ast = program.produce_ast(input);
+            println!("Formatted {}", path);
+        }
+    }
+    code
+}
 
-            let mut debug = false;
+// Parses each file without running it, printing every syntax error found. Exits with 1 when any
+// file has errors
+fn check(parser: &mut Parser, paths: &[String]) -> i32 {
+    if paths.is_empty() {
+        eprintln!("Usage: check <files...>");
+        return 2;
+    }
 
-            if env.lock().unwrap().variables.get("debug").unwrap().equals(Box::new(MK_BOOL!(true))) {
-                debug = true;
+    let mut code = 0;
+    for path in paths {
+        let source = match fs::read_to_string(path) {
+            Ok(source) => source,
+            Err(e) => {
+                eprintln!("{}: {}", path, e);
+                code = 1;
+                continue;
             }
+        };
 
-            if debug {
-                println!("AST: {:?}", ast);
+        // Only errors count, warnings are for lint and strict mode
+        if let Err(errors) = diagnostics::scoped(Rc::new(|_, _: &str| {}), || parser.check(source.clone())) {
+            for e in errors {
+                eprint!("{}", diagnostics::render_parse_error(path, &source, &e, diagnostics::use_color()));
             }
-            let result = &eval(StmtWrapper::new(Box::new(ast)), Arc::clone(&env)).to_string();
-            if result != "null" {
-                println!("{}", result);
+            code = 1;
+        }
+    }
+    code
+}
+
+// Runs every *_test.tl file under `dir`, exiting with 1 when any test failed or there were none
+fn test(dir: &str) -> i32 {
+    let files = match testing::discover(std::path::Path::new(dir)) {
+        Ok(files) => files,
+        Err(e) => {
+            eprintln!("{}: {}", dir, e);
+            return 1;
+        }
+    };
+    if files.is_empty() {
+        eprintln!("No *_test.tl files found in {}", dir);
+        return 1;
+    }
+
+    let (mut passed, mut failed) = (0, 0);
+    for file in files {
+        let results = testing::run_file(&file);
+        println!("{}", results.path.display());
+        if let Some(e) = results.error {
+            println!("  ERROR {}", e);
+            failed += 1;
+        }
+        for test in results.tests {
+            match test.error {
+                None => {
+                    println!("  ok    {}", test.name);
+                    passed += 1;
+                },
+                Some(e) => {
+                    println!("  FAIL  {}: {}", test.name, e);
+                    failed += 1;
+                }
             }
         }
     }
 
+    println!();
+    println!("{} passed, {} failed", passed, failed);
+    if failed > 0 { 1 } else { 0 }
+}
+
+// Prints every warning as path:line: message, exiting with 1 when there were any
+fn lint(parser: &mut Parser, paths: &[String]) -> i32 {
+    if paths.is_empty() {
+        eprintln!("Usage: lint <files...>");
+        return 2;
+    }
+
+    let mut code = 0;
+    for path in paths {
+        // Parser warnings, like missing semicolons, would be reported twice
+        let result = fs::read_to_string(path)
+            .map_err(|e| e.to_string())
+            .and_then(|source| diagnostics::scoped(Rc::new(|_, _: &str| {}), || parser.parse(source)).map_err(|e| e.to_string()));
+        let program = match result {
+            Ok(program) => program,
+            Err(e) => {
+                eprintln!("{}: {}", path, e);
+                code = 1;
+                continue;
+            }
+        };
+
+        for warning in analysis::lint(&program) {
+            println!("{}:{}: {}", path, warning.line, warning.message);
+            code = 1;
+        }
+    }
+    code
+}
+
+// --dump-ast prints the script's tree instead of running it. Compiled scripts are decoded first
+fn dump_ast(parser: &mut Parser, path: &str) -> i32 {
+    match read_program(parser, path) {
+        Ok(ast) => {
+            print!("{}", tree::dump(&ast));
+            0
+        },
+        Err(e) => {
+            eprintln!("{}: {}", path, e);
+            1
+        }
+    }
+}
+
+// Parses a source file, or decodes a compiled one
+fn read_program(parser: &mut Parser, path: &str) -> Result<Program, String> {
+    fs::read(path)
+        .map_err(|e| e.to_string())
+        .and_then(|bytes| if compiled::is_compiled(&bytes) {
+            compiled::decode(&bytes).map_err(|e| e.to_string())
+        } else {
+            parser.parse(String::from_utf8_lossy(&bytes).into_owned()).map_err(|e| e.to_string())
+        })
+}
+
+// --dump-tokens prints what the lexer makes of the script, with where each token starts
+fn dump_tokens(parser: &Parser, path: &str) -> i32 {
+    let result = fs::read_to_string(path)
+        .map_err(|e| e.to_string())
+        .and_then(|source| parser.tokenizer.try_tokenize(source).map_err(|e| e.to_string()));
+
+    match result {
+        Ok(tokens) => {
+            print!("{}", lexer::dump(&tokens));
+            0
+        },
+        Err(e) => {
+            eprintln!("{}: {}", path, e);
+            1
+        }
+    }
+}
+
+// --watch re-runs the script every time it's saved, each run in a child process so exit() or a
+// crash only ends that run. A save during a run that's still going restarts it. `flags` are passed
+// on to each run
+fn watch_file(path: &str, args: &[String], flags: &[&str]) -> ! {
+    let modified = || fs::metadata(path).and_then(|metadata| metadata.modified()).ok();
+    let mut last_modified = None;
+    let mut running: Option<(Child, Instant)> = None;
+
+    loop {
+        let current = modified();
+        if current.is_some() && current != last_modified {
+            last_modified = current;
+            if let Some((mut child, _)) = running.take() {
+                let _ = child.kill();
+                let _ = child.wait();
+                println!("--- Restarting, {} changed ---", path);
+            }
+
+            println!("--- Running {} ---", path);
+            let started = Instant::now();
+            match Command::new(env::current_exe().unwrap()).args(flags).arg(path).arg("--").args(args).spawn() {
+                Ok(child) => running = Some((child, started)),
+                Err(e) => println!("--- Could not run {}: {} ---", path, e)
+            }
+        }
+
+        if let Some((child, started)) = running.as_mut() {
+            if let Ok(Some(status)) = child.try_wait() {
+                let result = match status.code() {
+                    Some(0) => "finished".to_string(),
+                    Some(code) => format!("failed with exit code {}", code),
+                    None => "was killed".to_string()
+                };
+                println!("--- {} {} in {:.2?}, waiting for changes ---", path, result, started.elapsed());
+                running = None;
+            }
+        }
+
+        // Checked often while a run is going so its timing is close
+        thread::sleep(Duration::from_millis(if running.is_some() { 5 } else { 100 }));
+    }
+}
+
+// Runs a source or compiled script, returning the process's exit code. Runtime errors are
+// reported as they happen with the line they're on, parse errors are printed here. With `debug` it runs under the debugger
+fn run_file(path: &str, args: &[String], strict: bool, arity: Arity, debug: bool) -> i32 {
+    let mut interpreter = Interpreter::with_environment(EnvironmentBuilder::new().args(args.to_vec()).strict(strict).arity(arity).build());
+    interpreter.parser.strict = strict;
+
+    // Compiled scripts have no source to show, errors and the debugger fall back to line numbers
+    let source = fs::read(path).ok()
+        .filter(|bytes| !compiled::is_compiled(bytes))
+        .map(|bytes| String::from_utf8_lossy(&bytes).into_owned())
+        .unwrap_or_default();
+    interpreter.set_diagnostics(SourceDiagnostics { path: path.to_string(), source: source.clone() });
+
+    if !debug {
+        return exit_code(path, &source, interpreter.eval_file(path));
+    }
+
+    let debugger = Rc::new(Debugger::new(&source));
+
+    let result = trace::scoped(Rc::clone(&debugger) as Rc<dyn Tracer>, || interpreter.eval_file(path));
+    if debugger.quit() {
+        return 0;
+    }
+    exit_code(path, &source, result)
+}
+
+fn exit_code(path: &str, source: &str, result: Result<Value, Error>) -> i32 {
+    match result {
+        Ok(_) => 0,
+        Err(Error::Parse(e)) => {
+            eprint!("{}", diagnostics::render_parse_error(path, source, &e, diagnostics::use_color()));
+            1
+        },
+        Err(_) => 1
+    }
 }
\ No newline at end of file
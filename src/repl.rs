@@ -0,0 +1,147 @@
+use std::{io::{self, BufRead, IsTerminal, Read, Write}, process::{Command, Stdio}, sync::{Arc, Mutex}};
+
+use crate::{is_valid_ident_char, runtime::environment::Environment};
+
+// Names visible from `env` and keywords starting with `word`, sorted and without repeats
+pub fn completions(env: &Arc<Mutex<Environment>>, keywords: &[String], word: &str) -> Vec<String> {
+    let mut names = keywords.iter().filter(|keyword| keyword.starts_with(word)).cloned().collect::<Vec<String>>();
+
+    let mut current = Some(Arc::clone(env));
+    while let Some(scope) = current {
+        let scope = scope.lock().unwrap();
+        names.extend(scope.variables.keys().map(|name| name.as_str()).filter(|name| name.starts_with(word)).map(str::to_string));
+        current = scope.parent.clone();
+    }
+
+    names.sort();
+    names.dedup();
+    names
+}
+
+// The identifier the cursor is at the end of, empty after a space or symbol
+fn last_word(line: &str) -> &str {
+    let start = line.rfind(|c: char| !is_valid_ident_char(c)).map(|index| index + 1).unwrap_or(0);
+    &line[start..]
+}
+
+fn common_prefix(names: &[String]) -> String {
+    let mut prefix = names[0].clone();
+    for name in &names[1..] {
+        let length = prefix.chars().zip(name.chars()).take_while(|(a, b)| a == b).map(|(a, _)| a.len_utf8()).sum();
+        prefix.truncate(length);
+    }
+    prefix
+}
+
+// Puts the terminal in raw mode until dropped, going through stty so nothing outside std is needed
+struct RawMode {
+    saved: String
+}
+
+impl RawMode {
+    fn enable() -> Option<Self> {
+        let saved = stty(&["-g"])?;
+        // Ctrl-C comes through as a key too, so the terminal is never left raw by a signal
+        stty(&["-icanon", "-echo", "-isig", "min", "1"])?;
+        Some(RawMode { saved: saved.trim().to_string() })
+    }
+}
+
+impl Drop for RawMode {
+    fn drop(&mut self) {
+        stty(&[&self.saved]);
+    }
+}
+
+fn stty(args: &[&str]) -> Option<String> {
+    let output = Command::new("stty").args(args).stdin(Stdio::inherit()).stderr(Stdio::null()).output().ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    String::from_utf8(output.stdout).ok()
+}
+
+// Reads one line of REPL input, with tab completing names from `env` and `keywords` when stdin is
+// a terminal. The line comes back with its line ending, and is empty once the input has run out.
+// Ctrl-C gives an Interrupted error, for dropping what has been typed so far
+pub fn read_line(prompt: &str, env: &Arc<Mutex<Environment>>, keywords: &[String]) -> io::Result<String> {
+    let mut stdout = io::stdout();
+    write!(stdout, "{}", prompt)?;
+    stdout.flush()?;
+
+    let raw = if io::stdin().is_terminal() { RawMode::enable() } else { None };
+    let Some(_raw) = raw else {
+        let mut line = String::new();
+        io::stdin().lock().read_line(&mut line)?;
+        return Ok(line);
+    };
+
+    let mut line = String::new();
+    let mut pending = Vec::new();
+    let mut stdin = io::stdin().lock();
+    let mut byte = [0];
+
+    loop {
+        if stdin.read(&mut byte)? == 0 {
+            return Ok(String::new());
+        }
+
+        match byte[0] {
+            b'\r' | b'\n' => {
+                writeln!(stdout)?;
+                line.push('\n');
+                return Ok(line);
+            },
+            // Ctrl-D on an empty line ends the input like it would without raw mode
+            4 if line.is_empty() => {
+                writeln!(stdout)?;
+                return Ok(String::new());
+            },
+            3 => {
+                writeln!(stdout, "^C")?;
+                return Err(io::ErrorKind::Interrupted.into());
+            },
+            // Backspace and delete
+            8 | 127 => {
+                if line.pop().is_some() {
+                    write!(stdout, "\x08 \x08")?;
+                }
+            },
+            b'\t' => {
+                let word = last_word(&line);
+                let names = completions(env, keywords, word);
+                if names.is_empty() {
+                    continue;
+                }
+
+                let prefix = common_prefix(&names);
+                if prefix.len() > word.len() {
+                    let rest = prefix[word.len()..].to_string();
+                    write!(stdout, "{}", rest)?;
+                    line.push_str(&rest);
+                } else if names.len() > 1 {
+                    // Nothing more in common, list the options and redraw the line under them
+                    write!(stdout, "\n{}\n{}{}", names.join("  "), prompt, line)?;
+                }
+            },
+            // Escape sequences, like the arrow keys, aren't supported so they're dropped
+            27 => {
+                let mut sequence = [0; 2];
+                stdin.read_exact(&mut sequence)?;
+            },
+            byte if byte < 32 => {},
+            byte => {
+                // Multi byte characters are held back until they're complete
+                pending.push(byte);
+                if let Ok(text) = std::str::from_utf8(&pending) {
+                    write!(stdout, "{}", text)?;
+                    line.push_str(text);
+                    pending.clear();
+                } else if pending.len() >= 4 {
+                    pending.clear();
+                }
+            }
+        }
+        stdout.flush()?;
+    }
+}
\ No newline at end of file
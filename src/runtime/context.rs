@@ -0,0 +1,55 @@
+use std::sync::{Arc, Mutex};
+
+use crate::fatal_error;
+
+use super::{environment::{Environment, SharedEnvironment}, policy::Policy, stdio::{self, IoHandles}, values::Value};
+
+// Everything a native is handed besides its arguments. Built fresh for every call, so cloning
+// the scope out of it is cheap and never outlives the call by accident
+pub struct NativeContext {
+    // The scope the native was called from
+    pub env: Arc<Mutex<Environment>>
+}
+
+impl NativeContext {
+    pub fn new(env: Arc<Mutex<Environment>>) -> Self {
+        NativeContext { env }
+    }
+
+    pub fn global(&self) -> Arc<Mutex<Environment>> {
+        SharedEnvironment(Arc::clone(&self.env)).global_scope()
+    }
+
+    pub fn io(&self) -> IoHandles {
+        self.global().lock().unwrap().io.clone()
+    }
+
+    pub fn policy(&self) -> Policy {
+        self.global().lock().unwrap().policy.clone()
+    }
+
+    pub fn write(&self, text: &str) {
+        stdio::write_stdout(&self.env, text);
+    }
+
+    pub fn read_line(&self) -> String {
+        stdio::read_stdin_line(&self.env)
+    }
+
+    pub fn is_terminal(&self) -> bool {
+        stdio::stdout_is_terminal(&self.env)
+    }
+
+    // Calls a script or native function passed to a native. Script functions run in the scope
+    // they were declared in, as they do anywhere else, natives get this same context
+    pub fn call(&self, function: &Value, args: Vec<Value>) -> Value {
+        if let Value::Native(native) = function {
+            return (native.call.func)(args, self);
+        }
+
+        match function {
+            Value::Function(function) => function.call(args),
+            _ => fatal_error(&format!("Expected function, found {}", function.get_type()))
+        }
+    }
+}
\ No newline at end of file
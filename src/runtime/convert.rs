@@ -0,0 +1,208 @@
+use std::{any::{self, Any}, collections::HashMap, sync::Arc};
+
+use crate::fatal_error;
+
+use super::values::{ListValue, ObjectValue, UserDataValue, Value};
+
+// Rust values that can be handed to scripts
+pub trait IntoValue {
+    fn into_value(self) -> Value;
+}
+
+// Rust values that can be taken from script values. Fails with a message in the same form as
+// the natives' type errors
+pub trait FromValue: Sized {
+    fn from_value(value: &Value) -> Result<Self, String>;
+}
+
+// The argument at `index` as a Rust value, for natives. A missing or mistyped argument is fatal
+pub fn arg<T: FromValue>(args: &[Value], index: usize) -> T {
+    match args.get(index) {
+        Some(value) => match T::from_value(value) {
+            Ok(value) => value,
+            Err(e) => fatal_error(&format!("Argument {}: {}", index + 1, e))
+        },
+        None => fatal_error(&format!("Expected at least {} arguments, found {}", index + 1, args.len()))
+    }
+}
+
+fn expected(kind: &str, value: &Value) -> String {
+    format!("Expected {}, found {}", kind, value.get_type())
+}
+
+impl IntoValue for Value {
+    fn into_value(self) -> Value {
+        self
+    }
+}
+
+impl IntoValue for () {
+    fn into_value(self) -> Value {
+        Value::Null
+    }
+}
+
+impl IntoValue for f64 {
+    fn into_value(self) -> Value {
+        Value::Number(self)
+    }
+}
+
+impl IntoValue for i64 {
+    fn into_value(self) -> Value {
+        Value::Number(self as f64)
+    }
+}
+
+impl IntoValue for usize {
+    fn into_value(self) -> Value {
+        Value::Number(self as f64)
+    }
+}
+
+impl IntoValue for bool {
+    fn into_value(self) -> Value {
+        Value::Bool(self)
+    }
+}
+
+impl IntoValue for String {
+    fn into_value(self) -> Value {
+        Value::Str(self)
+    }
+}
+
+impl IntoValue for &str {
+    fn into_value(self) -> Value {
+        Value::Str(self.to_string())
+    }
+}
+
+// None becomes null
+impl<T: IntoValue> IntoValue for Option<T> {
+    fn into_value(self) -> Value {
+        match self {
+            Some(value) => value.into_value(),
+            None => Value::Null
+        }
+    }
+}
+
+impl<T: IntoValue> IntoValue for Vec<T> {
+    fn into_value(self) -> Value {
+        Value::List(ListValue::new(self.into_iter().map(IntoValue::into_value).collect()))
+    }
+}
+
+impl<T: IntoValue> IntoValue for HashMap<String, T> {
+    fn into_value(self) -> Value {
+        Value::Object(ObjectValue::new(self.into_iter().map(|(key, value)| (key, value.into_value())).collect()))
+    }
+}
+
+impl IntoValue for UserDataValue {
+    fn into_value(self) -> Value {
+        Value::UserData(self)
+    }
+}
+
+impl FromValue for Value {
+    fn from_value(value: &Value) -> Result<Self, String> {
+        Ok(value.clone())
+    }
+}
+
+impl FromValue for f64 {
+    fn from_value(value: &Value) -> Result<Self, String> {
+        match value {
+            Value::Number(number) => Ok(*number),
+            _ => Err(expected("number", value))
+        }
+    }
+}
+
+// Only whole numbers convert, 1.5 is an error rather than being rounded
+impl FromValue for i64 {
+    fn from_value(value: &Value) -> Result<Self, String> {
+        let number = f64::from_value(value)?;
+        if number.fract() != 0.0 || number < i64::MIN as f64 || number > i64::MAX as f64 {
+            return Err(format!("Expected whole number, found {}", number));
+        }
+        Ok(number as i64)
+    }
+}
+
+impl FromValue for usize {
+    fn from_value(value: &Value) -> Result<Self, String> {
+        let number = i64::from_value(value)?;
+        usize::try_from(number).map_err(|_| format!("Expected non-negative number, found {}", number))
+    }
+}
+
+impl FromValue for bool {
+    fn from_value(value: &Value) -> Result<Self, String> {
+        match value {
+            Value::Bool(boolean) => Ok(*boolean),
+            _ => Err(expected("bool", value))
+        }
+    }
+}
+
+impl FromValue for String {
+    fn from_value(value: &Value) -> Result<Self, String> {
+        match value {
+            Value::Str(string) => Ok(string.clone()),
+            _ => Err(expected("string", value))
+        }
+    }
+}
+
+// null becomes None
+impl<T: FromValue> FromValue for Option<T> {
+    fn from_value(value: &Value) -> Result<Self, String> {
+        if let Value::Null = value {
+            return Ok(None);
+        }
+        T::from_value(value).map(Some)
+    }
+}
+
+impl<T: FromValue> FromValue for Vec<T> {
+    fn from_value(value: &Value) -> Result<Self, String> {
+        match value {
+            Value::List(list) => list.snapshot().iter().map(T::from_value).collect(),
+            _ => Err(expected("list", value))
+        }
+    }
+}
+
+impl<T: FromValue> FromValue for HashMap<String, T> {
+    fn from_value(value: &Value) -> Result<Self, String> {
+        match value {
+            Value::Object(object) => object.snapshot().iter()
+                .map(|(key, value)| T::from_value(value).map(|value| (key.clone(), value)))
+                .collect(),
+            _ => Err(expected("object", value))
+        }
+    }
+}
+
+impl FromValue for UserDataValue {
+    fn from_value(value: &Value) -> Result<Self, String> {
+        match value {
+            Value::UserData(user_data) => Ok(user_data.clone()),
+            _ => Err(expected("userdata", value))
+        }
+    }
+}
+
+// The object inside a userdata value, which has to hold a T
+impl<T: Any + Send + Sync> FromValue for Arc<T> {
+    fn from_value(value: &Value) -> Result<Self, String> {
+        let user_data = UserDataValue::from_value(value)?;
+        match user_data.get::<T>() {
+            Some(data) => Ok(data),
+            None => Err(format!("Expected {}, found {}", any::type_name::<T>(), user_data.type_name))
+        }
+    }
+}
\ No newline at end of file
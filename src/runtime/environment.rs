@@ -1,37 +1,455 @@
-use std::collections::HashMap;
-use std::rc::Rc;
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::io;
+use std::path::Path;
 use std::sync::{Arc, Mutex};
 
-use crate::{fatal_error, MK_BOOL, MK_NATIVE_FN, MK_NULL};
+use crate::frontend::symbol::{Symbol, SymbolMap};
+use crate::{fatal_error, raise, MK_BOOL, MK_NATIVE_FN, MK_NULL, MK_NUMBER};
 
-use super::values::{BooleanValue, FunctionCall, NativeFnValue, NullValue, RuntimeValue};
-use super::native_funcs::{native_exit, native_input, native_print, native_sleep, native_time, to_int, to_string};
+use super::json::{from_json, object_to_json, to_json};
+use super::context::NativeContext;
+use super::policy::{self, Policy};
+use super::stdio::IoHandles;
+use super::values::{make_read_only, ListValue, ObjectValue, Value, ValueType};
+use super::native_funcs::csv::{native_csv_parse, native_csv_write};
+use super::native_funcs::list::{native_enumerate, native_filter, native_flatten, native_get, native_index_of, native_join, native_len, native_map, native_range, native_reduce, native_reverse, native_slice, native_sort, native_sort_by, native_unique, native_zip};
+use super::native_funcs::log::{LogSettings, native_log_debug, native_log_error, native_log_info, native_log_set_level, native_log_timestamps, native_log_warn};
+use super::native_funcs::math::{native_abs, native_clamp, native_is_finite, native_is_nan, native_max, native_min, native_sum};
+#[cfg(not(target_arch = "wasm32"))]
+use super::native_funcs::net::{native_net_connect, native_net_listen};
+use super::native_funcs::random::{native_choice, native_randint, native_random, native_seed, native_shuffle, native_uuid, native_uuid7};
+use super::native_funcs::regex::{native_regex_find_all, native_regex_match, native_regex_replace};
+use super::native_funcs::encoding::{native_chr, native_decode_base64, native_encode_base64, native_from_bytes, native_md5, native_ord, native_sha256, native_to_bytes};
+use super::native_funcs::format::{native_format, native_printf};
+#[cfg(not(target_arch = "wasm32"))]
+use super::native_funcs::fs::{native_copy_file, native_list_dir, native_mkdir, native_path_join, native_remove_file, native_rename};
+use super::native_funcs::stats::{native_mean, native_median, native_percentile, native_stdev, native_variance};
+use super::native_funcs::term::{native_bold, native_clear_screen, native_color};
+use super::native_funcs::time::{native_format_time, native_parse_time, native_timeit};
+use super::native_funcs::{native_assert, native_eval, native_expect_eq, native_exit, native_globals, native_input, native_locals, native_panic, native_print, native_sleep, native_time, native_type, native_vars, to_int, to_string, type_check};
 
 pub fn setup_scope(env: &mut Environment) {
+    setup_base_scope(env);
+
+    for group in NativeGroup::ALL {
+        group.declare(env);
+    }
+}
+
+// Values every global scope has, whichever native groups are included
+fn setup_base_scope(env: &mut Environment) {
     // DEBUG ONLY
-    env.declare_var(String::from("debug"), Box::new(MK_BOOL!(false)), false);
+    env.declare_var(Symbol::intern("debug"), MK_BOOL!(false), false);
+
+    env.declare_var(Symbol::intern("null"), MK_NULL!(), true);
+    env.declare_var(Symbol::intern("true"), MK_BOOL!(true), true);
+    env.declare_var(Symbol::intern("false"), MK_BOOL!(false), true);
+    // What dividing by zero gives outside strict mode
+    env.declare_var(Symbol::intern("Infinity"), MK_NUMBER!(f64::INFINITY), true);
+    env.declare_var(Symbol::intern("NaN"), MK_NUMBER!(f64::NAN), true);
+
+    // Filled in with the script's command line arguments when running a file
+    env.set_args(&[]);
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum NativeGroup {
+    // print, printf, input
+    Io,
+    // time, sleep, format_time, parse_time, timeit
+    Time,
+    // exit, assert, expect_eq, panic
+    Process,
+    // str, int, format
+    Conversion,
+    // vars, globals, locals, eval, type, is_null, is_number, is_string, is_bool, is_object, is_list, is_function, is_range, is_userdata
+    Introspection,
+    // list_dir, mkdir, remove_file, copy_file, rename, path_join
+    #[cfg(not(target_arch = "wasm32"))]
+    Fs,
+    // csv.parse, csv.write
+    Csv,
+    // regex.match, regex.find_all, regex.replace
+    Regex,
+    // http.get, http.post
+    #[cfg(all(feature = "http", not(target_arch = "wasm32")))]
+    Http,
+    // net.connect, net.listen
+    #[cfg(not(target_arch = "wasm32"))]
+    Net,
+    // encode_base64, decode_base64, sha256, md5, chr, ord, to_bytes, from_bytes
+    Encoding,
+    // random.random, random.randint, random.choice, random.shuffle, random.seed, uuid, uuid7
+    Random,
+    // sort, sort_by, map, filter, reduce, range, len, zip, enumerate, reverse, unique, flatten, join, index_of, slice, get
+    List,
+    // color, bold, clear_screen
+    Terminal,
+    // log.debug, log.info, log.warn, log.error, log.set_level, log.timestamps
+    Log,
+    // mean, median, variance, stdev, percentile
+    Stats,
+    // min, max, sum, abs, clamp, is_finite, is_nan
+    Math
+}
+
+impl NativeGroup {
+    pub const ALL: &[NativeGroup] = &[
+        NativeGroup::Io,
+        NativeGroup::Time,
+        NativeGroup::Process,
+        NativeGroup::Conversion,
+        NativeGroup::Introspection,
+        #[cfg(not(target_arch = "wasm32"))]
+        NativeGroup::Fs,
+        NativeGroup::Csv,
+        NativeGroup::Regex,
+        #[cfg(all(feature = "http", not(target_arch = "wasm32")))]
+        NativeGroup::Http,
+        #[cfg(not(target_arch = "wasm32"))]
+        NativeGroup::Net,
+        NativeGroup::Encoding,
+        NativeGroup::Random,
+        NativeGroup::List,
+        NativeGroup::Terminal,
+        NativeGroup::Log,
+        NativeGroup::Stats,
+        NativeGroup::Math
+    ];
+
+    pub fn declare(&self, env: &mut Environment) {
+        match self {
+            NativeGroup::Io => {
+                env.declare_var(Symbol::intern("print"), MK_NATIVE_FN!(native_print), true);
+                env.declare_var(Symbol::intern("printf"), MK_NATIVE_FN!(native_printf), true);
+                env.declare_var(Symbol::intern("input"), MK_NATIVE_FN!(native_input), true);
+            },
+            NativeGroup::Time => {
+                // wasm32-unknown-unknown has no clock and can't block, format_time and parse_time still work
+                #[cfg(not(target_arch = "wasm32"))] {
+                    env.declare_var(Symbol::intern("time"), MK_NATIVE_FN!(native_time), true);
+                    env.declare_var(Symbol::intern("sleep"), MK_NATIVE_FN!(native_sleep), true);
+                    env.declare_var(Symbol::intern("timeit"), MK_NATIVE_FN!(native_timeit), true);
+                }
+                env.declare_var(Symbol::intern("format_time"), MK_NATIVE_FN!(native_format_time), true);
+                env.declare_var(Symbol::intern("parse_time"), MK_NATIVE_FN!(native_parse_time), true);
+            },
+            NativeGroup::Process => {
+                #[cfg(not(target_arch = "wasm32"))]
+                env.declare_var(Symbol::intern("exit"), MK_NATIVE_FN!(native_exit), true);
+                env.declare_var(Symbol::intern("assert"), MK_NATIVE_FN!(native_assert), true);
+                env.declare_var(Symbol::intern("expect_eq"), MK_NATIVE_FN!(native_expect_eq), true);
+                env.declare_var(Symbol::intern("panic"), MK_NATIVE_FN!(native_panic), true);
+            },
+            NativeGroup::Conversion => {
+                env.declare_var(Symbol::intern("str"), MK_NATIVE_FN!(to_string), true);
+                env.declare_var(Symbol::intern("int"), MK_NATIVE_FN!(to_int), true);
+                env.declare_var(Symbol::intern("format"), MK_NATIVE_FN!(native_format), true);
+            },
+            NativeGroup::Introspection => {
+                env.declare_var(Symbol::intern("vars"), MK_NATIVE_FN!(native_vars), true);
+                env.declare_var(Symbol::intern("globals"), MK_NATIVE_FN!(native_globals), true);
+                env.declare_var(Symbol::intern("locals"), MK_NATIVE_FN!(native_locals), true);
+                env.declare_var(Symbol::intern("eval"), MK_NATIVE_FN!(native_eval), true);
+                env.declare_var(Symbol::intern("type"), MK_NATIVE_FN!(native_type), true);
+
+                let checks: [(&str, &'static [ValueType]); 9] = [
+                    ("is_null", &[ValueType::Null]),
+                    ("is_number", &[ValueType::Number]),
+                    ("is_string", &[ValueType::String]),
+                    ("is_bool", &[ValueType::Boolean]),
+                    ("is_object", &[ValueType::Object]),
+                    ("is_list", &[ValueType::List]),
+                    ("is_function", &[ValueType::Function, ValueType::NativeFn]),
+                    ("is_range", &[ValueType::Range]),
+                    ("is_userdata", &[ValueType::UserData])
+                ];
+                for (name, value_types) in checks {
+                    env.declare_var(Symbol::intern(name), type_check(value_types), true);
+                }
+            },
+            #[cfg(not(target_arch = "wasm32"))]
+            NativeGroup::Fs => {
+                env.declare_var(Symbol::intern("list_dir"), MK_NATIVE_FN!(native_list_dir), true);
+                env.declare_var(Symbol::intern("mkdir"), MK_NATIVE_FN!(native_mkdir), true);
+                env.declare_var(Symbol::intern("remove_file"), MK_NATIVE_FN!(native_remove_file), true);
+                env.declare_var(Symbol::intern("copy_file"), MK_NATIVE_FN!(native_copy_file), true);
+                env.declare_var(Symbol::intern("rename"), MK_NATIVE_FN!(native_rename), true);
+                env.declare_var(Symbol::intern("path_join"), MK_NATIVE_FN!(native_path_join), true);
+            },
+            NativeGroup::Csv => {
+                env.declare_var(Symbol::intern("csv"), Value::Object(namespace(&[
+                    ("parse", native_csv_parse),
+                    ("write", native_csv_write)
+                ])), true);
+            },
+            NativeGroup::Regex => {
+                env.declare_var(Symbol::intern("regex"), Value::Object(namespace(&[
+                    ("match", native_regex_match),
+                    ("find_all", native_regex_find_all),
+                    ("replace", native_regex_replace)
+                ])), true);
+            },
+            #[cfg(all(feature = "http", not(target_arch = "wasm32")))]
+            NativeGroup::Http => {
+                env.declare_var(Symbol::intern("http"), Value::Object(namespace(&[
+                    ("get", super::native_funcs::http::native_http_get),
+                    ("post", super::native_funcs::http::native_http_post)
+                ])), true);
+            },
+            #[cfg(not(target_arch = "wasm32"))]
+            NativeGroup::Net => {
+                env.declare_var(Symbol::intern("net"), Value::Object(namespace(&[
+                    ("connect", native_net_connect),
+                    ("listen", native_net_listen)
+                ])), true);
+            },
+            NativeGroup::Encoding => {
+                env.declare_var(Symbol::intern("encode_base64"), MK_NATIVE_FN!(native_encode_base64), true);
+                env.declare_var(Symbol::intern("decode_base64"), MK_NATIVE_FN!(native_decode_base64), true);
+                env.declare_var(Symbol::intern("sha256"), MK_NATIVE_FN!(native_sha256), true);
+                env.declare_var(Symbol::intern("md5"), MK_NATIVE_FN!(native_md5), true);
+                env.declare_var(Symbol::intern("chr"), MK_NATIVE_FN!(native_chr), true);
+                env.declare_var(Symbol::intern("ord"), MK_NATIVE_FN!(native_ord), true);
+                env.declare_var(Symbol::intern("to_bytes"), MK_NATIVE_FN!(native_to_bytes), true);
+                env.declare_var(Symbol::intern("from_bytes"), MK_NATIVE_FN!(native_from_bytes), true);
+            },
+            NativeGroup::Random => {
+                env.declare_var(Symbol::intern("random"), Value::Object(namespace(&[
+                    ("random", native_random),
+                    ("randint", native_randint),
+                    ("choice", native_choice),
+                    ("shuffle", native_shuffle),
+                    ("seed", native_seed)
+                ])), true);
+                env.declare_var(Symbol::intern("uuid"), MK_NATIVE_FN!(native_uuid), true);
+                #[cfg(not(target_arch = "wasm32"))]
+                env.declare_var(Symbol::intern("uuid7"), MK_NATIVE_FN!(native_uuid7), true);
+            },
+            NativeGroup::List => {
+                env.declare_var(Symbol::intern("sort"), MK_NATIVE_FN!(native_sort), true);
+                env.declare_var(Symbol::intern("sort_by"), MK_NATIVE_FN!(native_sort_by), true);
+                env.declare_var(Symbol::intern("map"), MK_NATIVE_FN!(native_map), true);
+                env.declare_var(Symbol::intern("filter"), MK_NATIVE_FN!(native_filter), true);
+                env.declare_var(Symbol::intern("reduce"), MK_NATIVE_FN!(native_reduce), true);
+                env.declare_var(Symbol::intern("range"), MK_NATIVE_FN!(native_range), true);
+                env.declare_var(Symbol::intern("len"), MK_NATIVE_FN!(native_len), true);
+                env.declare_var(Symbol::intern("zip"), MK_NATIVE_FN!(native_zip), true);
+                env.declare_var(Symbol::intern("enumerate"), MK_NATIVE_FN!(native_enumerate), true);
+                env.declare_var(Symbol::intern("reverse"), MK_NATIVE_FN!(native_reverse), true);
+                env.declare_var(Symbol::intern("unique"), MK_NATIVE_FN!(native_unique), true);
+                env.declare_var(Symbol::intern("flatten"), MK_NATIVE_FN!(native_flatten), true);
+                env.declare_var(Symbol::intern("join"), MK_NATIVE_FN!(native_join), true);
+                env.declare_var(Symbol::intern("index_of"), MK_NATIVE_FN!(native_index_of), true);
+                env.declare_var(Symbol::intern("slice"), MK_NATIVE_FN!(native_slice), true);
+                env.declare_var(Symbol::intern("get"), MK_NATIVE_FN!(native_get), true);
+            },
+            NativeGroup::Terminal => {
+                env.declare_var(Symbol::intern("color"), MK_NATIVE_FN!(native_color), true);
+                env.declare_var(Symbol::intern("bold"), MK_NATIVE_FN!(native_bold), true);
+                env.declare_var(Symbol::intern("clear_screen"), MK_NATIVE_FN!(native_clear_screen), true);
+            },
+            NativeGroup::Log => {
+                env.declare_var(Symbol::intern("log"), Value::Object(namespace(&[
+                    ("debug", native_log_debug),
+                    ("info", native_log_info),
+                    ("warn", native_log_warn),
+                    ("error", native_log_error),
+                    ("set_level", native_log_set_level),
+                    ("timestamps", native_log_timestamps)
+                ])), true);
+            },
+            NativeGroup::Stats => {
+                env.declare_var(Symbol::intern("mean"), MK_NATIVE_FN!(native_mean), true);
+                env.declare_var(Symbol::intern("median"), MK_NATIVE_FN!(native_median), true);
+                env.declare_var(Symbol::intern("variance"), MK_NATIVE_FN!(native_variance), true);
+                env.declare_var(Symbol::intern("stdev"), MK_NATIVE_FN!(native_stdev), true);
+                env.declare_var(Symbol::intern("percentile"), MK_NATIVE_FN!(native_percentile), true);
+            },
+            NativeGroup::Math => {
+                env.declare_var(Symbol::intern("min"), MK_NATIVE_FN!(native_min), true);
+                env.declare_var(Symbol::intern("max"), MK_NATIVE_FN!(native_max), true);
+                env.declare_var(Symbol::intern("sum"), MK_NATIVE_FN!(native_sum), true);
+                env.declare_var(Symbol::intern("abs"), MK_NATIVE_FN!(native_abs), true);
+                env.declare_var(Symbol::intern("clamp"), MK_NATIVE_FN!(native_clamp), true);
+                env.declare_var(Symbol::intern("is_finite"), MK_NATIVE_FN!(native_is_finite), true);
+                env.declare_var(Symbol::intern("is_nan"), MK_NATIVE_FN!(native_is_nan), true);
+            }
+        }
+    }
+}
+
+pub type NativeFn = fn(Vec<Value>, &NativeContext) -> Value;
+
+// An object of natives, so related functions can be grouped as csv.parse, csv.write, ...
+fn namespace(functions: &[(&str, NativeFn)]) -> ObjectValue {
+    let mut properties = HashMap::new();
+
+    for (name, function) in functions {
+        properties.insert(name.to_string(), MK_NATIVE_FN!(*function));
+    }
+
+    ObjectValue::new(properties)
+}
+
+// Builds a global scope for embedders who don't want every native setup_scope adds
+pub struct EnvironmentBuilder {
+    groups: Vec<NativeGroup>,
+    globals: Vec<(Symbol, Value, bool)>,
+    args: Vec<String>,
+    policy: Policy,
+    strict: bool,
+    arity: Arity
+}
+
+impl Default for EnvironmentBuilder {
+    fn default() -> Self {
+        EnvironmentBuilder::new()
+    }
+}
+
+impl EnvironmentBuilder {
+    // Starts with every native group, same as Environment::new(None)
+    pub fn new() -> Self {
+        EnvironmentBuilder {
+            groups: NativeGroup::ALL.to_vec(),
+            globals: Vec::new(),
+            args: Vec::new(),
+            policy: Policy::default(),
+            strict: false,
+            arity: Arity::Strict
+        }
+    }
+
+    // Starts with no native groups, only null, true and false are defined
+    pub fn empty() -> Self {
+        EnvironmentBuilder {
+            groups: Vec::new(),
+            globals: Vec::new(),
+            args: Vec::new(),
+            policy: Policy::default(),
+            strict: false,
+            arity: Arity::Strict
+        }
+    }
+
+    pub fn with_group(mut self, group: NativeGroup) -> Self {
+        if !self.groups.contains(&group) {
+            self.groups.push(group);
+        }
+        self
+    }
+
+    pub fn without_group(mut self, group: NativeGroup) -> Self {
+        self.groups.retain(|g| *g != group);
+        self
+    }
+
+    pub fn global(mut self, name: &str, value: Value, constant: bool) -> Self {
+        self.globals.push((Symbol::intern(name), value, constant));
+        self
+    }
+
+    // Sets the args list scripts see
+    pub fn args(mut self, args: Vec<String>) -> Self {
+        self.args = args;
+        self
+    }
 
-    env.declare_var(String::from("null"), Box::new(MK_NULL!()), true);
-    env.declare_var(String::from("true"), Box::new(MK_BOOL!(true)), true);
-    env.declare_var(String::from("false"), Box::new(MK_BOOL!(false)), true);
+    // Leaves out the natives the policy denies, even if their group is added afterwards
+    pub fn policy(mut self, policy: Policy) -> Self {
+        self.policy = policy;
+        self
+    }
+
+    // See Environment::strict
+    pub fn strict(mut self, strict: bool) -> Self {
+        self.strict = strict;
+        self
+    }
+
+    pub fn arity(mut self, arity: Arity) -> Self {
+        self.arity = arity;
+        self
+    }
+
+    pub fn native(self, name: &str, function: NativeFn) -> Self {
+        self.global(name, MK_NATIVE_FN!(function), true)
+    }
+
+    pub fn build(self) -> Environment {
+        let mut env = Environment::empty(None);
+
+        setup_base_scope(&mut env);
+        env.set_args(&self.args);
+        for group in self.groups {
+            if self.policy.allows_group(group) {
+                group.declare(&mut env);
+            }
+        }
+        for name in self.policy.denied_names() {
+            let name = Symbol::intern(name);
+            env.variables.remove(&name);
+            env.constants.remove(&name);
+        }
+        env.policy = self.policy;
+        env.strict = self.strict;
+        env.arity = self.arity;
+        for (name, value, constant) in self.globals {
+            env.declare_var(name, value, constant);
+        }
+
+        env
+    }
+}
 
-    env.declare_var(String::from("print"), Box::new(MK_NATIVE_FN!(native_print)), true);
-    env.declare_var(String::from("time"), Box::new(MK_NATIVE_FN!(native_time)), true);
-    env.declare_var(String::from("sleep"), Box::new(MK_NATIVE_FN!(native_sleep)), true);
-    env.declare_var(String::from("input"), Box::new(MK_NATIVE_FN!(native_input)), true);
-    env.declare_var(String::from("exit"), Box::new(MK_NATIVE_FN!(native_exit)), true);
+// What happens when a script function is called with a number of arguments its parameters don't
+// allow. Either way missing optional arguments are null
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Arity {
+    // The call is an error naming the function and what it takes
+    Strict,
+    // Missing arguments are null and extra ones are dropped
+    Lenient
+}
 
-    env.declare_var(String::from("str"), Box::new(MK_NATIVE_FN!(to_string)), true);
-    env.declare_var(String::from("int"), Box::new(MK_NATIVE_FN!(to_int)), true);
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum OuterScope {
+    Global,
+    Nonlocal
 }
 
 #[derive(Debug, Clone)]
 pub struct Environment {
     pub parent: Option<Arc<Mutex<Environment>>>,
-    pub variables: HashMap<String, Box<dyn RuntimeValue>>,
-    pub constants: Vec<String>,
+    pub variables: SymbolMap<Value>,
+    pub constants: HashSet<Symbol>,
     pub position: usize,
-    pub continue_interpreting: bool
+    // Cleared on a function scope once a return has run in the call, so the rest of it is skipped
+    pub continue_interpreting: bool,
+    // Set while a script is hot reloaded into this scope, see HotReloader
+    pub reloading: bool,
+    // Set on the scope a function call runs in, plain assignments inside the call create locals here
+    pub function: bool,
+    // Names declared global or nonlocal in this function scope
+    pub outer_names: SymbolMap<OuterScope>,
+    // Only used on the global scope, see IoHandles
+    pub io: IoHandles,
+    // Only used on the global scope, see Policy
+    pub policy: Policy,
+    // Only used on the global scope, the random natives' generator state
+    pub random_state: Option<u64>,
+    // Only used on the global scope, see LogSettings
+    pub log: LogSettings,
+    // Only used on the global scope. Turns what would otherwise quietly give a value into an error:
+    // dividing by zero (Infinity or NaN), ordering values that have no order (false) and reading
+    // a property an object doesn't have (null)
+    pub strict: bool,
+    // Only used on the global scope, see Arity
+    pub arity: Arity,
+    // A frozen scope rejects every declaration and assignment
+    frozen: bool
 }
 
 impl Environment {
@@ -50,13 +468,7 @@ impl Environment {
             global = false;
         }
 
-        let mut env = Environment {
-            parent,
-            variables: HashMap::new(),
-            constants: Vec::new(),
-            position: 0,
-            continue_interpreting: true
-        };
+        let mut env = Environment::empty(parent);
 
         if global {
             setup_scope(&mut env)
@@ -65,6 +477,26 @@ impl Environment {
         env
     }
 
+    fn empty(parent: Option<Arc<Mutex<Environment>>>) -> Self {
+        Environment {
+            parent,
+            variables: SymbolMap::default(),
+            constants: HashSet::new(),
+            position: 0,
+            continue_interpreting: true,
+            reloading: false,
+            function: false,
+            outer_names: SymbolMap::default(),
+            io: IoHandles::default(),
+            policy: Policy::default(),
+            random_state: None,
+            log: LogSettings::default(),
+            strict: false,
+            arity: Arity::Strict,
+            frozen: false
+        }
+    }
+
     pub fn is_global(&self) -> bool {
         if let None = self.parent {
             true
@@ -73,50 +505,269 @@ impl Environment {
         }
     }
     
-    pub fn get_constants(&self) -> &Vec<String> {
+    pub fn get_constants(&self) -> &HashSet<Symbol> {
         &self.constants
     }
 
-    pub fn declare_var(&mut self, varname: String, value: Box<dyn RuntimeValue>, constant: bool) -> Box<dyn RuntimeValue> {
+    // The lists and objects in the scope can't be changed either, through these variables or any
+    // other handle to them
+    pub fn freeze(&mut self) {
+        self.frozen = true;
+        for value in self.variables.values() {
+            make_read_only(value);
+        }
+    }
+
+    pub fn is_frozen(&self) -> bool {
+        self.frozen
+    }
+
+    pub fn declare_var(&mut self, varname: Symbol, value: Value, constant: bool) -> Value {
+        if self.frozen {
+            fatal_error(&format!("Cannot declare variable {} as the scope is frozen.", varname));
+        }
+
         if self.variables.contains_key(&varname) {
             fatal_error(&format!("Cannot declare variable {} as it is already defined.", varname));
         }
 
+        policy::check_value(&value);
         if constant {
-            self.constants.insert(0, varname.clone());
+            make_read_only(&value);
+            self.constants.insert(varname);
         }
-        self.variables.insert(varname, value.clone_self());
+        self.variables.insert(varname, value.clone());
 
         value
     }
+
+    // Declares a variable, replacing it if it already exists (even if it is a constant)
+    pub fn redeclare_var(&mut self, varname: Symbol, value: Value, constant: bool) -> Value {
+        if self.frozen {
+            fatal_error(&format!("Cannot declare variable {} as the scope is frozen.", varname));
+        }
+
+        self.variables.remove(&varname);
+        self.constants.remove(&varname);
+        self.declare_var(varname, value, constant)
+    }
+
+    // Replaces the global args list, a constant list of strings
+    pub fn set_args(&mut self, args: &[String]) {
+        let args = ListValue::new(
+            args.iter().map(|arg| Value::Str(arg.clone())).collect()
+        );
+
+        self.redeclare_var(Symbol::intern("args"), Value::List(args), true);
+    }
+
+    // Writes the plain data variables of this scope (null, bools, numbers, strings, lists and objects)
+    // to a JSON file, anything else such as functions is left out
+    pub fn save(&self, path: impl AsRef<Path>) -> io::Result<()> {
+        let mut variables = Vec::new();
+        let mut constants = Vec::new();
+
+        for (name, value) in self.variables.iter() {
+            if to_json(value).is_none() {
+                continue;
+            }
+
+            if self.constants.contains(name) {
+                constants.push((name.to_string(), value));
+            } else {
+                variables.push((name.to_string(), value));
+            }
+        }
+
+        let json = format!(
+            "{{\"variables\": {}, \"constants\": {}}}\n",
+            object_to_json(variables.into_iter()).unwrap(),
+            object_to_json(constants.into_iter()).unwrap()
+        );
+
+        fs::write(path, json)
+    }
+
+    // Loads variables written by save into this scope. Existing variables are overwritten,
+    // except constants which keep their current value
+    pub fn load(&mut self, path: impl AsRef<Path>) -> io::Result<()> {
+        let invalid = |message: &str| io::Error::new(io::ErrorKind::InvalidData, message.to_string());
+
+        let saved = from_json(&fs::read_to_string(path)?).map_err(|e| invalid(&e))?;
+        let saved = match saved {
+            Value::Object(object) => object.clone(),
+            _ => return Err(invalid("Saved environment must be an object"))
+        };
+
+        for (section, constant) in [("variables", false), ("constants", true)] {
+            let values = match saved.get(section).and_then(|values| values.as_object().cloned()) {
+                Some(values) => values,
+                None => return Err(invalid(&format!("Saved environment is missing `{}`", section)))
+            };
+
+            for (name, value) in values.snapshot().iter() {
+                let name = Symbol::intern(name);
+
+                if self.constants.contains(&name) {
+                    continue;
+                }
+
+                if self.variables.contains_key(&name) {
+                    if self.frozen {
+                        fatal_error(&format!("Cannot assign to variable {} as its scope is frozen.", name));
+                    }
+                    self.variables.insert(name, value.clone());
+                } else {
+                    self.declare_var(name, value.clone(), constant);
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    // Removes a variable declared in this scope, returning its value
+    pub fn delete_var(&mut self, varname: Symbol) -> Value {
+        if self.frozen {
+            fatal_error(&format!("Cannot unset variable {} as its scope is frozen.", varname));
+        }
+
+        if self.constants.contains(&varname) {
+            fatal_error(&format!("Cannot unset constant variable {}.", varname));
+        }
+
+        match self.variables.remove(&varname) {
+            Some(value) => value,
+            None => fatal_error(&format!("Cannot unset variable {} as it is not defined in this scope.", varname))
+        }
+    }
 }
 
 
 pub struct SharedEnvironment(pub Arc<Mutex<Environment>>);
 
 impl SharedEnvironment {
-    pub fn resolve(&mut self, varname: &String) -> Arc<Mutex<Environment>> {
-        let inner = &self.0;
-        if inner.lock().unwrap().variables.contains_key(varname) {
-            Arc::clone(&inner)
-        } else {
-            let mut parent = SharedEnvironment(match &inner.lock().unwrap().parent {
-                Some(v) => Arc::clone(&v),
-                None => {
-                    fatal_error(&format!("Error resolving variable {}", varname))
-                }
+    pub fn resolve(&mut self, varname: &Symbol) -> Arc<Mutex<Environment>> {
+        let mut current = Arc::clone(&self.0);
+        loop {
+            if current.lock().unwrap().variables.contains_key(varname) {
+                return current;
+            }
+            let parent = current.lock().unwrap().parent.clone();
+            match parent {
+                Some(parent) => current = parent,
+                None => self.undefined(varname)
+            }
+        }
+    }
+
+    // Raised rather than fatal so a typo only costs the REPL that input. Lists the scopes that
+    // were searched, and suggests the closest name visible from here if there's one near enough
+    fn undefined(&self, varname: &Symbol) -> ! {
+        let mut scopes = Vec::new();
+        let mut names = HashSet::new();
+        let mut current = Some(Arc::clone(&self.0));
+        while let Some(scope) = current {
+            let scope = scope.lock().unwrap();
+            scopes.push(match (&scope.parent, scope.function) {
+                (None, _) => "global",
+                (Some(_), true) => "function",
+                (Some(_), false) => "block"
             });
-            parent.resolve(varname)
+            names.extend(scope.variables.keys().map(|name| name.as_str()));
+            current = scope.parent.clone();
+        }
+
+        let mut message = format!("Undefined variable `{}` (searched {} scope{})", varname, scopes.join(" → "), if scopes.len() == 1 { "" } else { "s" });
+        if let Some(suggestion) = closest_name(varname.as_str(), names) {
+            message += &format!(". Did you mean `{}`?", suggestion);
         }
+        raise(&message)
     }
 
-    pub fn lookup_var(&mut self, varname: String) -> Box<dyn RuntimeValue> {
+    pub fn lookup_var(&mut self, varname: Symbol) -> Value {
         let env = self.resolve(&varname);
         let x = env.lock().unwrap().variables.get(&varname).unwrap().clone();
         x
     }
 
-    pub fn assign_var(&mut self, varname: String, value: Box<dyn RuntimeValue>, bypass: bool) -> Box<dyn RuntimeValue> {
+    // The scope of the function call this scope is in, if any
+    pub fn function_scope(&self) -> Option<Arc<Mutex<Environment>>> {
+        let mut current = Arc::clone(&self.0);
+        loop {
+            if current.lock().unwrap().function {
+                return Some(current);
+            }
+            let parent = current.lock().unwrap().parent.clone()?;
+            current = parent;
+        }
+    }
+
+    // Whether a return has run in the function call this scope is in
+    pub fn returning(&self) -> bool {
+        self.function_scope().is_some_and(|scope| !scope.lock().unwrap().continue_interpreting)
+    }
+
+    pub fn global_scope(&self) -> Arc<Mutex<Environment>> {
+        let mut current = Arc::clone(&self.0);
+        loop {
+            let parent = current.lock().unwrap().parent.clone();
+            match parent {
+                Some(parent) => current = parent,
+                None => return current
+            }
+        }
+    }
+
+    // Assigns to a plain name. Outside of functions this assigns to wherever the name is declared,
+    // inside one it creates a local unless the name was declared global or nonlocal
+    pub fn assign_scoped(&mut self, varname: Symbol, value: Value) -> Value {
+        let function = match self.function_scope() {
+            Some(function) => function,
+            None => return self.assign_var(varname, value, false)
+        };
+
+        let outer = function.lock().unwrap().outer_names.get(&varname).copied();
+        match outer {
+            Some(OuterScope::Global) => {
+                let global = self.global_scope();
+                let declared = global.lock().unwrap().variables.contains_key(&varname);
+                if declared {
+                    SharedEnvironment(global).assign_var(varname, value, true)
+                } else {
+                    global.lock().unwrap().declare_var(varname, value, false)
+                }
+            },
+            Some(OuterScope::Nonlocal) => {
+                let parent = function.lock().unwrap().parent.clone().unwrap();
+                SharedEnvironment(parent).assign_var(varname, value, false)
+            },
+            None => {
+                let mut current = Arc::clone(&self.0);
+                loop {
+                    if current.lock().unwrap().variables.contains_key(&varname) {
+                        return SharedEnvironment(current).assign_var(varname, value, true);
+                    }
+                    if Arc::ptr_eq(&current, &function) {
+                        break;
+                    }
+                    let parent = current.lock().unwrap().parent.clone().unwrap();
+                    current = parent;
+                }
+
+                let value = function.lock().unwrap().declare_var(varname, value, false);
+                value
+            }
+        }
+    }
+
+    pub fn delete_var(&mut self, varname: Symbol) -> Value {
+        let env = self.resolve(&varname);
+        let value = env.lock().unwrap().delete_var(varname);
+        value
+    }
+
+    pub fn assign_var(&mut self, varname: Symbol, value: Value, bypass: bool) -> Value {
         let env;
         if bypass {
             env = Arc::clone(&self.0);
@@ -125,14 +776,71 @@ impl SharedEnvironment {
         }
 
 
+        if env.lock().unwrap().is_frozen() {
+            fatal_error(&format!("Cannot assign to variable {} as its scope is frozen.", varname));
+        }
+
         let is_constant = env.lock().unwrap().get_constants().contains(&varname);
 
         if is_constant {
             fatal_error("Cannot re-assign a constant variable.");
         }
 
-        env.lock().unwrap().variables.insert(varname, value.clone_self());
+        policy::check_value(&value);
+        env.lock().unwrap().variables.insert(varname, value.clone());
 
         value
     }
+
+    // Setting a member or element directly through a variable is only allowed where assigning to
+    // the variable would be. Checked before the new value is worked out, so nothing it does
+    // happens first
+    pub fn check_member_assignment(&mut self, varname: Symbol) {
+        let env = self.resolve(&varname);
+        let env = env.lock().unwrap();
+
+        if env.is_frozen() {
+            fatal_error(&format!("Cannot assign to a member of {} as its scope is frozen.", varname));
+        }
+        if env.get_constants().contains(&varname) {
+            fatal_error(&format!("Cannot assign to a member of {} as it is a constant.", varname));
+        }
+    }
+}
+
+// The name at the smallest edit distance from `name`, as long as it's close enough to be a typo
+// (a third of the name's length, at least 1). Ties go to whichever sorts first
+fn closest_name<'a>(name: &str, candidates: impl IntoIterator<Item = &'a str>) -> Option<&'a str> {
+    let limit = (name.chars().count() / 3).max(1);
+    candidates.into_iter()
+        .filter(|candidate| *candidate != name)
+        .map(|candidate| (edit_distance(name, candidate), candidate))
+        .filter(|(distance, _)| *distance <= limit)
+        .min()
+        .map(|(_, candidate)| candidate)
+}
+
+// Edit distance where inserting, deleting or changing a character, or swapping two next to each
+// other, is one edit each (optimal string alignment), so pritn is one edit from print
+fn edit_distance(a: &str, b: &str) -> usize {
+    let (a, b) = (a.chars().collect::<Vec<_>>(), b.chars().collect::<Vec<_>>());
+    let mut distances = vec![vec![0; b.len() + 1]; a.len() + 1];
+    for (i, row) in distances.iter_mut().enumerate() {
+        row[0] = i;
+    }
+    for (j, cell) in distances[0].iter_mut().enumerate() {
+        *cell = j;
+    }
+
+    for i in 1..=a.len() {
+        for j in 1..=b.len() {
+            let cost = if a[i - 1] == b[j - 1] { 0 } else { 1 };
+            let mut distance = (distances[i - 1][j] + 1).min(distances[i][j - 1] + 1).min(distances[i - 1][j - 1] + cost);
+            if i > 1 && j > 1 && a[i - 1] == b[j - 2] && a[i - 2] == b[j - 1] {
+                distance = distance.min(distances[i - 2][j - 2] + 1);
+            }
+            distances[i][j] = distance;
+        }
+    }
+    distances[a.len()][b.len()]
 }
\ No newline at end of file
@@ -0,0 +1,82 @@
+use std::{fs, path::{Path, PathBuf}, sync::{Arc, Mutex, PoisonError}, thread, time::{Duration, SystemTime}};
+
+use crate::{catch_errors, diagnostics, frontend::{errors::ParseError, parser::Parser}, Error, LoggingLevel};
+
+use super::{environment::Environment, interpreter::eval_program, values::Value};
+
+// Re-runs a script into the same global environment whenever it changes. Functions are
+// declared again with their new bodies, but variables that already exist keep their values
+pub struct HotReloader {
+    path: PathBuf,
+    env: Arc<Mutex<Environment>>,
+    pub parser: Parser,
+    last_modified: Option<SystemTime>
+}
+
+impl HotReloader {
+    pub fn new(path: impl AsRef<Path>, env: Arc<Mutex<Environment>>) -> Self {
+        HotReloader {
+            path: path.as_ref().to_path_buf(),
+            env,
+            parser: Parser::new(),
+            last_modified: None
+        }
+    }
+
+    // A runtime error only ends this run of the script, the next change is loaded as usual
+    pub fn reload(&mut self) -> Result<Value, Error> {
+        self.last_modified = self.modified();
+
+        let source = fs::read_to_string(&self.path).map_err(ParseError::io)?;
+        let program = self.parser.parse(source)?;
+
+        let result = {
+            let _reloading = Reloading::start(&self.env);
+            catch_errors(|| eval_program(program, Arc::clone(&self.env)))
+        };
+        // An error raised while the scope was locked leaves it poisoned, but it's still usable
+        self.env.clear_poison();
+
+        result
+    }
+
+    // Reloads if the file changed since it was last loaded
+    pub fn poll(&mut self) -> Option<Result<Value, Error>> {
+        if self.last_modified.is_some() && self.modified() == self.last_modified {
+            return None;
+        }
+
+        Some(self.reload())
+    }
+
+    pub fn watch(&mut self, interval: Duration) -> ! {
+        loop {
+            // Runtime errors were reported as they happened
+            if let Some(Err(Error::Parse(e))) = self.poll() {
+                diagnostics::report(LoggingLevel::Error, &e.to_string());
+            }
+
+            thread::sleep(interval);
+        }
+    }
+
+    fn modified(&self) -> Option<SystemTime> {
+        fs::metadata(&self.path).and_then(|metadata| metadata.modified()).ok()
+    }
+}
+
+// Marks the scope as reloading until dropped, also when a runtime error unwinds out of the reload
+struct Reloading<'a>(&'a Arc<Mutex<Environment>>);
+
+impl<'a> Reloading<'a> {
+    fn start(env: &'a Arc<Mutex<Environment>>) -> Self {
+        env.lock().unwrap().reloading = true;
+        Reloading(env)
+    }
+}
+
+impl Drop for Reloading<'_> {
+    fn drop(&mut self) {
+        self.0.lock().unwrap_or_else(PoisonError::into_inner).reloading = false;
+    }
+}
\ No newline at end of file
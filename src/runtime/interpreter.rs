@@ -1,92 +1,56 @@
 use std::sync::{Arc, Mutex};
 
-use crate::{fatal_error, MK_NUMBER, MK_STRING};
-use crate::runtime::values::{NumberValue, RuntimeValue};
-use crate::frontend::ast::{AssignmentExpr, BinaryExpr, CallExpr, ComparativeExpr, ForStmt, FunctionDeclaration, Identifier, IfStmt, ListLiteral, MemberExpr, NodeType, ObjectLiteral, Program, ReturnStmt, Stmt, StmtValue, StmtWrapper, VarDeclaration, WhileStmt};
+use crate::{MK_NUMBER, MK_STRING};
+use crate::runtime::values::Value;
+use crate::frontend::ast::{Ast, Expr, ExprId, Program, Stmt};
 
-use super::environment::Environment;
-use super::values::StringValue;
+use super::environment::{Environment, OuterScope};
+use super::policy;
 
 use crate::eval::eval_statements::*;
 use crate::eval::eval_expressions::*;
 
-pub fn eval(ast_node: StmtWrapper, env: Arc<Mutex<Environment>>) -> Box<dyn RuntimeValue> {
-    match ast_node.get_kind() {
-        // Handle expressions
-        NodeType::NumericLiteral => {
-            Box::new(MK_NUMBER!(if let StmtValue::F64(val) = ast_node.get_value().unwrap() { val } else { 0.0 as f64}))},
-        NodeType::String => {
-            Box::new(MK_STRING!(if let StmtValue::StringVal(val) = ast_node.get_value().unwrap() { val } else { String::new() }))},
-        NodeType::BinaryExpr => {
-            let bin_expr = ast_node.as_any().downcast_ref::<BinaryExpr>().expect("Failed to downcast to BinaryExpr.");
-            eval_binop_expr(bin_expr.clone(), env)
-        },
-        NodeType::ComparativeExpr => {
-            let comp_expr = ast_node.as_any().downcast_ref::<ComparativeExpr>().expect("Failed to downcast to ComparativeExpr.");
-            eval_comp_expr(comp_expr.clone(), env)
-        }
-        NodeType::Identifier => {
-            let identifier = ast_node.as_any().downcast_ref::<Identifier>().expect("Failed to downcast to Identifier.");
-            let value = eval_identifier(identifier.clone(), Arc::clone(&env));
-            value
-        },
-        NodeType::Object => {
-            let object = ast_node.as_any().downcast_ref::<ObjectLiteral>().expect("Failed to downcast to ObjectLiteral.");
-            let value = eval_object_expr(object.clone(), Arc::clone(&env));
-            value
-        },
-        NodeType::List => {
-            let list = ast_node.as_any().downcast_ref::<ListLiteral>().expect("Failed to downcast to ListLiteral.");
-            eval_list_expr(list.clone(), Arc::clone(&env))
-        },
-        NodeType::MemberExpr => {
-            let member_expr = ast_node.as_any().downcast_ref::<MemberExpr>().expect("Failed to downcast to MemberExpr.");
-            let value = eval_member_expr(member_expr.clone(), Arc::clone(&env));
-            value
-        },
-        NodeType::AssignmentExpr => {
-            let assignment_expr = ast_node.as_any().downcast_ref::<AssignmentExpr>().expect("Failed to downcast to AssignmentExpr.");
-            let value = eval_assignment(assignment_expr.clone(), Arc::clone(&env));
-            value
-        },
-        NodeType::CallExpr => {
-            let call_expr = ast_node.as_any().downcast_ref::<CallExpr>().expect("Failed to downcast to CallExpr.");
-            let value = eval_call(call_expr.clone(), Arc::clone(&env));
-            value
-        },
-        // Handle statements
-        NodeType::VarDeclaration => {
-            let var_declaration = ast_node.as_any().downcast_ref::<VarDeclaration>().expect("Failed to downcast to VarDeclaration.");
-            let value = eval_var_declaration(var_declaration.clone(), Arc::clone(&env));
-            value
-        },
-        NodeType::FunctionDeclaration => {
-            let function_declaration = ast_node.as_any().downcast_ref::<FunctionDeclaration>().expect("Failed to downcast to FunctionDeclaration.");
-            let value = eval_function_declaration(function_declaration.clone(), Arc::clone(&env));
-            value
-        },
-        NodeType::Return => {
-            let return_stmt = ast_node.as_any().downcast_ref::<ReturnStmt>().expect("Failed to downcast to ReturnStmt");
-            eval_return(return_stmt.clone(), env)
-        },
-        NodeType::If => {
-            let if_stmt = ast_node.as_any().downcast_ref::<IfStmt>().expect("Failed to downcast to IfStmt");
-            eval_if(if_stmt.clone(), env)
-        },
-        NodeType::While => {
-            let while_stmt = ast_node.as_any().downcast_ref::<WhileStmt>().expect("Failed to downcast to WhileStmt");
-            eval_while(while_stmt.clone(), env)
-        },
-        NodeType::For => {
-            let for_stmt = ast_node.as_any().downcast_ref::<ForStmt>().expect("Failed to downcast to ForStmt");
-            eval_for(for_stmt.clone(), env)
-        }
-        NodeType::Program => {
-            let program = ast_node.as_any().downcast_ref::<Program>().expect("Failed to downcast to Program.");
-            eval_program(program.clone(), env)
-        },
-        _ =>  {
-            fatal_error(&format!("This statement has not yet been set up for interpretation:\n{:?}", ast_node));
-        }
+// Runs a whole program, its Ast is kept for as long as any function it declared
+pub fn eval_program(program: Program, env: Arc<Mutex<Environment>>) -> Value {
+    let ast = Arc::new(program.ast);
+    ast[program.body].run(&ast, env, false).0
+}
+
+pub fn eval(stmt: &Stmt, ast: &Arc<Ast>, env: Arc<Mutex<Environment>>) -> Value {
+    // Expressions are checked by eval_expr
+    if !matches!(stmt, Stmt::Expr(_)) {
+        policy::check(&env);
+    }
+
+    match stmt {
+        Stmt::VarDeclaration(var_declaration) => eval_var_declaration(var_declaration, ast, env),
+        Stmt::Unset(unset_stmt) => eval_unset(unset_stmt, env),
+        Stmt::Global(outer_scope_stmt) => eval_outer_scope(outer_scope_stmt, OuterScope::Global, env),
+        Stmt::Nonlocal(outer_scope_stmt) => eval_outer_scope(outer_scope_stmt, OuterScope::Nonlocal, env),
+        Stmt::FunctionDeclaration(function_declaration) => eval_function_declaration(function_declaration, ast, env),
+        Stmt::Return(return_stmt) => eval_return(return_stmt, ast, env),
+        Stmt::While(while_stmt) => eval_while(while_stmt, ast, env),
+        Stmt::For(for_stmt) => eval_for(for_stmt, ast, env),
+        Stmt::Expr(expr) => eval_expr(*expr, ast, env)
+    }
+}
+
+pub fn eval_expr(expr: ExprId, ast: &Arc<Ast>, env: Arc<Mutex<Environment>>) -> Value {
+    policy::check(&env);
+
+    match &ast[expr] {
+        Expr::Number(number) => MK_NUMBER!(number.value),
+        Expr::String(string) => MK_STRING!(string.string.to_string()),
+        Expr::Binary(bin_expr) => eval_binop_expr(bin_expr, ast, env),
+        Expr::Comparative(comp_expr) => eval_comp_expr(comp_expr, ast, env),
+        Expr::Identifier(identifier) => eval_identifier(identifier, env),
+        Expr::Object(object) => eval_object_expr(object, ast, env),
+        Expr::List(list) => eval_list_expr(list, ast, env),
+        Expr::Member(member_expr) => eval_member_expr(member_expr, ast, env),
+        Expr::Assignment(assignment_expr) => eval_assignment(assignment_expr, ast, env),
+        Expr::Call(call_expr) => eval_call(call_expr, ast, env),
+        Expr::Function(function_expr) => eval_function_expr(function_expr, ast, env),
+        Expr::If(if_stmt) => eval_if(if_stmt, ast, env),
+        Expr::Body(body) => eval_block(*body, ast, env)
     }
 }
\ No newline at end of file
@@ -0,0 +1,302 @@
+use std::{collections::HashMap, sync::Arc};
+
+use super::values::{ListValue, ObjectValue, Value};
+
+// Converts plain data values (null, bools, numbers, strings, lists and objects) to JSON.
+// Returns None if the value, or anything inside it, has no JSON form (functions, NaN, ...)
+pub fn to_json(value: &Value) -> Option<String> {
+    serialize(value).ok()
+}
+
+// Same as to_json, but the error says what couldn't be converted and where, for hosts handing
+// results on (to a web client, a file, ...) that need to report why
+pub fn serialize(value: &Value) -> Result<String, String> {
+    serialize_at(value, "value", &mut Vec::new())
+}
+
+// `within` holds the lists and objects the value is inside of, as one that contains itself has
+// no JSON form
+fn serialize_at(value: &Value, path: &str, within: &mut Vec<usize>) -> Result<String, String> {
+    match value {
+        Value::Null => Ok(String::from("null")),
+        Value::Bool(_) => Ok(value.to_string()),
+        Value::Number(number) => {
+            if number.is_finite() {
+                Ok(value.to_string())
+            } else {
+                Err(format!("Cannot serialize {} at {}, JSON has no infinite or NaN numbers", number, path))
+            }
+        },
+        Value::Str(string) => Ok(quote(string)),
+        Value::List(list) => {
+            enter(within, Arc::as_ptr(&list.elements) as usize, path)?;
+            let mut elements = Vec::new();
+            for (index, element) in list.snapshot().iter().enumerate() {
+                elements.push(serialize_at(element, &format!("{}[{}]", path, index), within)?);
+            }
+            within.pop();
+
+            Ok(format!("[{}]", elements.join(", ")))
+        },
+        Value::Object(object) => {
+            enter(within, Arc::as_ptr(&object.properties) as usize, path)?;
+            let properties = object.snapshot();
+            let mut properties = properties.iter().collect::<Vec<_>>();
+            properties.sort_by(|a, b| a.0.cmp(b.0));
+
+            let mut members = Vec::new();
+            for (key, value) in properties {
+                members.push(format!("{}: {}", quote(key), serialize_at(value, &format!("{}.{}", path, key), within)?));
+            }
+            within.pop();
+
+            Ok(format!("{{{}}}", members.join(", ")))
+        },
+        _ => Err(format!("Cannot serialize {} at {}, only data values have a JSON form", value.get_type(), path))
+    }
+}
+
+fn enter(within: &mut Vec<usize>, address: usize, path: &str) -> Result<(), String> {
+    if within.contains(&address) {
+        return Err(format!("Cannot serialize {}, it contains itself", path));
+    }
+    within.push(address);
+    Ok(())
+}
+
+// Builds a JSON object, keys are sorted so the output is stable
+pub fn object_to_json<'a>(properties: impl Iterator<Item = (String, &'a Value)>) -> Option<String> {
+    let mut properties = properties.collect::<Vec<_>>();
+    properties.sort_by(|a, b| a.0.cmp(&b.0));
+
+    let mut members = Vec::new();
+    for (key, value) in properties {
+        members.push(format!("{}: {}", quote(&key), to_json(value)?));
+    }
+
+    Some(format!("{{{}}}", members.join(", ")))
+}
+
+fn quote(string: &str) -> String {
+    let mut quoted = String::from("\"");
+
+    for c in string.chars() {
+        match c {
+            '"' => quoted.push_str("\\\""),
+            '\\' => quoted.push_str("\\\\"),
+            '\n' => quoted.push_str("\\n"),
+            '\r' => quoted.push_str("\\r"),
+            '\t' => quoted.push_str("\\t"),
+            c if (c as u32) < 0x20 => quoted.push_str(&format!("\\u{:04x}", c as u32)),
+            c => quoted.push(c)
+        }
+    }
+
+    quoted.push('"');
+    quoted
+}
+
+pub fn from_json(source: &str) -> Result<Value, String> {
+    let mut reader = JsonReader { chars: source.chars().collect(), position: 0 };
+
+    let value = reader.read_value()?;
+    reader.skip_whitespace();
+    if reader.position != reader.chars.len() {
+        return Err(reader.error("Unexpected trailing characters"));
+    }
+
+    Ok(value)
+}
+
+struct JsonReader {
+    chars: Vec<char>,
+    position: usize
+}
+
+impl JsonReader {
+    fn error(&self, message: &str) -> String {
+        format!("Invalid JSON at character {}: {}", self.position, message)
+    }
+
+    fn at(&self) -> Option<char> {
+        self.chars.get(self.position).copied()
+    }
+
+    fn skip_whitespace(&mut self) {
+        while self.at().is_some_and(|c| c.is_whitespace()) {
+            self.position += 1;
+        }
+    }
+
+    fn expect(&mut self, expected: char) -> Result<(), String> {
+        self.skip_whitespace();
+        if self.at() != Some(expected) {
+            return Err(self.error(&format!("Expected `{}`", expected)));
+        }
+        self.position += 1;
+        Ok(())
+    }
+
+    fn read_word(&mut self, word: &str) -> Result<(), String> {
+        for expected in word.chars() {
+            if self.at() != Some(expected) {
+                return Err(self.error(&format!("Expected `{}`", word)));
+            }
+            self.position += 1;
+        }
+        Ok(())
+    }
+
+    fn read_value(&mut self) -> Result<Value, String> {
+        self.skip_whitespace();
+
+        match self.at() {
+            Some('n') => {
+                self.read_word("null")?;
+                Ok(Value::Null)
+            },
+            Some('t') => {
+                self.read_word("true")?;
+                Ok(Value::Bool(true))
+            },
+            Some('f') => {
+                self.read_word("false")?;
+                Ok(Value::Bool(false))
+            },
+            Some('"') => Ok(Value::Str(self.read_string()?)),
+            Some('[') => {
+                self.position += 1;
+                let mut elements = Vec::new();
+
+                self.skip_whitespace();
+                if self.at() == Some(']') {
+                    self.position += 1;
+                    return Ok(Value::List(ListValue::new(elements)));
+                }
+
+                loop {
+                    elements.push(self.read_value()?);
+                    self.skip_whitespace();
+                    match self.at() {
+                        Some(',') => self.position += 1,
+                        Some(']') => {
+                            self.position += 1;
+                            break;
+                        },
+                        _ => return Err(self.error("Expected `,` or `]` in list"))
+                    }
+                }
+
+                Ok(Value::List(ListValue::new(elements)))
+            },
+            Some('{') => {
+                self.position += 1;
+                let mut properties = HashMap::new();
+
+                self.skip_whitespace();
+                if self.at() == Some('}') {
+                    self.position += 1;
+                    return Ok(Value::Object(ObjectValue::new(properties)));
+                }
+
+                loop {
+                    self.skip_whitespace();
+                    if self.at() != Some('"') {
+                        return Err(self.error("Expected string key in object"));
+                    }
+                    let key = self.read_string()?;
+                    self.expect(':')?;
+                    properties.insert(key, self.read_value()?);
+
+                    self.skip_whitespace();
+                    match self.at() {
+                        Some(',') => self.position += 1,
+                        Some('}') => {
+                            self.position += 1;
+                            break;
+                        },
+                        _ => return Err(self.error("Expected `,` or `}` in object"))
+                    }
+                }
+
+                Ok(Value::Object(ObjectValue::new(properties)))
+            },
+            Some(c) if c == '-' || c.is_ascii_digit() => {
+                let start = self.position;
+                while self.at().is_some_and(|c| c == '-' || c == '+' || c == '.' || c == 'e' || c == 'E' || c.is_ascii_digit()) {
+                    self.position += 1;
+                }
+
+                let number = self.chars[start..self.position].iter().collect::<String>();
+                match number.parse::<f64>() {
+                    Ok(value) => Ok(Value::Number(value)),
+                    Err(_) => Err(self.error(&format!("Invalid number `{}`", number)))
+                }
+            },
+            Some(c) => Err(self.error(&format!("Unexpected character `{}`", c))),
+            None => Err(self.error("Unexpected end of input"))
+        }
+    }
+
+    fn read_string(&mut self) -> Result<String, String> {
+        // Skip the opening quote
+        self.position += 1;
+        let mut string = String::new();
+
+        loop {
+            let c = match self.at() {
+                Some(c) => c,
+                None => return Err(self.error("Unterminated string"))
+            };
+            self.position += 1;
+
+            match c {
+                '"' => return Ok(string),
+                '\\' => {
+                    let escaped = match self.at() {
+                        Some(c) => c,
+                        None => return Err(self.error("Unterminated string"))
+                    };
+                    self.position += 1;
+
+                    match escaped {
+                        '"' => string.push('"'),
+                        '\\' => string.push('\\'),
+                        '/' => string.push('/'),
+                        'n' => string.push('\n'),
+                        'r' => string.push('\r'),
+                        't' => string.push('\t'),
+                        'b' => string.push('\u{8}'),
+                        'f' => string.push('\u{c}'),
+                        'u' => {
+                            let mut code = self.read_hex()?;
+                            // Surrogate pairs are written as two escapes
+                            if (0xD800..0xDC00).contains(&code) {
+                                self.read_word("\\u")?;
+                                let low = self.read_hex()?;
+                                code = 0x10000 + ((code - 0xD800) << 10) + (low.wrapping_sub(0xDC00) & 0x3FF);
+                            }
+                            match char::from_u32(code) {
+                                Some(c) => string.push(c),
+                                None => return Err(self.error("Invalid unicode escape"))
+                            }
+                        },
+                        _ => return Err(self.error(&format!("Invalid escape `\\{}`", escaped)))
+                    }
+                },
+                c => string.push(c)
+            }
+        }
+    }
+
+    fn read_hex(&mut self) -> Result<u32, String> {
+        if self.position + 4 > self.chars.len() {
+            return Err(self.error("Invalid unicode escape"));
+        }
+
+        let hex = self.chars[self.position..self.position + 4].iter().collect::<String>();
+        self.position += 4;
+
+        u32::from_str_radix(&hex, 16).map_err(|_| self.error("Invalid unicode escape"))
+    }
+}
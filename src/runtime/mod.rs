@@ -1,4 +1,12 @@
 pub mod interpreter;
 pub mod values;
 pub mod environment;
-pub mod native_funcs;
\ No newline at end of file
+pub mod native_funcs;
+pub mod json;
+pub mod regex;
+pub mod hot_reload;
+pub mod convert;
+pub mod stdio;
+pub mod context;
+pub mod policy;
+pub mod trace;
\ No newline at end of file
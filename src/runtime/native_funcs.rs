@@ -1,9 +1,40 @@
-use crate::{fatal_error, runtime::values::NullValue, MK_STRING};
-use std::{io::{self, Write}, process::exit, sync::Mutex, thread, time::{Duration, SystemTime}};
+use crate::{fatal_error, raise, frontend::parser::Parser, MK_BOOL, MK_NATIVE_FN, MK_STRING};
+use std::{collections::HashMap, process::exit, sync::Arc, time::{Duration, SystemTime}};
+
+use super::{context::NativeContext, environment::Environment, interpreter::eval_program, policy, values::{strict_equals, ObjectValue, Value, ValueType}};
+
+pub mod csv;
+pub mod encoding;
+pub mod format;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod fs;
+#[cfg(all(feature = "http", not(target_arch = "wasm32")))]
+pub mod http;
+pub mod list;
+pub mod log;
+pub mod math;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod net;
+pub mod random;
+pub mod regex;
+pub mod stats;
+pub mod term;
+pub mod time;
+
+fn expect_arg_count(args: &[Value], count: usize) {
+    if args.len() != count {
+        fatal_error(&format!("Expected {} argument{}, found {}", count, if count == 1 { "" } else { "s" }, args.len()));
+    }
+}
 
-use super::{environment::Environment, values::{NumberValue, RuntimeValue, StringValue, ValueType}};
+fn string_arg(args: &[Value], index: usize) -> String {
+    match &args[index] {
+        Value::Str(string) => string.clone(),
+        _ => fatal_error(&format!("Expected string, found {}", args[index].get_type()))
+    }
+}
 
-pub fn native_print(args: Vec<Box<dyn RuntimeValue>>, _env: &Mutex<Environment>) -> Box<dyn RuntimeValue> {
+pub fn native_print(args: Vec<Value>, ctx: &NativeContext) -> Value {
     let mut to_print = String::new();
 
     for arg in args {
@@ -11,83 +42,109 @@ pub fn native_print(args: Vec<Box<dyn RuntimeValue>>, _env: &Mutex<Environment>)
         to_print.push(' ');
     }
 
-    
-    println!("{}", to_print);
+    to_print.push('\n');
+    ctx.write(&to_print);
 
-    Box::new(NullValue {})
+    Value::Null
 }
 
-pub fn native_time(_args: Vec<Box<dyn RuntimeValue>>, _env: &Mutex<Environment>) -> Box<dyn RuntimeValue> {
-    return Box::new(NumberValue {
-        value: SystemTime::now().duration_since(SystemTime::UNIX_EPOCH).unwrap().as_secs_f64()
-    });
+pub fn native_time(_args: Vec<Value>, _ctx: &NativeContext) -> Value {
+    return Value::Number(SystemTime::now().duration_since(SystemTime::UNIX_EPOCH).unwrap().as_secs_f64());
 }
 
-pub fn native_sleep(args: Vec<Box<dyn RuntimeValue>>, _env: &Mutex<Environment>) -> Box<dyn RuntimeValue> {
+pub fn native_sleep(args: Vec<Value>, _ctx: &NativeContext) -> Value {
     if args.len() != 1 {
         fatal_error(&format!("Expected 1 argument, found {}.", args.len()));
     }
 
-    if args[0].get_type() != ValueType::Number {
-        fatal_error(&format!("Expected number, found {}", args[0].get_type()))
-    }
-
-    let number = args[0].as_any().downcast_ref::<NumberValue>().unwrap().clone();
+    let seconds = match args[0] {
+        Value::Number(seconds) => seconds,
+        _ => fatal_error(&format!("Expected number, found {}", args[0].get_type()))
+    };
 
-    thread::sleep(Duration::from_secs_f64(number.value));
+    policy::sleep(Duration::from_secs_f64(seconds));
 
-    Box::new(NullValue {})
+    Value::Null
 }
 
-pub fn native_input(args: Vec<Box<dyn RuntimeValue>>, _env: &Mutex<Environment>) -> Box<dyn RuntimeValue> {
+pub fn native_input(args: Vec<Value>, ctx: &NativeContext) -> Value {
     if args.len() > 1 {
         fatal_error(&format!("Expected less than 2 arguments, found {}", args.len()));
     }
 
-    if args.len() == 1 {
-        if args[0].get_type() == ValueType::String {
-            print!("{}", args[0].as_any().downcast_ref::<StringValue>().expect("Failed to downcast to StringValue.").to_string());
-            io::stdout().flush().unwrap();
-        }
+    if let Some(Value::Str(prompt)) = args.first() {
+        ctx.write(prompt);
     }
 
-    let mut input = String::new();
-    io::stdin()
-        .read_line(&mut input)
-        .unwrap();
-
-    // Remove the \n or \r at end
-    let mut chars = input.chars();
-    chars.next_back();
-    input = String::from(chars.as_str());
-
-    Box::new(StringValue { value: input })
+    Value::Str(ctx.read_line())
 
 }
 
-pub fn native_exit(args: Vec<Box<dyn RuntimeValue>>, _env: &Mutex<Environment>) -> Box<dyn RuntimeValue> {
+pub fn native_exit(args: Vec<Value>, _ctx: &NativeContext) -> Value {
     let mut code = 0;
 
     if args.len() == 1 {
-        if args[0].get_type() == ValueType::Number {
-            code = args[0].as_any().downcast_ref::<NumberValue>().unwrap().value as i32;
-        } else {
-            fatal_error(&format!("Expected number, found {}", args[0].get_type()));
+        match args[0] {
+            Value::Number(number) => code = number as i32,
+            _ => fatal_error(&format!("Expected number, found {}", args[0].get_type()))
         }
     }
 
     exit(code);
 }
 
-pub fn to_string(args: Vec<Box<dyn RuntimeValue>>, _env: &Mutex<Environment>) -> Box<dyn RuntimeValue> {
+pub fn native_assert(args: Vec<Value>, _ctx: &NativeContext) -> Value {
+    if args.is_empty() || args.len() > 2 {
+        fatal_error(&format!("Expected 1 or 2 arguments, found {}", args.len()));
+    }
+
+    if !args[0].as_bool() {
+        match args.get(1) {
+            Some(message) => raise(&format!("Assertion failed: {}", message)),
+            None => raise("Assertion failed")
+        }
+    }
+
+    Value::Null
+}
+
+// expect_eq(actual, expected) fails like assert unless the two are equal by ==
+pub fn native_expect_eq(args: Vec<Value>, _ctx: &NativeContext) -> Value {
+    if args.len() != 2 {
+        fatal_error(&format!("Expected 2 arguments, found {}", args.len()));
+    }
+
+    let (actual, expected) = (&args[0], &args[1]);
+    if actual.get_type() != expected.get_type() {
+        raise(&format!("Expected {} ({}), found {} ({})", expected, expected.get_type(), actual, actual.get_type()));
+    }
+    if !strict_equals(actual, expected) {
+        raise(&format!("Expected {}, found {}", expected, actual));
+    }
+
+    Value::Null
+}
+
+pub fn native_panic(args: Vec<Value>, _ctx: &NativeContext) -> Value {
+    if args.len() > 1 {
+        fatal_error(&format!("Expected less than 2 arguments, found {}", args.len()));
+    }
+
+    match args.first() {
+        Some(message) => raise(&format!("Panic: {}", message)),
+        None => raise("Panic")
+    }
+}
+
+pub fn to_string(args: Vec<Value>, _ctx: &NativeContext) -> Value {
     if args.len() != 1 {
         fatal_error(&format!("Expected 1 argument, found {}", args.len()));
     }
 
-    Box::new(MK_STRING!(args[1].to_string()))
+    MK_STRING!(args[0].to_string())
 }
 
-pub fn to_int(args: Vec<Box<dyn RuntimeValue>>, _env: &Mutex<Environment>) -> Box<dyn RuntimeValue> {
+pub fn to_int(args: Vec<Value>, _ctx: &NativeContext) -> Value {
     if args.len() != 1 {
         fatal_error(&format!("Expected 1 argument, found {}", args.len()));
     }
@@ -97,10 +154,91 @@ pub fn to_int(args: Vec<Box<dyn RuntimeValue>>, _env: &Mutex<Environment>) -> Bo
             Ok(v) => v,
             Err(e) => fatal_error(&e.to_string())
         };
-        return Box::new(NumberValue { value: parsed });
+        return Value::Number(parsed);
     } else if args[0].get_type() == ValueType::Number {
         return args[0].clone();
     }
 
     fatal_error("Cannot convert to number");
+}
+
+fn scope_to_object(env: &Environment) -> ObjectValue {
+    let mut properties = HashMap::new();
+
+    for (name, value) in env.variables.iter() {
+        properties.insert(name.to_string(), value.clone());
+    }
+
+    ObjectValue::new(properties)
+}
+
+// Variables declared in the calling scope only
+pub fn native_locals(args: Vec<Value>, ctx: &NativeContext) -> Value {
+    if !args.is_empty() {
+        fatal_error(&format!("Expected 0 arguments, found {}", args.len()));
+    }
+
+    Value::Object(scope_to_object(&ctx.env.lock().unwrap()))
+}
+
+// Variables declared in the global scope
+pub fn native_globals(args: Vec<Value>, ctx: &NativeContext) -> Value {
+    if !args.is_empty() {
+        fatal_error(&format!("Expected 0 arguments, found {}", args.len()));
+    }
+
+    let global = ctx.global();
+    let object = scope_to_object(&global.lock().unwrap());
+    Value::Object(object)
+}
+
+// Every variable visible from the calling scope, inner declarations shadowing outer ones
+pub fn native_vars(args: Vec<Value>, ctx: &NativeContext) -> Value {
+    if !args.is_empty() {
+        fatal_error(&format!("Expected 0 arguments, found {}", args.len()));
+    }
+
+    let object = scope_to_object(&ctx.env.lock().unwrap());
+    let mut parent = ctx.env.lock().unwrap().parent.clone();
+
+    while let Some(scope) = parent {
+        let scope = scope.lock().unwrap();
+        for (name, value) in scope.variables.iter() {
+            if object.get(name.as_str()).is_none() {
+                object.set(name.to_string(), value.clone());
+            }
+        }
+        parent = scope.parent.clone();
+    }
+
+    Value::Object(object)
+}
+
+// The name of a value's type, as used in error messages
+pub fn native_type(args: Vec<Value>, _ctx: &NativeContext) -> Value {
+    expect_arg_count(&args, 1);
+
+    MK_STRING!(args[0].get_type().to_string())
+}
+
+// Builds is_number, is_string and the rest. is_function is true for natives as well
+pub fn type_check(value_types: &'static [ValueType]) -> Value {
+    MK_NATIVE_FN!(move |args: Vec<Value>, _ctx: &NativeContext| -> Value {
+        expect_arg_count(&args, 1);
+
+        MK_BOOL!(value_types.contains(&args[0].get_type()))
+    })
+}
+
+// Runs source code in the calling scope, so declarations made by it stay visible afterwards.
+// Evaluates to the value of the last statement
+pub fn native_eval(args: Vec<Value>, ctx: &NativeContext) -> Value {
+    expect_arg_count(&args, 1);
+
+    let program = match Parser::new().parse(string_arg(&args, 0)) {
+        Ok(program) => program,
+        Err(e) => fatal_error(&e.to_string())
+    };
+
+    eval_program(program, Arc::clone(&ctx.env))
 }
\ No newline at end of file
@@ -0,0 +1,162 @@
+use std::collections::HashMap;
+
+use crate::{fatal_error, runtime::{context::NativeContext, values::{ListValue, ObjectValue, Value}}};
+
+use super::string_arg;
+
+// csv.parse(text) gives a list of rows, each a list of strings. csv.parse(text, { header: true })
+// uses the first row as keys and gives a list of objects instead
+pub fn native_csv_parse(args: Vec<Value>, _ctx: &NativeContext) -> Value {
+    if args.is_empty() || args.len() > 2 {
+        fatal_error(&format!("Expected 1 or 2 arguments, found {}", args.len()));
+    }
+
+    let rows = parse_rows(&string_arg(&args, 0));
+
+    let header = match args.get(1) {
+        Some(options) => match options {
+            Value::Object(options) => options.get("header").is_some_and(|header| header.as_bool()),
+            _ => fatal_error(&format!("Expected object, found {}", options.get_type()))
+        },
+        None => false
+    };
+
+    if !header {
+        return Value::List(ListValue::new(
+            rows.into_iter().map(|row| Value::List(string_list(row))).collect()
+        ));
+    }
+
+    let mut rows = rows.into_iter();
+    let keys = rows.next().unwrap_or_default();
+
+    let mut elements: Vec<Value> = Vec::new();
+    for row in rows {
+        if row.len() != keys.len() {
+            fatal_error(&format!("CSV row has {} fields but the header has {}", row.len(), keys.len()));
+        }
+
+        let mut properties = HashMap::new();
+        for (key, field) in keys.iter().zip(row) {
+            properties.insert(key.clone(), Value::Str(field));
+        }
+        elements.push(Value::Object(ObjectValue::new(properties)));
+    }
+
+    Value::List(ListValue::new(elements))
+}
+
+// csv.write(rows) takes a list of lists, or a list of objects which get a header row of their sorted keys
+pub fn native_csv_write(args: Vec<Value>, _ctx: &NativeContext) -> Value {
+    if args.len() != 1 {
+        fatal_error(&format!("Expected 1 argument, found {}", args.len()));
+    }
+
+    let rows = match &args[0] {
+        Value::List(rows) => rows,
+        _ => fatal_error(&format!("Expected list, found {}", args[0].get_type()))
+    };
+
+    let mut output = String::new();
+    let mut keys: Option<Vec<String>> = None;
+
+    for row in rows.snapshot().iter() {
+        let fields = match row {
+            Value::List(list) => list.snapshot().iter().map(field_to_string).collect::<Vec<_>>(),
+            Value::Object(object) => {
+                let keys = keys.get_or_insert_with(|| {
+                    let keys = object.keys();
+                    write_row(&mut output, &keys);
+                    keys
+                });
+
+                keys.iter().map(|key| match object.get(key) {
+                    Some(field) => field_to_string(&field),
+                    None => String::new()
+                }).collect()
+            },
+            _ => fatal_error(&format!("Expected list or object as CSV row, found {}", row.get_type()))
+        };
+
+        write_row(&mut output, &fields);
+    }
+
+    Value::Str(output)
+}
+
+fn string_list(strings: Vec<String>) -> ListValue {
+    ListValue::new(
+        strings.into_iter().map(Value::Str).collect()
+    )
+}
+
+fn field_to_string(field: &Value) -> String {
+    match field {
+        Value::Str(string) => string.clone(),
+        _ => field.to_string()
+    }
+}
+
+fn write_row(output: &mut String, fields: &[String]) {
+    let fields = fields.iter().map(|field| {
+        if field.contains(['"', ',', '\n', '\r']) {
+            format!("\"{}\"", field.replace('"', "\"\""))
+        } else {
+            field.clone()
+        }
+    }).collect::<Vec<_>>();
+
+    output.push_str(&fields.join(","));
+    output.push('\n');
+}
+
+// Follows RFC 4180: fields may be quoted, quotes inside them are doubled and quoted fields
+// can contain commas and newlines
+fn parse_rows(text: &str) -> Vec<Vec<String>> {
+    let mut rows = Vec::new();
+    let mut row = Vec::new();
+    let mut field = String::new();
+    let mut chars = text.chars().peekable();
+    let mut in_quotes = false;
+    // Whether anything has been read since the last row ended, so a trailing newline doesn't add an empty row
+    let mut pending = false;
+
+    while let Some(c) = chars.next() {
+        pending = true;
+
+        if in_quotes {
+            match c {
+                '"' if chars.peek() == Some(&'"') => {
+                    chars.next();
+                    field.push('"');
+                },
+                '"' => in_quotes = false,
+                c => field.push(c)
+            }
+            continue;
+        }
+
+        match c {
+            '"' if field.is_empty() => in_quotes = true,
+            ',' => row.push(std::mem::take(&mut field)),
+            '\r' if chars.peek() == Some(&'\n') => {},
+            '\n' | '\r' => {
+                row.push(std::mem::take(&mut field));
+                rows.push(std::mem::take(&mut row));
+                pending = false;
+            },
+            c => field.push(c)
+        }
+    }
+
+    if in_quotes {
+        fatal_error("Unterminated quoted field in CSV");
+    }
+
+    if pending {
+        row.push(field);
+        rows.push(row);
+    }
+
+    rows
+}
\ No newline at end of file
@@ -0,0 +1,261 @@
+use crate::{fatal_error, runtime::{context::NativeContext, values::{ListValue, Value}}};
+
+use super::{expect_arg_count, string_arg};
+
+const BASE64_ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+pub fn native_encode_base64(args: Vec<Value>, _ctx: &NativeContext) -> Value {
+    expect_arg_count(&args, 1);
+    Value::Str(encode_base64(string_arg(&args, 0).as_bytes()))
+}
+
+// The decoded bytes must be valid UTF-8 as strings can't hold anything else
+pub fn native_decode_base64(args: Vec<Value>, _ctx: &NativeContext) -> Value {
+    expect_arg_count(&args, 1);
+
+    let bytes = match decode_base64(&string_arg(&args, 0)) {
+        Some(bytes) => bytes,
+        None => fatal_error("Invalid base64")
+    };
+
+    match String::from_utf8(bytes) {
+        Ok(value) => Value::Str(value),
+        Err(_) => fatal_error("Decoded base64 is not valid UTF-8")
+    }
+}
+
+// Hex digest of the string's UTF-8 bytes
+pub fn native_sha256(args: Vec<Value>, _ctx: &NativeContext) -> Value {
+    expect_arg_count(&args, 1);
+    Value::Str(to_hex(&sha256(string_arg(&args, 0).as_bytes())))
+}
+
+// Hex digest of the string's UTF-8 bytes. MD5 is broken, only use it for checksums
+pub fn native_md5(args: Vec<Value>, _ctx: &NativeContext) -> Value {
+    expect_arg_count(&args, 1);
+    Value::Str(to_hex(&md5(string_arg(&args, 0).as_bytes())))
+}
+
+fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+pub fn encode_base64(bytes: &[u8]) -> String {
+    let mut encoded = String::new();
+
+    for chunk in bytes.chunks(3) {
+        let block = (chunk[0] as u32) << 16 | (*chunk.get(1).unwrap_or(&0) as u32) << 8 | *chunk.get(2).unwrap_or(&0) as u32;
+
+        for i in 0..4 {
+            if i <= chunk.len() {
+                encoded.push(BASE64_ALPHABET[(block >> (18 - i * 6) & 0x3F) as usize] as char);
+            } else {
+                encoded.push('=');
+            }
+        }
+    }
+
+    encoded
+}
+
+// Whitespace is skipped and padding is optional
+pub fn decode_base64(encoded: &str) -> Option<Vec<u8>> {
+    let mut values = Vec::new();
+    let mut padding = 0;
+
+    for c in encoded.chars().filter(|c| !c.is_whitespace()) {
+        if c == '=' {
+            padding += 1;
+            continue;
+        }
+        // Nothing but padding can follow padding
+        if padding > 0 {
+            return None;
+        }
+        values.push(BASE64_ALPHABET.iter().position(|a| *a as char == c)? as u32);
+    }
+
+    if padding > 2 || values.len() % 4 == 1 {
+        return None;
+    }
+
+    let mut bytes = Vec::new();
+    for chunk in values.chunks(4) {
+        let block = chunk.iter().enumerate().fold(0, |block, (i, value)| block | value << (18 - i * 6));
+        for i in 0..chunk.len() - 1 {
+            bytes.push((block >> (16 - i * 8)) as u8);
+        }
+    }
+
+    Some(bytes)
+}
+
+// Adds the final 1 bit and the message length in bits, sha256 wants it big endian and md5 little endian
+fn pad_message(bytes: &[u8], big_endian: bool) -> Vec<u8> {
+    let mut message = bytes.to_vec();
+    let length = (bytes.len() as u64).wrapping_mul(8);
+
+    message.push(0x80);
+    while message.len() % 64 != 56 {
+        message.push(0);
+    }
+    message.extend_from_slice(&if big_endian { length.to_be_bytes() } else { length.to_le_bytes() });
+
+    message
+}
+
+const SHA256_K: [u32; 64] = [
+    0x428a2f98, 0x71374491, 0xb5c0fbcf, 0xe9b5dba5, 0x3956c25b, 0x59f111f1, 0x923f82a4, 0xab1c5ed5,
+    0xd807aa98, 0x12835b01, 0x243185be, 0x550c7dc3, 0x72be5d74, 0x80deb1fe, 0x9bdc06a7, 0xc19bf174,
+    0xe49b69c1, 0xefbe4786, 0x0fc19dc6, 0x240ca1cc, 0x2de92c6f, 0x4a7484aa, 0x5cb0a9dc, 0x76f988da,
+    0x983e5152, 0xa831c66d, 0xb00327c8, 0xbf597fc7, 0xc6e00bf3, 0xd5a79147, 0x06ca6351, 0x14292967,
+    0x27b70a85, 0x2e1b2138, 0x4d2c6dfc, 0x53380d13, 0x650a7354, 0x766a0abb, 0x81c2c92e, 0x92722c85,
+    0xa2bfe8a1, 0xa81a664b, 0xc24b8b70, 0xc76c51a3, 0xd192e819, 0xd6990624, 0xf40e3585, 0x106aa070,
+    0x19a4c116, 0x1e376c08, 0x2748774c, 0x34b0bcb5, 0x391c0cb3, 0x4ed8aa4a, 0x5b9cca4f, 0x682e6ff3,
+    0x748f82ee, 0x78a5636f, 0x84c87814, 0x8cc70208, 0x90befffa, 0xa4506ceb, 0xbef9a3f7, 0xc67178f2
+];
+
+pub fn sha256(bytes: &[u8]) -> [u8; 32] {
+    let mut hash: [u32; 8] = [0x6a09e667, 0xbb67ae85, 0x3c6ef372, 0xa54ff53a, 0x510e527f, 0x9b05688c, 0x1f83d9ab, 0x5be0cd19];
+
+    for block in pad_message(bytes, true).chunks(64) {
+        let mut w = [0u32; 64];
+        for i in 0..16 {
+            w[i] = u32::from_be_bytes(block[i * 4..i * 4 + 4].try_into().unwrap());
+        }
+        for i in 16..64 {
+            let s0 = w[i - 15].rotate_right(7) ^ w[i - 15].rotate_right(18) ^ (w[i - 15] >> 3);
+            let s1 = w[i - 2].rotate_right(17) ^ w[i - 2].rotate_right(19) ^ (w[i - 2] >> 10);
+            w[i] = w[i - 16].wrapping_add(s0).wrapping_add(w[i - 7]).wrapping_add(s1);
+        }
+
+        let [mut a, mut b, mut c, mut d, mut e, mut f, mut g, mut h] = hash;
+        for i in 0..64 {
+            let s1 = e.rotate_right(6) ^ e.rotate_right(11) ^ e.rotate_right(25);
+            let ch = (e & f) ^ (!e & g);
+            let temp1 = h.wrapping_add(s1).wrapping_add(ch).wrapping_add(SHA256_K[i]).wrapping_add(w[i]);
+            let s0 = a.rotate_right(2) ^ a.rotate_right(13) ^ a.rotate_right(22);
+            let maj = (a & b) ^ (a & c) ^ (b & c);
+            let temp2 = s0.wrapping_add(maj);
+
+            h = g;
+            g = f;
+            f = e;
+            e = d.wrapping_add(temp1);
+            d = c;
+            c = b;
+            b = a;
+            a = temp1.wrapping_add(temp2);
+        }
+
+        for (value, add) in hash.iter_mut().zip([a, b, c, d, e, f, g, h]) {
+            *value = value.wrapping_add(add);
+        }
+    }
+
+    let mut digest = [0u8; 32];
+    for (i, value) in hash.iter().enumerate() {
+        digest[i * 4..i * 4 + 4].copy_from_slice(&value.to_be_bytes());
+    }
+    digest
+}
+
+const MD5_SHIFTS: [u32; 64] = [
+    7, 12, 17, 22, 7, 12, 17, 22, 7, 12, 17, 22, 7, 12, 17, 22,
+    5, 9, 14, 20, 5, 9, 14, 20, 5, 9, 14, 20, 5, 9, 14, 20,
+    4, 11, 16, 23, 4, 11, 16, 23, 4, 11, 16, 23, 4, 11, 16, 23,
+    6, 10, 15, 21, 6, 10, 15, 21, 6, 10, 15, 21, 6, 10, 15, 21
+];
+
+pub fn md5(bytes: &[u8]) -> [u8; 16] {
+    // The constants are the integer parts of abs(sin(i + 1)) * 2^32
+    let k = (0..64).map(|i| ((i as f64 + 1.0).sin().abs() * 4294967296.0) as u32).collect::<Vec<u32>>();
+    let mut hash: [u32; 4] = [0x67452301, 0xefcdab89, 0x98badcfe, 0x10325476];
+
+    for block in pad_message(bytes, false).chunks(64) {
+        let mut m = [0u32; 16];
+        for (i, word) in m.iter_mut().enumerate() {
+            *word = u32::from_le_bytes(block[i * 4..i * 4 + 4].try_into().unwrap());
+        }
+
+        let [mut a, mut b, mut c, mut d] = hash;
+        for i in 0..64 {
+            let (f, g) = match i / 16 {
+                0 => ((b & c) | (!b & d), i),
+                1 => ((d & b) | (!d & c), (5 * i + 1) % 16),
+                2 => (b ^ c ^ d, (3 * i + 5) % 16),
+                _ => (c ^ (b | !d), (7 * i) % 16)
+            };
+
+            let f = f.wrapping_add(a).wrapping_add(k[i]).wrapping_add(m[g]);
+            a = d;
+            d = c;
+            c = b;
+            b = b.wrapping_add(f.rotate_left(MD5_SHIFTS[i]));
+        }
+
+        for (value, add) in hash.iter_mut().zip([a, b, c, d]) {
+            *value = value.wrapping_add(add);
+        }
+    }
+
+    let mut digest = [0u8; 16];
+    for (i, value) in hash.iter().enumerate() {
+        digest[i * 4..i * 4 + 4].copy_from_slice(&value.to_le_bytes());
+    }
+    digest
+}
+
+// chr(n) is the string holding the character with code point n
+pub fn native_chr(args: Vec<Value>, _ctx: &NativeContext) -> Value {
+    expect_arg_count(&args, 1);
+
+    let code = match &args[0] {
+        Value::Number(number) => *number,
+        _ => fatal_error(&format!("Expected number, found {}", args[0].get_type()))
+    };
+
+    match char::from_u32(code as u32) {
+        Some(character) if code.fract() == 0.0 && code >= 0.0 => Value::Str(character.to_string()),
+        _ => fatal_error(&format!("{} is not a valid character code", code))
+    }
+}
+
+// ord(c) is the code point of a single character string
+pub fn native_ord(args: Vec<Value>, _ctx: &NativeContext) -> Value {
+    expect_arg_count(&args, 1);
+
+    let string = string_arg(&args, 0);
+    let mut chars = string.chars();
+    match (chars.next(), chars.next()) {
+        (Some(character), None) => Value::Number(character as u32 as f64),
+        _ => fatal_error(&format!("Expected a single character, found a string of length {}", string.chars().count()))
+    }
+}
+
+// The UTF-8 bytes of a string as a list of numbers
+pub fn native_to_bytes(args: Vec<Value>, _ctx: &NativeContext) -> Value {
+    expect_arg_count(&args, 1);
+
+    let elements = string_arg(&args, 0).bytes().map(|byte| Value::Number(byte as f64)).collect();
+    Value::List(ListValue::new(elements))
+}
+
+pub fn native_from_bytes(args: Vec<Value>, _ctx: &NativeContext) -> Value {
+    expect_arg_count(&args, 1);
+
+    let list = match &args[0] {
+        Value::List(list) => list,
+        _ => fatal_error(&format!("Expected list, found {}", args[0].get_type()))
+    };
+
+    let bytes = list.snapshot().iter().map(|element| match element {
+        Value::Number(number) if number.fract() == 0.0 && (0.0..=255.0).contains(number) => *number as u8,
+        _ => fatal_error(&format!("Expected bytes (whole numbers from 0 to 255), found {}", element))
+    }).collect::<Vec<u8>>();
+
+    match String::from_utf8(bytes) {
+        Ok(value) => Value::Str(value),
+        Err(_) => fatal_error("Bytes are not valid UTF-8")
+    }
+}
\ No newline at end of file
@@ -0,0 +1,189 @@
+use crate::{fatal_error, runtime::{context::NativeContext, values::Value}};
+
+use super::string_arg;
+
+// format("x={} y={:.2}", x, y). Placeholders are {} for the next argument or {n} for the
+// nth, optionally followed by :[fill][<^>][0][width][.precision]. {{ and }} are literal braces
+pub fn native_format(args: Vec<Value>, _ctx: &NativeContext) -> Value {
+    if args.is_empty() {
+        fatal_error("Expected at least 1 argument, found 0");
+    }
+
+    match format_string(&string_arg(&args, 0), &args[1..]) {
+        Ok(value) => Value::Str(value),
+        Err(e) => fatal_error(&e)
+    }
+}
+
+// printf(format, args...) is print(format(format, args...))
+pub fn native_printf(args: Vec<Value>, ctx: &NativeContext) -> Value {
+    if args.is_empty() {
+        fatal_error("Expected at least 1 argument, found 0");
+    }
+
+    match format_string(&string_arg(&args, 0), &args[1..]) {
+        Ok(formatted) => ctx.write(&format!("{}\n", formatted)),
+        Err(e) => fatal_error(&e)
+    }
+
+    Value::Null
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum Align {
+    Left,
+    Center,
+    Right
+}
+
+struct Spec {
+    fill: char,
+    align: Option<Align>,
+    zero: bool,
+    width: usize,
+    precision: Option<usize>
+}
+
+pub fn format_string(format: &str, args: &[Value]) -> Result<String, String> {
+    let mut output = String::new();
+    let mut chars = format.chars().peekable();
+    let mut next_arg = 0;
+
+    while let Some(c) = chars.next() {
+        match c {
+            '{' if chars.peek() == Some(&'{') => {
+                chars.next();
+                output.push('{');
+            },
+            '}' if chars.peek() == Some(&'}') => {
+                chars.next();
+                output.push('}');
+            },
+            '}' => return Err(String::from("Unmatched `}` in format string, use `}}` for a literal brace")),
+            '{' => {
+                let mut placeholder = String::new();
+                loop {
+                    match chars.next() {
+                        Some('}') => break,
+                        Some(c) => placeholder.push(c),
+                        None => return Err(String::from("Unclosed `{` in format string"))
+                    }
+                }
+
+                let (index, spec) = match placeholder.split_once(':') {
+                    Some((index, spec)) => (index, parse_spec(spec)?),
+                    None => (placeholder.as_str(), parse_spec("")?)
+                };
+
+                let index = if index.is_empty() {
+                    next_arg += 1;
+                    next_arg - 1
+                } else {
+                    index.trim().parse::<usize>().map_err(|_| format!("Invalid placeholder {{{}}}", placeholder))?
+                };
+
+                let arg = match args.get(index) {
+                    Some(arg) => arg,
+                    None => return Err(format!("Format string wants argument {} but only {} were given", index, args.len()))
+                };
+
+                output.push_str(&apply_spec(arg, &spec));
+            },
+            c => output.push(c)
+        }
+    }
+
+    Ok(output)
+}
+
+fn parse_spec(spec: &str) -> Result<Spec, String> {
+    let invalid = || format!("Invalid format spec `{}`", spec);
+    let chars = spec.chars().collect::<Vec<char>>();
+    let mut position = 0;
+
+    let align_of = |c: char| match c {
+        '<' => Some(Align::Left),
+        '^' => Some(Align::Center),
+        '>' => Some(Align::Right),
+        _ => None
+    };
+
+    let mut fill = ' ';
+    let mut align = None;
+    if chars.len() >= 2 && align_of(chars[1]).is_some() {
+        fill = chars[0];
+        align = align_of(chars[1]);
+        position = 2;
+    } else if !chars.is_empty() && align_of(chars[0]).is_some() {
+        align = align_of(chars[0]);
+        position = 1;
+    }
+
+    let zero = chars.get(position) == Some(&'0');
+    if zero {
+        position += 1;
+    }
+
+    let start = position;
+    while chars.get(position).is_some_and(|c| c.is_ascii_digit()) {
+        position += 1;
+    }
+    let width = if position > start { chars[start..position].iter().collect::<String>().parse().map_err(|_| invalid())? } else { 0 };
+
+    let mut precision = None;
+    if chars.get(position) == Some(&'.') {
+        position += 1;
+        let start = position;
+        while chars.get(position).is_some_and(|c| c.is_ascii_digit()) {
+            position += 1;
+        }
+        if position == start {
+            return Err(invalid());
+        }
+        precision = Some(chars[start..position].iter().collect::<String>().parse().map_err(|_| invalid())?);
+    }
+
+    if position != chars.len() {
+        return Err(invalid());
+    }
+
+    Ok(Spec { fill, align, zero, width, precision })
+}
+
+fn apply_spec(value: &Value, spec: &Spec) -> String {
+    let number = value.as_number();
+
+    let text = match (number, spec.precision) {
+        (Some(number), Some(precision)) => format!("{:.*}", precision, number),
+        // Precision on anything else truncates it, like Rust does for strings
+        (None, Some(precision)) => value.to_string().chars().take(precision).collect(),
+        _ => match value {
+            Value::Str(string) => string.clone(),
+            _ => value.to_string()
+        }
+    };
+
+    let length = text.chars().count();
+    if length >= spec.width {
+        return text;
+    }
+    let padding = spec.width - length;
+
+    // Zero padding goes between the sign and the digits
+    if spec.zero && spec.align.is_none() && number.is_some() {
+        return match text.strip_prefix('-') {
+            Some(digits) => format!("-{}{}", "0".repeat(padding), digits),
+            None => format!("{}{}", "0".repeat(padding), text)
+        };
+    }
+
+    // Numbers line up on the right by default, everything else on the left
+    let align = spec.align.unwrap_or(if number.is_some() { Align::Right } else { Align::Left });
+    let fill = |count: usize| spec.fill.to_string().repeat(count);
+
+    match align {
+        Align::Left => format!("{}{}", text, fill(padding)),
+        Align::Right => format!("{}{}", fill(padding), text),
+        Align::Center => format!("{}{}{}", fill(padding / 2), text, fill(padding - padding / 2))
+    }
+}
\ No newline at end of file
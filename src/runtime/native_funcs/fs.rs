@@ -0,0 +1,90 @@
+use std::{fs, path::PathBuf};
+
+use crate::{fatal_error, runtime::{context::NativeContext, values::{ListValue, Value}}};
+
+use super::{expect_arg_count, string_arg};
+
+// Names of the entries in a directory, sorted
+pub fn native_list_dir(args: Vec<Value>, _ctx: &NativeContext) -> Value {
+    expect_arg_count(&args, 1);
+    let path = string_arg(&args, 0);
+
+    let entries = match fs::read_dir(&path) {
+        Ok(entries) => entries,
+        Err(e) => fatal_error(&format!("Could not list directory {}: {}", path, e))
+    };
+
+    let mut names = Vec::new();
+    for entry in entries {
+        match entry {
+            Ok(entry) => names.push(entry.file_name().to_string_lossy().to_string()),
+            Err(e) => fatal_error(&format!("Could not list directory {}: {}", path, e))
+        }
+    }
+    names.sort();
+
+    Value::List(ListValue::new(
+        names.into_iter().map(Value::Str).collect()
+    ))
+}
+
+// Creates a directory along with any missing parents
+pub fn native_mkdir(args: Vec<Value>, _ctx: &NativeContext) -> Value {
+    expect_arg_count(&args, 1);
+    let path = string_arg(&args, 0);
+
+    if let Err(e) = fs::create_dir_all(&path) {
+        fatal_error(&format!("Could not create directory {}: {}", path, e));
+    }
+
+    Value::Null
+}
+
+pub fn native_remove_file(args: Vec<Value>, _ctx: &NativeContext) -> Value {
+    expect_arg_count(&args, 1);
+    let path = string_arg(&args, 0);
+
+    if let Err(e) = fs::remove_file(&path) {
+        fatal_error(&format!("Could not remove file {}: {}", path, e));
+    }
+
+    Value::Null
+}
+
+// Returns the number of bytes copied
+pub fn native_copy_file(args: Vec<Value>, _ctx: &NativeContext) -> Value {
+    expect_arg_count(&args, 2);
+    let from = string_arg(&args, 0);
+    let to = string_arg(&args, 1);
+
+    match fs::copy(&from, &to) {
+        Ok(bytes) => Value::Number(bytes as f64),
+        Err(e) => fatal_error(&format!("Could not copy {} to {}: {}", from, to, e))
+    }
+}
+
+pub fn native_rename(args: Vec<Value>, _ctx: &NativeContext) -> Value {
+    expect_arg_count(&args, 2);
+    let from = string_arg(&args, 0);
+    let to = string_arg(&args, 1);
+
+    if let Err(e) = fs::rename(&from, &to) {
+        fatal_error(&format!("Could not rename {} to {}: {}", from, to, e));
+    }
+
+    Value::Null
+}
+
+// Joins any number of path parts with the platform's separator
+pub fn native_path_join(args: Vec<Value>, _ctx: &NativeContext) -> Value {
+    if args.is_empty() {
+        fatal_error("Expected at least 1 argument, found 0");
+    }
+
+    let mut path = PathBuf::new();
+    for index in 0..args.len() {
+        path.push(string_arg(&args, index));
+    }
+
+    Value::Str(path.to_string_lossy().to_string())
+}
\ No newline at end of file
@@ -0,0 +1,182 @@
+use std::{collections::HashMap, io::{Read, Write}, net::TcpStream, time::Duration};
+
+use crate::{fatal_error, runtime::{context::NativeContext, values::{ObjectValue, Value}}};
+
+use super::string_arg;
+
+const TIMEOUT: Duration = Duration::from_secs(30);
+
+// http.get(url, headers?)
+pub fn native_http_get(args: Vec<Value>, _ctx: &NativeContext) -> Value {
+    if args.is_empty() || args.len() > 2 {
+        fatal_error(&format!("Expected 1 or 2 arguments, found {}", args.len()));
+    }
+
+    let headers = headers_arg(&args, 1);
+    Value::Object(request("GET", &string_arg(&args, 0), None, &headers))
+}
+
+// http.post(url, body, headers?)
+pub fn native_http_post(args: Vec<Value>, _ctx: &NativeContext) -> Value {
+    if args.len() < 2 || args.len() > 3 {
+        fatal_error(&format!("Expected 2 or 3 arguments, found {}", args.len()));
+    }
+
+    let headers = headers_arg(&args, 2);
+    Value::Object(request("POST", &string_arg(&args, 0), Some(&string_arg(&args, 1)), &headers))
+}
+
+fn headers_arg(args: &[Value], index: usize) -> Vec<(String, String)> {
+    let headers = match args.get(index) {
+        Some(headers) => match headers {
+            Value::Object(headers) => headers,
+            _ => fatal_error(&format!("Expected object of headers, found {}", headers.get_type()))
+        },
+        None => return Vec::new()
+    };
+
+    let mut headers = headers.snapshot().iter().map(|(name, value)| {
+        match value {
+            Value::Str(value) => (name.clone(), value.clone()),
+            _ => (name.clone(), value.to_string())
+        }
+    }).collect::<Vec<_>>();
+    headers.sort();
+
+    headers
+}
+
+struct Url {
+    host: String,
+    port: u16,
+    path: String
+}
+
+fn parse_url(url: &str) -> Url {
+    let rest = match url.strip_prefix("http://") {
+        Some(rest) => rest,
+        None if url.starts_with("https://") => fatal_error("https is not supported, only plain http"),
+        None => fatal_error(&format!("Invalid url {}", url))
+    };
+
+    let (authority, path) = match rest.find('/') {
+        Some(index) => (&rest[..index], &rest[index..]),
+        None => (rest, "/")
+    };
+
+    let (host, port) = match authority.rsplit_once(':') {
+        Some((host, port)) => match port.parse::<u16>() {
+            Ok(port) => (host, port),
+            Err(_) => fatal_error(&format!("Invalid port in url {}", url))
+        },
+        None => (authority, 80)
+    };
+
+    if host.is_empty() {
+        fatal_error(&format!("Invalid url {}", url));
+    }
+
+    Url { host: host.to_string(), port, path: path.to_string() }
+}
+
+// Sends an HTTP/1.1 request and gives back { status, headers, body }. Header names are lowercased
+fn request(method: &str, url: &str, body: Option<&str>, headers: &[(String, String)]) -> ObjectValue {
+    let parsed = parse_url(url);
+
+    let mut stream = match TcpStream::connect((parsed.host.as_str(), parsed.port)) {
+        Ok(stream) => stream,
+        Err(e) => fatal_error(&format!("Could not connect to {}: {}", url, e))
+    };
+    stream.set_read_timeout(Some(TIMEOUT)).unwrap();
+    stream.set_write_timeout(Some(TIMEOUT)).unwrap();
+
+    let mut message = format!("{} {} HTTP/1.1\r\nHost: {}\r\nConnection: close\r\n", method, parsed.path, parsed.host);
+    for (name, value) in headers {
+        message.push_str(&format!("{}: {}\r\n", name, value));
+    }
+    if let Some(body) = body {
+        message.push_str(&format!("Content-Length: {}\r\n", body.len()));
+    }
+    message.push_str("\r\n");
+    message.push_str(body.unwrap_or(""));
+
+    let mut response = Vec::new();
+    if let Err(e) = stream.write_all(message.as_bytes()).and_then(|_| stream.read_to_end(&mut response)) {
+        fatal_error(&format!("Request to {} failed: {}", url, e));
+    }
+
+    parse_response(url, &response)
+}
+
+fn parse_response(url: &str, response: &[u8]) -> ObjectValue {
+    let head_end = match response.windows(4).position(|window| window == b"\r\n\r\n") {
+        Some(index) => index,
+        None => fatal_error(&format!("Invalid response from {}", url))
+    };
+
+    let head = String::from_utf8_lossy(&response[..head_end]);
+    let mut lines = head.split("\r\n");
+
+    let status = lines.next()
+        .and_then(|line| line.split(' ').nth(1))
+        .and_then(|status| status.parse::<f64>().ok())
+        .unwrap_or_else(|| fatal_error(&format!("Invalid status line from {}", url)));
+
+    let mut headers: HashMap<String, String> = HashMap::new();
+    for line in lines {
+        if let Some((name, value)) = line.split_once(':') {
+            let name = name.trim().to_lowercase();
+            let value = value.trim();
+            headers.entry(name)
+                .and_modify(|existing| {
+                    existing.push_str(", ");
+                    existing.push_str(value);
+                })
+                .or_insert_with(|| value.to_string());
+        }
+    }
+
+    let mut body = response[head_end + 4..].to_vec();
+    if headers.get("transfer-encoding").is_some_and(|encoding| encoding.eq_ignore_ascii_case("chunked")) {
+        body = decode_chunked(url, &body);
+    } else if let Some(length) = headers.get("content-length").and_then(|length| length.parse::<usize>().ok()) {
+        body.truncate(length);
+    }
+
+    let mut properties: HashMap<String, Value> = HashMap::new();
+    properties.insert(String::from("status"), Value::Number(status));
+    properties.insert(String::from("headers"), Value::Object(ObjectValue::new(
+        headers.into_iter().map(|(name, value)| (name, Value::Str(value))).collect()
+    )));
+    properties.insert(String::from("body"), Value::Str(String::from_utf8_lossy(&body).to_string()));
+
+    ObjectValue::new(properties)
+}
+
+fn decode_chunked(url: &str, mut chunked: &[u8]) -> Vec<u8> {
+    let mut body = Vec::new();
+
+    loop {
+        let line_end = match chunked.windows(2).position(|window| window == b"\r\n") {
+            Some(index) => index,
+            None => fatal_error(&format!("Invalid chunked response from {}", url))
+        };
+
+        // Chunk extensions after a ; are ignored
+        let size = String::from_utf8_lossy(&chunked[..line_end]);
+        let size = match usize::from_str_radix(size.split(';').next().unwrap().trim(), 16) {
+            Ok(size) => size,
+            Err(_) => fatal_error(&format!("Invalid chunked response from {}", url))
+        };
+
+        chunked = &chunked[line_end + 2..];
+        if size == 0 || chunked.len() < size {
+            break;
+        }
+
+        body.extend_from_slice(&chunked[..size]);
+        chunked = &chunked[(size + 2).min(chunked.len())..];
+    }
+
+    body
+}
\ No newline at end of file
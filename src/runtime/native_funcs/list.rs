@@ -0,0 +1,306 @@
+use std::{cmp::Ordering};
+
+use crate::{fatal_error, runtime::{context::NativeContext, values::{strict_equals, ListValue, RangeValue, Value}}};
+
+use super::{expect_arg_count, string_arg};
+
+fn list_arg(args: &[Value], index: usize) -> ListValue {
+    match &args[index] {
+        Value::List(list) => list.clone(),
+        _ => fatal_error(&format!("Expected list, found {}", args[index].get_type()))
+    }
+}
+
+// Numbers compare numerically and strings by their characters, nothing else can be ordered
+pub fn compare_values(a: &Value, b: &Value) -> Ordering {
+    match (a, b) {
+        (Value::Number(a), Value::Number(b)) => match a.partial_cmp(b) {
+            Some(ordering) => ordering,
+            None => fatal_error("Cannot order NaN")
+        },
+        (Value::Str(a), Value::Str(b)) => a.cmp(b),
+        _ => fatal_error(&format!("Cannot order {} and {}", a.get_type(), b.get_type()))
+    }
+}
+
+// A stable merge sort. The slice sorts in std may panic if a script's comparator isn't
+// consistent, this just gives some order instead
+fn merge_sort<T: Clone>(items: &mut [T], compare: &mut dyn FnMut(&T, &T) -> Ordering) {
+    if items.len() <= 1 {
+        return;
+    }
+
+    let middle = items.len() / 2;
+    merge_sort(&mut items[..middle], compare);
+    merge_sort(&mut items[middle..], compare);
+
+    let mut merged = Vec::with_capacity(items.len());
+    let (mut left, mut right) = (0, middle);
+    while left < middle && right < items.len() {
+        if compare(&items[right], &items[left]) == Ordering::Less {
+            merged.push(items[right].clone());
+            right += 1;
+        } else {
+            merged.push(items[left].clone());
+            left += 1;
+        }
+    }
+    merged.extend_from_slice(&items[left..middle]);
+    merged.extend_from_slice(&items[right..]);
+
+    items.clone_from_slice(&merged);
+}
+
+// sort(list) gives a sorted copy of a list of numbers or strings
+pub fn native_sort(args: Vec<Value>, _ctx: &NativeContext) -> Value {
+    expect_arg_count(&args, 1);
+    let mut elements = list_arg(&args, 0).to_vec();
+
+    merge_sort(&mut elements, &mut |a, b| compare_values(a, b));
+
+    Value::List(ListValue::new(elements))
+}
+
+// sort_by(list, fn) gives a sorted copy. A script function taking two parameters is a comparator
+// returning a negative number, 0 or a positive number, anything else is a key function and the
+// list is sorted by the keys it gives
+pub fn native_sort_by(args: Vec<Value>, ctx: &NativeContext) -> Value {
+    expect_arg_count(&args, 2);
+    let mut elements = list_arg(&args, 0).to_vec();
+    let function = &args[1];
+
+    let comparator = matches!(function, Value::Function(function) if function.parameters.len() == 2);
+
+    if comparator {
+        merge_sort(&mut elements, &mut |a, b| {
+            let result = ctx.call(function, vec![a.clone(), b.clone()]);
+            match result {
+                Value::Number(number) => number.partial_cmp(&0.0).unwrap_or(Ordering::Equal),
+                _ => fatal_error(&format!("Comparator must return a number, found {}", result.get_type()))
+            }
+        });
+
+        return Value::List(ListValue::new(elements));
+    }
+
+    let mut keyed = elements.into_iter()
+        .map(|element| (ctx.call(function, vec![element.clone()]), element))
+        .collect::<Vec<_>>();
+    merge_sort(&mut keyed, &mut |a, b| compare_values(&a.0, &b.0));
+
+    Value::List(ListValue::new(keyed.into_iter().map(|(_, element)| element).collect()))
+}
+
+// map(list, fn) gives a list of fn(element) for each element
+pub fn native_map(args: Vec<Value>, ctx: &NativeContext) -> Value {
+    expect_arg_count(&args, 2);
+    let list = list_arg(&args, 0);
+
+    Value::List(ListValue::new(
+        list.to_vec().into_iter().map(|element| ctx.call(&args[1], vec![element])).collect()
+    ))
+}
+
+// filter(list, fn) gives the elements fn returns something truthy for
+pub fn native_filter(args: Vec<Value>, ctx: &NativeContext) -> Value {
+    expect_arg_count(&args, 2);
+    let list = list_arg(&args, 0);
+
+    Value::List(ListValue::new(
+        list.to_vec().into_iter().filter(|element| ctx.call(&args[1], vec![element.clone()]).as_bool()).collect()
+    ))
+}
+
+// reduce(list, fn, init) folds the list with fn(accumulator, element). Without init the first
+// element is used as the starting accumulator
+pub fn native_reduce(args: Vec<Value>, ctx: &NativeContext) -> Value {
+    if args.len() < 2 || args.len() > 3 {
+        fatal_error(&format!("Expected 2 or 3 arguments, found {}", args.len()));
+    }
+    let mut elements = list_arg(&args, 0).to_vec().into_iter();
+
+    let mut accumulator = match args.get(2) {
+        Some(init) => init.clone(),
+        None => match elements.next() {
+            Some(first) => first,
+            None => fatal_error("Cannot reduce an empty list without an initial value")
+        }
+    };
+
+    for element in elements {
+        accumulator = ctx.call(&args[1], vec![accumulator, element]);
+    }
+
+    accumulator
+}
+
+// range(stop), range(start, stop) or range(start, stop, step). Counts up from start (0 by default)
+// by step (1 by default), stopping before stop
+pub fn native_range(args: Vec<Value>, _ctx: &NativeContext) -> Value {
+    if args.is_empty() || args.len() > 3 {
+        fatal_error(&format!("Expected 1 to 3 arguments, found {}", args.len()));
+    }
+
+    let mut numbers = Vec::new();
+    for arg in args.iter() {
+        match arg {
+            Value::Number(number) if number.is_finite() => numbers.push(*number),
+            _ => fatal_error(&format!("Expected number, found {}", arg))
+        }
+    }
+
+    let (start, stop, step) = match numbers[..] {
+        [stop] => (0.0, stop, 1.0),
+        [start, stop] => (start, stop, 1.0),
+        [start, stop, step] => (start, stop, step),
+        _ => unreachable!()
+    };
+
+    if step == 0.0 {
+        fatal_error("range step cannot be 0");
+    }
+
+    Value::Range(RangeValue { start, stop, step })
+}
+
+// len(x) is the number of characters in a string, elements in a list or range, or properties in an object
+pub fn native_len(args: Vec<Value>, _ctx: &NativeContext) -> Value {
+    expect_arg_count(&args, 1);
+    let length = match &args[0] {
+        Value::Str(string) => string.chars().count(),
+        Value::List(list) => list.len(),
+        Value::Object(object) => object.len(),
+        Value::Range(range) => range.len(),
+        value => fatal_error(&format!("Cannot get the length of {}, expected string, list, object or range", value.get_type()))
+    };
+
+    Value::Number(length as f64)
+}
+
+// The elements of a list or the numbers of a range
+fn elements_arg(args: &[Value], index: usize) -> Vec<Value> {
+    if let Value::Range(range) = &args[index] {
+        return range.iter().map(Value::Number).collect();
+    }
+
+    list_arg(args, index).to_vec()
+}
+
+fn pair(first: Value, second: Value) -> Value {
+    Value::List(ListValue::new(vec![first, second]))
+}
+
+// zip(a, b) gives [a[i], b[i]] pairs, as many as the shorter of the two has elements
+pub fn native_zip(args: Vec<Value>, _ctx: &NativeContext) -> Value {
+    expect_arg_count(&args, 2);
+
+    Value::List(ListValue::new(
+        elements_arg(&args, 0).into_iter().zip(elements_arg(&args, 1)).map(|(a, b)| pair(a, b)).collect()
+    ))
+}
+
+// enumerate(list) gives [index, element] pairs
+pub fn native_enumerate(args: Vec<Value>, _ctx: &NativeContext) -> Value {
+    expect_arg_count(&args, 1);
+
+    Value::List(ListValue::new(
+        elements_arg(&args, 0).into_iter().enumerate().map(|(index, element)| pair(Value::Number(index as f64), element)).collect()
+    ))
+}
+
+fn number_arg(args: &[Value], index: usize) -> f64 {
+    match &args[index] {
+        Value::Number(number) if number.fract() == 0.0 => *number,
+        _ => fatal_error(&format!("Expected whole number, found {}", args[index]))
+    }
+}
+
+// reverse(list) gives a reversed copy
+pub fn native_reverse(args: Vec<Value>, _ctx: &NativeContext) -> Value {
+    expect_arg_count(&args, 1);
+    let mut elements = list_arg(&args, 0).to_vec();
+
+    elements.reverse();
+
+    Value::List(ListValue::new(elements))
+}
+
+// unique(list) drops elements equal to an earlier one, keeping the original order
+pub fn native_unique(args: Vec<Value>, _ctx: &NativeContext) -> Value {
+    expect_arg_count(&args, 1);
+
+    let mut elements: Vec<Value> = Vec::new();
+    for element in list_arg(&args, 0).to_vec() {
+        if !elements.iter().any(|seen| strict_equals(seen, &element)) {
+            elements.push(element);
+        }
+    }
+
+    Value::List(ListValue::new(elements))
+}
+
+// flatten(list) removes one level of nesting, [[1, 2], 3] becomes [1, 2, 3]
+pub fn native_flatten(args: Vec<Value>, _ctx: &NativeContext) -> Value {
+    expect_arg_count(&args, 1);
+
+    let mut elements = Vec::new();
+    for element in list_arg(&args, 0).to_vec() {
+        match element {
+            Value::List(inner) => elements.extend(inner.snapshot().iter().cloned()),
+            _ => elements.push(element)
+        }
+    }
+
+    Value::List(ListValue::new(elements))
+}
+
+// join(list, sep) puts sep between the elements as strings
+pub fn native_join(args: Vec<Value>, _ctx: &NativeContext) -> Value {
+    expect_arg_count(&args, 2);
+    let separator = string_arg(&args, 1);
+
+    let strings = list_arg(&args, 0).snapshot().iter().map(|element| element.to_string()).collect::<Vec<String>>();
+
+    Value::Str(strings.join(&separator))
+}
+
+// index_of(list, value) is the index of the first element equal to value, or -1
+pub fn native_index_of(args: Vec<Value>, _ctx: &NativeContext) -> Value {
+    expect_arg_count(&args, 2);
+
+    let index = list_arg(&args, 0).snapshot().iter().position(|element| strict_equals(element, &args[1]));
+
+    Value::Number(index.map_or(-1.0, |index| index as f64))
+}
+
+// get(list, index, default) is the element at index, negative ones counting back from the end, or
+// default when there's none there. get(object, key, default) is the same for properties
+pub fn native_get(args: Vec<Value>, _ctx: &NativeContext) -> Value {
+    expect_arg_count(&args, 3);
+
+    if let Value::Object(object) = &args[0] {
+        return object.get(&string_arg(&args, 1)).unwrap_or_else(|| args[2].clone());
+    }
+
+    let list = list_arg(&args, 0);
+    match list.position(number_arg(&args, 1)) {
+        Some(position) => list.get(position).unwrap(),
+        None => args[2].clone()
+    }
+}
+
+// slice(list, start, end) gives the elements from start up to but not including end (the end of
+// the list when left out). Negative indices count back from the end
+pub fn native_slice(args: Vec<Value>, _ctx: &NativeContext) -> Value {
+    if args.len() < 2 || args.len() > 3 {
+        fatal_error(&format!("Expected 2 or 3 arguments, found {}", args.len()));
+    }
+    let elements = list_arg(&args, 0).snapshot();
+    let length = elements.len() as f64;
+    let resolve = |index: f64| (if index < 0.0 { length + index } else { index }).clamp(0.0, length) as usize;
+
+    let start = resolve(number_arg(&args, 1));
+    let end = if args.len() == 3 { resolve(number_arg(&args, 2)) } else { elements.len() };
+
+    Value::List(ListValue::new(elements[start..end.max(start)].to_vec()))
+}
\ No newline at end of file
@@ -0,0 +1,133 @@
+use std::{fmt::{Debug, Display}, sync::Arc, time::SystemTime};
+
+use crate::{fatal_error, runtime::{context::NativeContext, values::Value}};
+
+use super::{expect_arg_count, string_arg, time::format_time};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum LogLevel {
+    Debug,
+    Info,
+    Warn,
+    Error
+}
+
+impl LogLevel {
+    pub fn parse(name: &str) -> Option<LogLevel> {
+        match name {
+            "debug" => Some(LogLevel::Debug),
+            "info" => Some(LogLevel::Info),
+            "warn" => Some(LogLevel::Warn),
+            "error" => Some(LogLevel::Error),
+            _ => None
+        }
+    }
+}
+
+impl Display for LogLevel {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            LogLevel::Debug => write!(f, "DEBUG"),
+            LogLevel::Info => write!(f, "INFO"),
+            LogLevel::Warn => write!(f, "WARN"),
+            LogLevel::Error => write!(f, "ERROR")
+        }
+    }
+}
+
+// Receives every message that passes the level filter, already formatted. Shared and Send so
+// the interpreter holding it can be moved to another thread
+pub type LogSink = Arc<dyn Fn(LogLevel, &str) + Send + Sync>;
+
+// What scripts can change with log.set_level and log.timestamps, and where the embedder has the
+// messages sent. Kept on the interpreter's global scope so each instance has its own
+#[derive(Clone)]
+pub struct LogSettings {
+    pub level: LogLevel,
+    pub timestamps: bool,
+    // None writes to stderr
+    pub sink: Option<LogSink>
+}
+
+impl Default for LogSettings {
+    fn default() -> Self {
+        LogSettings { level: LogLevel::Info, timestamps: false, sink: None }
+    }
+}
+
+impl Debug for LogSettings {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("LogSettings")
+            .field("level", &self.level)
+            .field("timestamps", &self.timestamps)
+            .field("sink", &if self.sink.is_some() { "redirected" } else { "stderr" })
+            .finish()
+    }
+}
+
+pub fn log(settings: &LogSettings, level: LogLevel, message: &str) {
+    if level < settings.level {
+        return;
+    }
+
+    let mut line = format!("[{}] {}", level, message);
+    if settings.timestamps && cfg!(not(target_arch = "wasm32")) {
+        let now = SystemTime::now().duration_since(SystemTime::UNIX_EPOCH).unwrap().as_secs_f64();
+        line = format!("{} {}", format_time(now, "%Y-%m-%d %H:%M:%S").unwrap(), line);
+    }
+
+    match &settings.sink {
+        Some(sink) => sink(level, &line),
+        None => eprintln!("{}", line)
+    }
+}
+
+// Arguments are joined with spaces, like print
+fn log_args(ctx: &NativeContext, level: LogLevel, args: Vec<Value>) -> Value {
+    let message = args.iter().map(|arg| arg.to_string()).collect::<Vec<String>>().join(" ");
+    let settings = ctx.global().lock().unwrap().log.clone();
+    log(&settings, level, &message);
+
+    Value::Null
+}
+
+pub fn native_log_debug(args: Vec<Value>, ctx: &NativeContext) -> Value {
+    log_args(ctx, LogLevel::Debug, args)
+}
+
+pub fn native_log_info(args: Vec<Value>, ctx: &NativeContext) -> Value {
+    log_args(ctx, LogLevel::Info, args)
+}
+
+pub fn native_log_warn(args: Vec<Value>, ctx: &NativeContext) -> Value {
+    log_args(ctx, LogLevel::Warn, args)
+}
+
+pub fn native_log_error(args: Vec<Value>, ctx: &NativeContext) -> Value {
+    log_args(ctx, LogLevel::Error, args)
+}
+
+// log.set_level("warn") hides debug and info messages
+pub fn native_log_set_level(args: Vec<Value>, ctx: &NativeContext) -> Value {
+    expect_arg_count(&args, 1);
+
+    let name = string_arg(&args, 0);
+    match LogLevel::parse(&name) {
+        Some(level) => ctx.global().lock().unwrap().log.level = level,
+        None => fatal_error(&format!("Unknown log level {}, expected debug, info, warn or error", name))
+    }
+
+    Value::Null
+}
+
+pub fn native_log_timestamps(args: Vec<Value>, ctx: &NativeContext) -> Value {
+    expect_arg_count(&args, 1);
+
+    let enabled = match &args[0] {
+        Value::Bool(boolean) => *boolean,
+        _ => fatal_error(&format!("Expected bool, found {}", args[0].get_type()))
+    };
+    ctx.global().lock().unwrap().log.timestamps = enabled;
+
+    Value::Null
+}
\ No newline at end of file
@@ -0,0 +1,85 @@
+use std::{cmp::Ordering};
+
+use crate::{fatal_error, runtime::{context::NativeContext, values::Value}};
+
+use super::{expect_arg_count, list::compare_values};
+
+// min, max and sum take either a single list (or range) or the values as separate arguments
+fn values_arg(args: Vec<Value>) -> Vec<Value> {
+    if args.len() == 1 {
+        if let Value::List(list) = &args[0] {
+            return list.to_vec();
+        }
+        if let Value::Range(range) = &args[0] {
+            return range.iter().map(Value::Number).collect();
+        }
+    }
+
+    args
+}
+
+fn number(value: &Value) -> f64 {
+    match value {
+        Value::Number(number) => *number,
+        _ => fatal_error(&format!("Expected number, found {}", value.get_type()))
+    }
+}
+
+// The first of the values that compares as `wanted` against all the others
+fn extreme(args: Vec<Value>, wanted: Ordering) -> Value {
+    let mut values = values_arg(args).into_iter();
+
+    let mut best = match values.next() {
+        Some(first) => first,
+        None => fatal_error("Expected at least 1 value, found none")
+    };
+
+    for value in values {
+        if compare_values(&value, &best) == wanted {
+            best = value;
+        }
+    }
+
+    best
+}
+
+pub fn native_min(args: Vec<Value>, _ctx: &NativeContext) -> Value {
+    extreme(args, Ordering::Less)
+}
+
+pub fn native_max(args: Vec<Value>, _ctx: &NativeContext) -> Value {
+    extreme(args, Ordering::Greater)
+}
+
+// The sum of no values is 0
+pub fn native_sum(args: Vec<Value>, _ctx: &NativeContext) -> Value {
+    Value::Number(values_arg(args).iter().fold(0.0, |total, value| total + number(value)))
+}
+
+pub fn native_abs(args: Vec<Value>, _ctx: &NativeContext) -> Value {
+    expect_arg_count(&args, 1);
+    Value::Number(number(&args[0]).abs())
+}
+
+// Dividing by zero gives Infinity or NaN outside strict mode, these tell them apart from other numbers
+pub fn native_is_finite(args: Vec<Value>, _ctx: &NativeContext) -> Value {
+    expect_arg_count(&args, 1);
+    Value::Bool(number(&args[0]).is_finite())
+}
+
+pub fn native_is_nan(args: Vec<Value>, _ctx: &NativeContext) -> Value {
+    expect_arg_count(&args, 1);
+    Value::Bool(number(&args[0]).is_nan())
+}
+
+// clamp(x, low, high) keeps x between low and high
+pub fn native_clamp(args: Vec<Value>, _ctx: &NativeContext) -> Value {
+    expect_arg_count(&args, 3);
+
+    let (value, low, high) = (number(&args[0]), number(&args[1]), number(&args[2]));
+    if low > high {
+        fatal_error(&format!("Cannot clamp between {} and {}, the lower bound is greater than the upper", low, high));
+    }
+
+    Value::Number(value.max(low).min(high))
+}
\ No newline at end of file
@@ -0,0 +1,153 @@
+use std::{collections::HashMap, io::{BufRead, BufReader, Read, Write}, net::{TcpListener, TcpStream}, sync::{Arc, Mutex}};
+
+use crate::{fatal_error, runtime::{context::NativeContext, values::{FunctionCall, NativeFnValue, ObjectValue, Value}}};
+
+use super::{expect_arg_count, string_arg};
+
+// Reads are buffered so read_line can't lose anything a later read would want.
+// None once the connection has been closed
+type Connection = Arc<Mutex<Option<BufReader<TcpStream>>>>;
+
+fn method(function: impl Fn(Vec<Value>, &NativeContext) -> Value + Send + Sync + 'static) -> Value {
+    Value::Native(NativeFnValue {
+        call: FunctionCall {
+            func: Arc::new(function)
+        }
+    })
+}
+
+fn port_arg(args: &[Value], index: usize) -> u16 {
+    match &args[index] {
+        Value::Number(port) if *port >= 0.0 && *port <= u16::MAX as f64 && port.fract() == 0.0 => *port as u16,
+        Value::Number(port) => fatal_error(&format!("Invalid port {}", *port)),
+        _ => fatal_error(&format!("Expected number, found {}", args[index].get_type()))
+    }
+}
+
+fn with_stream<T>(connection: &Connection, action: impl FnOnce(&mut BufReader<TcpStream>) -> T) -> T {
+    match connection.lock().unwrap().as_mut() {
+        Some(stream) => action(stream),
+        None => fatal_error("Connection is closed")
+    }
+}
+
+// A connection handle, an object with read(max_bytes?), read_line(), write(s), close() and peer
+fn connection_object(stream: TcpStream) -> ObjectValue {
+    let peer = match stream.peer_addr() {
+        Ok(address) => address.to_string(),
+        Err(_) => String::new()
+    };
+    let connection: Connection = Arc::new(Mutex::new(Some(BufReader::new(stream))));
+
+    let mut properties: HashMap<String, Value> = HashMap::new();
+    properties.insert(String::from("peer"), Value::Str(peer));
+
+    // Gives up to max_bytes (4096 by default), an empty string means the other side closed
+    let reader = Arc::clone(&connection);
+    properties.insert(String::from("read"), method(move |args, _env| {
+        let max = match args.first() {
+            Some(Value::Number(max)) => *max as usize,
+            Some(max) => fatal_error(&format!("Expected number, found {}", max.get_type())),
+            None => 4096
+        };
+
+        let mut buffer = vec![0; max];
+        let read = with_stream(&reader, |stream| stream.read(&mut buffer));
+        match read {
+            Ok(read) => Value::Str(String::from_utf8_lossy(&buffer[..read]).to_string()),
+            Err(e) => fatal_error(&format!("Could not read from connection: {}", e))
+        }
+    }));
+
+    // Gives the next line without its line ending, or null once the other side closed
+    let line_reader = Arc::clone(&connection);
+    properties.insert(String::from("read_line"), method(move |args, _env| {
+        expect_arg_count(&args, 0);
+
+        let mut line = String::new();
+        match with_stream(&line_reader, |stream| stream.read_line(&mut line)) {
+            Ok(0) => Value::Null,
+            Ok(_) => {
+                let trimmed = line.trim_end_matches(['\n', '\r']).len();
+                line.truncate(trimmed);
+                Value::Str(line)
+            },
+            Err(e) => fatal_error(&format!("Could not read from connection: {}", e))
+        }
+    }));
+
+    // Returns the number of bytes written
+    let writer = Arc::clone(&connection);
+    properties.insert(String::from("write"), method(move |args, _env| {
+        expect_arg_count(&args, 1);
+        let data = string_arg(&args, 0);
+
+        match with_stream(&writer, |stream| stream.get_mut().write_all(data.as_bytes())) {
+            Ok(()) => Value::Number(data.len() as f64),
+            Err(e) => fatal_error(&format!("Could not write to connection: {}", e))
+        }
+    }));
+
+    properties.insert(String::from("close"), method(move |args, _env| {
+        expect_arg_count(&args, 0);
+        connection.lock().unwrap().take();
+        Value::Null
+    }));
+
+    ObjectValue::new(properties)
+}
+
+// net.connect(host, port)
+pub fn native_net_connect(args: Vec<Value>, _ctx: &NativeContext) -> Value {
+    expect_arg_count(&args, 2);
+    let host = string_arg(&args, 0);
+    let port = port_arg(&args, 1);
+
+    match TcpStream::connect((host.as_str(), port)) {
+        Ok(stream) => Value::Object(connection_object(stream)),
+        Err(e) => fatal_error(&format!("Could not connect to {}:{}: {}", host, port, e))
+    }
+}
+
+// net.listen(port, host?) gives a listener with accept(), close() and port, which is the
+// port actually bound so net.listen(0) can pick a free one. host defaults to 127.0.0.1
+pub fn native_net_listen(args: Vec<Value>, _ctx: &NativeContext) -> Value {
+    if args.is_empty() || args.len() > 2 {
+        fatal_error(&format!("Expected 1 or 2 arguments, found {}", args.len()));
+    }
+    let port = port_arg(&args, 0);
+    let host = if args.len() == 2 { string_arg(&args, 1) } else { String::from("127.0.0.1") };
+
+    let listener = match TcpListener::bind((host.as_str(), port)) {
+        Ok(listener) => listener,
+        Err(e) => fatal_error(&format!("Could not listen on {}:{}: {}", host, port, e))
+    };
+    let bound = listener.local_addr().map(|address| address.port()).unwrap_or(port);
+    let listener = Arc::new(Mutex::new(Some(listener)));
+
+    let mut properties: HashMap<String, Value> = HashMap::new();
+    properties.insert(String::from("port"), Value::Number(bound as f64));
+
+    // Waits for the next client and gives its connection
+    let acceptor = Arc::clone(&listener);
+    properties.insert(String::from("accept"), method(move |args, _env| {
+        expect_arg_count(&args, 0);
+
+        let accepted = match acceptor.lock().unwrap().as_ref() {
+            Some(listener) => listener.accept(),
+            None => fatal_error("Listener is closed")
+        };
+        match accepted {
+            Ok((stream, _)) => Value::Object(connection_object(stream)),
+            Err(e) => fatal_error(&format!("Could not accept connection: {}", e))
+        }
+    }));
+
+    properties.insert(String::from("close"), method(move |args, _env| {
+        expect_arg_count(&args, 0);
+        listener.lock().unwrap().take();
+        Value::Null
+    }));
+
+    Value::Object(ObjectValue::new(properties))
+}
\ No newline at end of file
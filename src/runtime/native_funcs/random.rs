@@ -0,0 +1,156 @@
+use std::time::SystemTime;
+
+use crate::{fatal_error, runtime::{context::NativeContext, values::{ListValue, Value}}};
+
+use super::expect_arg_count;
+
+// The generator is SplitMix64, its state kept on the interpreter's global scope so instances
+// don't share a sequence. Seeded from the clock on first use unless seed() is called first
+#[cfg(not(target_arch = "wasm32"))]
+fn initial_seed() -> u64 {
+    SystemTime::now().duration_since(SystemTime::UNIX_EPOCH).unwrap().as_nanos() as u64
+}
+
+// There's no clock to seed from, so runs are the same until a script calls seed()
+#[cfg(target_arch = "wasm32")]
+fn initial_seed() -> u64 {
+    0x2545_F491_4F6C_DD1D
+}
+
+fn next_u64(ctx: &NativeContext) -> u64 {
+    let global = ctx.global();
+    let mut global = global.lock().unwrap();
+    let state = global.random_state.get_or_insert_with(initial_seed);
+    *state = state.wrapping_add(0x9E37_79B9_7F4A_7C15);
+
+    let mut z = *state;
+    z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+    z ^ (z >> 31)
+}
+
+// Uniform in [0, 1)
+fn next_f64(ctx: &NativeContext) -> f64 {
+    (next_u64(ctx) >> 11) as f64 / (1u64 << 53) as f64
+}
+
+// Uniform in [0, bound)
+fn below(ctx: &NativeContext, bound: u64) -> u64 {
+    // Rejecting the top partial range keeps every value equally likely
+    let zone = u64::MAX - u64::MAX % bound;
+    loop {
+        let value = next_u64(ctx);
+        if value < zone {
+            return value % bound;
+        }
+    }
+}
+
+fn number_arg(args: &[Value], index: usize) -> f64 {
+    match &args[index] {
+        Value::Number(number) => *number,
+        _ => fatal_error(&format!("Expected number, found {}", args[index].get_type()))
+    }
+}
+
+fn list_arg(args: &[Value], index: usize) -> ListValue {
+    match &args[index] {
+        Value::List(list) => list.clone(),
+        _ => fatal_error(&format!("Expected list, found {}", args[index].get_type()))
+    }
+}
+
+// random.random() gives a number in [0, 1)
+pub fn native_random(args: Vec<Value>, ctx: &NativeContext) -> Value {
+    expect_arg_count(&args, 0);
+    Value::Number(next_f64(ctx))
+}
+
+// random.randint(a, b) gives a whole number from a to b, including both
+pub fn native_randint(args: Vec<Value>, ctx: &NativeContext) -> Value {
+    expect_arg_count(&args, 2);
+    let low = number_arg(&args, 0).ceil();
+    let high = number_arg(&args, 1).floor();
+
+    if low > high {
+        fatal_error(&format!("Empty range for randint({}, {})", low, high));
+    }
+
+    Value::Number(low + below(ctx, (high - low) as u64 + 1) as f64)
+}
+
+// random.choice(list) gives a random element
+pub fn native_choice(args: Vec<Value>, ctx: &NativeContext) -> Value {
+    expect_arg_count(&args, 1);
+    let elements = list_arg(&args, 0).snapshot();
+
+    if elements.is_empty() {
+        fatal_error("Cannot choose from an empty list");
+    }
+
+    elements[below(ctx, elements.len() as u64) as usize].clone()
+}
+
+// random.shuffle(list) gives a shuffled copy of the list
+pub fn native_shuffle(args: Vec<Value>, ctx: &NativeContext) -> Value {
+    expect_arg_count(&args, 1);
+    let mut elements = list_arg(&args, 0).to_vec();
+
+    // Fisher-Yates
+    for i in (1..elements.len()).rev() {
+        let j = below(ctx, i as u64 + 1) as usize;
+        elements.swap(i, j);
+    }
+
+    Value::List(ListValue::new(elements))
+}
+
+// random.seed(n) makes everything after it repeatable
+pub fn native_seed(args: Vec<Value>, ctx: &NativeContext) -> Value {
+    expect_arg_count(&args, 1);
+    let seed = number_arg(&args, 0);
+
+    ctx.global().lock().unwrap().random_state = Some(seed.to_bits());
+
+    Value::Null
+}
+
+fn format_uuid(bytes: [u8; 16]) -> String {
+    let hex = bytes.iter().map(|byte| format!("{:02x}", byte)).collect::<String>();
+    format!("{}-{}-{}-{}-{}", &hex[..8], &hex[8..12], &hex[12..16], &hex[16..20], &hex[20..])
+}
+
+// Sets the version nibble and the RFC 4122 variant bits
+fn stamp_uuid(bytes: &mut [u8; 16], version: u8) {
+    bytes[6] = (bytes[6] & 0x0f) | (version << 4);
+    bytes[8] = (bytes[8] & 0x3f) | 0x80;
+}
+
+fn random_bytes(ctx: &NativeContext) -> [u8; 16] {
+    let mut bytes = [0; 16];
+    bytes[..8].copy_from_slice(&next_u64(ctx).to_be_bytes());
+    bytes[8..].copy_from_slice(&next_u64(ctx).to_be_bytes());
+    bytes
+}
+
+// Random version 4 UUID. Shares the generator with random, so seed() makes these repeatable too
+pub fn native_uuid(args: Vec<Value>, ctx: &NativeContext) -> Value {
+    expect_arg_count(&args, 0);
+
+    let mut bytes = random_bytes(ctx);
+    stamp_uuid(&mut bytes, 4);
+
+    Value::Str(format_uuid(bytes))
+}
+
+// Version 7 UUID, which starts with the millisecond timestamp so later ones sort after earlier ones
+pub fn native_uuid7(args: Vec<Value>, ctx: &NativeContext) -> Value {
+    expect_arg_count(&args, 0);
+
+    let millis = SystemTime::now().duration_since(SystemTime::UNIX_EPOCH).unwrap().as_millis() as u64;
+    let mut bytes = random_bytes(ctx);
+    bytes[..6].copy_from_slice(&millis.to_be_bytes()[2..]);
+    stamp_uuid(&mut bytes, 7);
+
+    Value::Str(format_uuid(bytes))
+}
\ No newline at end of file
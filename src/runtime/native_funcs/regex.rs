@@ -0,0 +1,154 @@
+use std::{collections::HashMap};
+
+use crate::{fatal_error, runtime::{context::NativeContext, regex::{Match, Regex}, values::{ListValue, ObjectValue, Value}}};
+
+use super::{expect_arg_count, string_arg};
+
+fn compile(pattern: &str) -> Regex {
+    match Regex::new(pattern) {
+        Ok(regex) => regex,
+        Err(e) => fatal_error(&format!("Invalid regex {}: {}", pattern, e))
+    }
+}
+
+fn slice(text: &[char], span: Option<(usize, usize)>) -> Value {
+    match span {
+        Some((start, end)) => Value::Str(text[start..end].iter().collect()),
+        None => Value::Null
+    }
+}
+
+// { text, start, end, groups, named }, groups being a list of every capture group
+// and named an object of the named ones. Groups that didn't match are null
+fn match_to_object(regex: &Regex, text: &[char], found: &Match) -> ObjectValue {
+    let mut properties: HashMap<String, Value> = HashMap::new();
+
+    properties.insert(String::from("text"), slice(text, Some((found.start, found.end))));
+    properties.insert(String::from("start"), Value::Number(found.start as f64));
+    properties.insert(String::from("end"), Value::Number(found.end as f64));
+    properties.insert(String::from("groups"), Value::List(ListValue::new(
+        found.groups.iter().map(|group| slice(text, *group)).collect()
+    )));
+
+    let mut named = HashMap::new();
+    for (name, index) in regex.group_names.iter() {
+        named.insert(name.clone(), slice(text, found.groups[index - 1]));
+    }
+    properties.insert(String::from("named"), Value::Object(ObjectValue::new(named)));
+
+    ObjectValue::new(properties)
+}
+
+// regex.match(pattern, s) gives the first match as an object, or null if there isn't one
+pub fn native_regex_match(args: Vec<Value>, _ctx: &NativeContext) -> Value {
+    expect_arg_count(&args, 2);
+    let regex = compile(&string_arg(&args, 0));
+    let text = string_arg(&args, 1).chars().collect::<Vec<char>>();
+
+    match regex.find_at(&text, 0) {
+        Some(found) => Value::Object(match_to_object(&regex, &text, &found)),
+        None => Value::Null
+    }
+}
+
+// regex.find_all(pattern, s) gives every match. Each is the matched string if the pattern has no
+// groups, otherwise a list of its groups
+pub fn native_regex_find_all(args: Vec<Value>, _ctx: &NativeContext) -> Value {
+    expect_arg_count(&args, 2);
+    let regex = compile(&string_arg(&args, 0));
+    let text = string_arg(&args, 1).chars().collect::<Vec<char>>();
+
+    let elements = regex.find_all(&text).iter().map(|found| {
+        if regex.group_count == 0 {
+            slice(&text, Some((found.start, found.end)))
+        } else {
+            Value::List(ListValue::new(
+                found.groups.iter().map(|group| slice(&text, *group)).collect()
+            ))
+        }
+    }).collect();
+
+    Value::List(ListValue::new(elements))
+}
+
+// regex.replace(pattern, s, replacement) replaces every match. The replacement can refer to
+// groups with $1 or ${name}, $0 being the whole match and $$ a literal $
+pub fn native_regex_replace(args: Vec<Value>, _ctx: &NativeContext) -> Value {
+    expect_arg_count(&args, 3);
+    let regex = compile(&string_arg(&args, 0));
+    let text = string_arg(&args, 1).chars().collect::<Vec<char>>();
+    let replacement = string_arg(&args, 2).chars().collect::<Vec<char>>();
+
+    let mut output = String::new();
+    let mut last = 0;
+
+    for found in regex.find_all(&text) {
+        output.extend(&text[last..found.start]);
+        expand(&regex, &text, &found, &replacement, &mut output);
+        last = found.end;
+    }
+    output.extend(&text[last..]);
+
+    Value::Str(output)
+}
+
+fn expand(regex: &Regex, text: &[char], found: &Match, replacement: &[char], output: &mut String) {
+    let group = |reference: &str| -> Option<(usize, usize)> {
+        let index = match reference.parse::<usize>() {
+            Ok(index) => index,
+            Err(_) => match regex.group_names.iter().find(|(name, _)| name == reference) {
+                Some((_, index)) => *index,
+                None => fatal_error(&format!("Unknown group {} in replacement", reference))
+            }
+        };
+
+        match index {
+            0 => Some((found.start, found.end)),
+            index if index <= regex.group_count => found.groups[index - 1],
+            _ => fatal_error(&format!("Unknown group {} in replacement", index))
+        }
+    };
+
+    let mut position = 0;
+    while position < replacement.len() {
+        let c = replacement[position];
+        position += 1;
+
+        if c != '$' || position == replacement.len() {
+            output.push(c);
+            continue;
+        }
+
+        let reference = match replacement[position] {
+            '$' => {
+                position += 1;
+                output.push('$');
+                continue;
+            },
+            '{' => {
+                let end = match replacement[position..].iter().position(|c| *c == '}') {
+                    Some(end) => position + end,
+                    None => fatal_error("Missing `}` in replacement")
+                };
+                let reference = replacement[position + 1..end].iter().collect::<String>();
+                position = end + 1;
+                reference
+            },
+            c if c.is_ascii_digit() => {
+                let start = position;
+                while position < replacement.len() && replacement[position].is_ascii_digit() {
+                    position += 1;
+                }
+                replacement[start..position].iter().collect::<String>()
+            },
+            _ => {
+                output.push('$');
+                continue;
+            }
+        };
+
+        if let Some((start, end)) = group(&reference) {
+            output.extend(&text[start..end]);
+        }
+    }
+}
\ No newline at end of file
@@ -0,0 +1,80 @@
+use crate::{fatal_error, runtime::{context::NativeContext, values::Value}};
+
+use super::expect_arg_count;
+
+// The numbers in a list, which must have at least `min_len` of them
+fn numbers_arg(args: &[Value], index: usize, min_len: usize) -> Vec<f64> {
+    let list = match &args[index] {
+        Value::List(list) => list,
+        _ => fatal_error(&format!("Expected list, found {}", args[index].get_type()))
+    };
+
+    let numbers = list.snapshot().iter().map(|element| match element {
+        Value::Number(number) => *number,
+        _ => fatal_error(&format!("Expected list of numbers, found {}", element.get_type()))
+    }).collect::<Vec<f64>>();
+
+    if numbers.len() < min_len {
+        fatal_error(&format!("Expected at least {} number{}, found {}", min_len, if min_len == 1 { "" } else { "s" }, numbers.len()));
+    }
+
+    numbers
+}
+
+fn mean(numbers: &[f64]) -> f64 {
+    numbers.iter().sum::<f64>() / numbers.len() as f64
+}
+
+// Sample variance, dividing by n - 1
+fn variance(numbers: &[f64]) -> f64 {
+    let mean = mean(numbers);
+    numbers.iter().map(|number| (number - mean).powi(2)).sum::<f64>() / (numbers.len() - 1) as f64
+}
+
+fn sorted(mut numbers: Vec<f64>) -> Vec<f64> {
+    if numbers.iter().any(|number| number.is_nan()) {
+        fatal_error("Cannot order NaN");
+    }
+    numbers.sort_by(|a, b| a.partial_cmp(b).unwrap());
+    numbers
+}
+
+// Linearly interpolates between the closest ranks, so the 50th percentile is the median
+fn percentile(sorted: &[f64], percent: f64) -> f64 {
+    let rank = percent / 100.0 * (sorted.len() - 1) as f64;
+    let (lower, upper) = (rank.floor() as usize, rank.ceil() as usize);
+    sorted[lower] + (sorted[upper] - sorted[lower]) * (rank - lower as f64)
+}
+
+pub fn native_mean(args: Vec<Value>, _ctx: &NativeContext) -> Value {
+    expect_arg_count(&args, 1);
+    Value::Number(mean(&numbers_arg(&args, 0, 1)))
+}
+
+pub fn native_median(args: Vec<Value>, _ctx: &NativeContext) -> Value {
+    expect_arg_count(&args, 1);
+    Value::Number(percentile(&sorted(numbers_arg(&args, 0, 1)), 50.0))
+}
+
+pub fn native_variance(args: Vec<Value>, _ctx: &NativeContext) -> Value {
+    expect_arg_count(&args, 1);
+    Value::Number(variance(&numbers_arg(&args, 0, 2)))
+}
+
+pub fn native_stdev(args: Vec<Value>, _ctx: &NativeContext) -> Value {
+    expect_arg_count(&args, 1);
+    Value::Number(variance(&numbers_arg(&args, 0, 2)).sqrt())
+}
+
+// percentile(list, p) with p between 0 and 100
+pub fn native_percentile(args: Vec<Value>, _ctx: &NativeContext) -> Value {
+    expect_arg_count(&args, 2);
+
+    let percent = match &args[1] {
+        Value::Number(number) if (0.0..=100.0).contains(number) => *number,
+        Value::Number(number) => fatal_error(&format!("Percentile must be between 0 and 100, found {}", *number)),
+        _ => fatal_error(&format!("Expected number, found {}", args[1].get_type()))
+    };
+
+    Value::Number(percentile(&sorted(numbers_arg(&args, 0, 1)), percent))
+}
\ No newline at end of file
@@ -0,0 +1,45 @@
+use crate::{fatal_error, runtime::{context::NativeContext, values::Value}};
+
+use super::{expect_arg_count, string_arg};
+
+const COLORS: [&str; 8] = ["black", "red", "green", "yellow", "blue", "magenta", "cyan", "white"];
+
+// Escape codes only mean something to a terminal, anywhere else (pipes, files) they'd be noise
+fn styled(ctx: &NativeContext, text: String, code: &str) -> Value {
+    if !ctx.is_terminal() {
+        return Value::Str(text);
+    }
+
+    Value::Str(format!("\x1b[{}m{}\x1b[0m", code, text))
+}
+
+// color(text, name) where name is one of COLORS, optionally prefixed with bright_
+pub fn native_color(args: Vec<Value>, ctx: &NativeContext) -> Value {
+    expect_arg_count(&args, 2);
+
+    let name = string_arg(&args, 1);
+    let (base, offset) = match name.strip_prefix("bright_") {
+        Some(base) => (base, 90),
+        None => (name.as_str(), 30)
+    };
+
+    match COLORS.iter().position(|color| *color == base) {
+        Some(index) => styled(ctx, args[0].to_string(), &(offset + index).to_string()),
+        None => fatal_error(&format!("Unknown color {}, expected one of {}", name, COLORS.join(", ")))
+    }
+}
+
+pub fn native_bold(args: Vec<Value>, ctx: &NativeContext) -> Value {
+    expect_arg_count(&args, 1);
+    styled(ctx, args[0].to_string(), "1")
+}
+
+pub fn native_clear_screen(args: Vec<Value>, ctx: &NativeContext) -> Value {
+    expect_arg_count(&args, 0);
+
+    if ctx.is_terminal() {
+        ctx.write("\x1b[2J\x1b[H");
+    }
+
+    Value::Null
+}
\ No newline at end of file
@@ -0,0 +1,320 @@
+use std::{collections::HashMap, time::Instant};
+
+use crate::{fatal_error, runtime::{context::NativeContext, values::{ObjectValue, Value}}};
+
+use super::{expect_arg_count, string_arg};
+
+// Times are UTC, timestamps are seconds since the Unix epoch like time() gives
+const DEFAULT_FORMAT: &str = "%Y-%m-%d %H:%M:%S";
+
+const MONTHS: [&str; 12] = ["January", "February", "March", "April", "May", "June", "July", "August", "September", "October", "November", "December"];
+const WEEKDAYS: [&str; 7] = ["Monday", "Tuesday", "Wednesday", "Thursday", "Friday", "Saturday", "Sunday"];
+
+// format_time(timestamp, fmt?). Supports %Y %y %m %d %e %j %H %I %M %S %f (milliseconds) %p
+// %a %A %b %B %s %z %Z %F %T and %%
+pub fn native_format_time(args: Vec<Value>, _ctx: &NativeContext) -> Value {
+    if args.is_empty() || args.len() > 2 {
+        fatal_error(&format!("Expected 1 or 2 arguments, found {}", args.len()));
+    }
+
+    let timestamp = match &args[0] {
+        Value::Number(timestamp) if timestamp.is_finite() => *timestamp,
+        _ => fatal_error(&format!("Expected timestamp, found {}", args[0]))
+    };
+    let format = if args.len() == 2 { string_arg(&args, 1) } else { DEFAULT_FORMAT.to_string() };
+
+    match format_time(timestamp, &format) {
+        Ok(value) => Value::Str(value),
+        Err(e) => fatal_error(&e)
+    }
+}
+
+// parse_time(string, fmt?) gives the timestamp, the inverse of format_time. %a %A %j and %Z are
+// matched but don't affect the result
+pub fn native_parse_time(args: Vec<Value>, _ctx: &NativeContext) -> Value {
+    if args.is_empty() || args.len() > 2 {
+        fatal_error(&format!("Expected 1 or 2 arguments, found {}", args.len()));
+    }
+
+    let string = string_arg(&args, 0);
+    let format = if args.len() == 2 { string_arg(&args, 1) } else { DEFAULT_FORMAT.to_string() };
+
+    match parse_time(&string, &format) {
+        Ok(value) => Value::Number(value),
+        Err(e) => fatal_error(&format!("Could not parse time {:?} as {:?}: {}", string, format, e))
+    }
+}
+
+// timeit(fn, iterations) calls fn with no arguments that many times and gives
+// {iterations, total, min, mean, max}, all in seconds
+pub fn native_timeit(args: Vec<Value>, ctx: &NativeContext) -> Value {
+    expect_arg_count(&args, 2);
+
+    let iterations = match &args[1] {
+        Value::Number(number) if *number >= 1.0 && number.fract() == 0.0 => *number as usize,
+        _ => fatal_error(&format!("Expected a positive whole number of iterations, found {}", args[1]))
+    };
+
+    let mut times = Vec::with_capacity(iterations);
+    for _ in 0..iterations {
+        let start = Instant::now();
+        ctx.call(&args[0], vec![]);
+        times.push(start.elapsed().as_secs_f64());
+    }
+
+    let total = times.iter().sum::<f64>();
+    let mut properties: HashMap<String, Value> = HashMap::new();
+    properties.insert("iterations".to_string(), Value::Number(iterations as f64));
+    properties.insert("total".to_string(), Value::Number(total));
+    properties.insert("min".to_string(), Value::Number(times.iter().cloned().fold(f64::INFINITY, f64::min)));
+    properties.insert("mean".to_string(), Value::Number(total / iterations as f64));
+    properties.insert("max".to_string(), Value::Number(times.iter().cloned().fold(0.0, f64::max)));
+
+    Value::Object(ObjectValue::new(properties))
+}
+
+// Days since 1970-01-01 for a date in the proleptic Gregorian calendar
+fn days_from_civil(year: i64, month: i64, day: i64) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let year_of_era = year - era * 400;
+    let day_of_year = (153 * (month + if month > 2 { -3 } else { 9 }) + 2) / 5 + day - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+
+    era * 146097 + day_of_era - 719468
+}
+
+fn civil_from_days(days: i64) -> (i64, i64, i64) {
+    let days = days + 719468;
+    let era = days.div_euclid(146097);
+    let day_of_era = days - era * 146097;
+    let year_of_era = (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month_index = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * month_index + 2) / 5 + 1;
+    let month = if month_index < 10 { month_index + 3 } else { month_index - 9 };
+
+    (year_of_era + era * 400 + if month <= 2 { 1 } else { 0 }, month, day)
+}
+
+fn is_leap_year(year: i64) -> bool {
+    (year % 4 == 0 && year % 100 != 0) || year % 400 == 0
+}
+
+fn days_in_month(year: i64, month: i64) -> i64 {
+    match month {
+        2 if is_leap_year(year) => 29,
+        2 => 28,
+        4 | 6 | 9 | 11 => 30,
+        _ => 31
+    }
+}
+
+pub fn format_time(timestamp: f64, format: &str) -> Result<String, String> {
+    let seconds = timestamp.floor() as i64;
+    let millis = ((timestamp - timestamp.floor()) * 1000.0) as i64;
+
+    let days = seconds.div_euclid(86400);
+    let seconds_of_day = seconds.rem_euclid(86400);
+    let (year, month, day) = civil_from_days(days);
+    let (hour, minute, second) = (seconds_of_day / 3600, seconds_of_day / 60 % 60, seconds_of_day % 60);
+    // 1970-01-01 was a Thursday
+    let weekday = (days + 3).rem_euclid(7) as usize;
+    let day_of_year = days - days_from_civil(year, 1, 1) + 1;
+    let hour_12 = if hour % 12 == 0 { 12 } else { hour % 12 };
+
+    let mut output = String::new();
+    let mut chars = format.chars();
+
+    while let Some(c) = chars.next() {
+        if c != '%' {
+            output.push(c);
+            continue;
+        }
+
+        match chars.next() {
+            Some('Y') => output.push_str(&format!("{:04}", year)),
+            Some('y') => output.push_str(&format!("{:02}", year.rem_euclid(100))),
+            Some('m') => output.push_str(&format!("{:02}", month)),
+            Some('d') => output.push_str(&format!("{:02}", day)),
+            Some('e') => output.push_str(&format!("{:>2}", day)),
+            Some('j') => output.push_str(&format!("{:03}", day_of_year)),
+            Some('H') => output.push_str(&format!("{:02}", hour)),
+            Some('I') => output.push_str(&format!("{:02}", hour_12)),
+            Some('M') => output.push_str(&format!("{:02}", minute)),
+            Some('S') => output.push_str(&format!("{:02}", second)),
+            Some('f') => output.push_str(&format!("{:03}", millis)),
+            Some('p') => output.push_str(if hour < 12 { "AM" } else { "PM" }),
+            Some('a') => output.push_str(&WEEKDAYS[weekday][..3]),
+            Some('A') => output.push_str(WEEKDAYS[weekday]),
+            Some('b') => output.push_str(&MONTHS[month as usize - 1][..3]),
+            Some('B') => output.push_str(MONTHS[month as usize - 1]),
+            Some('s') => output.push_str(&seconds.to_string()),
+            Some('z') => output.push_str("+0000"),
+            Some('Z') => output.push_str("UTC"),
+            Some('F') => output.push_str(&format!("{:04}-{:02}-{:02}", year, month, day)),
+            Some('T') => output.push_str(&format!("{:02}:{:02}:{:02}", hour, minute, second)),
+            Some('%') => output.push('%'),
+            Some(c) => return Err(format!("Unknown time format specifier %{}", c)),
+            None => return Err(String::from("Time format ends with a lone %"))
+        }
+    }
+
+    Ok(output)
+}
+
+struct TimeReader<'a> {
+    chars: &'a [char],
+    position: usize
+}
+
+impl TimeReader<'_> {
+    fn number(&mut self, max_digits: usize) -> Result<i64, String> {
+        let negative = self.chars.get(self.position) == Some(&'-');
+        if negative {
+            self.position += 1;
+        }
+
+        let start = self.position;
+        while self.position - start < max_digits && self.chars.get(self.position).is_some_and(|c| c.is_ascii_digit()) {
+            self.position += 1;
+        }
+        if self.position == start {
+            return Err(format!("Expected a number at character {}", start));
+        }
+
+        let number = self.chars[start..self.position].iter().collect::<String>().parse::<i64>().unwrap();
+        Ok(if negative { -number } else { number })
+    }
+
+    // Matches one of the names, full or shortened to 3 letters, ignoring case
+    fn name(&mut self, names: &[&str]) -> Result<usize, String> {
+        for (index, name) in names.iter().enumerate() {
+            for candidate in [*name, &name[..name.len().min(3)]] {
+                let end = self.position + candidate.len();
+                if end <= self.chars.len() && self.chars[self.position..end].iter().collect::<String>().eq_ignore_ascii_case(candidate) {
+                    self.position = end;
+                    return Ok(index);
+                }
+            }
+        }
+
+        Err(format!("Expected a name at character {}", self.position))
+    }
+
+    fn literal(&mut self, expected: char) -> Result<(), String> {
+        if self.chars.get(self.position) != Some(&expected) {
+            return Err(format!("Expected {:?} at character {}", expected, self.position));
+        }
+        self.position += 1;
+        Ok(())
+    }
+}
+
+pub fn parse_time(string: &str, format: &str) -> Result<f64, String> {
+    let chars = string.chars().collect::<Vec<char>>();
+    let mut reader = TimeReader { chars: &chars, position: 0 };
+
+    let (mut year, mut month, mut day) = (1970, 1, 1);
+    let (mut hour, mut minute, mut second, mut millis) = (0, 0, 0, 0);
+    let mut pm = None;
+    let mut offset = 0;
+    let mut epoch = None;
+
+    let mut format_chars = format.chars();
+    while let Some(c) = format_chars.next() {
+        if c != '%' {
+            if c.is_whitespace() {
+                while reader.chars.get(reader.position).is_some_and(|c| c.is_whitespace()) {
+                    reader.position += 1;
+                }
+            } else {
+                reader.literal(c)?;
+            }
+            continue;
+        }
+
+        match format_chars.next() {
+            Some('Y') => year = reader.number(4)?,
+            Some('y') => year = 2000 + reader.number(2)?,
+            Some('m') => month = reader.number(2)?,
+            Some('d') | Some('e') => {
+                while reader.chars.get(reader.position) == Some(&' ') {
+                    reader.position += 1;
+                }
+                day = reader.number(2)?;
+            },
+            Some('j') => { reader.number(3)?; },
+            Some('H') => hour = reader.number(2)?,
+            Some('I') => hour = reader.number(2)?,
+            Some('M') => minute = reader.number(2)?,
+            Some('S') => second = reader.number(2)?,
+            Some('f') => millis = reader.number(3)?,
+            Some('p') => pm = Some(reader.name(&["AM", "PM"])? == 1),
+            Some('a') | Some('A') => { reader.name(&WEEKDAYS)?; },
+            Some('b') | Some('B') => month = reader.name(&MONTHS)? as i64 + 1,
+            Some('s') => epoch = Some(reader.number(20)?),
+            Some('z') => {
+                let sign = match reader.chars.get(reader.position) {
+                    Some('+') => 1,
+                    Some('-') => -1,
+                    _ => return Err(format!("Expected + or - at character {}", reader.position))
+                };
+                reader.position += 1;
+                let hours = reader.number(2)?;
+                if reader.chars.get(reader.position) == Some(&':') {
+                    reader.position += 1;
+                }
+                let minutes = reader.number(2)?;
+                offset = sign * (hours * 3600 + minutes * 60);
+            },
+            Some('Z') => {
+                for expected in ['U', 'T', 'C'] {
+                    reader.literal(expected)?;
+                }
+            },
+            Some('F') => {
+                year = reader.number(4)?;
+                reader.literal('-')?;
+                month = reader.number(2)?;
+                reader.literal('-')?;
+                day = reader.number(2)?;
+            },
+            Some('T') => {
+                hour = reader.number(2)?;
+                reader.literal(':')?;
+                minute = reader.number(2)?;
+                reader.literal(':')?;
+                second = reader.number(2)?;
+            },
+            Some('%') => reader.literal('%')?,
+            Some(c) => return Err(format!("Unknown time format specifier %{}", c)),
+            None => return Err(String::from("Time format ends with a lone %"))
+        }
+    }
+
+    if reader.position != chars.len() {
+        return Err(format!("Unexpected text at character {}", reader.position));
+    }
+
+    if let Some(epoch) = epoch {
+        return Ok(epoch as f64);
+    }
+
+    if let Some(pm) = pm {
+        if !(1..=12).contains(&hour) {
+            return Err(format!("Hour {} is out of range for a 12 hour clock", hour));
+        }
+        hour = hour % 12 + if pm { 12 } else { 0 };
+    }
+
+    if !(1..=12).contains(&month) || day < 1 || day > days_in_month(year, month) {
+        return Err(format!("{:04}-{:02}-{:02} is not a date", year, month, day));
+    }
+    if hour > 23 || minute > 59 || second > 60 {
+        return Err(format!("{:02}:{:02}:{:02} is not a time", hour, minute, second));
+    }
+
+    let seconds = days_from_civil(year, month, day) * 86400 + hour * 3600 + minute * 60 + second - offset;
+    Ok(seconds as f64 + millis as f64 / 1000.0)
+}
\ No newline at end of file
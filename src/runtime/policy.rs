@@ -0,0 +1,273 @@
+use std::{cell::RefCell, collections::HashSet, mem, sync::{Arc, Mutex}, thread, time::{Duration, Instant}};
+
+use crate::fatal_error;
+
+use super::{environment::{Environment, NativeGroup}, values::Value};
+
+// How deeply script functions may call each other. Each call takes a few kB of stack, so these
+// keep runaway recursion an error instead of overflowing it: the default is sized for a main
+// thread's 8MB and the sandboxed one for the 2MB threads are spawned with
+pub const DEFAULT_MAX_CALL_DEPTH: usize = 1000;
+pub const SANDBOXED_MAX_CALL_DEPTH: usize = 256;
+
+// What scripts may do, for running untrusted code from a host application. Kept on the global
+// scope like IoHandles. The default allows everything with no limits besides the call depth, same as the CLI
+#[derive(Debug, Clone)]
+pub struct Policy {
+    // The Fs group
+    pub fs: bool,
+    // The Http and Net groups
+    pub network: bool,
+    // exit, which would end the host's process too
+    pub process: bool,
+    // input
+    pub stdin: bool,
+    // How long a single eval or call may run for
+    pub time_limit: Option<Duration>,
+    // Roughly how many bytes the variables in scope may hold, see estimate_size. Checked when a
+    // variable is set and every so often while running
+    pub memory_limit: Option<usize>,
+    // How many script function calls may be nested
+    pub max_call_depth: Option<usize>
+}
+
+impl Default for Policy {
+    fn default() -> Self {
+        Policy {
+            fs: true,
+            network: true,
+            process: true,
+            stdin: true,
+            time_limit: None,
+            memory_limit: None,
+            max_call_depth: Some(DEFAULT_MAX_CALL_DEPTH)
+        }
+    }
+}
+
+impl Policy {
+    // Nothing outside the interpreter is reachable, limits besides the call depth are left for the host to pick
+    pub fn sandboxed() -> Self {
+        Policy {
+            fs: false,
+            network: false,
+            process: false,
+            stdin: false,
+            time_limit: None,
+            memory_limit: None,
+            max_call_depth: Some(SANDBOXED_MAX_CALL_DEPTH)
+        }
+    }
+
+    pub fn allows_group(&self, group: NativeGroup) -> bool {
+        match group {
+            #[cfg(not(target_arch = "wasm32"))]
+            NativeGroup::Fs => self.fs,
+            #[cfg(all(feature = "http", not(target_arch = "wasm32")))]
+            NativeGroup::Http => self.network,
+            #[cfg(not(target_arch = "wasm32"))]
+            NativeGroup::Net => self.network,
+            _ => true
+        }
+    }
+
+    // Natives taken out of groups that are otherwise allowed
+    pub fn denied_names(&self) -> Vec<&'static str> {
+        let mut names = Vec::new();
+        if !self.process {
+            names.push("exit");
+        }
+        if !self.stdin {
+            names.push("input");
+        }
+        names
+    }
+}
+
+struct Limits {
+    deadline: Option<(Instant, Duration)>,
+    memory_limit: Option<usize>,
+    max_call_depth: Option<usize>,
+    call_depth: usize,
+    steps: u32
+}
+
+thread_local! {
+    static LIMITS: RefCell<Option<Limits>> = const { RefCell::new(None) };
+}
+
+// How often, in evaluated nodes, the clock and the memory estimate are checked
+const TIME_CHECK_INTERVAL: u32 = 256;
+const MEMORY_CHECK_INTERVAL: u32 = 4096;
+
+// Puts the previous limits back when dropped, also when a runtime error unwinds
+struct Restore(Option<Limits>);
+
+impl Drop for Restore {
+    fn drop(&mut self) {
+        let previous = self.0.take();
+        LIMITS.with(|limits| *limits.borrow_mut() = previous);
+    }
+}
+
+// Runs `run` with the policy's limits applied to everything evaluated on this thread meanwhile
+pub fn enforce<T>(policy: &Policy, run: impl FnOnce() -> T) -> T {
+    let limits = if policy.time_limit.is_none() && policy.memory_limit.is_none() && policy.max_call_depth.is_none() {
+        None
+    } else {
+        Some(Limits {
+            deadline: policy.time_limit.map(|limit| (Instant::now() + limit, limit)),
+            memory_limit: policy.memory_limit,
+            max_call_depth: policy.max_call_depth,
+            call_depth: 0,
+            steps: 0
+        })
+    };
+
+    let _restore = Restore(LIMITS.with(|current| mem::replace(&mut *current.borrow_mut(), limits)));
+    run()
+}
+
+// Called by eval for every node, fails once a limit has been gone over
+pub fn check(env: &Arc<Mutex<Environment>>) {
+    let exceeded = LIMITS.with(|limits| {
+        let mut limits = limits.borrow_mut();
+        let limits = limits.as_mut()?;
+        limits.steps = limits.steps.wrapping_add(1);
+
+        if let Some((deadline, limit)) = limits.deadline {
+            if limits.steps % TIME_CHECK_INTERVAL == 0 && Instant::now() > deadline {
+                return Some(time_limit_error(limit));
+            }
+        }
+        if let Some(limit) = limits.memory_limit {
+            if limits.steps % MEMORY_CHECK_INTERVAL == 0 && scope_size(env) > limit {
+                return Some(format!("Memory limit of {} bytes exceeded", limit));
+            }
+        }
+        None
+    });
+
+    if let Some(message) = exceeded {
+        fatal_error(&message);
+    }
+}
+
+// For natives that can run for a while without evaluating anything, like the regex matcher
+pub fn check_time() {
+    let exceeded = LIMITS.with(|limits| {
+        let (deadline, limit) = limits.borrow().as_ref()?.deadline?;
+        (Instant::now() > deadline).then_some(limit)
+    });
+
+    if let Some(limit) = exceeded {
+        fatal_error(&time_limit_error(limit));
+    }
+}
+
+// Sleeps for `duration`, or fails once the time limit runs out if that comes first
+pub fn sleep(duration: Duration) {
+    let deadline = LIMITS.with(|limits| limits.borrow().as_ref().and_then(|limits| limits.deadline));
+
+    match deadline.map(|(deadline, limit)| (deadline.saturating_duration_since(Instant::now()), limit)) {
+        Some((left, limit)) if left < duration => {
+            thread::sleep(left);
+            fatal_error(&time_limit_error(limit));
+        },
+        _ => thread::sleep(duration)
+    }
+}
+
+fn time_limit_error(limit: Duration) -> String {
+    format!("Time limit of {:?} exceeded", limit)
+}
+
+// Counts a script function call until dropped, failing if that goes over the call depth. The
+// count is taken back when dropped, also when a runtime error unwinds out of the call
+pub struct Call;
+
+pub fn enter_call() -> Call {
+    let exceeded = LIMITS.with(|limits| {
+        let mut limits = limits.borrow_mut();
+        let limits = limits.as_mut()?;
+        limits.call_depth += 1;
+        limits.max_call_depth.filter(|max| limits.call_depth > *max)
+    });
+
+    let call = Call;
+    if let Some(max) = exceeded {
+        fatal_error(&format!("Maximum call depth of {} exceeded", max));
+    }
+    call
+}
+
+impl Drop for Call {
+    fn drop(&mut self) {
+        LIMITS.with(|limits| {
+            if let Some(limits) = limits.borrow_mut().as_mut() {
+                limits.call_depth = limits.call_depth.saturating_sub(1);
+            }
+        });
+    }
+}
+
+// Called when a variable is set, so one value growing quickly (a string doubled in a loop) is
+// caught before the next periodic check
+pub fn check_value(value: &Value) {
+    let limit = LIMITS.with(|limits| limits.borrow().as_ref().and_then(|limits| limits.memory_limit));
+
+    if let Some(limit) = limit {
+        if estimate_size(value) > limit {
+            fatal_error(&format!("Memory limit of {} bytes exceeded", limit));
+        }
+    }
+}
+
+// The variables of the scope and every scope around it. Scopes locked further up the call
+// stack are skipped rather than waited on
+fn scope_size(env: &Arc<Mutex<Environment>>) -> usize {
+    let mut size = 0;
+    let mut counted = HashSet::new();
+    let mut current = Some(Arc::clone(env));
+
+    while let Some(scope) = current {
+        current = match scope.try_lock() {
+            Ok(scope) => {
+                size += scope.variables.values().map(|value| size_once(value, &mut counted)).sum::<usize>();
+                scope.parent.clone()
+            },
+            Err(_) => break
+        };
+    }
+
+    size
+}
+
+// An estimate of the memory a value takes up, counting what it owns but not code or host data
+pub fn estimate_size(value: &Value) -> usize {
+    size_once(value, &mut HashSet::new())
+}
+
+// Lists and objects are shared, so each is only counted the first time it's reached. That also
+// stops one that contains itself being counted forever
+fn size_once(value: &Value, counted: &mut HashSet<usize>) -> usize {
+    let owned = match value {
+        Value::Str(string) => string.len(),
+        Value::List(list) => {
+            if !counted.insert(Arc::as_ptr(&list.elements) as usize) {
+                return mem::size_of::<Value>();
+            }
+            list.snapshot().iter().map(|element| size_once(element, counted)).sum()
+        },
+        Value::Object(object) => {
+            if !counted.insert(Arc::as_ptr(&object.properties) as usize) {
+                return mem::size_of::<Value>();
+            }
+            object.snapshot().iter().map(|(key, value)| key.len() + size_once(value, counted)).sum()
+        },
+        Value::UserData(user_data) => user_data.type_name.len(),
+        _ => 0
+    };
+
+    mem::size_of_val(value) + mem::size_of::<Value>() + owned
+}
\ No newline at end of file
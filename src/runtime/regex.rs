@@ -0,0 +1,635 @@
+// A small regex engine for the regex natives. Supports literals, `.`, classes ([a-z], [^0-9],
+// \d \w \s and their negations), anchors (^ $ \b \B), capturing, non-capturing and named groups,
+// alternation and greedy or lazy quantifiers (* + ? {n} {n,} {n,m}). Positions are in chars, not bytes.
+// Patterns compile to a program for a Pike VM, which follows every way of matching at once instead of
+// backtracking, so matching takes time proportional to the text times the pattern and never recurses
+
+use std::mem;
+
+use super::policy;
+
+// How often, in positions of the text, a match checks the time limit
+const TIME_CHECK_INTERVAL: usize = 256;
+
+#[derive(Debug, Clone)]
+enum ClassItem {
+    Range(char, char),
+    Digit(bool),
+    Word(bool),
+    Space(bool)
+}
+
+impl ClassItem {
+    fn matches(&self, c: char) -> bool {
+        match self {
+            ClassItem::Range(start, end) => *start <= c && c <= *end,
+            ClassItem::Digit(negated) => c.is_ascii_digit() != *negated,
+            ClassItem::Word(negated) => is_word(c) != *negated,
+            ClassItem::Space(negated) => c.is_whitespace() != *negated
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+enum Node {
+    Char(char),
+    Any,
+    Class(Vec<ClassItem>, bool),
+    Start,
+    End,
+    WordBoundary(bool),
+    Group(Box<Node>, Option<usize>),
+    Concat(Vec<Node>),
+    Alternate(Vec<Node>),
+    Repeat {
+        node: Box<Node>,
+        min: usize,
+        max: Option<usize>,
+        greedy: bool
+    }
+}
+
+fn is_word(c: char) -> bool {
+    c.is_alphanumeric() || c == '_'
+}
+
+// What a pattern compiles to
+#[derive(Debug, Clone)]
+enum Inst {
+    Char(char),
+    Any,
+    Class(Vec<ClassItem>, bool),
+    Start,
+    End,
+    WordBoundary(bool),
+    // Carries on at both, the first being preferred
+    Split(usize, usize),
+    Jump(usize),
+    // Records the position in a slot, 2n and 2n + 1 being the start and end of group n
+    Save(usize),
+    Match
+}
+
+type Slots = Vec<Option<usize>>;
+
+#[derive(Debug, Clone)]
+pub struct Regex {
+    program: Vec<Inst>,
+    // Number of capturing groups, not counting the whole match
+    pub group_count: usize,
+    pub group_names: Vec<(String, usize)>
+}
+
+#[derive(Debug, Clone)]
+pub struct Match {
+    pub start: usize,
+    pub end: usize,
+    // Start and end of each capturing group, None if the group didn't take part in the match
+    pub groups: Vec<Option<(usize, usize)>>
+}
+
+impl Regex {
+    pub fn new(pattern: &str) -> Result<Self, String> {
+        let mut parser = RegexParser {
+            chars: pattern.chars().collect(),
+            position: 0,
+            group_count: 0,
+            group_names: Vec::new(),
+            depth: 0
+        };
+
+        let node = parser.parse_alternate()?;
+        if parser.position != parser.chars.len() {
+            return Err(String::from("Unmatched `)`"));
+        }
+
+        // Group 0 is the whole match
+        let mut compiler = Compiler { program: Vec::new() };
+        compiler.emit(Inst::Save(0))?;
+        compiler.compile(&node)?;
+        compiler.emit(Inst::Save(1))?;
+        compiler.emit(Inst::Match)?;
+
+        Ok(Regex {
+            program: compiler.program,
+            group_count: parser.group_count,
+            group_names: parser.group_names
+        })
+    }
+
+    // First match starting at or after `start`
+    pub fn find_at(&self, text: &[char], start: usize) -> Option<Match> {
+        let slots = self.run(text, start)?;
+        let span = |group: usize| slots[group * 2].zip(slots[group * 2 + 1]);
+
+        Some(Match {
+            start: slots[0]?,
+            end: slots[1]?,
+            groups: (1..=self.group_count).map(span).collect()
+        })
+    }
+
+    // Every non-overlapping match, left to right
+    pub fn find_all(&self, text: &[char]) -> Vec<Match> {
+        let mut matches = Vec::new();
+        let mut position = 0;
+
+        while position <= text.len() {
+            let found = match self.find_at(text, position) {
+                Some(found) => found,
+                None => break
+            };
+
+            // Step past empty matches so they aren't found forever
+            position = if found.end == found.start { found.end + 1 } else { found.end };
+            matches.push(found);
+        }
+
+        matches
+    }
+
+    // Runs the program from `start`, adding a new thread at each position until one of them
+    // matches. Threads are kept in order of preference, so the first to reach Match is the match
+    // a backtracking engine would have found, and the ones after it are dropped
+    fn run(&self, text: &[char], start: usize) -> Option<Slots> {
+        let mut current = Threads::new(self.program.len());
+        let mut next = Threads::new(self.program.len());
+        let mut matched = None;
+
+        for position in start..=text.len() {
+            if (position - start) % TIME_CHECK_INTERVAL == TIME_CHECK_INTERVAL - 1 {
+                policy::check_time();
+            }
+            if matched.is_none() {
+                self.add_thread(&mut current, 0, vec![None; (self.group_count + 1) * 2], text, position);
+            }
+            if current.list.is_empty() && matched.is_some() {
+                break;
+            }
+
+            next.clear();
+            for (pc, slots) in current.list.drain(..) {
+                let consumed = match &self.program[pc] {
+                    Inst::Char(c) => text.get(position) == Some(c),
+                    Inst::Any => text.get(position).is_some_and(|c| *c != '\n'),
+                    Inst::Class(items, negated) => text.get(position).is_some_and(|c| items.iter().any(|item| item.matches(*c)) != *negated),
+                    Inst::Match => {
+                        matched = Some(slots);
+                        break;
+                    },
+                    _ => false
+                };
+
+                if consumed {
+                    self.add_thread(&mut next, pc + 1, slots, text, position + 1);
+                }
+            }
+            mem::swap(&mut current, &mut next);
+        }
+
+        matched
+    }
+
+    // Follows jumps, splits, saves and assertions from `pc` until each path reaches an
+    // instruction that consumes a char or matches, and adds those threads in order of preference.
+    // An instruction already reached at this position is skipped, which is also what ends empty
+    // loops like (a*)*
+    fn add_thread(&self, threads: &mut Threads, pc: usize, slots: Slots, text: &[char], position: usize) {
+        let mut stack = vec![(pc, slots)];
+
+        while let Some((pc, mut slots)) = stack.pop() {
+            if threads.seen[pc] == threads.generation {
+                continue;
+            }
+            threads.seen[pc] = threads.generation;
+
+            match &self.program[pc] {
+                Inst::Jump(to) => stack.push((*to, slots)),
+                // Pushed in reverse, so the preferred branch is followed first
+                Inst::Split(first, second) => {
+                    stack.push((*second, slots.clone()));
+                    stack.push((*first, slots));
+                },
+                Inst::Save(slot) => {
+                    slots[*slot] = Some(position);
+                    stack.push((pc + 1, slots));
+                },
+                Inst::Start => if position == 0 {
+                    stack.push((pc + 1, slots));
+                },
+                Inst::End => if position == text.len() {
+                    stack.push((pc + 1, slots));
+                },
+                Inst::WordBoundary(negated) => {
+                    let before = position > 0 && is_word(text[position - 1]);
+                    let after = position < text.len() && is_word(text[position]);
+                    if (before != after) != *negated {
+                        stack.push((pc + 1, slots));
+                    }
+                },
+                _ => threads.list.push((pc, slots))
+            }
+        }
+    }
+}
+
+// The threads waiting at one position. `seen` marks the instructions reached at this position,
+// those holding the current generation, so clearing doesn't have to touch every instruction
+struct Threads {
+    list: Vec<(usize, Slots)>,
+    seen: Vec<usize>,
+    generation: usize
+}
+
+impl Threads {
+    fn new(size: usize) -> Self {
+        Threads { list: Vec::new(), seen: vec![0; size], generation: 1 }
+    }
+
+    fn clear(&mut self) {
+        self.list.clear();
+        self.generation += 1;
+    }
+}
+
+// Patterns are expanded as they compile, a{1000} being a repeated 1000 times, so this keeps one
+// like (a{1000}){1000} from compiling to a program too big to run
+const MAX_PROGRAM_SIZE: usize = 100_000;
+
+struct Compiler {
+    program: Vec<Inst>
+}
+
+impl Compiler {
+    fn emit(&mut self, inst: Inst) -> Result<usize, String> {
+        if self.program.len() >= MAX_PROGRAM_SIZE {
+            return Err(String::from("Pattern too large"));
+        }
+        self.program.push(inst);
+        Ok(self.program.len() - 1)
+    }
+
+    fn compile(&mut self, node: &Node) -> Result<(), String> {
+        match node {
+            Node::Char(c) => { self.emit(Inst::Char(*c))?; },
+            Node::Any => { self.emit(Inst::Any)?; },
+            Node::Class(items, negated) => { self.emit(Inst::Class(items.clone(), *negated))?; },
+            Node::Start => { self.emit(Inst::Start)?; },
+            Node::End => { self.emit(Inst::End)?; },
+            Node::WordBoundary(negated) => { self.emit(Inst::WordBoundary(*negated))?; },
+            Node::Group(inner, index) => match index {
+                Some(index) => {
+                    self.emit(Inst::Save(index * 2))?;
+                    self.compile(inner)?;
+                    self.emit(Inst::Save(index * 2 + 1))?;
+                },
+                None => self.compile(inner)?
+            },
+            Node::Concat(nodes) => {
+                for node in nodes {
+                    self.compile(node)?;
+                }
+            },
+            // Each option but the last is tried through a split, and jumps past the rest when it's done
+            Node::Alternate(options) => {
+                let mut jumps = Vec::new();
+                for (index, option) in options.iter().enumerate() {
+                    if index == options.len() - 1 {
+                        self.compile(option)?;
+                        break;
+                    }
+                    let split = self.emit(Inst::Split(0, 0))?;
+                    self.compile(option)?;
+                    jumps.push(self.emit(Inst::Jump(0))?);
+                    self.program[split] = Inst::Split(split + 1, self.program.len());
+                }
+
+                let end = self.program.len();
+                for jump in jumps {
+                    self.program[jump] = Inst::Jump(end);
+                }
+            },
+            // The required copies one after another, then either a loop or the optional copies
+            Node::Repeat { node, min, max, greedy } => {
+                for _ in 0..*min {
+                    let before = self.program.len();
+                    self.compile(node)?;
+                    // Nothing was emitted, so the other copies wouldn't emit anything either
+                    if self.program.len() == before {
+                        break;
+                    }
+                }
+
+                match max {
+                    None => {
+                        let split = self.emit(Inst::Split(0, 0))?;
+                        self.compile(node)?;
+                        self.emit(Inst::Jump(split))?;
+                        self.program[split] = split_to(split + 1, self.program.len(), *greedy);
+                    },
+                    Some(max) => {
+                        let mut splits = Vec::new();
+                        for _ in *min..*max {
+                            splits.push(self.emit(Inst::Split(0, 0))?);
+                            self.compile(node)?;
+                        }
+
+                        let end = self.program.len();
+                        for split in splits {
+                            self.program[split] = split_to(split + 1, end, *greedy);
+                        }
+                    }
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+// A split between repeating once more, at `repeat`, and going on without, at `skip`
+fn split_to(repeat: usize, skip: usize, greedy: bool) -> Inst {
+    if greedy {
+        Inst::Split(repeat, skip)
+    } else {
+        Inst::Split(skip, repeat)
+    }
+}
+
+// Parsing and compiling recurse into groups, this keeps deeply nested ones from overflowing the stack
+const MAX_GROUP_DEPTH: usize = 256;
+
+struct RegexParser {
+    chars: Vec<char>,
+    position: usize,
+    group_count: usize,
+    group_names: Vec<(String, usize)>,
+    // How many groups the parser is inside
+    depth: usize
+}
+
+impl RegexParser {
+    fn at(&self) -> Option<char> {
+        self.chars.get(self.position).copied()
+    }
+
+    fn eat(&mut self) -> Option<char> {
+        let c = self.at();
+        self.position += 1;
+        c
+    }
+
+    fn parse_alternate(&mut self) -> Result<Node, String> {
+        let mut options = vec![self.parse_concat()?];
+
+        while self.at() == Some('|') {
+            self.position += 1;
+            options.push(self.parse_concat()?);
+        }
+
+        if options.len() == 1 {
+            Ok(options.pop().unwrap())
+        } else {
+            Ok(Node::Alternate(options))
+        }
+    }
+
+    fn parse_concat(&mut self) -> Result<Node, String> {
+        let mut nodes = Vec::new();
+
+        while let Some(c) = self.at() {
+            if c == '|' || c == ')' {
+                break;
+            }
+
+            let atom = self.parse_atom()?;
+            nodes.push(self.parse_quantifier(atom)?);
+        }
+
+        Ok(Node::Concat(nodes))
+    }
+
+    fn parse_quantifier(&mut self, atom: Node) -> Result<Node, String> {
+        let start = self.position;
+
+        let (min, max) = match self.at() {
+            Some('*') => (0, None),
+            Some('+') => (1, None),
+            Some('?') => (0, Some(1)),
+            Some('{') => match self.parse_braces() {
+                Some(bounds) => bounds,
+                None => {
+                    // Not a valid {n,m}, so the brace is just a character
+                    self.position = start;
+                    return Ok(atom);
+                }
+            },
+            _ => return Ok(atom)
+        };
+
+        if self.position == start {
+            self.position += 1;
+        }
+
+        if let Some(max) = max {
+            if max < min {
+                return Err(format!("Invalid repetition {{{},{}}}", min, max));
+            }
+        }
+
+        let greedy = if self.at() == Some('?') {
+            self.position += 1;
+            false
+        } else {
+            true
+        };
+
+        if matches!(atom, Node::Start | Node::End | Node::WordBoundary(_)) {
+            return Err(String::from("Nothing to repeat"));
+        }
+
+        Ok(Node::Repeat { node: Box::new(atom), min, max, greedy })
+    }
+
+    // {n}, {n,} or {n,m}
+    fn parse_braces(&mut self) -> Option<(usize, Option<usize>)> {
+        self.position += 1;
+
+        let min = self.parse_number()?;
+        let max = match self.eat()? {
+            '}' => return Some((min, Some(min))),
+            ',' if self.at() == Some('}') => None,
+            ',' => Some(self.parse_number()?),
+            _ => return None
+        };
+
+        if self.eat()? != '}' {
+            return None;
+        }
+
+        Some((min, max))
+    }
+
+    fn parse_number(&mut self) -> Option<usize> {
+        let start = self.position;
+        while self.at().is_some_and(|c| c.is_ascii_digit()) {
+            self.position += 1;
+        }
+
+        self.chars[start..self.position].iter().collect::<String>().parse().ok()
+    }
+
+    fn parse_atom(&mut self) -> Result<Node, String> {
+        match self.eat().unwrap() {
+            '(' => self.parse_group(),
+            '[' => self.parse_class(),
+            '.' => Ok(Node::Any),
+            '^' => Ok(Node::Start),
+            '$' => Ok(Node::End),
+            '\\' => self.parse_escape(false).map(|item| match item {
+                Escape::Node(node) => node,
+                Escape::Item(item) => Node::Class(vec![item], false)
+            }),
+            '*' | '+' | '?' => Err(String::from("Nothing to repeat")),
+            c => Ok(Node::Char(c))
+        }
+    }
+
+    fn parse_group(&mut self) -> Result<Node, String> {
+        if self.depth == MAX_GROUP_DEPTH {
+            return Err(format!("Groups nested too deeply (maximum depth is {})", MAX_GROUP_DEPTH));
+        }
+        let mut index = None;
+
+        if self.at() == Some('?') {
+            self.position += 1;
+            match self.eat() {
+                Some(':') => {},
+                Some('P') if self.at() == Some('<') => {
+                    self.position += 1;
+                    index = Some(self.parse_group_name()?);
+                },
+                Some('<') => index = Some(self.parse_group_name()?),
+                _ => return Err(String::from("Unknown group type"))
+            }
+        } else {
+            self.group_count += 1;
+            index = Some(self.group_count);
+        }
+
+        self.depth += 1;
+        let inner = self.parse_alternate()?;
+        self.depth -= 1;
+
+        if self.eat() != Some(')') {
+            return Err(String::from("Missing `)`"));
+        }
+
+        Ok(Node::Group(Box::new(inner), index))
+    }
+
+    fn parse_group_name(&mut self) -> Result<usize, String> {
+        let mut name = String::new();
+
+        loop {
+            match self.eat() {
+                Some('>') => break,
+                Some(c) if is_word(c) => name.push(c),
+                _ => return Err(String::from("Invalid group name"))
+            }
+        }
+
+        if name.is_empty() || self.group_names.iter().any(|(existing, _)| *existing == name) {
+            return Err(format!("Invalid or duplicate group name `{}`", name));
+        }
+
+        self.group_count += 1;
+        self.group_names.push((name, self.group_count));
+
+        Ok(self.group_count)
+    }
+
+    fn parse_class(&mut self) -> Result<Node, String> {
+        let negated = self.at() == Some('^');
+        if negated {
+            self.position += 1;
+        }
+
+        let mut items = Vec::new();
+        let mut first = true;
+
+        loop {
+            let c = match self.eat() {
+                Some(c) => c,
+                None => return Err(String::from("Missing `]`"))
+            };
+
+            // A ] straight after the opening bracket is a literal
+            if c == ']' && !first {
+                break;
+            }
+            first = false;
+
+            let start = if c == '\\' {
+                match self.parse_escape(true)? {
+                    Escape::Item(item) => {
+                        items.push(item);
+                        continue;
+                    },
+                    Escape::Node(Node::Char(c)) => c,
+                    Escape::Node(_) => return Err(String::from("Invalid escape in class"))
+                }
+            } else {
+                c
+            };
+
+            if self.at() == Some('-') && self.chars.get(self.position + 1).is_some_and(|c| *c != ']') {
+                self.position += 1;
+                let end = match self.eat().unwrap() {
+                    '\\' => match self.parse_escape(true)? {
+                        Escape::Node(Node::Char(c)) => c,
+                        _ => return Err(String::from("Invalid range in class"))
+                    },
+                    c => c
+                };
+
+                if end < start {
+                    return Err(format!("Invalid range {}-{}", start, end));
+                }
+                items.push(ClassItem::Range(start, end));
+            } else {
+                items.push(ClassItem::Range(start, start));
+            }
+        }
+
+        Ok(Node::Class(items, negated))
+    }
+
+    fn parse_escape(&mut self, in_class: bool) -> Result<Escape, String> {
+        let c = match self.eat() {
+            Some(c) => c,
+            None => return Err(String::from("Trailing `\\`"))
+        };
+
+        Ok(match c {
+            'd' => Escape::Item(ClassItem::Digit(false)),
+            'D' => Escape::Item(ClassItem::Digit(true)),
+            'w' => Escape::Item(ClassItem::Word(false)),
+            'W' => Escape::Item(ClassItem::Word(true)),
+            's' => Escape::Item(ClassItem::Space(false)),
+            'S' => Escape::Item(ClassItem::Space(true)),
+            'b' if !in_class => Escape::Node(Node::WordBoundary(false)),
+            'B' if !in_class => Escape::Node(Node::WordBoundary(true)),
+            'n' => Escape::Node(Node::Char('\n')),
+            't' => Escape::Node(Node::Char('\t')),
+            'r' => Escape::Node(Node::Char('\r')),
+            c if c.is_alphanumeric() => return Err(format!("Unknown escape `\\{}`", c)),
+            c => Escape::Node(Node::Char(c))
+        })
+    }
+}
+
+enum Escape {
+    Node(Node),
+    Item(ClassItem)
+}
\ No newline at end of file
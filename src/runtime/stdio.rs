@@ -0,0 +1,72 @@
+use std::{fmt::Debug, io::{self, BufRead, IsTerminal, Write}, sync::{Arc, Mutex}};
+
+use crate::fatal_error;
+
+use super::environment::{Environment, SharedEnvironment};
+
+// Where natives like print and input read and write. Kept on the global scope, anything left
+// as None uses the process's real stdio
+#[derive(Clone, Default)]
+pub struct IoHandles {
+    pub stdout: Option<Arc<Mutex<dyn Write + Send>>>,
+    pub stdin: Option<Arc<Mutex<dyn BufRead + Send>>>
+}
+
+impl Debug for IoHandles {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("IoHandles")
+            .field("stdout", &if self.stdout.is_some() { "redirected" } else { "stdout" })
+            .field("stdin", &if self.stdin.is_some() { "redirected" } else { "stdin" })
+            .finish()
+    }
+}
+
+fn handles(env: &Arc<Mutex<Environment>>) -> IoHandles {
+    let global = SharedEnvironment(Arc::clone(env)).global_scope();
+    let handles = global.lock().unwrap().io.clone();
+    handles
+}
+
+pub fn write_stdout(env: &Arc<Mutex<Environment>>, text: &str) {
+    let result = match handles(env).stdout {
+        Some(stdout) => {
+            let mut stdout = stdout.lock().unwrap();
+            stdout.write_all(text.as_bytes()).and_then(|_| stdout.flush())
+        },
+        None => {
+            let mut stdout = io::stdout();
+            stdout.write_all(text.as_bytes()).and_then(|_| stdout.flush())
+        }
+    };
+
+    if let Err(e) = result {
+        fatal_error(&format!("Could not write output: {}", e));
+    }
+}
+
+// A line without its line ending, empty once the input has run out
+pub fn read_stdin_line(env: &Arc<Mutex<Environment>>) -> String {
+    let mut line = String::new();
+    let result = match handles(env).stdin {
+        Some(stdin) => stdin.lock().unwrap().read_line(&mut line),
+        None => io::stdin().read_line(&mut line)
+    };
+
+    if let Err(e) = result {
+        fatal_error(&format!("Could not read input: {}", e));
+    }
+
+    if line.ends_with('\n') {
+        line.pop();
+        if line.ends_with('\r') {
+            line.pop();
+        }
+    }
+
+    line
+}
+
+// Redirected output never counts as a terminal
+pub fn stdout_is_terminal(env: &Arc<Mutex<Environment>>) -> bool {
+    handles(env).stdout.is_none() && io::stdout().is_terminal()
+}
\ No newline at end of file
@@ -0,0 +1,82 @@
+use std::{cell::{Cell, RefCell}, rc::Rc, sync::{Arc, Mutex}};
+
+use super::environment::Environment;
+
+// Hooks for following a script as it runs, what the debugger is built on. A tracer is installed
+// per thread like diagnostics, and costs nothing while none is. The line being run is kept either
+// way, so errors can point at where they happened
+pub trait Tracer {
+    // Before each statement that came from source, with the line it starts on and the scope it
+    // runs in. Blocking here pauses the script
+    fn line(&self, line: usize, env: &Arc<Mutex<Environment>>);
+    // Around every call to a script function, so the tracer can follow how deep the stack is
+    fn enter(&self, _function: &str) {}
+    fn leave(&self, _function: &str) {}
+}
+
+thread_local! {
+    static TRACER: RefCell<Option<Rc<dyn Tracer>>> = const { RefCell::new(None) };
+    static LINE: Cell<usize> = const { Cell::new(0) };
+}
+
+// The line of the statement running on this thread, or last run when a script has failed. 0 before
+// any statement with a known position
+pub fn current_line() -> usize {
+    LINE.with(Cell::get)
+}
+
+pub fn set_tracer(tracer: impl Tracer + 'static) {
+    TRACER.with(|current| *current.borrow_mut() = Some(Rc::new(tracer)));
+}
+
+pub fn clear_tracer() {
+    TRACER.with(|current| *current.borrow_mut() = None);
+}
+
+// Puts the previous tracer back when dropped, also when a fatal error unwinds
+struct Restore(Option<Rc<dyn Tracer>>);
+
+impl Drop for Restore {
+    fn drop(&mut self) {
+        let previous = self.0.take();
+        TRACER.with(|current| *current.borrow_mut() = previous);
+    }
+}
+
+// Traces with `tracer` while `run` runs
+pub fn scoped<T>(tracer: Rc<dyn Tracer>, run: impl FnOnce() -> T) -> T {
+    let _restore = Restore(TRACER.with(|current| current.borrow_mut().replace(tracer)));
+    run()
+}
+
+// Cloned out before calling so a tracer that runs script code doesn't hit a borrowed RefCell
+fn current() -> Option<Rc<dyn Tracer>> {
+    TRACER.with(|current| current.borrow().clone())
+}
+
+// Line 0 is a statement with no known position, those aren't traced
+pub(crate) fn line(line: usize, env: &Arc<Mutex<Environment>>) {
+    if line == 0 {
+        return;
+    }
+    LINE.with(|current| current.set(line));
+    if let Some(tracer) = current() {
+        tracer.line(line, env);
+    }
+}
+
+// Hands back the caller's line for leave to put back, so an error later in the calling statement
+// isn't blamed on the last line of the function
+pub(crate) fn enter(function: &str) -> usize {
+    if let Some(tracer) = current() {
+        tracer.enter(function);
+    }
+    current_line()
+}
+
+pub(crate) fn leave(function: &str, line: usize) {
+    LINE.with(|current| current.set(line));
+    if let Some(tracer) = current() {
+        tracer.leave(function);
+    }
+}
\ No newline at end of file
@@ -1,8 +1,8 @@
-use std::{any::Any, collections::HashMap, fmt::{Debug, Display}, rc::Rc, sync::{Arc, Mutex}};
+use std::{any::Any, cell::RefCell, collections::HashMap, fmt::{Debug, Display}, ops::Deref, sync::{Arc, Mutex}};
 
-use crate::{fatal_error, frontend::ast::Body, pad_each_line};
+use crate::{fatal_error, frontend::{ast::FunctionBody, symbol::Symbol}, pad_each_line};
 
-use super::environment::Environment;
+use super::{context::NativeContext, environment::{Arity, Environment, SharedEnvironment}, policy, trace};
 
 #[derive(PartialEq, Debug)]
 pub enum ValueType {
@@ -13,7 +13,9 @@ pub enum ValueType {
     Object,
     List,
     NativeFn,
-    Function
+    Function,
+    Range,
+    UserData
 }
 
 impl Display for ValueType {
@@ -26,341 +28,655 @@ impl Display for ValueType {
             Self::Object => write!(f, "object"),
             Self::List => write!(f, "list"),
             Self::String => write!(f, "string"),
-            Self::Function => write!(f, "function")
+            Self::Function => write!(f, "function"),
+            Self::Range => write!(f, "range"),
+            Self::UserData => write!(f, "userdata")
         }?;
 
         Ok(())
     }
 }
 
-pub trait RuntimeValue: Debug + Any + 'static {
-    fn get_type(&self) -> ValueType;
-    fn as_any(&self) -> &dyn Any;
-    fn clone_self(&self) -> Box<dyn RuntimeValue>;
-    fn to_string(&self) -> String;
-    fn as_bool(&self) -> bool;
-    fn equals(&self, other: Box<dyn RuntimeValue>) -> bool;
-    fn less_than(&self, _other: Box<dyn RuntimeValue>) -> bool {
-        fatal_error("Cannot compare with this operator");
-    }
-    fn greater_than(&self, _other: Box<dyn RuntimeValue>) -> bool {
-        fatal_error("Cannot compare with this operator");
-    }
+// A value scripts work with. Send and Sync so an interpreter, and the values it hands out, can
+// move to other threads
+#[derive(Debug, Clone)]
+pub enum Value {
+    Null,
+    Bool(bool),
+    Number(f64),
+    Str(String),
+    List(ListValue),
+    Object(ObjectValue),
+    Range(RangeValue),
+    Function(FunctionValue),
+    Native(NativeFnValue),
+    UserData(UserDataValue)
 }
 
-impl Clone for Box<dyn RuntimeValue> {
-    fn clone(&self) -> Self {
-        self.clone_self()
+impl Value {
+    pub fn get_type(&self) -> ValueType {
+        match self {
+            Value::Null => ValueType::Null,
+            Value::Bool(_) => ValueType::Boolean,
+            Value::Number(_) => ValueType::Number,
+            Value::Str(_) => ValueType::String,
+            Value::List(_) => ValueType::List,
+            Value::Object(_) => ValueType::Object,
+            Value::Range(_) => ValueType::Range,
+            Value::Function(_) => ValueType::Function,
+            Value::Native(_) => ValueType::NativeFn,
+            Value::UserData(_) => ValueType::UserData
+        }
     }
-}
 
-#[derive(Debug, Clone, Copy)]
-pub struct BooleanValue {
-    pub value: bool
-}
+    // Same as strict_equals, for hosts that called equals on values before they were an enum
+    pub fn equals(&self, other: &Value) -> bool {
+        strict_equals(self, other)
+    }
 
-impl RuntimeValue for BooleanValue {
-    fn get_type(&self) -> ValueType {
-        ValueType::Boolean
+    pub fn as_bool(&self) -> bool {
+        match self {
+            Value::Null => false,
+            Value::Bool(value) => *value,
+            Value::Number(value) => *value != 0.0,
+            Value::Str(value) => !value.is_empty(),
+            Value::List(list) => !list.is_empty(),
+            Value::Object(object) => !object.is_empty(),
+            Value::Range(range) => !range.is_empty(),
+            Value::Function(_) | Value::Native(_) | Value::UserData(_) => true
+        }
     }
-    fn as_any(&self) -> &dyn Any {
-        self
+
+    // What a for loop over the value goes through, None for values that can't be looped over.
+    // Lists go through a snapshot, objects through their sorted keys and strings through each
+    // character as a one character string
+    pub fn iterate(&self) -> Option<Box<dyn Iterator<Item = Value>>> {
+        match self {
+            Value::List(list) => {
+                let elements = list.snapshot();
+                Some(Box::new((0..elements.len()).map(move |index| elements[index].clone())))
+            },
+            Value::Object(object) => Some(Box::new(object.keys().into_iter().map(Value::Str))),
+            Value::Range(range) => Some(Box::new(range.iter().map(Value::Number))),
+            Value::Str(value) => Some(Box::new(value.chars().map(|character| character.to_string()).collect::<Vec<_>>().into_iter().map(Value::Str))),
+            _ => None
+        }
     }
-    fn clone_self(&self) -> Box<dyn RuntimeValue> {
-        Box::new(self.clone())
+
+    pub fn as_number(&self) -> Option<f64> {
+        match self {
+            Value::Number(value) => Some(*value),
+            _ => None
+        }
     }
-    fn to_string(&self) -> String {
-        if self.value {
-            String::from("true")
-        } else {
-            String::from("false")
+
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            Value::Str(value) => Some(value),
+            _ => None
         }
     }
-    fn as_bool(&self) -> bool {
-        self.value
+
+    pub fn as_list(&self) -> Option<&ListValue> {
+        match self {
+            Value::List(list) => Some(list),
+            _ => None
+        }
     }
-    fn equals(&self, other: Box<dyn RuntimeValue>) -> bool {
-        self.value == other.as_any().downcast_ref::<BooleanValue>().unwrap().value
+
+    pub fn as_object(&self) -> Option<&ObjectValue> {
+        match self {
+            Value::Object(object) => Some(object),
+            _ => None
+        }
     }
 }
 
-#[derive(Debug, Clone)]
-pub struct NullValue {}
+impl Display for Value {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Value::Null => f.write_str("null"),
+            Value::Bool(value) => write!(f, "{}", value),
+            Value::Number(value) => f.write_str(&number_to_string(*value)),
+            Value::Str(value) => f.write_str(value),
+            Value::List(list) => f.write_str(&list.to_string()),
+            Value::Object(object) => f.write_str(&object.to_string()),
+            Value::Range(range) => {
+                let number = |value: f64| number_to_string(value);
+                write!(f, "range({}, {}, {})", number(range.start), number(range.stop), number(range.step))
+            },
+            Value::Function(function) => write!(f, "{}", function.name),
+            Value::Native(_) => f.write_str("NativeFn"),
+            Value::UserData(user_data) => write!(f, "<{}>", user_data.type_name)
+        }
+    }
+}
 
-impl RuntimeValue for NullValue {
-    fn get_type(&self) -> ValueType {
-        ValueType::Null
+// Whole numbers print without a fraction, infinities are named like the globals that hold them
+pub fn number_to_string(value: f64) -> String {
+    if value.is_infinite() {
+        return String::from(if value > 0.0 { "Infinity" } else { "-Infinity" });
     }
-    fn as_any(&self) -> &dyn Any {
-        self
+    if value.fract() == 0.0 {
+        value.to_string().replace(".0", "")
+    } else {
+        value.to_string()
     }
-    fn clone_self(&self) -> Box<dyn RuntimeValue> {
-        Box::new(self.clone())
+}
+
+// The same type and equal, what === checks. Everything that compares values for equality goes
+// through here, so the types are checked in one place:
+// - null is only equal to itself
+// - lists and objects are equal when their contents are, or they're the same one
+// - functions, natives and userdata are only equal to themselves (copies of the same one)
+pub fn strict_equals(left: &Value, right: &Value) -> bool {
+    match (left, right) {
+        (Value::Null, Value::Null) => true,
+        (Value::Bool(left), Value::Bool(right)) => left == right,
+        (Value::Number(left), Value::Number(right)) => left == right,
+        (Value::Str(left), Value::Str(right)) => left == right,
+        (Value::List(left), Value::List(right)) => left.equals(right),
+        (Value::Object(left), Value::Object(right)) => left.equals(right),
+        (Value::Range(left), Value::Range(right)) => left.start == right.start && left.stop == right.stop && left.step == right.step,
+        // The same function, a copy of what one run of a declaration or function expression made
+        (Value::Function(left), Value::Function(right)) => Arc::ptr_eq(&left.body, &right.body),
+        (Value::Native(left), Value::Native(right)) => Arc::ptr_eq(&left.call.func, &right.call.func),
+        (Value::UserData(left), Value::UserData(right)) => Arc::ptr_eq(&left.data, &right.data),
+        _ => false
     }
-    fn to_string(&self) -> String {
-        String::from("null")
+}
+
+// What a list or object handle leads to. Read only once it's been bound to a constant or its
+// scope frozen, which then holds through every handle to it, not just the variable's name
+pub struct Contents<T> {
+    pub items: Arc<T>,
+    pub read_only: bool
+}
+
+impl<T> Contents<T> {
+    fn new(items: T) -> Self {
+        Contents { items: Arc::new(items), read_only: false }
     }
-    fn as_bool(&self) -> bool {
-        false
+}
+
+impl<T> Deref for Contents<T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        &self.items
     }
-    fn equals(&self, _other: Box<dyn RuntimeValue>) -> bool {
-        true
+}
+
+// Makes a list or object read only, along with every list and object inside it. Ones already
+// read only are skipped, which also stops at cycles
+pub fn make_read_only(value: &Value) {
+    let mut pending = vec![value.clone()];
+
+    while let Some(value) = pending.pop() {
+        match value {
+            Value::List(list) => {
+                let mut elements = list.elements.lock().unwrap();
+                if !elements.read_only {
+                    elements.read_only = true;
+                    pending.extend(elements.iter().cloned());
+                }
+            },
+            Value::Object(object) => {
+                let mut properties = object.properties.lock().unwrap();
+                if !properties.read_only {
+                    properties.read_only = true;
+                    pending.extend(properties.values().cloned());
+                }
+            },
+            _ => {}
+        }
     }
 }
 
-#[derive(Debug, Clone, Copy)]
-pub struct NumberValue {
-    pub value: f64
+// Lists and objects are shared: copying one copies a handle to the same contents, so a change
+// made through any copy shows in all of them. The lock is only held for a moment and never while
+// running script code or looking inside another value, which is what lets a list contain itself.
+// The contents are copy-on-write, so reading them (iterating, printing, comparing) takes a
+// snapshot without copying anything, and a change only copies them if a snapshot is still in use
+#[derive(Clone)]
+pub struct ObjectValue {
+    pub properties: Arc<Mutex<Contents<Properties>>>
 }
 
-impl RuntimeValue for NumberValue {
-    fn get_type(&self) -> ValueType {
-        ValueType::Number
+pub type Properties = HashMap<String, Value>;
+
+impl ObjectValue {
+    pub fn new(properties: HashMap<String, Value>) -> Self {
+        ObjectValue { properties: Arc::new(Mutex::new(Contents::new(properties))) }
     }
-    fn as_any(&self) -> &dyn Any {
-        self
+
+    // The properties as they are now, unaffected by later changes
+    pub fn snapshot(&self) -> Arc<Properties> {
+        Arc::clone(&self.properties.lock().unwrap().items)
     }
-    fn clone_self(&self) -> Box<dyn RuntimeValue> {
-        Box::new(self.clone())
+
+    pub fn get(&self, key: &str) -> Option<Value> {
+        self.properties.lock().unwrap().get(key).cloned()
     }
-    fn to_string(&self) -> String {
-        if self.value.fract() == 0.0 {
-            String::from(self.value.to_string().replace(".0", ""))
-        } else {
-            String::from(self.value.to_string())
+
+    pub fn set(&self, key: String, value: Value) {
+        let mut properties = self.properties.lock().unwrap();
+        if properties.read_only {
+            drop(properties);
+            fatal_error("Cannot change an object that is a constant or in a frozen scope.");
         }
+        Arc::make_mut(&mut properties.items).insert(key, value);
     }
-    fn as_bool(&self) -> bool {
-        self.value != 0.0
+
+    // Sorted, so everything that goes through them does so in the same order every run
+    pub fn keys(&self) -> Vec<String> {
+        let mut keys = self.properties.lock().unwrap().keys().cloned().collect::<Vec<String>>();
+        keys.sort();
+        keys
     }
-    fn equals(&self, other: Box<dyn RuntimeValue>) -> bool {
-        self.value == other.as_any().downcast_ref::<NumberValue>().unwrap().value
+
+    pub fn len(&self) -> usize {
+        self.properties.lock().unwrap().len()
     }
-    fn greater_than(&self, other: Box<dyn RuntimeValue>) -> bool {
-        self.value > other.as_any().downcast_ref::<NumberValue>().unwrap().value
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
     }
-    fn less_than(&self, other: Box<dyn RuntimeValue>) -> bool {
-        self.value < other.as_any().downcast_ref::<NumberValue>().unwrap().value
+
+    // Whether both are handles to the same object, rather than equal ones
+    pub fn same(&self, other: &ObjectValue) -> bool {
+        Arc::ptr_eq(&self.properties, &other.properties)
     }
-}
 
-#[derive(Debug, Clone)]
-pub struct ObjectValue {
-    pub properties: HashMap<String, Box<dyn RuntimeValue>>
+    pub fn equals(&self, other: &ObjectValue) -> bool {
+        if self.same(other) {
+            return true;
+        }
+        compare_once((Arc::as_ptr(&self.properties) as usize, Arc::as_ptr(&other.properties) as usize), || {
+            let (properties, other) = (self.snapshot(), other.snapshot());
+            properties.len() == other.len() && properties.iter()
+                .all(|(key, value)| other.get(key).is_some_and(|other| strict_equals(value, other)))
+        })
+    }
 }
 
-impl RuntimeValue for ObjectValue {
-    fn get_type(&self) -> ValueType {
-        ValueType::Object
-    }
-    fn as_any(&self) -> &dyn Any {
-        self
-    }
-    fn clone_self(&self) -> Box<dyn RuntimeValue> {
-        Box::new(self.clone())
+impl Debug for ObjectValue {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.to_string())
     }
-    fn to_string(&self) -> String {
-        let mut value = String::new();
+}
 
-        value.push('{');
-        value.push('\n');
+impl Display for ObjectValue {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&print_once(Arc::as_ptr(&self.properties) as usize, "{...}", || {
+            let mut value = String::new();
 
-        for property in self.properties.iter() {
-            value += &pad_each_line(4, format!("{}: {}", property.0, property.1.to_string()));
+            value.push('{');
             value.push('\n');
-        }
-        
-        value.push('}');
-        
-        value
-    }
-    fn as_bool(&self) -> bool {
-        self.properties.len() != 0
-    }
-    fn equals(&self, other: Box<dyn RuntimeValue>) -> bool {
-        let other = other.as_any().downcast_ref::<ObjectValue>().unwrap();
-        if self.properties.len() != other.properties.len() {
-            return false;
-        }
-        self.to_string() == other.to_string()
+
+            for property in self.snapshot().iter() {
+                value += &pad_each_line(4, format!("{}: {}", property.0, property.1));
+                value.push('\n');
+            }
+            
+            value.push('}');
+            
+            value
+        }))
     }
 }
 
-#[derive(Debug, Clone)]
+#[derive(Clone)]
 pub struct ListValue {
-    pub elements: Vec<Box<dyn RuntimeValue>>
+    pub elements: Arc<Mutex<Contents<Elements>>>
 }
 
-impl RuntimeValue for ListValue {
-    fn get_type(&self) -> ValueType {
-        ValueType::List
-    }
-    
-    fn as_any(&self) -> &dyn Any {
-        self
+pub type Elements = Vec<Value>;
+
+impl ListValue {
+    pub fn new(elements: Vec<Value>) -> Self {
+        ListValue { elements: Arc::new(Mutex::new(Contents::new(elements))) }
     }
 
-    fn clone_self(&self) -> Box<dyn RuntimeValue> {
-        Box::new(self.clone())
+    // The elements as they are now, unaffected by later changes
+    pub fn snapshot(&self) -> Arc<Elements> {
+        Arc::clone(&self.elements.lock().unwrap().items)
     }
 
-    fn to_string(&self) -> String {
-        let mut value = String::new();
+    // A copy of the elements to change or take apart, for natives giving back a new list
+    pub fn to_vec(&self) -> Vec<Value> {
+        self.snapshot().as_ref().clone()
+    }
 
-        value.push('[');
+    pub fn get(&self, position: usize) -> Option<Value> {
+        self.elements.lock().unwrap().get(position).cloned()
+    }
 
-        let mut first = true;
-        for property in self.elements.iter() {
-            if !first {
-                value.push_str(", ");
-            } else {
-                first = false;
-            }
-            value += &format!("{}", property.to_string());
+    pub fn set(&self, position: usize, value: Value) {
+        let mut elements = self.elements.lock().unwrap();
+        if elements.read_only {
+            drop(elements);
+            fatal_error("Cannot change a list that is a constant or in a frozen scope.");
         }
-        
-        value.push(']');
-        
-        value
+        Arc::make_mut(&mut elements.items)[position] = value;
     }
 
-    fn as_bool(&self) -> bool {
-        self.elements.len() != 0
+    pub fn len(&self) -> usize {
+        self.elements.lock().unwrap().len()
     }
 
-    fn equals(&self, other: Box<dyn RuntimeValue>) -> bool {
-        let other = other.as_any().downcast_ref::<ObjectValue>().unwrap();
-        if self.elements.len() != other.properties.len() {
-            return false;
-        }
-        self.to_string() == other.to_string()
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
     }
-}
 
-pub struct FunctionCall {
-    pub func: Rc<dyn Fn(Vec<Box<dyn RuntimeValue>>, &Mutex<Environment>) -> Box<dyn RuntimeValue> + 'static>,
-}
+    // Whether both are handles to the same list, rather than equal ones
+    pub fn same(&self, other: &ListValue) -> bool {
+        Arc::ptr_eq(&self.elements, &other.elements)
+    }
 
-impl Clone for FunctionCall {
-    fn clone(&self) -> Self {
-        Self {
-            func: Rc::clone(&self.func)
+    pub fn equals(&self, other: &ListValue) -> bool {
+        if self.same(other) {
+            return true;
         }
+        compare_once((Arc::as_ptr(&self.elements) as usize, Arc::as_ptr(&other.elements) as usize), || {
+            let (elements, other) = (self.snapshot(), other.snapshot());
+            elements.len() == other.len() && elements.iter().zip(other.iter()).all(|(value, other)| strict_equals(value, other))
+        })
+    }
+
+    pub fn position(&self, index: f64) -> Option<usize> {
+        position(index, self.len())
+    }
+
+    pub fn index_error(&self, index: f64) -> String {
+        index_error("list", index, self.len())
     }
 }
 
-impl Debug for FunctionCall {
+impl Debug for ListValue {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "FunctionCall")
+        f.write_str(&self.to_string())
     }
 }
 
-#[derive(Debug, Clone)]
-pub struct NativeFnValue {
-    pub call: FunctionCall,
+// Where `index` is among `length` elements or characters, negative ones counting back from the
+// end so -1 is the last. None when that's outside them or the index isn't a whole number
+pub fn position(index: f64, length: usize) -> Option<usize> {
+    let length = length as f64;
+    let position = if index < 0.0 { length + index } else { index };
+    if index.fract() != 0.0 || position < 0.0 || position >= length {
+        return None;
+    }
+    Some(position as usize)
 }
 
-impl RuntimeValue for NativeFnValue {
-    fn get_type(&self) -> ValueType {
-        ValueType::NativeFn
+// The error for an index position gave None for, `kind` being what was indexed
+pub fn index_error(kind: &str, index: f64, length: usize) -> String {
+    if index.fract() != 0.0 {
+        format!("{}{} index {} is not a whole number", kind[..1].to_uppercase(), &kind[1..], number_to_string(index))
+    } else {
+        format!("Index {} is out of range for a {} of length {}", number_to_string(index), kind, length)
     }
-    fn as_any(&self) -> &dyn Any {
-        self
+}
+
+thread_local! {
+    // The lists and objects being turned into strings on this thread, innermost last
+    static PRINTING: RefCell<Vec<usize>> = const { RefCell::new(Vec::new()) };
+    // The pairs of lists and objects being compared on this thread, innermost last
+    static COMPARING: RefCell<Vec<(usize, usize)>> = const { RefCell::new(Vec::new()) };
+}
+
+// Prints a list or object, or `cycle` in its place if it's already being printed further out,
+// so one that contains itself doesn't print forever
+fn print_once(address: usize, cycle: &str, print: impl FnOnce() -> String) -> String {
+    struct Printing;
+    impl Drop for Printing {
+        fn drop(&mut self) {
+            PRINTING.with(|printing| printing.borrow_mut().pop());
+        }
     }
-    fn clone_self(&self) -> Box<dyn RuntimeValue> {
-        Box::new(self.clone())
+
+    if PRINTING.with(|printing| printing.borrow().contains(&address)) {
+        return cycle.to_string();
     }
-    fn to_string(&self) -> String {
-        String::from("NativeFn")
+    PRINTING.with(|printing| printing.borrow_mut().push(address));
+    let _printing = Printing;
+    print()
+}
+
+// Compares two lists or objects, or counts them as equal if the same pair is already being
+// compared further out, so ones that contain themselves don't compare forever. Any difference
+// between them still shows up in the comparison further out
+fn compare_once(addresses: (usize, usize), compare: impl FnOnce() -> bool) -> bool {
+    struct Comparing;
+    impl Drop for Comparing {
+        fn drop(&mut self) {
+            COMPARING.with(|comparing| comparing.borrow_mut().pop());
+        }
     }
-    fn as_bool(&self) -> bool {
-        true
+
+    if COMPARING.with(|comparing| comparing.borrow().contains(&addresses)) {
+        return true;
     }
-    fn equals(&self, other: Box<dyn RuntimeValue>) -> bool {
-        Rc::ptr_eq(&self.call.func, &other.as_any().downcast_ref::<NativeFnValue>().unwrap().call.func)
+    COMPARING.with(|comparing| comparing.borrow_mut().push(addresses));
+    let _comparing = Comparing;
+    compare()
+}
+
+impl Display for ListValue {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&print_once(Arc::as_ptr(&self.elements) as usize, "[...]", || {
+            let mut value = String::new();
+
+            value.push('[');
+
+            let mut first = true;
+            for property in self.snapshot().iter() {
+                if !first {
+                    value.push_str(", ");
+                } else {
+                    first = false;
+                }
+                value += &property.to_string();
+            }
+            
+            value.push(']');
+            
+            value
+        }))
     }
 }
 
-#[derive(Debug, Clone)]
-pub struct StringValue {
-    pub value: String
+// What range() gives. The numbers are worked out as it's iterated rather than stored
+#[derive(Debug, Clone, Copy)]
+pub struct RangeValue {
+    pub start: f64,
+    pub stop: f64,
+    pub step: f64
 }
 
-impl RuntimeValue for StringValue {
-    fn get_type(&self) -> ValueType {
-        ValueType::String
-    }
-    fn as_any(&self) -> &dyn Any {
-        self
+impl RangeValue {
+    pub fn len(&self) -> usize {
+        let steps = ((self.stop - self.start) / self.step).ceil();
+        if steps > 0.0 { steps as usize } else { 0 }
     }
-    fn clone_self(&self) -> Box<dyn RuntimeValue> {
-        Box::new(self.clone())
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
     }
-    fn to_string(&self) -> String {
-        self.value.clone()
+
+    pub fn iter(&self) -> impl Iterator<Item = f64> {
+        let range = *self;
+        (0..range.len()).map(move |i| range.start + i as f64 * range.step)
     }
-    fn as_bool(&self) -> bool {
-        self.value.len() != 0
+}
+
+pub struct FunctionCall {
+    pub func: Arc<dyn Fn(Vec<Value>, &NativeContext) -> Value + Send + Sync + 'static>,
+}
+
+impl Clone for FunctionCall {
+    fn clone(&self) -> Self {
+        Self {
+            func: Arc::clone(&self.func)
+        }
     }
-    fn equals(&self, other: Box<dyn RuntimeValue>) -> bool {
-        self.value == other.as_any().downcast_ref::<StringValue>().unwrap().value
+}
+
+impl Debug for FunctionCall {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "FunctionCall")
     }
 }
 
+#[derive(Debug, Clone)]
+pub struct NativeFnValue {
+    pub call: FunctionCall,
+}
+
 #[derive(Debug)]
 pub struct FunctionValue {
-    pub name: String,
-    pub parameters: Vec<String>,
+    pub name: Symbol,
+    pub parameters: Vec<Symbol>,
+    // The parameters after these are optional, null when left out
+    pub required: usize,
     pub declaration_env: Arc<Mutex<Environment>>,
-    pub body: Body
+    // Shared by copies of the function, and new each time its declaration runs
+    pub body: Arc<FunctionBody>
 }
 
 impl FunctionValue {
-    pub fn call(&self, env: Arc<Mutex<Environment>>, args: Vec<Box<dyn RuntimeValue>>) -> Box<dyn RuntimeValue> {
-        let new_env = Arc::new(Mutex::new(Environment::new(Some(Arc::clone(&env)))));
+    // Runs in a new scope inside the one the function was declared in, so names in the body mean
+    // what they meant where it was written, whoever calls it
+    pub fn call(&self, args: Vec<Value>) -> Value {
+        self.invoke(None, args)
+    }
 
-        if args.len() != self.parameters.len() {
-            fatal_error(&format!("Expected {} arguments, found {}", self.parameters.len(), args.len()));
+    // Called as obj.method(), with this bound to the object
+    pub fn call_method(&self, this: Value, args: Vec<Value>) -> Value {
+        self.invoke(Some(this), args)
+    }
+
+    fn invoke(&self, this: Option<Value>, args: Vec<Value>) -> Value {
+        let _call = policy::enter_call();
+
+        // The function's own name is bound just outside the call, so it can always call itself
+        // however it was reached, and the body can still declare something by that name
+        let closure_env = Arc::new(Mutex::new(Environment::new(Some(Arc::clone(&self.declaration_env)))));
+        if self.name.as_str() != "<anonymous>" {
+            closure_env.lock().unwrap().declare_var(self.name, Value::Function(self.clone()), true);
         }
 
-        for i in 0..(self.parameters.len()) {
-            new_env.lock().unwrap().declare_var(self.parameters[i].clone(), args.get(i).unwrap().clone(), false);
+        let new_env = Arc::new(Mutex::new(Environment::new(Some(closure_env))));
+        new_env.lock().unwrap().function = true;
+
+        if let Some(this) = this {
+            new_env.lock().unwrap().declare_var(Symbol::intern("this"), this, false);
         }
 
-        self.body.run(env, true).0
-    }
-}
+        if args.len() < self.required || args.len() > self.parameters.len() {
+            // Only looked up on a mismatch, calls with the right number of arguments don't pay for it
+            let arity = SharedEnvironment(Arc::clone(&self.declaration_env)).global_scope().lock().unwrap().arity;
+            if arity == Arity::Strict {
+                fatal_error(&self.arity_error(args.len()));
+            }
+        }
 
-impl RuntimeValue for FunctionValue {
-    fn get_type(&self) -> ValueType {
-        ValueType::Function
-    }
-    fn as_any(&self) -> &dyn Any {
-        self
-    }
-    fn clone_self(&self) -> Box<dyn RuntimeValue> {
-        Box::new(self.clone())
-    }
-    fn to_string(&self) -> String {
-        self.name.clone()
-    }
-    fn as_bool(&self) -> bool {
-        true
+        // Anything past the last parameter is dropped, missing arguments are null
+        let mut args = args.into_iter();
+        for parameter in self.parameters.iter() {
+            let value = args.next().unwrap_or(Value::Null);
+            new_env.lock().unwrap().declare_var(*parameter, value, false);
+        }
+
+        let line = trace::enter(self.name.as_str());
+        let result = self.body.run(Arc::clone(&new_env));
+        trace::leave(self.name.as_str(), line);
+
+        result
     }
-    fn equals(&self, other: Box<dyn RuntimeValue>) -> bool {
-        let other = other.as_any().downcast_ref::<FunctionValue>().unwrap();
 
-        Arc::ptr_eq(&other.declaration_env, &self.declaration_env)
+    // f expects 1 to 2 arguments (a, b?), found 3
+    fn arity_error(&self, found: usize) -> String {
+        let total = self.parameters.len();
+        let expected = if self.required == total {
+            format!("{} argument{}", total, if total == 1 { "" } else { "s" })
+        } else {
+            format!("{} to {} arguments", self.required, total)
+        };
+        let parameters = self.parameters.iter().enumerate()
+            .map(|(index, parameter)| if index < self.required { parameter.to_string() } else { format!("{}?", parameter) })
+            .collect::<Vec<String>>();
+        format!("{} expects {} ({}), found {}", self.name, expected, parameters.join(", "), found)
     }
 }
 
 impl Clone for FunctionValue {
     fn clone(&self) -> Self {
         FunctionValue {
-            name: self.name.clone(),
+            name: self.name,
             parameters: self.parameters.clone(),
+            required: self.required,
             declaration_env: Arc::clone(&self.declaration_env),
-            body: self.body.clone()
+            body: Arc::clone(&self.body)
         }
     }
+}
+
+// An opaque Rust object handed to scripts by the host. Scripts can only pass it around, compare
+// it and call its methods, natives get the object back with downcast or get
+#[derive(Clone)]
+pub struct UserDataValue {
+    pub type_name: String,
+    pub data: Arc<dyn Any + Send + Sync>,
+    // Called as value.name(args), with the value itself as the first argument
+    pub methods: Arc<HashMap<String, NativeFnValue>>
+}
+
+impl UserDataValue {
+    pub fn new<T: Any + Send + Sync>(type_name: &str, data: T) -> Self {
+        UserDataValue {
+            type_name: type_name.to_string(),
+            data: Arc::new(data),
+            methods: Arc::new(HashMap::new())
+        }
+    }
+
+    pub fn with_method<F>(mut self, name: &str, method: F) -> Self
+    where
+        F: Fn(Vec<Value>, &NativeContext) -> Value + Send + Sync + 'static
+    {
+        Arc::make_mut(&mut self.methods).insert(name.to_string(), NativeFnValue { call: FunctionCall { func: Arc::new(method) } });
+        self
+    }
+
+    pub fn downcast_ref<T: Any>(&self) -> Option<&T> {
+        self.data.downcast_ref::<T>()
+    }
+
+    // A shared handle to the object, for keeping it after the call returns
+    pub fn get<T: Any + Send + Sync>(&self) -> Option<Arc<T>> {
+        Arc::clone(&self.data).downcast::<T>().ok()
+    }
+
+    // The method bound to this value, so calling it needs only the remaining arguments
+    pub fn method(&self, name: &str) -> Option<NativeFnValue> {
+        let method = self.methods.get(name)?.clone();
+        let this = self.clone();
+
+        Some(NativeFnValue {
+            call: FunctionCall {
+                func: Arc::new(move |mut args: Vec<Value>, ctx: &NativeContext| {
+                    args.insert(0, Value::UserData(this.clone()));
+                    (method.call.func)(args, ctx)
+                })
+            }
+        })
+    }
+}
+
+impl Debug for UserDataValue {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "UserData({})", self.type_name)
+    }
 }
\ No newline at end of file
@@ -0,0 +1,107 @@
+use std::{fs, io, path::{Path, PathBuf}, sync::{Arc, Mutex}};
+
+use crate::{fatal_error, runtime::{context::NativeContext, environment::EnvironmentBuilder, values::{Value, ValueType}}, Interpreter, LoggingLevel, MK_NATIVE_FN};
+
+// The test runner behind `interpreter test`. Test files are the ones named *_test.tl, each call
+// to test(name, fn) in them is a test. Every test runs in an interpreter of its own: the file is
+// run again from the top with only that test's function called, so nothing one test does to the
+// globals is seen by another
+
+pub struct TestResult {
+    pub name: String,
+    // Why the test failed, None when it passed
+    pub error: Option<String>
+}
+
+pub struct FileResults {
+    pub path: PathBuf,
+    pub tests: Vec<TestResult>,
+    // The file itself failed to parse or run, outside of any test
+    pub error: Option<String>
+}
+
+// Every *_test.tl file under `path`, in a stable order. A file given directly is used as it is
+pub fn discover(path: &Path) -> io::Result<Vec<PathBuf>> {
+    if path.is_file() {
+        return Ok(vec![path.to_path_buf()]);
+    }
+
+    let mut files = Vec::new();
+    for entry in fs::read_dir(path)? {
+        let path = entry?.path();
+        if path.is_dir() {
+            files.extend(discover(&path)?);
+        } else if path.file_name().and_then(|name| name.to_str()).is_some_and(|name| name.ends_with("_test.tl")) {
+            files.push(path);
+        }
+    }
+    files.sort();
+    Ok(files)
+}
+
+#[derive(Default)]
+struct Registry {
+    names: Vec<String>,
+    // Which test's function to call this run, None while only finding out what tests there are
+    target: Option<usize>,
+    finished: bool
+}
+
+pub fn run_file(path: &Path) -> FileResults {
+    let mut results = FileResults {
+        path: path.to_path_buf(),
+        tests: Vec::new(),
+        error: None
+    };
+
+    let names = match run(path, None) {
+        Ok(registry) => registry.names,
+        Err(e) => {
+            results.error = Some(e);
+            return results;
+        }
+    };
+
+    for (index, name) in names.into_iter().enumerate() {
+        let error = run(path, Some(index)).err();
+        results.tests.push(TestResult { name, error });
+    }
+    results
+}
+
+fn run(path: &Path, target: Option<usize>) -> Result<Registry, String> {
+    let registry = Arc::new(Mutex::new(Registry { target, ..Registry::default() }));
+
+    let shared = Arc::clone(&registry);
+    let test = move |args: Vec<Value>, ctx: &NativeContext| -> Value {
+        if args.len() != 2 || args[0].get_type() != ValueType::String {
+            fatal_error("Expected test(name, function)");
+        }
+
+        let run_it = {
+            let mut registry = shared.lock().unwrap();
+            let index = registry.names.len();
+            registry.names.push(args[0].to_string());
+            registry.target == Some(index)
+        };
+        if run_it {
+            ctx.call(&args[1], Vec::new());
+            shared.lock().unwrap().finished = true;
+        }
+
+        Value::Null
+    };
+
+    let mut interpreter = Interpreter::with_environment(EnvironmentBuilder::new().global("test", MK_NATIVE_FN!(test), true).build());
+    // Failures are collected into the results instead of being printed as they happen
+    interpreter.set_diagnostics(|_: LoggingLevel, _: &str| {});
+
+    let result = interpreter.eval_file(path);
+    let registry = std::mem::take(&mut *registry.lock().unwrap());
+    match result {
+        Ok(_) => Ok(registry),
+        // An error after the test finished belongs to the rest of the file, which the first run already covered
+        Err(_) if registry.finished => Ok(registry),
+        Err(e) => Err(e.to_string())
+    }
+}
\ No newline at end of file
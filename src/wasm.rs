@@ -0,0 +1,97 @@
+// Bindings for running in a browser, built with
+//
+//     cargo build --lib --release --target wasm32-unknown-unknown
+//
+// Strings cross as pointer and length pairs into the module's memory rather than through
+// wasm-bindgen, so the module has no dependencies. From JavaScript:
+//
+//     const bytes = new TextEncoder().encode(source);
+//     const ptr = exports.wasm_alloc(bytes.length);
+//     new Uint8Array(exports.memory.buffer, ptr, bytes.length).set(bytes);
+//     const ok = exports.wasm_eval(ptr, bytes.length);
+//     exports.wasm_free(ptr, bytes.length);
+//     const output = new TextDecoder().decode(new Uint8Array(exports.memory.buffer, exports.wasm_output_ptr(), exports.wasm_output_len()));
+//
+// Panics abort on this target, so a runtime error traps instead of coming back from wasm_eval.
+// The error is written to the output first, read it and then start a new instance
+use std::{cell::RefCell, sync::{Arc, Mutex}};
+
+use crate::{diagnostics::set_diagnostics, runtime::stdio::IoHandles, Interpreter};
+
+thread_local! {
+    static INTERPRETER: RefCell<Option<Interpreter>> = const { RefCell::new(None) };
+    static OUTPUT: Arc<Mutex<Vec<u8>>> = Arc::new(Mutex::new(Vec::new()));
+}
+
+fn output() -> Arc<Mutex<Vec<u8>>> {
+    OUTPUT.with(Arc::clone)
+}
+
+fn new_interpreter() -> Interpreter {
+    let mut interpreter = Interpreter::new();
+    interpreter.set_io(IoHandles { stdout: Some(output()), stdin: None });
+
+    let diagnostics = output();
+    set_diagnostics(move |_, message: &str| {
+        diagnostics.lock().unwrap().extend_from_slice(format!("{}\n", message).as_bytes());
+    });
+
+    interpreter
+}
+
+#[no_mangle]
+pub extern "C" fn wasm_alloc(len: usize) -> *mut u8 {
+    let mut buffer = Vec::<u8>::with_capacity(len);
+    let ptr = buffer.as_mut_ptr();
+    std::mem::forget(buffer);
+    ptr
+}
+
+/// # Safety
+/// `ptr` and `len` must come from a single wasm_alloc call
+#[no_mangle]
+pub unsafe extern "C" fn wasm_free(ptr: *mut u8, len: usize) {
+    drop(Vec::from_raw_parts(ptr, 0, len));
+}
+
+/// Runs the UTF-8 source at `ptr` in a global scope kept between calls. The output buffer is
+/// cleared first, then collects everything printed, the result (unless null) and any error.
+/// Returns 1 if the code ran and 0 if it didn't parse
+///
+/// # Safety
+/// `ptr` must point to `len` readable bytes
+#[no_mangle]
+pub unsafe extern "C" fn wasm_eval(ptr: *const u8, len: usize) -> u32 {
+    let source = String::from_utf8_lossy(std::slice::from_raw_parts(ptr, len)).into_owned();
+    output().lock().unwrap().clear();
+
+    INTERPRETER.with(|interpreter| {
+        let mut interpreter = interpreter.borrow_mut();
+        let interpreter = interpreter.get_or_insert_with(new_interpreter);
+
+        let (text, ok) = match interpreter.eval_str(&source) {
+            Ok(value) if value.to_string() == "null" => (String::new(), 1),
+            Ok(value) => (format!("{}\n", value.to_string()), 1),
+            Err(e) => (format!("{}\n", e), 0)
+        };
+        output().lock().unwrap().extend_from_slice(text.as_bytes());
+
+        ok
+    })
+}
+
+// Forgets every global, the next wasm_eval starts from a fresh scope
+#[no_mangle]
+pub extern "C" fn wasm_reset() {
+    INTERPRETER.with(|interpreter| *interpreter.borrow_mut() = None);
+}
+
+#[no_mangle]
+pub extern "C" fn wasm_output_ptr() -> *const u8 {
+    OUTPUT.with(|output| output.lock().unwrap().as_ptr())
+}
+
+#[no_mangle]
+pub extern "C" fn wasm_output_len() -> usize {
+    OUTPUT.with(|output| output.lock().unwrap().len())
+}
\ No newline at end of file
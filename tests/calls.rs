@@ -0,0 +1,18 @@
+mod common;
+
+use common::run;
+
+#[test]
+fn returned_closures_can_be_called_straight_away() {
+    assert_eq!(run("function make_adder(a) { return function (b) { a + b }; }\nmake_adder(1)(2);"), "3");
+    assert_eq!(run("function outer() { var local = 5; return function () { return local * 2; }; }\nouter()();"), "10");
+}
+
+#[test]
+fn calls_members_and_indexes_chain_in_any_order() {
+    let source = "function get_config() { return {port: 8080}; }\n\
+        function handle(event) { return {result: event * 2}; }\nvar handlers = [handle];\n\
+        function curry(a) { return function (b) { return function (c) { return [a, b, c]; }; }; }\n\
+        [get_config().port, handlers[0](21).result, curry(1)(2)(3)[2]];";
+    assert_eq!(run(source), "[8080, 42, 3]");
+}
\ No newline at end of file
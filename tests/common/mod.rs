@@ -0,0 +1,20 @@
+// Shared by the test files, each only uses some of it
+#![allow(dead_code)]
+
+use interpreter::{runtime::policy::Policy, Interpreter, LoggingLevel};
+
+// An interpreter that keeps the warnings and errors of failing scripts out of the test output
+pub fn quiet() -> Interpreter {
+    quiet_with(Policy::default())
+}
+
+pub fn quiet_with(policy: Policy) -> Interpreter {
+    let mut interpreter = Interpreter::with_policy(policy);
+    interpreter.set_diagnostics(|_: LoggingLevel, _: &str| {});
+    interpreter
+}
+
+// What a script evaluates to, as print would show it
+pub fn run(source: &str) -> String {
+    quiet().eval_str(source).unwrap().to_string()
+}
\ No newline at end of file
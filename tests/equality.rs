@@ -0,0 +1,95 @@
+mod common;
+
+use common::quiet;
+use interpreter::{runtime::values::{strict_equals, UserDataValue, Value}, Interpreter};
+
+// One value of every type, each paired with a second value equal to it
+fn values() -> Vec<(&'static str, Value, Value)> {
+    let mut interpreter = quiet();
+    interpreter.eval_str("function f(a) { return a; }\nfunction g(a) { return a; }").unwrap();
+
+    let mut eval = |source: &str| interpreter.eval_str(source).unwrap();
+    let data = UserDataValue::new("Point", (1, 2));
+
+    vec![
+        ("null", eval("null"), eval("null")),
+        ("boolean", eval("true"), eval("true")),
+        ("number", eval("1"), eval("1")),
+        ("string", eval("\"a\""), eval("\"a\"")),
+        ("list", eval("[1, \"a\"]"), eval("[1, \"a\"]")),
+        ("object", eval("({a: 1})"), eval("({a: 1})")),
+        ("range", eval("range(3)"), eval("range(3)")),
+        ("native function", eval("print"), eval("print")),
+        ("function", eval("f"), eval("f")),
+        ("userdata", Value::UserData(data.clone()), Value::UserData(data)),
+    ]
+}
+
+#[test]
+fn values_equal_themselves() {
+    for (name, value, same) in values() {
+        assert!(strict_equals(&value, &same), "{} should equal itself", name);
+    }
+}
+
+#[test]
+fn values_of_different_types_are_never_equal() {
+    let values = values();
+    for (left_name, left, _) in &values {
+        for (right_name, right, _) in &values {
+            if left_name != right_name {
+                assert!(!strict_equals(left, right), "{} should not equal {}", left_name, right_name);
+            }
+        }
+    }
+}
+
+#[test]
+fn equals_never_panics_on_mismatched_types() {
+    let values = values();
+    for (_, left, _) in &values {
+        for (_, right, _) in &values {
+            let _ = left.equals(right);
+        }
+    }
+}
+
+#[test]
+fn null_only_equals_null() {
+    for (name, value, _) in values() {
+        let null = Interpreter::new().eval_str("null").unwrap();
+        assert_eq!(strict_equals(&null, &value), name == "null", "null against {}", name);
+        assert_eq!(null.equals(&value), name == "null", "null.equals({})", name);
+    }
+}
+
+#[test]
+fn distinct_values_of_the_same_type_differ() {
+    let mut interpreter = Interpreter::new();
+    let pairs = [("false", "true"), ("1", "2"), ("\"a\"", "\"b\""), ("[1]", "[1, 2]"), ("({a: 1})", "({a: 2})"), ("range(3)", "range(4)"), ("print", "len"), ("f", "g"), ("function (a) { a }", "function (a) { a }")];
+    interpreter.eval_str("function f(a) { return a; }\nfunction g(a) { return a; }").unwrap();
+
+    for (left, right) in pairs {
+        let (left_value, right_value) = (interpreter.eval_str(left).unwrap(), interpreter.eval_str(right).unwrap());
+        assert!(!strict_equals(&left_value, &right_value), "{} should not equal {}", left, right);
+    }
+}
+
+#[test]
+fn list_helpers_compare_across_types() {
+    let mut interpreter = Interpreter::new();
+    assert_eq!(interpreter.eval_str("index_of([1, null, \"a\"], \"a\")").unwrap().to_string(), "2");
+    assert_eq!(interpreter.eval_str("len(unique([1, \"1\", null, null, [1], [1]]))").unwrap().to_string(), "4");
+}
+#[test]
+fn lists_and_objects_that_contain_themselves_compare_without_overflowing() {
+    let mut interpreter = Interpreter::new();
+    interpreter.eval_str("var a = [1]; a[0] = a; var b = [1]; b[0] = b; var c = [1, 2]; c[0] = c;").unwrap();
+    interpreter.eval_str("var o = ({x: 1}); o.x = o; var p = ({x: 1}); p.x = p; var q = ({x: 1, y: 2}); q.x = q;").unwrap();
+
+    assert_eq!(interpreter.eval_str("a == b").unwrap().to_string(), "true");
+    assert_eq!(interpreter.eval_str("a == c").unwrap().to_string(), "false");
+    assert_eq!(interpreter.eval_str("a == a").unwrap().to_string(), "true");
+    assert_eq!(interpreter.eval_str("o == p").unwrap().to_string(), "true");
+    assert_eq!(interpreter.eval_str("o == q").unwrap().to_string(), "false");
+}
\ No newline at end of file
@@ -0,0 +1,23 @@
+mod common;
+
+use std::{fs, process, sync::Arc};
+
+use common::quiet;
+use interpreter::{runtime::hot_reload::HotReloader, Error};
+
+#[test]
+fn runtime_errors_only_end_that_reload() {
+    let path = std::env::temp_dir().join(format!("hot_reload_{}.tl", process::id()));
+    let interpreter = quiet();
+    let mut reloader = HotReloader::new(&path, Arc::clone(interpreter.env()));
+
+    fs::write(&path, "var count = 1;\nprint(nope);").unwrap();
+    assert!(matches!(reloader.reload(), Err(Error::Script(_) | Error::Runtime(_))));
+    assert!(!interpreter.env().is_poisoned());
+    assert!(!interpreter.env().lock().unwrap().reloading);
+
+    fs::write(&path, "var count = 1;\ncount + 1").unwrap();
+    let result = reloader.reload();
+    fs::remove_file(&path).unwrap();
+    assert_eq!(result.unwrap().to_string(), "2");
+}
\ No newline at end of file
@@ -0,0 +1,156 @@
+mod common;
+
+use std::{sync::{Arc, Mutex}, thread, time::Duration};
+
+use common::{quiet, quiet_with};
+use interpreter::{runtime::{native_funcs::log::LogSink, policy::Policy, stdio::IoHandles}, Error, Interpreter, LoggingLevel};
+
+// An interpreter whose print output is collected instead of written to stdout
+fn capturing() -> (Interpreter, Arc<Mutex<Vec<u8>>>) {
+    let output = Arc::new(Mutex::new(Vec::new()));
+    let mut interpreter = quiet();
+    interpreter.set_io(IoHandles { stdout: Some(output.clone()), stdin: None });
+    (interpreter, output)
+}
+
+fn text(output: &Arc<Mutex<Vec<u8>>>) -> String {
+    String::from_utf8(output.lock().unwrap().clone()).unwrap()
+}
+
+// A log sink that collects the lines it's sent
+fn collecting() -> (Arc<Mutex<Vec<String>>>, LogSink) {
+    let lines = Arc::new(Mutex::new(Vec::new()));
+    let collected = lines.clone();
+    (lines, Arc::new(move |_, line| collected.lock().unwrap().push(line.to_string())))
+}
+
+#[test]
+fn interpreters_can_move_between_threads() {
+    fn assert_send<T: Send>() {}
+    assert_send::<Interpreter>();
+}
+
+#[test]
+fn concurrent_instances_keep_their_own_globals() {
+    let handles = (0..16).map(|id| thread::spawn(move || {
+        let (mut interpreter, output) = capturing();
+        let source = format!("var id = {};\nvar total = 0;\nvar i = 0;\nwhile (i < 2000) {{ total = total + id; i = i + 1; }}\nprint(id, total);", id);
+        interpreter.eval_str(&source).unwrap();
+
+        assert_eq!(interpreter.get("total").unwrap().to_string(), (id * 2000).to_string());
+        assert_eq!(text(&output), format!("{} {} \n", id, id * 2000));
+    })).collect::<Vec<_>>();
+
+    for handle in handles {
+        handle.join().unwrap();
+    }
+}
+
+#[test]
+fn errors_stay_in_their_instance() {
+    let handles = (0..8).map(|id| thread::spawn(move || {
+        let (mut interpreter, output) = capturing();
+        let failing = match id % 3 {
+            0 => "const c = 1; c = 2;",
+            1 => "assert(false, \"nope\")",
+            _ => "print(\"fine\")"
+        };
+        let result = interpreter.eval_str(failing);
+        match id % 3 {
+            0 => assert!(matches!(result, Err(Error::Runtime(_)))),
+            1 => assert!(matches!(result, Err(Error::Script(_)))),
+            _ => assert!(result.is_ok())
+        }
+
+        // Still usable afterwards
+        interpreter.eval_str(&format!("print({})", id)).unwrap();
+        assert!(text(&output).ends_with(&format!("{} \n", id)));
+    })).collect::<Vec<_>>();
+
+    for handle in handles {
+        handle.join().unwrap();
+    }
+}
+
+#[test]
+fn instances_on_one_thread_have_their_own_state() {
+    let (mut first, _) = capturing();
+    let (mut second, _) = capturing();
+    first.eval_str("var shared = 1").unwrap();
+    assert!(second.get("shared").is_none());
+
+    // Interleaved calls on the same seed give the same sequence to both
+    first.eval_str("random.seed(42)").unwrap();
+    second.eval_str("random.seed(42)").unwrap();
+    for _ in 0..5 {
+        let a = first.eval_str("random.random()").unwrap().to_string();
+        let b = second.eval_str("random.random()").unwrap().to_string();
+        assert_eq!(a, b);
+    }
+}
+
+#[test]
+fn diagnostics_go_to_their_own_instance() {
+    let reported = Arc::new(Mutex::new(Vec::new()));
+    let (mut quiet, _) = capturing();
+    let mut loud = Interpreter::new();
+    let sink = reported.clone();
+    loud.set_diagnostics(move |_: LoggingLevel, message: &str| sink.lock().unwrap().push(message.to_string()));
+
+    assert!(quiet.eval_str("missing_one").is_err());
+    assert!(loud.eval_str("missing_two").is_err());
+
+    let reported = reported.lock().unwrap();
+    assert_eq!(reported.len(), 1);
+    assert!(reported[0].contains("missing_two"));
+}
+
+#[test]
+fn log_level_is_per_instance() {
+    let (lines, sink) = collecting();
+    let (mut strict, _) = capturing();
+    let (mut chatty, _) = capturing();
+    strict.set_log_sink(sink.clone());
+    chatty.set_log_sink(sink);
+    strict.eval_str("log.set_level(\"error\")").unwrap();
+    strict.eval_str("log.info(\"hidden\")").unwrap();
+    chatty.eval_str("log.info(\"shown\")").unwrap();
+
+    assert_eq!(*lines.lock().unwrap(), vec![String::from("[INFO] shown")]);
+}
+
+#[test]
+fn log_sink_is_per_instance() {
+    let (first_lines, first_sink) = collecting();
+    let (second_lines, second_sink) = collecting();
+    let (mut first, _) = capturing();
+    let (mut second, _) = capturing();
+    first.set_log_sink(first_sink);
+    second.set_log_sink(second_sink);
+
+    first.eval_str("log.info(\"first\")").unwrap();
+    second.eval_str("log.warn(\"second\")").unwrap();
+    assert_eq!(*first_lines.lock().unwrap(), vec![String::from("[INFO] first")]);
+    assert_eq!(*second_lines.lock().unwrap(), vec![String::from("[WARN] second")]);
+
+    // The sink goes with the instance when it's moved to another thread
+    thread::spawn(move || first.eval_str("log.error(\"moved\")").unwrap()).join().unwrap();
+    assert_eq!(*first_lines.lock().unwrap(), vec![String::from("[INFO] first"), String::from("[ERROR] moved")]);
+    assert_eq!(second_lines.lock().unwrap().len(), 1);
+}
+
+#[test]
+fn time_limit_only_stops_its_instance() {
+    let limited = thread::spawn(|| {
+        quiet_with(Policy { time_limit: Some(Duration::from_millis(100)), ..Policy::default() }).eval_str("while (true) { var x = 1; }")
+    });
+    let others = (0..4).map(|_| thread::spawn(|| {
+        let (mut interpreter, _) = capturing();
+        interpreter.eval_str("var n = 0;\nwhile (n < 5000) { n = n + 1; }\nn").unwrap().to_string()
+    })).collect::<Vec<_>>();
+
+    assert!(matches!(limited.join().unwrap(), Err(Error::Runtime(_))));
+    for other in others {
+        assert_eq!(other.join().unwrap(), "5000");
+    }
+}
\ No newline at end of file
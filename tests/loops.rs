@@ -0,0 +1,96 @@
+mod common;
+
+use common::quiet;
+use interpreter::{Error, Interpreter};
+
+fn global(interpreter: &Interpreter, name: &str) -> String {
+    interpreter.get(name).unwrap().to_string()
+}
+
+#[test]
+fn while_condition_sees_assignments_from_the_body() {
+    let mut interpreter = quiet();
+    interpreter.eval_str("var n = 0;\nvar passes = 0;\nwhile n < 5 { n = n + 1; passes = passes + 1; }").unwrap();
+
+    assert_eq!(global(&interpreter, "n"), "5");
+    assert_eq!(global(&interpreter, "passes"), "5");
+}
+
+#[test]
+fn while_condition_ignores_declarations_in_the_body() {
+    // The body's own i is gone by the time the condition is checked again
+    let mut interpreter = quiet();
+    interpreter.eval_str("var i = 0;\nvar passes = 0;\nwhile i < 3 { i = i + 1; var i = 10; passes = passes + 1; }").unwrap();
+
+    assert_eq!(global(&interpreter, "i"), "3");
+    assert_eq!(global(&interpreter, "passes"), "3");
+}
+
+#[test]
+fn body_declarations_start_over_each_pass() {
+    let mut interpreter = quiet();
+    interpreter.eval_str("var seen = [];\nvar n = 0;\nwhile n < 3 { const doubled = n * 2; seen = flatten([seen, [doubled]]); n = n + 1; }").unwrap();
+
+    assert_eq!(global(&interpreter, "seen"), "[0, 2, 4]");
+}
+
+#[test]
+fn body_declarations_do_not_outlive_the_loop() {
+    let mut interpreter = quiet();
+    let result = interpreter.eval_str("var n = 0;\nwhile n < 1 { var inner = 1; n = n + 1; }\ninner;");
+
+    assert!(matches!(result, Err(Error::Script(_))));
+}
+
+#[test]
+fn for_variable_stays_inside_the_loop() {
+    let mut interpreter = quiet();
+    interpreter.eval_str("var x = \"outer\";\nvar total = 0;\nfor x in [1, 2, 3] { total = total + x; }").unwrap();
+
+    assert_eq!(global(&interpreter, "x"), "outer");
+    assert_eq!(global(&interpreter, "total"), "6");
+    assert!(matches!(interpreter.eval_str("for k in range(2) { }\nk;"), Err(Error::Script(_))));
+}
+
+#[test]
+fn for_loops_over_ranges_and_lists_alike() {
+    let mut interpreter = quiet();
+    interpreter.eval_str("var from_range = 0;\nfor i in range(4) { from_range = from_range + i; }\nvar from_list = 0;\nfor i in [0, 1, 2, 3] { from_list = from_list + i; }").unwrap();
+
+    assert_eq!(global(&interpreter, "from_range"), "6");
+    assert_eq!(global(&interpreter, "from_list"), "6");
+}
+
+#[test]
+fn nested_loops_keep_their_own_variables() {
+    let mut interpreter = quiet();
+    interpreter.eval_str("var pairs = 0;\nfor i in range(3) { for j in range(i) { pairs = pairs + 1; } }").unwrap();
+
+    assert_eq!(global(&interpreter, "pairs"), "3");
+}
+
+#[test]
+fn for_loops_over_string_characters() {
+    let mut interpreter = quiet();
+    interpreter.eval_str("var letters = [];\nfor c in \"héllo\" { letters = flatten([letters, [c]]); }").unwrap();
+
+    assert_eq!(global(&interpreter, "letters"), "[h, é, l, l, o]");
+    assert_eq!(interpreter.eval_str("var count = 0;\nfor c in \"\" { count = count + 1; }\ncount;").unwrap().to_string(), "0");
+}
+
+#[test]
+fn for_loops_over_object_keys_in_order() {
+    let mut interpreter = quiet();
+    interpreter.eval_str("var scores = {b: 2, c: 3, a: 1};\nvar keys = [];\nvar total = 0;\nfor key in scores { keys = flatten([keys, [key]]); total = total + scores[key]; }").unwrap();
+
+    assert_eq!(global(&interpreter, "keys"), "[a, b, c]");
+    assert_eq!(global(&interpreter, "total"), "6");
+}
+
+#[test]
+fn for_loops_over_range_steps() {
+    let mut interpreter = quiet();
+    interpreter.eval_str("var seen = [];\nfor i in range(10, 0, -3) { seen = flatten([seen, [i]]); }").unwrap();
+
+    assert_eq!(global(&interpreter, "seen"), "[10, 7, 4, 1]");
+}
\ No newline at end of file
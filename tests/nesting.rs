@@ -0,0 +1,71 @@
+mod common;
+
+use std::thread;
+
+use common::quiet_with;
+use interpreter::{frontend::parser::{Parser, DEFAULT_MAX_DEPTH}, runtime::policy::Policy, Error};
+
+// Parses on a thread with a main thread's 8MB of stack, what the default depth is sized for,
+// rather than the 2MB test threads get
+fn parse(source: String) -> Result<(), String> {
+    thread::Builder::new().stack_size(8 << 20)
+        .spawn(move || Parser::new().parse(source).map(|_| ()).map_err(|e| e.to_string()))
+        .unwrap().join().unwrap()
+}
+
+#[test]
+fn deeply_nested_calls_are_a_parse_error() {
+    let source = format!("{}0{};", "f(".repeat(5000), ")".repeat(5000));
+    let error = parse(source).unwrap_err();
+    assert!(error.contains("too deeply nested"), "{}", error);
+}
+
+#[test]
+fn long_assignment_chains_are_a_parse_error() {
+    let source = format!("{}0;", "a = ".repeat(50000));
+    let error = parse(source).unwrap_err();
+    assert!(error.contains("too deeply nested"), "{}", error);
+}
+
+#[test]
+fn nesting_under_the_limit_still_parses() {
+    let depth = DEFAULT_MAX_DEPTH - 1;
+    // The call and its argument each count as a level
+    assert!(parse(format!("{}0{};", "f(".repeat(depth / 2), ")".repeat(depth / 2))).is_ok());
+    assert!(parse(format!("{}0;", "a = ".repeat(depth))).is_ok());
+    assert!(parse(String::from("f(a = 1, b = 2);")).is_ok());
+}
+
+#[test]
+fn long_operator_and_call_chains_are_a_parse_error() {
+    let chains = [
+        format!("{}1;", "1 + ".repeat(10000)),
+        format!("{}1;", "2 * ".repeat(10000)),
+        format!("x{};", " < 2 == true".repeat(5000)),
+        format!("f{};", "()".repeat(10000)),
+        format!("a{};", ".b[0]".repeat(5000))
+    ];
+
+    for source in chains {
+        let error = parse(source.clone()).unwrap_err();
+        assert!(error.contains("too deeply nested"), "{}: {}", &source[..20], error);
+    }
+}
+
+#[test]
+fn chains_under_the_limit_still_parse() {
+    let links = DEFAULT_MAX_DEPTH - 1;
+    assert!(parse(format!("{}1;", "1 + ".repeat(links))).is_ok());
+    assert!(parse(format!("f{};", "()".repeat(links))).is_ok());
+}
+
+// A host running sandboxed scripts on an ordinary spawned thread gets an error back, not an abort
+#[test]
+fn sandboxed_hosts_survive_long_chains() {
+    let run = |source: String| thread::spawn(move || {
+        quiet_with(Policy::sandboxed()).eval_str(&source).map(|value| value.to_string())
+    }).join().unwrap();
+
+    assert!(matches!(run(format!("{}1", "1 + ".repeat(3000))), Err(Error::Parse(_))));
+    assert_eq!(run(format!("{}1", "1 + ".repeat(DEFAULT_MAX_DEPTH - 1))).unwrap(), DEFAULT_MAX_DEPTH.to_string());
+}
\ No newline at end of file
@@ -0,0 +1,105 @@
+mod common;
+
+use std::{thread, time::{Duration, Instant}};
+
+use common::quiet_with;
+use interpreter::{runtime::{policy::{Policy, DEFAULT_MAX_CALL_DEPTH}, values::Value}, Error};
+
+fn timed(source: &str) -> (Result<String, Error>, Duration) {
+    let mut interpreter = quiet_with(Policy { time_limit: Some(Duration::from_millis(300)), ..Policy::sandboxed() });
+
+    let started = Instant::now();
+    let result = interpreter.eval_str(source).map(|value| value.to_string());
+    (result, started.elapsed())
+}
+
+#[test]
+fn sleep_stops_at_the_time_limit() {
+    let (result, elapsed) = timed("sleep(3);");
+    assert!(matches!(result, Err(Error::Runtime(_))), "{:?}", result);
+    assert!(elapsed < Duration::from_secs(2), "took {:?}", elapsed);
+
+    let (result, _) = timed("sleep(0.01); 1");
+    assert_eq!(result.unwrap(), "1");
+}
+
+#[test]
+fn regex_matching_stops_at_the_time_limit() {
+    let (result, elapsed) = timed("var s = \"a\"; var i = 0; while i < 16 { s = s + s; i = i + 1; }\nregex.match(\"(a?){2000}a{2000}b\", s);");
+    assert!(matches!(result, Err(Error::Runtime(_))), "{:?}", result);
+    assert!(elapsed < Duration::from_secs(3), "took {:?}", elapsed);
+}
+
+#[test]
+fn runaway_recursion_is_an_error() {
+    let mut interpreter = quiet_with(Policy::sandboxed());
+    let result = interpreter.eval_str("function f(n) { return f(n + 1); }\nf(0);");
+    match result {
+        Err(Error::Runtime(error)) => assert!(error.message.contains("call depth"), "{}", error.message),
+        other => panic!("expected a runtime error, got {:?}", other)
+    }
+
+    // The depth is given back as the error unwinds, so the instance can still recurse
+    assert_eq!(interpreter.eval_str("function g(n) { if n == 0 { return 0; } return g(n - 1) + 1; }\ng(200)").unwrap().to_string(), "200");
+}
+
+#[test]
+fn call_depth_is_configurable() {
+    let mut interpreter = quiet_with(Policy { max_call_depth: Some(10), ..Policy::default() });
+    interpreter.eval_str("function g(n) { if n == 0 { return 0; } return g(n - 1); }").unwrap();
+
+    assert!(interpreter.eval_str("g(9)").is_ok());
+    assert!(matches!(interpreter.eval_str("g(10)"), Err(Error::Runtime(_))));
+}
+
+#[test]
+fn default_call_depth_fits_a_main_thread() {
+    let result = thread::Builder::new().stack_size(8 << 20).spawn(|| {
+        let mut interpreter = quiet_with(Policy::default());
+        interpreter.eval_str("function f(n) { return f(n + 1); }\nf(0);").is_err()
+    }).unwrap().join().unwrap();
+    assert!(result);
+
+    let mut interpreter = quiet_with(Policy::default());
+    let depth = DEFAULT_MAX_CALL_DEPTH / 4;
+    assert_eq!(interpreter.eval_str(&format!("function g(n) {{ if n == 0 {{ return 0; }} return g(n - 1) + 1; }}\ng({})", depth)).unwrap().to_string(), depth.to_string());
+}
+
+fn memory_error(result: Result<Value, Error>) {
+    match result {
+        Err(Error::Runtime(error)) => assert!(error.message.contains("Memory limit"), "{}", error.message),
+        other => panic!("expected a runtime error, got {:?}", other)
+    }
+}
+
+#[test]
+fn growing_a_variable_past_the_memory_limit_is_an_error() {
+    let mut interpreter = quiet_with(Policy { memory_limit: Some(10_000), ..Policy::sandboxed() });
+    // Goes over within a few hundred steps, before the periodic check first runs
+    memory_error(interpreter.eval_str("var s = \"a\"; var i = 0; while i < 20 { s = s + s; i = i + 1; }"));
+
+    assert!(interpreter.eval_str("var small = \"a\"; var j = 0; while j < 8 { small = small + small; j = j + 1; }").is_ok());
+}
+
+#[test]
+fn the_memory_limit_counts_every_variable_in_scope() {
+    let mut interpreter = quiet_with(Policy { memory_limit: Some(6_000), ..Policy::sandboxed() });
+    // Each string fits on its own, together they don't, which only the periodic check sees
+    memory_error(interpreter.eval_str("var a = \"a\"; var i = 0; while i < 12 { a = a + a; i = i + 1; }\nvar b = a + \"b\";\nvar j = 0; while j < 10000 { j = j + 1; }"));
+}
+
+#[test]
+fn sandboxed_policy_removes_outside_access() {
+    let mut names = vec!["list_dir", "net", "exit", "input"];
+    if cfg!(feature = "http") {
+        names.push("http");
+    }
+
+    let mut open = quiet_with(Policy::default());
+    let mut sandboxed = quiet_with(Policy::sandboxed());
+    for name in names {
+        assert!(open.eval_str(name).is_ok(), "{}", name);
+        assert!(sandboxed.eval_str(name).is_err(), "{}", name);
+    }
+    assert!(sandboxed.eval_str("print").is_ok());
+}
\ No newline at end of file
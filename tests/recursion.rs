@@ -0,0 +1,84 @@
+mod common;
+
+use common::{quiet, run};
+use interpreter::Error;
+
+#[test]
+fn fib_recurses_with_early_returns() {
+    let source = "function fib(n) { if n < 2 { return n; } return fib(n - 1) + fib(n - 2); }\nfib(15);";
+    assert_eq!(run(source), "610");
+}
+
+#[test]
+fn fib_recurses_with_if_expressions() {
+    let source = "function fib(n) { if n < 2 { n } else { fib(n - 1) + fib(n - 2) } }\nfib(10);";
+    assert_eq!(run(source), "55");
+}
+
+#[test]
+fn mutually_recursive_functions_see_each_other() {
+    let source = "function is_even(n) { if n == 0 { return true; } return is_odd(n - 1); }\n\
+        function is_odd(n) { if n == 0 { return false; } return is_even(n - 1); }\n\
+        [is_even(10), is_odd(7), is_even(3)];";
+    assert_eq!(run(source), "[true, true, false]");
+}
+
+#[test]
+fn nested_functions_recurse() {
+    let source = "function outer(n) {\n    function countdown(n) { if n == 0 { return 0; } return 1 + countdown(n - 1); }\n    countdown(n)\n}\nouter(20);";
+    assert_eq!(run(source), "20");
+}
+
+#[test]
+fn named_function_expressions_can_call_themselves() {
+    let source = "var fact = function factorial(n) { if n < 2 { return 1; } return n * factorial(n - 1); };\nfact(5);";
+    assert_eq!(run(source), "120");
+}
+
+#[test]
+fn own_name_wins_over_the_callers_variables() {
+    // Called from a scope where fib is a number, the body's fib is still the function
+    let source = "function fib(n) { if n < 2 { return n; } return fib(n - 1) + fib(n - 2); }\n\
+        var alias = fib;\nfunction caller(fib) { alias(10) }\ncaller(0);";
+    assert_eq!(run(source), "55");
+}
+
+#[test]
+fn bodies_can_declare_their_own_name() {
+    assert_eq!(run("function total() { var total = 3; total }\ntotal();"), "3");
+}
+
+#[test]
+fn return_leaves_loops_and_skips_the_rest() {
+    let source = "var after = false;\n\
+        function first_over(list, limit) { for x in list { if x > limit { return x; } } after = true; null }\n\
+        function count_to(n) { var i = 0; while true { i = i + 1; if i == n { return i; } } }\n\
+        [first_over([1, 5, 9], 4), after, count_to(7)];";
+    assert_eq!(run(source), "[5, false, 7]");
+}
+
+#[test]
+fn return_outside_a_function_is_an_error() {
+    let mut interpreter = quiet();
+    assert!(matches!(interpreter.eval_str("if true { return 1; }"), Err(Error::Runtime(_))));
+}
+
+#[test]
+fn free_variables_resolve_where_the_function_was_declared() {
+    let source = "var n = 1;\nfunction get_n() { return n; }\nfunction g() { var n = 99; return get_n(); }\ng();";
+    assert_eq!(run(source), "1");
+}
+
+#[test]
+fn functions_keep_the_parameters_of_the_call_that_made_them() {
+    let source = "function make_adder(a) { return function (b) { return a + b; }; }\n\
+        var add3 = make_adder(3);\nvar add10 = make_adder(10);\n[add3(1), add10(1)];";
+    assert_eq!(run(source), "[4, 11]");
+}
+
+#[test]
+fn callbacks_and_direct_calls_see_the_same_names() {
+    let source = "var scale = 2;\nfunction times(x) { return x * scale; }\n\
+        function apply() { var scale = 100; return [times(1), map([1], times)[0]]; }\napply();";
+    assert_eq!(run(source), "[2, 2]");
+}
\ No newline at end of file
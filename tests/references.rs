@@ -0,0 +1,129 @@
+mod common;
+
+use common::{quiet, run};
+use interpreter::{runtime::json::serialize, Error};
+
+#[test]
+fn assigning_an_object_shares_it() {
+    assert_eq!(run("var a = ({x: 1});\nvar b = a;\nb.x = 2;\na.x;"), "2");
+}
+
+#[test]
+fn assigning_a_list_shares_it() {
+    assert_eq!(run("var a = [1, 2, 3];\nvar b = a;\nb[0] = 10;\na;"), "[10, 2, 3]");
+}
+
+#[test]
+fn functions_change_the_lists_they_are_given() {
+    let source = "function fill(list, value) { list[0] = value; list[1] = value; }\nvar xs = [0, 0];\nfill(xs, 7);\nxs;";
+    assert_eq!(run(source), "[7, 7]");
+}
+
+#[test]
+fn nested_members_can_be_assigned() {
+    assert_eq!(run("var config = ({server: {port: 80}});\nconfig.server.port = 8080;\nconfig.server.port;"), "8080");
+    assert_eq!(run("var grid = [[0, 0], [0, 0]];\ngrid[1][0] = 5;\ngrid;"), "[[0, 0], [5, 0]]");
+}
+
+#[test]
+fn methods_change_the_object_they_are_called_on() {
+    let source = "var counter = ({count: 0, increment: function () { this.count = this.count + 1; }});\n\
+        var same = counter;\n\
+        counter.increment();\n\
+        same.increment();\n\
+        counter.count;";
+    assert_eq!(run(source), "2");
+}
+
+#[test]
+fn list_natives_give_copies() {
+    let source = "var xs = [3, 1, 2];\nvar sorted = sort(xs);\nvar reversed = reverse(xs);\nsorted[0] = 100;\n[xs, reversed];";
+    assert_eq!(run(source), "[[3, 1, 2], [2, 1, 3]]");
+}
+
+#[test]
+fn a_list_containing_itself_prints() {
+    let source = "var xs = [1, 2];\nxs[1] = xs;\nstr(xs);";
+    assert_eq!(run(source), "[1, [...]]");
+}
+
+#[test]
+fn a_list_containing_itself_is_equal_to_itself() {
+    assert_eq!(run("var xs = [1, 2];\nxs[0] = xs;\nxs == xs;"), "true");
+}
+
+#[test]
+fn an_object_containing_itself_has_no_json_form() {
+    let mut interpreter = quiet();
+    interpreter.eval_str("var node = ({name: \"root\"});\nnode.parent = node;").unwrap();
+
+    let error = serialize(&interpreter.get("node").unwrap()).unwrap_err();
+    assert_eq!(error, "Cannot serialize value.parent, it contains itself");
+}
+
+#[test]
+fn constants_still_refuse_member_assignment() {
+    let mut interpreter = quiet();
+    let error = interpreter.eval_str("const point = ({x: 1});\npoint.x = 2;").unwrap_err();
+    assert!(matches!(error, Error::Runtime(_)));
+}
+
+#[test]
+fn constants_refuse_changes_through_aliases_and_arguments() {
+    let mut interpreter = quiet();
+    interpreter.eval_str("const c = ({a: 1, inner: {b: 1}, list: [1]});").unwrap();
+
+    let changes = [
+        "var d = c; d.a = 5;",
+        "(function (o) { o.a = 7; })(c);",
+        "var inner = c.inner; inner.b = 2;",
+        "var list = c.list; list[0] = 2;"
+    ];
+    for source in changes {
+        assert!(matches!(interpreter.eval_str(source), Err(Error::Runtime(_))), "{}", source);
+    }
+    assert_eq!(interpreter.eval_str("[c.a, c.inner.b, c.list[0]]").unwrap().to_string(), "[1, 1, 1]");
+
+    // Copies made from a constant are new values, free to change
+    assert_eq!(interpreter.eval_str("var copy = map(c.list, function (x) { x }); copy[0] = 9; [copy[0], c.list[0]]").unwrap().to_string(), "[9, 1]");
+}
+
+#[test]
+fn frozen_scopes_refuse_changes_through_aliases_and_arguments() {
+    let mut interpreter = quiet();
+    interpreter.eval_str("var config = ({limit: 10, tags: [\"a\"]});").unwrap();
+    interpreter.env().lock().unwrap().freeze();
+
+    let changes = [
+        "(function () { var d = config; d.limit = 99; })();",
+        "(function (o) { o.limit = 7; })(config);",
+        "(function () { var tags = config.tags; tags[0] = \"b\"; })();"
+    ];
+    for source in changes {
+        assert!(matches!(interpreter.eval_str(source), Err(Error::Runtime(_))), "{}", source);
+    }
+    assert_eq!(interpreter.eval_str("[config.limit, config.tags[0]]").unwrap().to_string(), "[10, a]");
+}
+
+#[test]
+fn constants_refuse_assignment_to_nested_members() {
+    let mut interpreter = quiet();
+    interpreter.eval_str("const c = ({a: {b: 1}, list: [[1]]});").unwrap();
+
+    for source in ["c.a.b = 2;", "c[\"a\"][\"b\"] = 2;", "c.list[0][0] = 2;"] {
+        assert!(matches!(interpreter.eval_str(source), Err(Error::Runtime(_))), "{}", source);
+    }
+    assert_eq!(interpreter.eval_str("[c.a.b, c.list[0][0]]").unwrap().to_string(), "[1, 1]");
+}
+
+#[test]
+fn changing_a_list_while_looping_over_it_only_changes_the_list() {
+    let source = "var xs = [1, 2, 3];\nvar seen = [];\nfor x in xs { xs[2] = 30; seen = flatten([seen, [x]]); }\n[seen, xs];";
+    assert_eq!(run(source), "[[1, 2, 3], [1, 2, 30]]");
+}
+
+#[test]
+fn changing_a_list_inside_map_only_changes_the_list() {
+    let source = "var xs = [1, 2, 3];\nvar doubled = map(xs, function (x) { xs[0] = 100; x * 2 });\n[doubled, xs];";
+    assert_eq!(run(source), "[[2, 4, 6], [100, 2, 3]]");
+}
\ No newline at end of file
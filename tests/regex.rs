@@ -0,0 +1,60 @@
+use std::time::{Duration, Instant};
+
+use interpreter::{runtime::regex::Regex, Interpreter};
+
+fn chars(text: &str) -> Vec<char> {
+    text.chars().collect()
+}
+
+#[test]
+fn long_inputs_match_without_overflowing() {
+    let text = chars(&"a".repeat(200_000));
+    let matches = Regex::new("a+").unwrap().find_all(&text);
+    assert_eq!(matches.len(), 1);
+    assert_eq!((matches[0].start, matches[0].end), (0, 200_000));
+
+    let lazy = Regex::new("(a+?)$").unwrap().find_at(&text, 0).unwrap();
+    assert_eq!(lazy.groups, vec![Some((0, 200_000))]);
+}
+
+#[test]
+fn pathological_patterns_fail_quickly() {
+    let text = chars(&format!("{}c", "a".repeat(10_000)));
+    let started = Instant::now();
+
+    for pattern in ["(a*)*b", "(a|aa)*b", "(a+)+b", "(a?){30}a{30}b"] {
+        assert!(Regex::new(pattern).unwrap().find_at(&text, 0).is_none(), "{} should not match", pattern);
+    }
+    assert!(started.elapsed() < Duration::from_secs(10), "took {:?}", started.elapsed());
+}
+
+#[test]
+fn preferences_match_a_backtracking_engine() {
+    let cases = [
+        ("(a|ab)(c|bcd)(d*)", "abcd", vec![Some((0, 1)), Some((1, 4)), Some((4, 4))]),
+        ("(a*)+", "b", vec![Some((0, 0))]),
+        ("(a?)*?b", "aab", vec![Some((1, 2))]),
+        ("((a)|b)+", "ab", vec![Some((1, 2)), Some((0, 1))]),
+        ("(a)(?:b)(c)?", "ab", vec![Some((0, 1)), None])
+    ];
+
+    for (pattern, text, groups) in cases {
+        let found = Regex::new(pattern).unwrap().find_at(&chars(text), 0).unwrap();
+        assert_eq!(found.groups, groups, "{}", pattern);
+    }
+    assert_eq!(Regex::new("a*?b").unwrap().find_at(&chars("xaab"), 0).map(|found| (found.start, found.end)), Some((1, 4)));
+}
+
+#[test]
+fn oversized_patterns_are_errors() {
+    assert!(Regex::new("(a{1000}){1000}").is_err());
+    assert!(Regex::new(&format!("{}a{}", "(".repeat(10_000), ")".repeat(10_000))).is_err());
+}
+
+#[test]
+fn natives_handle_long_inputs() {
+    let mut interpreter = Interpreter::new();
+    interpreter.eval_str("var s = \"a\"; var i = 0; while i < 17 { s = s + s; i = i + 1; }").unwrap();
+    assert_eq!(interpreter.eval_str("len(regex.find_all(\"a+\", s)[0])").unwrap().to_string(), "131072");
+    assert_eq!(interpreter.eval_str("regex.match(\"(a*)*b\", s)").unwrap().to_string(), "null");
+}
\ No newline at end of file